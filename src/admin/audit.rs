@@ -0,0 +1,180 @@
+use std::env;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::auth::authenticator::Actor;
+
+/// Entries kept in memory for queries; older ones stay in the file only.
+const MAX_ENTRIES: usize = 10_000;
+
+/// An admin operation, with the state it changed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub at: DateTime<Utc>,
+    pub actor: String,
+    /// What was done, e.g. `product.paused`.
+    pub action: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub product: Option<String>,
+    #[serde(default)]
+    pub before: Value,
+    #[serde(default)]
+    pub after: Value,
+}
+
+/// Which entries to return, newest first.
+#[derive(Debug, Default, Deserialize)]
+pub struct AuditQuery {
+    pub product: Option<String>,
+    pub action: Option<String>,
+    pub actor: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub limit: Option<usize>,
+}
+
+/// Admin operations, appended to a JSON-lines file when one is configured
+/// and replayed from it on startup.
+#[derive(Debug, Default)]
+pub struct AuditLog {
+    entries: Mutex<Vec<AuditEntry>>,
+    file: Option<Mutex<File>>,
+}
+
+impl AuditLog {
+    /// Replays the entries in `path` and appends new ones to it.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let mut entries = Vec::new();
+        match File::open(path) {
+            Ok(existing) => {
+                for (number, line) in BufReader::new(existing).lines().enumerate() {
+                    match serde_json::from_str::<AuditEntry>(&line?) {
+                        Ok(entry) => entries.push(entry),
+                        Err(e) => warn!("Skipping audit entry on line {}: {}", number + 1, e),
+                    }
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        let excess = entries.len().saturating_sub(MAX_ENTRIES);
+        entries.drain(..excess);
+
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(AuditLog {
+            entries: Mutex::new(entries),
+            file: Some(Mutex::new(file)),
+        })
+    }
+
+    /// Persists to `AUDIT_LOG_FILE` if set; otherwise entries are only kept
+    /// in memory.
+    pub fn from_env() -> Self {
+        let Ok(path) = env::var("AUDIT_LOG_FILE") else {
+            return AuditLog::default();
+        };
+        match AuditLog::open(Path::new(&path)) {
+            Ok(log) => {
+                info!("Recording admin operations in {}", path);
+                log
+            }
+            Err(e) => {
+                error!(
+                    "Failed to open AUDIT_LOG_FILE {}: {}, keeping the audit log in memory",
+                    path, e
+                );
+                AuditLog::default()
+            }
+        }
+    }
+
+    /// Records that `actor` did `action`, changing `before` into `after`.
+    pub fn record(
+        &self,
+        actor: &Actor,
+        action: &str,
+        product: Option<&str>,
+        before: impl Serialize,
+        after: impl Serialize,
+    ) {
+        let entry = AuditEntry {
+            at: Utc::now(),
+            actor: actor.0.clone(),
+            action: action.to_string(),
+            product: product.map(str::to_string),
+            before: serde_json::to_value(before).unwrap_or_default(),
+            after: serde_json::to_value(after).unwrap_or_default(),
+        };
+        info!(
+            "Audit: {} {} {}",
+            entry.actor,
+            entry.action,
+            entry.product.as_deref().unwrap_or_default()
+        );
+
+        if let Some(file) = &self.file {
+            let written = serde_json::to_string(&entry)
+                .map_err(io::Error::from)
+                .and_then(|line| {
+                    let mut file = file.lock().unwrap_or_else(|e| e.into_inner());
+                    writeln!(file, "{}", line)
+                });
+            if let Err(e) = written {
+                error!("Failed to persist audit entry: {}", e);
+            }
+        }
+
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if entries.len() >= MAX_ENTRIES {
+            entries.remove(0);
+        }
+        entries.push(entry);
+    }
+
+    /// The entries matching `query`, newest first.
+    pub fn query(&self, query: &AuditQuery) -> Vec<AuditEntry> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries
+            .iter()
+            .rev()
+            .filter(|e| query.product.is_none() || e.product == query.product)
+            .filter(|e| query.action.as_ref().is_none_or(|a| &e.action == a))
+            .filter(|e| query.actor.as_ref().is_none_or(|a| &e.actor == a))
+            .filter(|e| query.since.is_none_or(|since| e.at >= since))
+            .take(query.limit.unwrap_or(100))
+            .cloned()
+            .collect()
+    }
+}
+
+#[test]
+fn test_audit_log() {
+    let path = env::temp_dir().join(format!("audit-{}.jsonl", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let admin = Actor("api_key:1a2b3c4d".to_string());
+
+    let log = AuditLog::open(&path).unwrap();
+    log.record(&admin, "maintenance.set", None, false, true);
+    log.record(&admin, "product.paused", Some("myapp"), false, true);
+    drop(log);
+
+    let log = AuditLog::open(&path).unwrap();
+    let all = log.query(&AuditQuery::default());
+    assert_eq!(all.len(), 2);
+    assert_eq!(all[0].action, "product.paused");
+    assert_eq!(all[0].after, Value::Bool(true));
+
+    let paused = log.query(&AuditQuery {
+        product: Some("myapp".to_string()),
+        ..AuditQuery::default()
+    });
+    assert_eq!(paused.len(), 1);
+    assert_eq!(paused[0].actor, "api_key:1a2b3c4d");
+    let _ = std::fs::remove_file(&path);
+}
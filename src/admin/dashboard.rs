@@ -0,0 +1,171 @@
+use std::fmt::Write as _;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use crate::admin::experiments::Experiment;
+use crate::formats::package::escape_xml;
+use crate::telemetry::errors::RecentError;
+
+/// What the dashboard shows about one product.
+#[derive(Debug, Clone, Default)]
+pub struct ProductOverview {
+    pub name: String,
+    /// Newest stable release among the cached ones.
+    pub latest_version: Option<String>,
+    /// When the release list was last fetched from GitHub.
+    pub synced_at: Option<DateTime<Utc>>,
+    pub paused: bool,
+    pub rollout_percentage: Option<u8>,
+    pub experiment: Option<Experiment>,
+    pub halted_versions: Vec<String>,
+    pub recent_errors: Vec<RecentError>,
+}
+
+const STYLE: &str = "body{font-family:sans-serif;margin:2em}\
+table{border-collapse:collapse;width:100%}\
+th,td{border:1px solid #ccc;padding:.4em .6em;text-align:left;vertical-align:top}\
+th{background:#f4f4f4}.warn{color:#b00}ul{margin:0;padding-left:1.2em}";
+
+fn timestamp(at: &DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+fn rollout_state(product: &ProductOverview) -> String {
+    let mut state = Vec::new();
+    if product.paused {
+        state.push("<span class=\"warn\">paused</span>".to_string());
+    }
+    if let Some(percentage) = product.rollout_percentage {
+        state.push(format!("{}% of devices", percentage));
+    }
+    if let Some(experiment) = &product.experiment {
+        let groups: Vec<String> = experiment
+            .groups
+            .iter()
+            .map(|g| {
+                format!(
+                    "{} {}%{}",
+                    escape_xml(&g.name),
+                    g.percentage,
+                    g.tag
+                        .as_deref()
+                        .map(|tag| format!(" → {}", escape_xml(tag)))
+                        .unwrap_or_default()
+                )
+            })
+            .collect();
+        state.push(format!(
+            "experiment {} on {}: {}",
+            escape_xml(&experiment.name),
+            escape_xml(&experiment.channel),
+            groups.join(", ")
+        ));
+    }
+    if !product.halted_versions.is_empty() {
+        let versions: Vec<String> = product
+            .halted_versions
+            .iter()
+            .map(|v| escape_xml(v))
+            .collect();
+        state.push(format!(
+            "<span class=\"warn\">halted: {}</span>",
+            versions.join(", ")
+        ));
+    }
+    if state.is_empty() {
+        "all devices".to_string()
+    } else {
+        state.join("<br>")
+    }
+}
+
+/// Renders the dashboard as a self-contained HTML page.
+pub fn render(products: &[ProductOverview], maintenance: bool) -> String {
+    let mut html = String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    let _ = writeln!(
+        html,
+        "<title>Update server</title>\n<style>{}</style>",
+        STYLE
+    );
+    html.push_str("</head>\n<body>\n<h1>Update server</h1>\n");
+    if maintenance {
+        html.push_str("<p class=\"warn\">Maintenance mode is on.</p>\n");
+    }
+    html.push_str(
+        "<table>\n<tr><th>Product</th><th>Latest version</th><th>Last sync</th>\
+         <th>Rollout</th><th>Recent errors</th></tr>\n",
+    );
+    for product in products {
+        let _ = write!(
+            html,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>",
+            escape_xml(&product.name),
+            product
+                .latest_version
+                .as_deref()
+                .map(escape_xml)
+                .unwrap_or_else(|| "–".to_string()),
+            product
+                .synced_at
+                .as_ref()
+                .map(timestamp)
+                .unwrap_or_else(|| "never".to_string()),
+            rollout_state(product),
+        );
+        if product.recent_errors.is_empty() {
+            html.push_str("none");
+        } else {
+            html.push_str("<ul>");
+            for error in &product.recent_errors {
+                let _ = write!(
+                    html,
+                    "<li>{} <code>{}</code> {}</li>",
+                    timestamp(&error.at),
+                    escape_xml(&error.code),
+                    escape_xml(&error.message)
+                );
+            }
+            html.push_str("</ul>");
+        }
+        html.push_str("</td></tr>\n");
+    }
+    html.push_str("</table>\n</body>\n</html>\n");
+    html
+}
+
+#[test]
+fn test_render_dashboard() {
+    let products = [
+        ProductOverview {
+            name: "myapp".to_string(),
+            latest_version: Some("1.2.0".to_string()),
+            synced_at: DateTime::from_timestamp(1700000000, 0),
+            rollout_percentage: Some(25),
+            halted_versions: vec!["1.1.0".to_string()],
+            recent_errors: vec![RecentError {
+                at: DateTime::from_timestamp(1700000060, 0).unwrap(),
+                product: Some("myapp".to_string()),
+                code: "upstream_error".to_string(),
+                message: "GitHub said <no>".to_string(),
+            }],
+            ..ProductOverview::default()
+        },
+        ProductOverview {
+            name: "other".to_string(),
+            paused: true,
+            ..ProductOverview::default()
+        },
+    ];
+
+    let html = render(&products, true);
+    assert!(html.contains("Maintenance mode is on."));
+    assert!(html.contains(
+        "<tr><td>myapp</td><td>1.2.0</td><td>2023-11-14T22:13:20Z</td>\
+         <td>25% of devices<br><span class=\"warn\">halted: 1.1.0</span></td>"
+    ));
+    assert!(html.contains("<code>upstream_error</code> GitHub said &lt;no&gt;</li>"));
+    assert!(html.contains(
+        "<tr><td>other</td><td>–</td><td>never</td>\
+         <td><span class=\"warn\">paused</span></td><td>none</td></tr>"
+    ));
+}
@@ -0,0 +1,163 @@
+use std::collections::{BTreeMap, HashSet};
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::release::rollout::bucket;
+
+/// A share of the devices in an experiment. Devices in a group with a `tag`
+/// are offered that release; a group without one is a control group that
+/// keeps getting the channel's regular release.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExperimentGroup {
+    pub name: String,
+    pub percentage: u8,
+    #[serde(default)]
+    pub tag: Option<String>,
+}
+
+/// Splits the devices on one channel of a product into groups that are
+/// served different releases, e.g. to compare crash rates before promoting
+/// a release candidate.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Experiment {
+    pub name: String,
+    #[serde(default = "default_channel")]
+    pub channel: String,
+    pub groups: Vec<ExperimentGroup>,
+    #[serde(default, skip_deserializing)]
+    pub started_at: Option<DateTime<Utc>>,
+}
+
+fn default_channel() -> String {
+    "stable".to_string()
+}
+
+impl Experiment {
+    /// Checks that the groups are named uniquely and cover at most 100%.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("the experiment needs a name".to_string());
+        }
+        if self.groups.is_empty() {
+            return Err("the experiment needs at least one group".to_string());
+        }
+        let mut names = HashSet::new();
+        if let Some(group) = self.groups.iter().find(|g| !names.insert(g.name.as_str())) {
+            return Err(format!("group {} is defined twice", group.name));
+        }
+        let total: u32 = self.groups.iter().map(|g| u32::from(g.percentage)).sum();
+        if total > 100 {
+            return Err(format!("groups cover {}% of devices", total));
+        }
+        Ok(())
+    }
+
+    /// The group of a device, or `None` for devices outside every group and
+    /// devices without an id. Assignment is stable for the experiment's
+    /// lifetime and independent of other experiments.
+    pub fn assign(&self, product: &str, device_id: Option<&str>) -> Option<&ExperimentGroup> {
+        let device_id = device_id.filter(|id| !id.trim().is_empty())?;
+        let bucket = bucket(&format!("{}:{}", product, self.name), device_id);
+        let mut end = 0;
+        self.groups.iter().find(|group| {
+            end += u32::from(group.percentage);
+            bucket < end
+        })
+    }
+}
+
+/// Running experiments, at most one per product. They're started and
+/// stopped through the admin API and don't survive a restart.
+#[derive(Debug, Default)]
+pub struct Experiments {
+    running: DashMap<String, Experiment>,
+}
+
+impl Experiments {
+    /// The experiment running for `product` on `channel`, if any.
+    pub fn get(&self, product: &str, channel: &str) -> Option<Experiment> {
+        self.running
+            .get(product)
+            .filter(|e| e.channel.eq_ignore_ascii_case(channel))
+            .map(|e| e.clone())
+    }
+
+    /// Starts `experiment`, replacing the product's previous one, and returns
+    /// it with its start time, along with the one it replaced.
+    pub fn start(
+        &self,
+        product: &str,
+        mut experiment: Experiment,
+    ) -> (Experiment, Option<Experiment>) {
+        experiment.started_at = Some(Utc::now());
+        let replaced = self
+            .running
+            .insert(product.to_lowercase(), experiment.clone());
+        (experiment, replaced)
+    }
+
+    /// Stops and returns the product's experiment.
+    pub fn stop(&self, product: &str) -> Option<Experiment> {
+        self.running
+            .remove(&product.to_lowercase())
+            .map(|(_, experiment)| experiment)
+    }
+
+    /// Running experiments by product.
+    pub fn running(&self) -> BTreeMap<String, Experiment> {
+        self.running
+            .iter()
+            .map(|e| (e.key().clone(), e.value().clone()))
+            .collect()
+    }
+}
+
+#[test]
+fn test_experiment_assignment() {
+    let group = |name: &str, percentage, tag: Option<&str>| ExperimentGroup {
+        name: name.to_string(),
+        percentage,
+        tag: tag.map(str::to_string),
+    };
+    let mut experiment = Experiment {
+        name: "rc-crashes".to_string(),
+        channel: "stable".to_string(),
+        groups: vec![
+            group("candidate", 50, Some("v2.1.0-rc.1")),
+            group("control", 50, None),
+        ],
+        started_at: None,
+    };
+    assert_eq!(experiment.validate(), Ok(()));
+
+    let candidates = (0..1000)
+        .map(|i| format!("device-{}", i))
+        .filter(|id| experiment.assign("myapp", Some(id)).unwrap().name == "candidate")
+        .count();
+    assert!((400..600).contains(&candidates), "{} of 1000", candidates);
+    assert_eq!(
+        experiment.assign("myapp", Some("device-1")),
+        experiment.assign("myapp", Some("device-1"))
+    );
+    assert_eq!(experiment.assign("myapp", None), None);
+
+    experiment.groups.push(group("control", 10, None));
+    assert_eq!(
+        experiment.validate(),
+        Err("group control is defined twice".to_string())
+    );
+    experiment.groups[2].name = "other".to_string();
+    assert_eq!(
+        experiment.validate(),
+        Err("groups cover 110% of devices".to_string())
+    );
+
+    let experiments = Experiments::default();
+    experiments.start("MyApp", experiment);
+    assert!(experiments.get("myapp", "stable").is_some());
+    assert!(experiments.get("myapp", "beta").is_none());
+    assert!(experiments.stop("myapp").is_some());
+    assert!(experiments.running().is_empty());
+}
@@ -0,0 +1,63 @@
+use std::env;
+
+use dashmap::DashSet;
+use log::warn;
+
+use crate::config::parse_list;
+
+/// Products whose updates are paused, e.g. because a release turned out to
+/// be broken after it was published. Update checks for a paused product
+/// report no update; other products are unaffected.
+#[derive(Debug, Default)]
+pub struct KillSwitch {
+    paused: DashSet<String>,
+}
+
+impl KillSwitch {
+    /// Reads the comma-separated `PAUSED_PRODUCTS`.
+    pub fn from_env() -> Self {
+        let switch = KillSwitch::default();
+        for product in env::var("PAUSED_PRODUCTS")
+            .map(|v| parse_list(&v))
+            .unwrap_or_default()
+        {
+            warn!("Updates of {} are paused", product);
+            switch.set_paused(&product, true);
+        }
+        switch
+    }
+
+    pub fn is_paused(&self, product: &str) -> bool {
+        self.paused.contains(product)
+    }
+
+    /// Pauses or resumes `product`, returning whether it was paused before.
+    pub fn set_paused(&self, product: &str, paused: bool) -> bool {
+        let product = product.to_lowercase();
+        if paused {
+            !self.paused.insert(product)
+        } else {
+            self.paused.remove(&product).is_some()
+        }
+    }
+
+    /// Currently paused products, sorted by name.
+    pub fn paused(&self) -> Vec<String> {
+        let mut paused: Vec<String> = self.paused.iter().map(|p| p.key().clone()).collect();
+        paused.sort();
+        paused
+    }
+}
+
+#[test]
+fn test_kill_switch() {
+    let switch = KillSwitch::default();
+    assert!(!switch.set_paused("MyApp", true));
+    assert!(switch.set_paused("myapp", true));
+    assert!(switch.is_paused("myapp"));
+    assert!(!switch.is_paused("otherapp"));
+    assert_eq!(switch.paused(), vec!["myapp".to_string()]);
+
+    assert!(switch.set_paused("myapp", false));
+    assert!(!switch.is_paused("myapp"));
+}
@@ -0,0 +1,68 @@
+use std::env;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use log::warn;
+
+use crate::config::parse_flag;
+use crate::error::ServerError;
+
+const DEFAULT_RETRY_AFTER_SECS: u64 = 300;
+
+/// Freezes the fleet during incidents: while enabled, update checks report
+/// no update and downloads are refused with `503 Service Unavailable`.
+#[derive(Debug)]
+pub struct Maintenance {
+    enabled: AtomicBool,
+    retry_after_secs: u64,
+}
+
+impl Default for Maintenance {
+    fn default() -> Self {
+        Maintenance::new(false, DEFAULT_RETRY_AFTER_SECS)
+    }
+}
+
+impl Maintenance {
+    pub fn new(enabled: bool, retry_after_secs: u64) -> Self {
+        Maintenance {
+            enabled: AtomicBool::new(enabled),
+            retry_after_secs,
+        }
+    }
+
+    /// Reads `MAINTENANCE_MODE` and `MAINTENANCE_RETRY_AFTER_SECS`.
+    pub fn from_env() -> Self {
+        let enabled = env::var("MAINTENANCE_MODE").is_ok_and(|v| parse_flag(&v));
+        if enabled {
+            warn!("Starting in maintenance mode");
+        }
+
+        Maintenance::new(
+            enabled,
+            env::var("MAINTENANCE_RETRY_AFTER_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_RETRY_AFTER_SECS),
+        )
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Switches maintenance mode, returning whether it was enabled before.
+    pub fn set_enabled(&self, enabled: bool) -> bool {
+        self.enabled.swap(enabled, Ordering::Relaxed)
+    }
+
+    pub fn retry_after_secs(&self) -> u64 {
+        self.retry_after_secs
+    }
+
+    /// The error for requests that are refused during maintenance.
+    pub fn unavailable(&self) -> ServerError {
+        ServerError::Maintenance {
+            retry_after_secs: self.retry_after_secs,
+        }
+    }
+}
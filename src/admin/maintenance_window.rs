@@ -0,0 +1,232 @@
+use std::fmt;
+
+use chrono::{DateTime, Datelike, FixedOffset, Timelike, Utc};
+use serde::Deserialize;
+
+use crate::config::parse_list;
+
+const DAYS: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+
+const MINUTES_PER_DAY: u32 = 24 * 60;
+
+/// A weekly time window, such as business hours, during which a product's
+/// clients aren't offered updates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MaintenanceWindow {
+    /// Days the window starts on, Monday first.
+    days: [bool; 7],
+    /// Minutes after midnight; a window ending before it starts runs past
+    /// midnight into the next day.
+    start: u32,
+    end: u32,
+    offset: FixedOffset,
+}
+
+impl MaintenanceWindow {
+    /// Parses `[days] HH:MM-HH:MM [offset]`, e.g. `mon-fri 09:00-17:00
+    /// +01:00` or `22:00-06:00`. Days are a day or a range of days and
+    /// default to every day; the offset defaults to UTC.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let parts: Vec<&str> = value.split_whitespace().collect();
+        let (days, times, offset) = match parts.as_slice() {
+            [times] => ("*", *times, None),
+            [first, second] if first.contains(':') => ("*", *first, Some(*second)),
+            [days, times] => (*days, *times, None),
+            [days, times, offset] => (*days, *times, Some(*offset)),
+            _ => {
+                return Err(format!(
+                    "expected [days] HH:MM-HH:MM [offset], got {}",
+                    value
+                ))
+            }
+        };
+
+        let (start, end) = times
+            .split_once('-')
+            .ok_or_else(|| format!("expected HH:MM-HH:MM, got {}", times))?;
+        let (start, end) = (parse_time(start)?, parse_time(end)?);
+        if start == end || start == MINUTES_PER_DAY {
+            return Err(format!("empty window {}", times));
+        }
+
+        Ok(MaintenanceWindow {
+            days: parse_days(days)?,
+            start,
+            end,
+            offset: match offset {
+                Some(offset) => parse_offset(offset)?,
+                None => FixedOffset::east_opt(0).expect("valid offset"),
+            },
+        })
+    }
+
+    pub fn contains(&self, at: DateTime<Utc>) -> bool {
+        let local = at.with_timezone(&self.offset);
+        let minute = local.hour() * 60 + local.minute();
+        let today = local.weekday().num_days_from_monday() as usize;
+        let yesterday = (today + 6) % 7;
+        if self.start < self.end {
+            self.days[today] && (self.start..self.end).contains(&minute)
+        } else {
+            (self.days[today] && minute >= self.start)
+                || (self.days[yesterday] && minute < self.end)
+        }
+    }
+}
+
+impl fmt::Display for MaintenanceWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let days: Vec<&str> = DAYS
+            .iter()
+            .zip(self.days)
+            .filter_map(|(day, included)| included.then_some(*day))
+            .collect();
+        let time = |minutes: u32| format!("{:02}:{:02}", minutes / 60, minutes % 60);
+        write!(
+            f,
+            "{} {}-{} {}",
+            if days.len() == 7 {
+                "daily".to_string()
+            } else {
+                days.join("|")
+            },
+            time(self.start),
+            time(self.end),
+            self.offset
+        )
+    }
+}
+
+/// A product's maintenance windows; updates are held back while any of
+/// them is open.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct MaintenanceWindows(Vec<MaintenanceWindow>);
+
+impl MaintenanceWindows {
+    /// Parses comma-separated windows, see [`MaintenanceWindow::parse`].
+    pub fn parse(value: &str) -> Result<Self, String> {
+        parse_list(value)
+            .iter()
+            .map(|window| MaintenanceWindow::parse(window))
+            .collect::<Result<_, _>>()
+            .map(MaintenanceWindows)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn is_open(&self, at: DateTime<Utc>) -> bool {
+        self.0.iter().any(|window| window.contains(at))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &MaintenanceWindow> {
+        self.0.iter()
+    }
+}
+
+impl TryFrom<String> for MaintenanceWindows {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        MaintenanceWindows::parse(&value)
+    }
+}
+
+/// Minutes after midnight of `HH:MM`; `24:00` is the end of the day.
+fn parse_time(time: &str) -> Result<u32, String> {
+    let invalid = || format!("invalid time {}", time);
+    let (hours, minutes) = time.trim().split_once(':').ok_or_else(invalid)?;
+    let hours: u32 = hours.parse().map_err(|_| invalid())?;
+    let minutes: u32 = minutes.parse().map_err(|_| invalid())?;
+    let total = hours * 60 + minutes;
+    if minutes >= 60 || total > MINUTES_PER_DAY {
+        return Err(invalid());
+    }
+    Ok(total)
+}
+
+/// `*`, a day such as `sat` or a range such as `mon-fri` or `fri-mon`.
+fn parse_days(days: &str) -> Result<[bool; 7], String> {
+    let day = |name: &str| {
+        let name = name.to_lowercase();
+        DAYS.iter()
+            .position(|day| name.starts_with(day))
+            .ok_or_else(|| format!("unknown day {}", name))
+    };
+    let (first, last) = match days {
+        "*" => return Ok([true; 7]),
+        days => match days.split_once('-') {
+            Some((first, last)) => (day(first)?, day(last)?),
+            None => (day(days)?, day(days)?),
+        },
+    };
+
+    let mut included = [false; 7];
+    let mut current = first;
+    loop {
+        included[current] = true;
+        if current == last {
+            return Ok(included);
+        }
+        current = (current + 1) % 7;
+    }
+}
+
+/// `UTC`, `Z` or an offset such as `+01:00`, `-0530` or `+2`.
+fn parse_offset(offset: &str) -> Result<FixedOffset, String> {
+    let invalid = || format!("invalid UTC offset {}", offset);
+    if matches!(offset.to_uppercase().as_str(), "UTC" | "Z") {
+        return Ok(FixedOffset::east_opt(0).expect("valid offset"));
+    }
+    let (sign, rest) = match offset.split_at_checked(1) {
+        Some(("+", rest)) => (1, rest),
+        Some(("-", rest)) => (-1, rest),
+        _ => return Err(invalid()),
+    };
+    let digits = rest.replace(':', "");
+    let (hours, minutes) = match digits.len() {
+        1 | 2 => (digits.as_str(), "0"),
+        4 => digits.split_at(2),
+        _ => return Err(invalid()),
+    };
+    let hours: i32 = hours.parse().map_err(|_| invalid())?;
+    let minutes: i32 = minutes.parse().map_err(|_| invalid())?;
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60)).ok_or_else(invalid)
+}
+
+#[test]
+fn test_maintenance_windows() {
+    let at = |time: &str| {
+        DateTime::parse_from_rfc3339(time)
+            .unwrap()
+            .with_timezone(&Utc)
+    };
+    // 2024-05-06 is a Monday
+    let windows = MaintenanceWindows::parse("mon-fri 09:00-17:00 +02:00, sat 22:00-06:00").unwrap();
+    assert!(windows.is_open(at("2024-05-06T07:00:00Z")));
+    assert!(!windows.is_open(at("2024-05-06T06:59:00Z")));
+    assert!(!windows.is_open(at("2024-05-06T15:00:00Z")));
+    assert!(windows.is_open(at("2024-05-10T14:59:00Z")));
+    assert!(!windows.is_open(at("2024-05-11T12:00:00Z")));
+    // Saturday night runs into Sunday morning
+    assert!(windows.is_open(at("2024-05-11T23:00:00Z")));
+    assert!(windows.is_open(at("2024-05-12T05:59:00Z")));
+    assert!(!windows.is_open(at("2024-05-12T22:00:00Z")));
+
+    let daily = MaintenanceWindows::parse("00:00-24:00").unwrap();
+    assert!(daily.is_open(at("2024-05-12T23:59:00Z")));
+    assert_eq!(
+        MaintenanceWindow::parse("fri-mon 08:30-12:00 -0530")
+            .unwrap()
+            .to_string(),
+        "mon|fri|sat|sun 08:30-12:00 -05:30"
+    );
+
+    assert!(MaintenanceWindows::parse("mon-fri").is_err());
+    assert!(MaintenanceWindows::parse("someday 09:00-17:00").is_err());
+    assert!(MaintenanceWindows::parse("09:00-09:00").is_err());
+    assert!(MaintenanceWindows::parse("09:00-25:00").is_err());
+    assert!(MaintenanceWindows::parse("09:00-17:00 CET").is_err());
+}
@@ -0,0 +1,6 @@
+pub mod audit;
+pub mod dashboard;
+pub mod experiments;
+pub mod kill_switch;
+pub mod maintenance;
+pub mod maintenance_window;
@@ -0,0 +1,124 @@
+use std::env;
+use std::net::IpAddr;
+
+use actix_web::HttpRequest;
+use ipnet::IpNet;
+use log::error;
+
+/// Parses comma-separated CIDR ranges and single addresses such as
+/// `10.0.0.0/8, 192.168.1.7, fd00::/8`.
+pub fn parse_ranges(value: &str) -> Result<Vec<IpNet>, String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|range| !range.is_empty())
+        .map(|range| {
+            range
+                .parse::<IpNet>()
+                .or_else(|_| range.parse::<IpAddr>().map(IpNet::from))
+                .map_err(|_| format!("{} is not an address or CIDR range", range))
+        })
+        .collect()
+}
+
+/// Reads `TRUSTED_PROXIES`; an invalid list trusts no proxy.
+pub fn trusted_proxies_from_env() -> Vec<IpNet> {
+    env::var("TRUSTED_PROXIES")
+        .map(|value| {
+            parse_ranges(&value).unwrap_or_else(|e| {
+                error!("Invalid TRUSTED_PROXIES: {}", e);
+                Vec::new()
+            })
+        })
+        .unwrap_or_default()
+}
+
+/// The client's address: the peer, or, if the peer is a trusted proxy, the
+/// last address in `X-Forwarded-For` that isn't one.
+pub fn client_ip(req: &HttpRequest, trusted_proxies: &[IpNet]) -> Option<IpAddr> {
+    let is_trusted = |address: &IpAddr| trusted_proxies.iter().any(|r| r.contains(address));
+    let peer = req.peer_addr()?.ip();
+    if !is_trusted(&peer) {
+        return Some(peer);
+    }
+    let forwarded = req
+        .headers()
+        .get_all("X-Forwarded-For")
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .filter_map(|address| address.trim().parse::<IpAddr>().ok())
+        .collect::<Vec<_>>();
+    Some(
+        forwarded
+            .into_iter()
+            .rev()
+            .find(|address| !is_trusted(address))
+            .unwrap_or(peer),
+    )
+}
+
+/// Address ranges allowed to call a route group, checked before any
+/// credentials. Behind a reverse proxy, the client's address is taken from
+/// `X-Forwarded-For` when the connection comes from a trusted proxy.
+#[derive(Debug, Clone, Default)]
+pub struct IpAllowlist {
+    allowed: Vec<IpNet>,
+    trusted_proxies: Vec<IpNet>,
+}
+
+impl IpAllowlist {
+    pub fn new(allowed: Vec<IpNet>, trusted_proxies: Vec<IpNet>) -> Self {
+        IpAllowlist {
+            allowed,
+            trusted_proxies,
+        }
+    }
+
+    /// Reads `key` (e.g. `AUTH_ADMIN_ALLOWED_IPS`) and `TRUSTED_PROXIES`.
+    /// An invalid list allows no address, so a typo doesn't open the routes.
+    pub fn from_env(key: &str) -> Option<Self> {
+        let value = env::var(key).ok()?;
+        let allowed = parse_ranges(&value).unwrap_or_else(|e| {
+            error!("Invalid {}: {}; denying every address", key, e);
+            Vec::new()
+        });
+        Some(IpAllowlist::new(allowed, trusted_proxies_from_env()))
+    }
+
+    /// The client's address, see [`client_ip`].
+    pub fn client_ip(&self, req: &HttpRequest) -> Option<IpAddr> {
+        client_ip(req, &self.trusted_proxies)
+    }
+
+    pub fn allows(&self, req: &HttpRequest) -> bool {
+        self.client_ip(req)
+            .is_some_and(|ip| self.allowed.iter().any(|range| range.contains(&ip)))
+    }
+}
+
+#[test]
+fn test_ip_allowlist() {
+    use actix_web::test::TestRequest;
+
+    assert!(parse_ranges("10.0.0.0/8, 192.168.1.7,fd00::/8").is_ok());
+    assert!(parse_ranges("10.0.0.0/33").is_err());
+
+    let allowlist = IpAllowlist::new(
+        parse_ranges("10.0.0.0/8").unwrap(),
+        parse_ranges("172.16.0.1").unwrap(),
+    );
+    let request = |peer: &str, forwarded: Option<&str>| {
+        let mut request = TestRequest::default().peer_addr(peer.parse().unwrap());
+        if let Some(forwarded) = forwarded {
+            request = request.insert_header(("X-Forwarded-For", forwarded));
+        }
+        request.to_http_request()
+    };
+
+    assert!(allowlist.allows(&request("10.1.2.3:5000", None)));
+    assert!(!allowlist.allows(&request("203.0.113.9:5000", None)));
+    // Only trusted proxies may vouch for the client's address
+    assert!(!allowlist.allows(&request("203.0.113.9:5000", Some("10.1.2.3"))));
+    assert!(allowlist.allows(&request("172.16.0.1:5000", Some("10.1.2.3"))));
+    assert!(!allowlist.allows(&request("172.16.0.1:5000", Some("10.1.2.3, 203.0.113.9"))));
+}
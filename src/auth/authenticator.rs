@@ -0,0 +1,657 @@
+use std::future::{ready, Ready};
+use std::{collections::HashMap, convert::Infallible, env, fmt, sync::Arc};
+
+use actix_web::dev::Payload;
+use actix_web::http::header::WWW_AUTHENTICATE;
+use actix_web::http::StatusCode;
+use actix_web::{FromRequest, HttpMessage, HttpRequest, ResponseError};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ipnet::IpNet;
+use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+use log::{debug, error};
+use ring::constant_time::verify_slices_are_equal;
+use ring::digest::{digest, SHA256};
+use serde::Deserialize;
+
+use crate::auth::allowlist::{trusted_proxies_from_env, IpAllowlist};
+use crate::cache::digest::hex;
+
+/// Groups of routes that can be protected by their own set of authenticators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RouteGroup {
+    Update,
+    Download,
+    Admin,
+}
+
+impl RouteGroup {
+    pub const ALL: [RouteGroup; 3] = [RouteGroup::Update, RouteGroup::Download, RouteGroup::Admin];
+
+    fn env_key(&self) -> &'static str {
+        match self {
+            RouteGroup::Update => "AUTH_UPDATE",
+            RouteGroup::Download => "AUTH_DOWNLOAD",
+            RouteGroup::Admin => "AUTH_ADMIN",
+        }
+    }
+}
+
+impl fmt::Display for RouteGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RouteGroup::Update => write!(f, "update"),
+            RouteGroup::Download => write!(f, "download"),
+            RouteGroup::Admin => write!(f, "admin"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum AuthError {
+    #[error("Missing credentials")]
+    MissingCredentials,
+    #[error("Invalid credentials")]
+    InvalidCredentials,
+    #[error("Authentication is misconfigured for this route")]
+    Misconfigured,
+    #[error("Requests from this address are not allowed")]
+    AddressNotAllowed,
+    /// `error`, answered with a `WWW-Authenticate` challenge.
+    #[error("{error}")]
+    Challenged {
+        error: Box<AuthError>,
+        challenge: String,
+    },
+}
+
+impl ResponseError for AuthError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            AuthError::MissingCredentials => StatusCode::UNAUTHORIZED,
+            AuthError::InvalidCredentials => StatusCode::UNAUTHORIZED,
+            AuthError::Misconfigured | AuthError::AddressNotAllowed => StatusCode::FORBIDDEN,
+            AuthError::Challenged { error, .. } => error.status_code(),
+        }
+    }
+
+    fn error_response(&self) -> actix_web::HttpResponse {
+        let mut response = actix_web::HttpResponse::build(self.status_code());
+        if let AuthError::Challenged { challenge, .. } = self {
+            response.insert_header((WWW_AUTHENTICATE, challenge.as_str()));
+        }
+        response.content_type("text/plain").body(self.to_string())
+    }
+}
+
+/// A single authentication scheme. Implement this to plug a custom scheme
+/// into the server and register it with [`AuthConfig::add`].
+pub trait Authenticator: Send + Sync {
+    fn name(&self) -> &str;
+    fn authenticate(&self, req: &HttpRequest) -> Result<(), AuthError>;
+
+    /// Who sent an accepted request, e.g. a JWT's subject, for the audit log.
+    fn principal(&self, _req: &HttpRequest) -> Option<String> {
+        None
+    }
+}
+
+/// Who made an authenticated request: the authenticator that accepted it,
+/// followed by the principal it reported, if any (e.g. `jwt:alice`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Actor(pub String);
+
+impl Actor {
+    pub fn anonymous() -> Self {
+        Actor("anonymous".to_string())
+    }
+}
+
+impl FromRequest for Actor {
+    type Error = Infallible;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(Ok(req
+            .extensions()
+            .get::<Actor>()
+            .cloned()
+            .unwrap_or_else(Actor::anonymous)))
+    }
+}
+
+fn header_value<'a>(req: &'a HttpRequest, name: &str) -> Option<&'a str> {
+    req.headers().get(name).and_then(|v| v.to_str().ok())
+}
+
+fn bearer_token(req: &HttpRequest) -> Option<&str> {
+    header_value(req, "Authorization").and_then(|v| v.strip_prefix("Bearer "))
+}
+
+/// Compares a presented secret with an expected one in constant time, so
+/// response times don't reveal how much of it matched. Their SHA-256
+/// digests are compared, which always have the same length, so the
+/// secret's length doesn't leak either.
+pub(crate) fn secrets_match(presented: &str, expected: &str) -> bool {
+    let presented = digest(&SHA256, presented.as_bytes());
+    let expected = digest(&SHA256, expected.as_bytes());
+    verify_slices_are_equal(presented.as_ref(), expected.as_ref()).is_ok()
+}
+
+/// A short hash of a secret, so secrets can be told apart without being
+/// logged.
+pub(crate) fn fingerprint(secret: &str) -> String {
+    hex(&digest(&SHA256, secret.as_bytes()).as_ref()[..4])
+}
+
+// Static API keys, read from a header or a bearer token
+pub struct ApiKeyAuthenticator {
+    header: String,
+    keys: Vec<String>,
+}
+
+impl ApiKeyAuthenticator {
+    pub fn new(header: String, keys: Vec<String>) -> Self {
+        Self { header, keys }
+    }
+}
+
+impl Authenticator for ApiKeyAuthenticator {
+    fn name(&self) -> &str {
+        "api_key"
+    }
+
+    fn authenticate(&self, req: &HttpRequest) -> Result<(), AuthError> {
+        let key = header_value(req, &self.header)
+            .or_else(|| bearer_token(req))
+            .ok_or(AuthError::MissingCredentials)?;
+
+        // Check every key, so the time taken doesn't tell which one matched
+        let matched = self
+            .keys
+            .iter()
+            .fold(false, |matched, k| secrets_match(key, k) | matched);
+        if matched {
+            Ok(())
+        } else {
+            Err(AuthError::InvalidCredentials)
+        }
+    }
+
+    /// A fingerprint of the key, so keys can be told apart without being
+    /// logged.
+    fn principal(&self, req: &HttpRequest) -> Option<String> {
+        let key = header_value(req, &self.header).or_else(|| bearer_token(req))?;
+        Some(fingerprint(key))
+    }
+}
+
+// Usernames and passwords sent with HTTP basic auth
+pub struct BasicAuthenticator {
+    users: HashMap<String, String>,
+}
+
+impl BasicAuthenticator {
+    /// `users` maps usernames to passwords.
+    pub fn new(users: HashMap<String, String>) -> Self {
+        Self { users }
+    }
+
+    fn credentials(req: &HttpRequest) -> Option<(String, String)> {
+        let encoded = header_value(req, "Authorization")?.strip_prefix("Basic ")?;
+        let decoded = STANDARD.decode(encoded.trim()).ok()?;
+        let (user, password) = String::from_utf8(decoded)
+            .ok()?
+            .split_once(':')
+            .map(|(user, password)| (user.to_string(), password.to_string()))?;
+        Some((user, password))
+    }
+}
+
+impl Authenticator for BasicAuthenticator {
+    fn name(&self) -> &str {
+        "basic"
+    }
+
+    fn authenticate(&self, req: &HttpRequest) -> Result<(), AuthError> {
+        let (user, password) = Self::credentials(req).ok_or(AuthError::MissingCredentials)?;
+        match self.users.get(&user) {
+            Some(expected) if secrets_match(&password, expected) => Ok(()),
+            _ => Err(AuthError::InvalidCredentials),
+        }
+    }
+
+    fn principal(&self, req: &HttpRequest) -> Option<String> {
+        Self::credentials(req).map(|(user, _)| user)
+    }
+}
+
+// HS256 JWTs passed as bearer tokens
+pub struct JwtAuthenticator {
+    key: DecodingKey,
+    validation: Validation,
+}
+
+#[derive(Deserialize)]
+struct Claims {
+    #[serde(default)]
+    sub: Option<String>,
+}
+
+impl JwtAuthenticator {
+    pub fn new(secret: &str, issuer: Option<String>, audience: Option<String>) -> Self {
+        let mut validation = Validation::new(Algorithm::HS256);
+        if let Some(issuer) = issuer {
+            validation.set_issuer(&[issuer]);
+        }
+        match audience {
+            Some(audience) => validation.set_audience(&[audience]),
+            None => validation.validate_aud = false,
+        }
+
+        Self {
+            key: DecodingKey::from_secret(secret.as_bytes()),
+            validation,
+        }
+    }
+}
+
+impl Authenticator for JwtAuthenticator {
+    fn name(&self) -> &str {
+        "jwt"
+    }
+
+    fn authenticate(&self, req: &HttpRequest) -> Result<(), AuthError> {
+        let token = bearer_token(req).ok_or(AuthError::MissingCredentials)?;
+
+        decode::<Claims>(token, &self.key, &self.validation)
+            .map(|_| ())
+            .map_err(|e| {
+                debug!("Rejected JWT: {}", e);
+                AuthError::InvalidCredentials
+            })
+    }
+
+    fn principal(&self, req: &HttpRequest) -> Option<String> {
+        let token = bearer_token(req)?;
+        decode::<Claims>(token, &self.key, &self.validation)
+            .ok()?
+            .claims
+            .sub
+    }
+}
+
+// A fixed header/value pair, e.g. a shared secret injected by a gateway
+pub struct HeaderAuthenticator {
+    header: String,
+    value: String,
+}
+
+impl HeaderAuthenticator {
+    pub fn new(header: String, value: String) -> Self {
+        Self { header, value }
+    }
+}
+
+impl Authenticator for HeaderAuthenticator {
+    fn name(&self) -> &str {
+        "header"
+    }
+
+    fn authenticate(&self, req: &HttpRequest) -> Result<(), AuthError> {
+        match header_value(req, &self.header) {
+            Some(value) if secrets_match(value, &self.value) => Ok(()),
+            Some(_) => Err(AuthError::InvalidCredentials),
+            None => Err(AuthError::MissingCredentials),
+        }
+    }
+}
+
+// Client certificates are verified by the TLS-terminating proxy, which
+// reports the outcome in a header (e.g. nginx `$ssl_client_verify`). Anyone
+// could send that header, so it only counts on connections from the trusted
+// proxies, which must overwrite it on every request.
+pub struct MtlsAuthenticator {
+    header: String,
+    trusted_proxies: Vec<IpNet>,
+}
+
+impl MtlsAuthenticator {
+    pub fn new(header: String, trusted_proxies: Vec<IpNet>) -> Self {
+        Self {
+            header,
+            trusted_proxies,
+        }
+    }
+}
+
+impl Authenticator for MtlsAuthenticator {
+    fn name(&self) -> &str {
+        "mtls"
+    }
+
+    fn authenticate(&self, req: &HttpRequest) -> Result<(), AuthError> {
+        let from_proxy = req
+            .peer_addr()
+            .is_some_and(|peer| self.trusted_proxies.iter().any(|r| r.contains(&peer.ip())));
+        match header_value(req, &self.header) {
+            Some(_) if !from_proxy => Err(AuthError::InvalidCredentials),
+            Some("SUCCESS") => Ok(()),
+            Some(_) => Err(AuthError::InvalidCredentials),
+            None => Err(AuthError::MissingCredentials),
+        }
+    }
+}
+
+/// Authenticators configured per route group. A group without an entry is
+/// public, except for admin routes, which are disabled until configured; a
+/// request to a protected group passes if any authenticator accepts it.
+#[derive(Default)]
+pub struct AuthConfig {
+    groups: HashMap<RouteGroup, Vec<Arc<dyn Authenticator>>>,
+    allowlists: HashMap<RouteGroup, IpAllowlist>,
+}
+
+impl AuthConfig {
+    pub fn from_env() -> Self {
+        let mut config = AuthConfig::default();
+
+        for group in RouteGroup::ALL {
+            if let Some(allowlist) =
+                IpAllowlist::from_env(&format!("{}_ALLOWED_IPS", group.env_key()))
+            {
+                config.allowlists.insert(group, allowlist);
+            }
+
+            let Ok(selected) = env::var(group.env_key()) else {
+                continue;
+            };
+
+            // Register the group even if every authenticator fails to build,
+            // so a misconfiguration denies requests instead of allowing them.
+            let authenticators = config.groups.entry(group).or_default();
+            for name in selected.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+                match build_authenticator(name, group) {
+                    Some(authenticator) => authenticators.push(authenticator),
+                    None => error!("Auth provider {} for {} routes is not configured", name, group),
+                }
+            }
+        }
+
+        config
+    }
+
+    pub fn add(&mut self, group: RouteGroup, authenticator: Arc<dyn Authenticator>) {
+        self.groups.entry(group).or_default().push(authenticator);
+    }
+
+    /// Only lets requests to `group` from the allowlist's addresses through,
+    /// whatever their credentials.
+    pub fn restrict(&mut self, group: RouteGroup, allowlist: IpAllowlist) {
+        self.allowlists.insert(group, allowlist);
+    }
+
+    /// The `WWW-Authenticate` challenge of rejected requests to `group`, so
+    /// browsers ask for a username and password where basic auth is accepted.
+    fn challenge(&self, group: RouteGroup) -> Option<String> {
+        self.groups
+            .get(&group)?
+            .iter()
+            .any(|a| a.name() == "basic")
+            .then(|| format!("Basic realm=\"{}\"", group))
+    }
+
+    /// Whether requests to `group` need credentials.
+    pub fn is_protected(&self, group: RouteGroup) -> bool {
+        group == RouteGroup::Admin || self.groups.contains_key(&group)
+    }
+
+    /// Checks `req` against the authenticators of `group` and returns who
+    /// sent it.
+    pub fn authenticate(&self, group: RouteGroup, req: &HttpRequest) -> Result<Actor, AuthError> {
+        if let Some(allowlist) = self.allowlists.get(&group) {
+            if !allowlist.allows(req) {
+                debug!(
+                    "Rejected {} request from {:?}",
+                    group,
+                    allowlist.client_ip(req)
+                );
+                return Err(AuthError::AddressNotAllowed);
+            }
+        }
+
+        let Some(authenticators) = self.groups.get(&group) else {
+            return match group {
+                RouteGroup::Admin => Err(AuthError::Misconfigured),
+                _ => Ok(Actor::anonymous()),
+            };
+        };
+
+        let mut last_error = AuthError::Misconfigured;
+        for authenticator in authenticators {
+            match authenticator.authenticate(req) {
+                Ok(()) => {
+                    let name = authenticator.name();
+                    return Ok(Actor(match authenticator.principal(req) {
+                        Some(principal) => format!("{}:{}", name, principal),
+                        None => name.to_string(),
+                    }));
+                }
+                Err(e) => {
+                    debug!("{} rejected {} request: {}", authenticator.name(), group, e);
+                    last_error = e;
+                }
+            }
+        }
+
+        match self.challenge(group) {
+            Some(challenge) if last_error != AuthError::Misconfigured => {
+                Err(AuthError::Challenged {
+                    error: Box::new(last_error),
+                    challenge,
+                })
+            }
+            _ => Err(last_error),
+        }
+    }
+}
+
+/// Builds the authenticator `name` for `group`. Each setting can be given
+/// for the group alone, e.g. `AUTH_ADMIN_API_KEYS` instead of
+/// `AUTH_API_KEYS`, so admin credentials can differ from the others.
+fn build_authenticator(name: &str, group: RouteGroup) -> Option<Arc<dyn Authenticator>> {
+    let setting = |key: &str| {
+        env::var(format!("{}_{}", group.env_key(), key))
+            .or_else(|_| env::var(format!("AUTH_{}", key)))
+            .ok()
+    };
+    match name {
+        "api_key" => {
+            let keys: Vec<String> = setting("API_KEYS")?
+                .split(',')
+                .map(|k| k.trim().to_string())
+                .filter(|k| !k.is_empty())
+                .collect();
+            let header = setting("API_KEY_HEADER").unwrap_or_else(|| "X-Api-Key".to_string());
+            Some(Arc::new(ApiKeyAuthenticator::new(header, keys)))
+        }
+        "basic" => {
+            let users = setting("BASIC_USERS")?
+                .split(',')
+                .filter_map(|entry| {
+                    let (user, password) = entry.trim().split_once(':')?;
+                    Some((user.to_string(), password.to_string()))
+                })
+                .collect();
+            Some(Arc::new(BasicAuthenticator::new(users)))
+        }
+        "jwt" => {
+            let secret = setting("JWT_SECRET")?;
+            Some(Arc::new(JwtAuthenticator::new(
+                &secret,
+                setting("JWT_ISSUER"),
+                setting("JWT_AUDIENCE"),
+            )))
+        }
+        "header" => {
+            let header = setting("HEADER_NAME")?;
+            let value = setting("HEADER_VALUE")?;
+            Some(Arc::new(HeaderAuthenticator::new(header, value)))
+        }
+        "mtls" => {
+            // Without a proxy to vouch for it, the header proves nothing
+            let trusted_proxies = trusted_proxies_from_env();
+            if trusted_proxies.is_empty() {
+                return None;
+            }
+            let header =
+                setting("MTLS_HEADER").unwrap_or_else(|| "X-SSL-Client-Verify".to_string());
+            Some(Arc::new(MtlsAuthenticator::new(header, trusted_proxies)))
+        }
+        _ => None,
+    }
+}
+
+#[test]
+fn test_secrets_match() {
+    assert!(secrets_match("secret", "secret"));
+    assert!(!secrets_match("secre", "secret"));
+    assert!(!secrets_match("secret-and-more", "secret"));
+    assert!(!secrets_match("", "secret"));
+}
+
+#[test]
+fn test_api_key_authenticator() {
+    use actix_web::test::TestRequest;
+
+    let authenticator = ApiKeyAuthenticator::new(
+        "X-Api-Key".to_string(),
+        vec!["first-key".to_string(), "second-key".to_string()],
+    );
+    let with_key = |key: &str| {
+        TestRequest::default()
+            .insert_header(("X-Api-Key", key))
+            .to_http_request()
+    };
+    assert_eq!(authenticator.authenticate(&with_key("second-key")), Ok(()));
+    assert_eq!(
+        authenticator.authenticate(&with_key("second-ke")),
+        Err(AuthError::InvalidCredentials)
+    );
+    assert_eq!(
+        authenticator.authenticate(&with_key("")),
+        Err(AuthError::InvalidCredentials)
+    );
+    let bearer = TestRequest::default()
+        .insert_header(("Authorization", "Bearer first-key"))
+        .to_http_request();
+    assert_eq!(authenticator.authenticate(&bearer), Ok(()));
+    assert_eq!(
+        authenticator.authenticate(&TestRequest::default().to_http_request()),
+        Err(AuthError::MissingCredentials)
+    );
+    assert_eq!(
+        authenticator.principal(&with_key("first-key")),
+        Some(fingerprint("first-key"))
+    );
+    assert_ne!(
+        authenticator.principal(&with_key("first-key")),
+        authenticator.principal(&with_key("second-key"))
+    );
+}
+
+#[test]
+fn test_header_authenticator() {
+    use actix_web::test::TestRequest;
+
+    let authenticator =
+        HeaderAuthenticator::new("X-Gateway-Secret".to_string(), "s3cret".to_string());
+    let with_value = |value: &str| {
+        TestRequest::default()
+            .insert_header(("X-Gateway-Secret", value))
+            .to_http_request()
+    };
+    assert_eq!(authenticator.authenticate(&with_value("s3cret")), Ok(()));
+    assert_eq!(
+        authenticator.authenticate(&with_value("s3cret ")),
+        Err(AuthError::InvalidCredentials)
+    );
+    assert_eq!(
+        authenticator.authenticate(&with_value("S3CRET")),
+        Err(AuthError::InvalidCredentials)
+    );
+    assert_eq!(
+        authenticator.authenticate(&TestRequest::default().to_http_request()),
+        Err(AuthError::MissingCredentials)
+    );
+    assert_eq!(authenticator.principal(&with_value("s3cret")), None);
+}
+
+#[test]
+fn test_basic_authenticator() {
+    use actix_web::test::TestRequest;
+
+    let authenticator =
+        BasicAuthenticator::new([("alice".to_string(), "secret".to_string())].into());
+    let with_credentials = |credentials: &str| {
+        TestRequest::default()
+            .insert_header((
+                "Authorization",
+                format!("Basic {}", STANDARD.encode(credentials)),
+            ))
+            .to_http_request()
+    };
+    assert_eq!(
+        authenticator.authenticate(&with_credentials("alice:secret")),
+        Ok(())
+    );
+    assert_eq!(
+        authenticator.principal(&with_credentials("alice:secret")),
+        Some("alice".to_string())
+    );
+    for wrong in ["alice:secre", "alice:secret2", "bob:secret"] {
+        assert_eq!(
+            authenticator.authenticate(&with_credentials(wrong)),
+            Err(AuthError::InvalidCredentials)
+        );
+    }
+    assert_eq!(
+        authenticator.authenticate(&with_credentials("alice")),
+        Err(AuthError::MissingCredentials)
+    );
+}
+
+#[test]
+fn test_mtls_authenticator() {
+    use actix_web::test::TestRequest;
+
+    let authenticator = MtlsAuthenticator::new(
+        "X-SSL-Client-Verify".to_string(),
+        crate::auth::allowlist::parse_ranges("10.0.0.1").unwrap(),
+    );
+    let request = |peer: &str, verify: Option<&str>| {
+        let mut request = TestRequest::default().peer_addr(peer.parse().unwrap());
+        if let Some(verify) = verify {
+            request = request.insert_header(("X-SSL-Client-Verify", verify));
+        }
+        request.to_http_request()
+    };
+
+    assert_eq!(
+        authenticator.authenticate(&request("10.0.0.1:5000", Some("SUCCESS"))),
+        Ok(())
+    );
+    assert_eq!(
+        authenticator.authenticate(&request("10.0.0.1:5000", Some("FAILED:unknown CA"))),
+        Err(AuthError::InvalidCredentials)
+    );
+    assert_eq!(
+        authenticator.authenticate(&request("10.0.0.1:5000", None)),
+        Err(AuthError::MissingCredentials)
+    );
+    // Clients reaching the server directly can't vouch for themselves
+    assert_eq!(
+        authenticator.authenticate(&request("203.0.113.9:5000", Some("SUCCESS"))),
+        Err(AuthError::InvalidCredentials)
+    );
+}
@@ -0,0 +1,79 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::Utc;
+use ring::hmac;
+
+use crate::config::ProductConfig;
+
+fn key(secret: &str) -> hmac::Key {
+    hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes())
+}
+
+fn message(product: &str, asset_id: u64, expires_at: i64) -> String {
+    format!("{}:{}:{}", product, asset_id, expires_at)
+}
+
+/// A token for downloading `asset_id` until `expires_at` (Unix time).
+/// Download links carry one when a product requires it, so its download
+/// route can't be hotlinked from other sites.
+pub fn issue(secret: &str, product: &str, asset_id: u64, expires_at: i64) -> String {
+    let tag = hmac::sign(
+        &key(secret),
+        message(product, asset_id, expires_at).as_bytes(),
+    );
+    format!("{}.{}", expires_at, URL_SAFE_NO_PAD.encode(tag))
+}
+
+/// Whether `token` allows downloading `asset_id` at `now` (Unix time).
+pub fn verify(secret: &str, product: &str, asset_id: u64, token: &str, now: i64) -> bool {
+    let Some((expires_at, tag)) = token.split_once('.') else {
+        return false;
+    };
+    let (Ok(expires_at), Ok(tag)) = (expires_at.parse::<i64>(), URL_SAFE_NO_PAD.decode(tag)) else {
+        return false;
+    };
+    now <= expires_at
+        && hmac::verify(
+            &key(secret),
+            message(product, asset_id, expires_at).as_bytes(),
+            &tag,
+        )
+        .is_ok()
+}
+
+/// `url` with a fresh download token for `asset_id`, if the product
+/// requires one.
+pub fn with_download_token(
+    url: String,
+    product_config: &ProductConfig,
+    product: &str,
+    asset_id: u64,
+) -> String {
+    let Some(secret) = &product_config.download_token_secret else {
+        return url;
+    };
+    let expires_at = Utc::now().timestamp() + product_config.download_token_ttl_secs as i64;
+    let separator = if url.contains('?') { '&' } else { '?' };
+    format!(
+        "{}{}token={}",
+        url,
+        separator,
+        issue(secret, product, asset_id, expires_at)
+    )
+}
+
+#[test]
+fn test_download_token() {
+    let token = issue("secret", "myapp", 201, 1_700_000_600);
+    assert!(verify("secret", "myapp", 201, &token, 1_700_000_000));
+    assert!(verify("secret", "myapp", 201, &token, 1_700_000_600));
+    // Expired, or for another asset, product or secret
+    assert!(!verify("secret", "myapp", 201, &token, 1_700_000_601));
+    assert!(!verify("secret", "myapp", 202, &token, 1_700_000_000));
+    assert!(!verify("secret", "otherapp", 201, &token, 1_700_000_000));
+    assert!(!verify("other", "myapp", 201, &token, 1_700_000_000));
+    // Tampered expiry
+    let extended = token.replacen("1700000600", "1800000600", 1);
+    assert!(!verify("secret", "myapp", 201, &extended, 1_700_000_000));
+    assert!(!verify("secret", "myapp", 201, "garbage", 1_700_000_000));
+}
@@ -0,0 +1,108 @@
+use std::future::{ready, Ready};
+use std::rc::Rc;
+
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::{web, Error, HttpMessage, HttpRequest};
+use futures_util::future::LocalBoxFuture;
+use serde::Deserialize;
+
+use crate::auth::authenticator::{AuthError, RouteGroup};
+use crate::config::AppState;
+use crate::error::ServerError;
+
+#[derive(Deserialize)]
+struct DistributionQuery {
+    distribution_token: Option<String>,
+}
+
+/// The distribution token a client presents, in the `X-Distribution-Token`
+/// header or the `distribution_token` query parameter.
+fn distribution_token(req: &HttpRequest) -> Option<String> {
+    req.headers()
+        .get("X-Distribution-Token")
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+        .or_else(|| {
+            web::Query::<DistributionQuery>::from_query(req.query_string())
+                .ok()?
+                .into_inner()
+                .distribution_token
+        })
+}
+
+/// Rejects requests that don't pass the authenticators configured for `group`,
+/// and answers client requests for a private product without its
+/// distribution token as if the product didn't exist.
+pub struct RequireAuth {
+    group: RouteGroup,
+}
+
+impl RequireAuth {
+    pub fn new(group: RouteGroup) -> Self {
+        Self { group }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RequireAuth
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = RequireAuthMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequireAuthMiddleware {
+            service: Rc::new(service),
+            group: self.group,
+        }))
+    }
+}
+
+pub struct RequireAuthMiddleware<S> {
+    service: Rc<S>,
+    group: RouteGroup,
+}
+
+impl<S, B> Service<ServiceRequest> for RequireAuthMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        let group = self.group;
+
+        Box::pin(async move {
+            let state = req
+                .app_data::<web::Data<AppState>>()
+                .ok_or(AuthError::Misconfigured)?;
+            let actor = state.auth.authenticate(group, req.request())?;
+            req.extensions_mut().insert(actor);
+
+            if group != RouteGroup::Admin {
+                if let Some(product) = req.match_info().get("product_name") {
+                    let product = product.to_lowercase();
+                    let token = distribution_token(req.request());
+                    if state
+                        .product(&product)
+                        .is_some_and(|config| !config.is_visible_to(token.as_deref()))
+                    {
+                        return Err(ServerError::ProductNotFound(product).into());
+                    }
+                }
+            }
+
+            service.call(req).await
+        })
+    }
+}
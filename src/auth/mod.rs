@@ -0,0 +1,5 @@
+pub mod allowlist;
+pub mod authenticator;
+pub mod download_token;
+pub mod middleware;
+pub mod rate_limit;
@@ -0,0 +1,123 @@
+use std::env;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+use actix_web::HttpRequest;
+use dashmap::DashMap;
+use ipnet::IpNet;
+use log::debug;
+
+use crate::auth::allowlist::{client_ip, trusted_proxies_from_env};
+use crate::error::ServerError;
+
+/// Addresses tracked at most; while that many have requests in the current
+/// window, requests from further addresses are rejected.
+const MAX_ADDRESSES: usize = 100_000;
+
+/// Requests allowed per client address and minute on a route. Requests past
+/// the limit are answered with `429 Too Many Requests` until the minute is
+/// over. Behind a reverse proxy, the client's address is taken from
+/// `X-Forwarded-For` as for IP allowlists.
+#[derive(Debug)]
+pub struct RateLimiter {
+    limit: Option<u32>,
+    window: Duration,
+    trusted_proxies: Vec<IpNet>,
+    /// Start of the current window and requests in it, by address. Requests
+    /// without a peer address (e.g. over a Unix socket) share one entry.
+    windows: DashMap<Option<IpAddr>, (Instant, u32)>,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        RateLimiter::new(None, Vec::new())
+    }
+}
+
+impl RateLimiter {
+    /// Allows `limit` requests per address and minute; `None` is unlimited.
+    pub fn new(limit: Option<u32>, trusted_proxies: Vec<IpNet>) -> Self {
+        RateLimiter {
+            limit,
+            window: Duration::from_secs(60),
+            trusted_proxies,
+            windows: DashMap::new(),
+        }
+    }
+
+    /// Reads the limit from `key`, falling back to `default`; `0` disables
+    /// it. Also reads `TRUSTED_PROXIES`.
+    pub fn from_env(key: &str, default: u32) -> Self {
+        let limit = env::var(key)
+            .ok()
+            .and_then(|v| v.trim().parse().ok())
+            .unwrap_or(default);
+        RateLimiter::new((limit > 0).then_some(limit), trusted_proxies_from_env())
+    }
+
+    pub fn limit(&self) -> Option<u32> {
+        self.limit
+    }
+
+    /// Counts `req` against its address's limit.
+    pub fn check(&self, req: &HttpRequest) -> Result<(), ServerError> {
+        let Some(limit) = self.limit else {
+            return Ok(());
+        };
+        let address = client_ip(req, &self.trusted_proxies);
+        let now = Instant::now();
+
+        if self.windows.len() >= MAX_ADDRESSES && !self.windows.contains_key(&address) {
+            self.windows
+                .retain(|_, (start, _)| now.duration_since(*start) < self.window);
+            if self.windows.len() >= MAX_ADDRESSES {
+                return Err(self.rejected(now, now));
+            }
+        }
+
+        let mut entry = self.windows.entry(address).or_insert((now, 0));
+        let (start, count) = &mut *entry;
+        if now.duration_since(*start) >= self.window {
+            *start = now;
+            *count = 0;
+        }
+        if *count >= limit {
+            debug!("Rate limited requests from {:?}", address);
+            return Err(self.rejected(*start, now));
+        }
+        *count += 1;
+        Ok(())
+    }
+
+    fn rejected(&self, start: Instant, now: Instant) -> ServerError {
+        let left = self.window.saturating_sub(now.duration_since(start));
+        ServerError::TooManyRequests {
+            retry_after_secs: left.as_secs().max(1),
+        }
+    }
+}
+
+#[test]
+fn test_rate_limiter() {
+    use actix_web::test::TestRequest;
+
+    let from = |address: &str| {
+        TestRequest::default()
+            .peer_addr(format!("{}:443", address).parse().unwrap())
+            .to_http_request()
+    };
+    let limiter = RateLimiter::new(Some(2), Vec::new());
+    assert!(limiter.check(&from("10.0.0.1")).is_ok());
+    assert!(limiter.check(&from("10.0.0.1")).is_ok());
+    let rejected = limiter.check(&from("10.0.0.1")).unwrap_err();
+    assert!(matches!(
+        rejected,
+        ServerError::TooManyRequests { retry_after_secs } if retry_after_secs <= 60
+    ));
+    assert!(limiter.check(&from("10.0.0.2")).is_ok());
+
+    let unlimited = RateLimiter::default();
+    for _ in 0..100 {
+        assert!(unlimited.check(&from("10.0.0.1")).is_ok());
+    }
+}
@@ -0,0 +1,35 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// Which build is running, as recorded at compile time.
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildInfo {
+    pub version: &'static str,
+    /// The git commit built, if it was known.
+    pub commit: Option<&'static str>,
+    pub built_at: Option<DateTime<Utc>>,
+    /// Cargo features enabled in the build.
+    pub features: Vec<&'static str>,
+}
+
+impl BuildInfo {
+    pub fn current() -> Self {
+        let commit = env!("BUILD_GIT_COMMIT");
+        let features = env!("BUILD_FEATURES");
+        BuildInfo {
+            version: env!("CARGO_PKG_VERSION"),
+            commit: (!commit.is_empty()).then_some(commit),
+            built_at: env!("BUILD_TIMESTAMP")
+                .parse()
+                .ok()
+                .and_then(|secs| DateTime::from_timestamp(secs, 0)),
+            features: features.split(',').filter(|f| !f.is_empty()).collect(),
+        }
+    }
+
+    /// The first characters of the commit, as commonly shown.
+    pub fn short_commit(&self) -> &'static str {
+        self.commit
+            .map_or("unknown", |commit| &commit[..commit.len().min(12)])
+    }
+}
@@ -0,0 +1,74 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::{debug, warn};
+
+use crate::cache::limits::MemoryBudget;
+use crate::cache::lru::LruCache;
+use crate::config::ProductConfig;
+use crate::error::ServerError;
+use crate::github::client::GitHubClient;
+
+/// Caches a product's changelog by repository, path and tag. A tag's
+/// changelog doesn't change, so entries never go stale; tags without a
+/// changelog are remembered as `None`.
+#[derive(Default)]
+pub struct ChangelogCache {
+    entries: LruCache<String, Option<Arc<str>>>,
+}
+
+impl ChangelogCache {
+    pub fn new(budget: Arc<MemoryBudget>, max_idle: Duration) -> Self {
+        ChangelogCache {
+            entries: LruCache::new(budget, max_idle),
+        }
+    }
+
+    /// Drops entries unused for the idle limit. Returns how many were
+    /// dropped.
+    pub fn expire(&self) -> usize {
+        self.entries.expire()
+    }
+
+    /// The product's changelog as of `tag`, fetching it the first time.
+    /// Failed fetches are logged and retried on the next call.
+    pub async fn changelog(
+        &self,
+        github: &GitHubClient,
+        product_config: &ProductConfig,
+        tag: &str,
+    ) -> Option<Arc<str>> {
+        let path = product_config.changelog.as_deref()?;
+        let key = format!(
+            "{}/{}/{}@{}",
+            product_config.repo_owner, product_config.repo_name, path, tag
+        );
+        if let Some(changelog) = self.entries.get(&key) {
+            return changelog;
+        }
+
+        debug!("Fetching {} as of {}", path, tag);
+        let changelog = match github
+            .get_file(
+                &product_config.repo_owner,
+                &product_config.repo_name,
+                path,
+                tag,
+            )
+            .await
+        {
+            Ok(changelog) => Some(Arc::from(changelog)),
+            Err(ServerError::UpstreamNotFound(_)) => {
+                warn!("No {} in {} as of {}", path, product_config.repo_name, tag);
+                None
+            }
+            Err(e) => {
+                warn!("Failed to fetch {} as of {}: {}", path, tag, e);
+                return None;
+            }
+        };
+        let size = key.len() + changelog.as_deref().map_or(0, str::len);
+        self.entries.insert(key, changelog.clone(), size as u64);
+        changelog
+    }
+}
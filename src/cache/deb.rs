@@ -0,0 +1,73 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::{debug, warn};
+
+use crate::cache::digest::sha256_hex;
+use crate::cache::limits::MemoryBudget;
+use crate::cache::lru::LruCache;
+use crate::config::ProductConfig;
+use crate::error::ServerError;
+use crate::formats::apt::DebPackage;
+use crate::formats::deb::read_control;
+use crate::github::client::GitHubClient;
+
+/// Caches the `control` data of `.deb` assets by asset id, so APT indexes
+/// download each package only once. Assets that aren't valid packages are
+/// remembered as `None`.
+#[derive(Default)]
+pub struct DebCache {
+    entries: LruCache<u64, Option<DebPackage>>,
+}
+
+impl DebCache {
+    pub fn new(budget: Arc<MemoryBudget>, max_idle: Duration) -> Self {
+        DebCache {
+            entries: LruCache::new(budget, max_idle),
+        }
+    }
+
+    /// Drops entries unused for the idle limit. Returns how many were
+    /// dropped.
+    pub fn expire(&self) -> usize {
+        self.entries.expire()
+    }
+
+    /// Returns the package's index data, downloading it the first time.
+    pub async fn package(
+        &self,
+        github: &GitHubClient,
+        product_config: &ProductConfig,
+        asset_id: u64,
+    ) -> Result<Option<DebPackage>, ServerError> {
+        if let Some(package) = self.entries.get(&asset_id) {
+            return Ok(package);
+        }
+
+        debug!("Reading control data of asset {}", asset_id);
+        let deb = github
+            .download_asset(
+                asset_id,
+                &product_config.repo_owner,
+                &product_config.repo_name,
+            )
+            .await?;
+
+        let package = match read_control(&deb) {
+            Ok(control) => Some(DebPackage {
+                control,
+                sha256: sha256_hex(&deb),
+                size: deb.len() as u64,
+            }),
+            Err(e) => {
+                warn!("Asset {} is not a valid .deb: {}", asset_id, e);
+                None
+            }
+        };
+        let size = package
+            .as_ref()
+            .map_or(0, |p| p.control.len() + p.sha256.len());
+        self.entries.insert(asset_id, package.clone(), size as u64);
+        Ok(package)
+    }
+}
@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::sync::RwLock;
+
+use crate::cache::limits::MemoryBudget;
+use crate::response::builder::PlatformUpdate;
+
+/// Identifies a resolved update: the same release, platform, channel and
+/// edition always resolve to the same installer and signature.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DecisionKey {
+    pub product: String,
+    pub release_id: u64,
+    pub platform: String,
+    pub channel: String,
+    pub edition: Option<String>,
+}
+
+/// How long resolved updates are cached by default.
+pub const DEFAULT_TTL: Duration = Duration::from_secs(3600);
+
+/// How many resolved updates are cached by default.
+pub const DEFAULT_MAX_ENTRIES: usize = 10_000;
+
+/// Caches resolved updates so repeated checks skip asset matching and the
+/// signature download. Entries expire after `ttl`, so updates for releases
+/// nobody asks for anymore don't pile up; beyond `max_entries` or the memory
+/// limit, the oldest ones make room for new ones.
+pub struct DecisionCache {
+    ttl: Duration,
+    max_entries: usize,
+    entries: RwLock<HashMap<DecisionKey, (Instant, PlatformUpdate)>>,
+    budget: Arc<MemoryBudget>,
+}
+
+impl Default for DecisionCache {
+    fn default() -> Self {
+        DecisionCache::new(DEFAULT_TTL, DEFAULT_MAX_ENTRIES, Arc::default())
+    }
+}
+
+impl DecisionCache {
+    pub fn new(ttl: Duration, max_entries: usize, budget: Arc<MemoryBudget>) -> Self {
+        DecisionCache {
+            ttl,
+            max_entries,
+            entries: RwLock::new(HashMap::new()),
+            budget,
+        }
+    }
+
+    pub async fn get(&self, key: &DecisionKey) -> Option<PlatformUpdate> {
+        let entries = self.entries.read().await;
+        entries
+            .get(key)
+            .filter(|(cached_at, _)| cached_at.elapsed() < self.ttl)
+            .map(|(_, update)| update.clone())
+    }
+
+    pub async fn insert(&self, key: DecisionKey, update: PlatformUpdate) {
+        let mut entries = self.entries.write().await;
+        if let Some((_, previous)) = entries.remove(&key) {
+            self.budget.release(size(&previous));
+        }
+        loop {
+            let full = entries.len() >= self.max_entries;
+            if !full && self.budget.reserve(size(&update)) {
+                entries.insert(key, (Instant::now(), update));
+                return;
+            }
+            let Some(oldest) = entries
+                .iter()
+                .min_by_key(|(_, (cached_at, _))| *cached_at)
+                .map(|(key, _)| key.clone())
+            else {
+                return;
+            };
+            if let Some((_, evicted)) = entries.remove(&oldest) {
+                self.budget.release(size(&evicted));
+            }
+        }
+    }
+
+    /// Drops expired decisions. Returns how many were dropped.
+    pub async fn expire(&self) -> usize {
+        let mut entries = self.entries.write().await;
+        let before = entries.len();
+        entries.retain(|_, (cached_at, update)| {
+            let fresh = cached_at.elapsed() < self.ttl;
+            if !fresh {
+                self.budget.release(size(update));
+            }
+            fresh
+        });
+        before - entries.len()
+    }
+
+    /// Drops every decision for `product`, e.g. after its releases changed.
+    pub async fn invalidate_product(&self, product: &str) {
+        self.entries.write().await.retain(|key, (_, update)| {
+            let other = key.product != product;
+            if !other {
+                self.budget.release(size(update));
+            }
+            other
+        });
+    }
+}
+
+/// Bytes a cached decision takes up, roughly.
+fn size(update: &PlatformUpdate) -> u64 {
+    (update.signature.len() + update.url.len() + update.filename.len() + update.key_id.len()) as u64
+}
+
+#[actix_web::test]
+async fn test_decision_cache_limits() {
+    let key = |release_id| DecisionKey {
+        product: "myapp".to_string(),
+        release_id,
+        platform: "windows-x86_64".to_string(),
+        channel: "stable".to_string(),
+        edition: None,
+    };
+    let update = PlatformUpdate {
+        signature: "signature".to_string(),
+        url: "https://updates.example.com/myapp/download/201/MyApp.msi".to_string(),
+        asset_id: 201,
+        filename: "MyApp.msi".to_string(),
+        key_id: "0807060504030201".to_string(),
+    };
+
+    let budget = Arc::new(MemoryBudget::default());
+    let cache = DecisionCache::new(DEFAULT_TTL, 2, Arc::clone(&budget));
+    for release_id in 1..=3 {
+        cache.insert(key(release_id), update.clone()).await;
+    }
+    // The oldest decision made room for the newest
+    assert!(cache.get(&key(1)).await.is_none());
+    assert!(cache.get(&key(3)).await.is_some());
+    assert_eq!(budget.used(), 2 * size(&update));
+    cache.invalidate_product("myapp").await;
+    assert_eq!(budget.used(), 0);
+
+    let budget = Arc::new(MemoryBudget::new(Some(size(&update))));
+    let cache = DecisionCache::new(DEFAULT_TTL, DEFAULT_MAX_ENTRIES, Arc::clone(&budget));
+    cache.insert(key(1), update.clone()).await;
+    cache.insert(key(2), update.clone()).await;
+    assert!(cache.get(&key(1)).await.is_none());
+    assert!(cache.get(&key(2)).await.is_some());
+}
@@ -0,0 +1,78 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::Bytes;
+use log::warn;
+use semver::Version;
+
+use crate::cache::limits::MemoryBudget;
+use crate::cache::lru::LruCache;
+
+/// A patch from an artifact of the previous release to the matching
+/// artifact of the newest one.
+#[derive(Debug)]
+pub struct DeltaPatch {
+    pub from_version: Version,
+    pub from_asset_id: u64,
+    pub to_asset_id: u64,
+    pub data: Bytes,
+    /// [`sha256_hex`](crate::cache::digest::sha256_hex) of the patch.
+    pub sha256: String,
+}
+
+/// Patches built by the delta worker, by the id of the artifact they
+/// produce. `None` records an artifact whose patch couldn't be built, so
+/// it isn't attempted again, as is one that doesn't fit the memory limit.
+#[derive(Default)]
+pub struct DeltaCache {
+    entries: LruCache<u64, Option<Arc<DeltaPatch>>>,
+}
+
+impl DeltaCache {
+    pub fn new(budget: Arc<MemoryBudget>, max_idle: Duration) -> Self {
+        DeltaCache {
+            entries: LruCache::new(budget, max_idle),
+        }
+    }
+
+    /// The patch producing `to_asset_id`, if one was built.
+    pub fn get(&self, to_asset_id: u64) -> Option<Arc<DeltaPatch>> {
+        self.entries.get(&to_asset_id).flatten()
+    }
+
+    /// Whether a patch to `to_asset_id` was already attempted.
+    pub fn contains(&self, to_asset_id: u64) -> bool {
+        self.entries.contains(&to_asset_id)
+    }
+
+    pub fn insert(&self, to_asset_id: u64, patch: Option<DeltaPatch>) {
+        let Some(patch) = patch else {
+            self.entries.insert(to_asset_id, None, 0);
+            return;
+        };
+        let size = patch.data.len() as u64;
+        if !self
+            .entries
+            .insert(to_asset_id, Some(Arc::new(patch)), size)
+        {
+            warn!(
+                "Patch to asset {} ({} bytes) exceeds the cache memory limit",
+                to_asset_id, size
+            );
+            self.entries.insert(to_asset_id, None, 0);
+        }
+    }
+
+    /// Drops patches to artifacts that are no longer the newest.
+    pub fn retain(&self, current: &HashSet<u64>) {
+        self.entries
+            .retain(|to_asset_id| current.contains(to_asset_id));
+    }
+
+    /// Drops patches unused for the idle limit. Returns how many were
+    /// dropped.
+    pub fn expire(&self) -> usize {
+        self.entries.expire()
+    }
+}
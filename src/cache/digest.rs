@@ -0,0 +1,108 @@
+use std::fmt::Write as _;
+use std::sync::Arc;
+use std::time::Duration;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use futures_util::StreamExt;
+use log::debug;
+use ring::digest::{digest, Context, SHA256, SHA512};
+
+use crate::cache::limits::MemoryBudget;
+use crate::cache::lru::LruCache;
+use crate::config::ProductConfig;
+use crate::error::ServerError;
+use crate::github::client::GitHubClient;
+
+/// Checksums of a release asset, in the encodings package formats expect.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssetDigests {
+    /// Lowercase hex SHA-256.
+    pub sha256: String,
+    /// Base64 SHA-512, as used by electron-updater.
+    pub sha512: String,
+}
+
+/// Caches asset checksums by asset id. GitHub gives a re-uploaded asset a
+/// new id, so entries never go stale.
+#[derive(Default)]
+pub struct DigestCache {
+    entries: LruCache<u64, AssetDigests>,
+}
+
+impl DigestCache {
+    pub fn new(budget: Arc<MemoryBudget>, max_idle: Duration) -> Self {
+        DigestCache {
+            entries: LruCache::new(budget, max_idle),
+        }
+    }
+
+    /// Drops entries unused for the idle limit. Returns how many were
+    /// dropped.
+    pub fn expire(&self) -> usize {
+        self.entries.expire()
+    }
+
+    /// Returns the asset's checksums, downloading it the first time.
+    pub async fn digests(
+        &self,
+        github: &GitHubClient,
+        product_config: &ProductConfig,
+        asset_id: u64,
+    ) -> Result<AssetDigests, ServerError> {
+        if let Some(digests) = self.entries.get(&asset_id) {
+            return Ok(digests);
+        }
+
+        debug!("Computing checksums of asset {}", asset_id);
+        let response = github
+            .open_asset(
+                asset_id,
+                &product_config.repo_owner,
+                &product_config.repo_name,
+                None,
+            )
+            .await?;
+
+        let mut sha256 = Context::new(&SHA256);
+        let mut sha512 = Context::new(&SHA512);
+        let mut stream = response.bytes_stream();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk?;
+            sha256.update(&chunk);
+            sha512.update(&chunk);
+        }
+
+        let digests = AssetDigests {
+            sha256: hex(sha256.finish().as_ref()),
+            sha512: STANDARD.encode(sha512.finish()),
+        };
+        let size = digests.sha256.len() + digests.sha512.len();
+        self.entries.insert(asset_id, digests.clone(), size as u64);
+        Ok(digests)
+    }
+}
+
+/// Lowercase hex encoding of `bytes`.
+pub fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .fold(String::with_capacity(bytes.len() * 2), |mut hex, b| {
+            let _ = write!(hex, "{:02x}", b);
+            hex
+        })
+}
+
+/// Lowercase hex SHA-256 of `data`, the checksum package formats list.
+pub fn sha256_hex(data: &[u8]) -> String {
+    hex(digest(&SHA256, data).as_ref())
+}
+
+#[test]
+fn test_sha256_hex() {
+    assert_eq!(hex(&[0x00, 0x0f, 0xa5, 0xff]), "000fa5ff");
+    assert_eq!(
+        sha256_hex(b""),
+        "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+    );
+}
@@ -0,0 +1,142 @@
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use actix_web::rt::time;
+use chrono::{DateTime, Utc};
+use log::{debug, info, warn};
+use serde::Serialize;
+
+use crate::config::{AppState, ProductConfig};
+use crate::error::ServerError;
+use crate::local::store::LocalStore;
+use crate::local::sync::mirrored_assets;
+use crate::logging;
+
+/// How often caches are collected by default.
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// What a garbage collection run freed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct GcReport {
+    pub at: DateTime<Utc>,
+    /// Resolved updates dropped from memory.
+    pub expired_manifests: usize,
+    /// Release notes, changelogs, package metadata, checksums and delta
+    /// patches dropped from memory.
+    pub expired_metadata: usize,
+    /// Files deleted from the local asset store.
+    pub removed_assets: usize,
+    /// Their total size, in bytes.
+    pub reclaimed_bytes: u64,
+}
+
+/// The outcome of the last garbage collection run, for `/status`.
+#[derive(Debug, Default)]
+pub struct GcLog {
+    last: Mutex<Option<GcReport>>,
+}
+
+impl GcLog {
+    pub fn record(&self, report: GcReport) {
+        *self.last.lock().unwrap_or_else(|e| e.into_inner()) = Some(report);
+    }
+
+    pub fn last(&self) -> Option<GcReport> {
+        *self.last.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Collects the caches every `interval`, until the server shuts down. With
+/// several instances, each drops what it holds in memory, but only the one
+/// holding the lock deletes stored assets.
+pub async fn run(state: AppState, interval: Duration) {
+    let mut ticker = time::interval(interval);
+    loop {
+        ticker.tick().await;
+        let prune_store = state.leadership.acquire("cache-gc", interval * 2).await;
+        sweep(&state, prune_store).await;
+    }
+}
+
+/// Drops expired resolved updates and metadata unused for
+/// `CACHE_MAX_IDLE_SECS`, and deletes stored assets of releases superseded
+/// by the newest `ASSET_CACHE_RELEASES`, or that no longer fit
+/// `CACHE_MAX_DISK_BYTES`. Products whose releases can't be listed keep
+/// their assets.
+pub async fn collect(state: &AppState) -> GcReport {
+    sweep(state, true).await
+}
+
+async fn sweep(state: &AppState, prune_store: bool) -> GcReport {
+    let mut report = GcReport {
+        at: Utc::now(),
+        expired_manifests: state.decision_cache.expire().await,
+        expired_metadata: state.deb_cache.expire()
+            + state.rpm_cache.expire()
+            + state.notes_cache.expire()
+            + state.digest_cache.expire()
+            + state.changelog_cache.expire()
+            + state.delta_cache.expire(),
+        ..GcReport::default()
+    };
+
+    if let Some(store) = state.local_store.as_ref().filter(|_| prune_store) {
+        let mut budget = store.max_bytes().unwrap_or(u64::MAX);
+        for (product_name, product_config) in state.products.load().iter() {
+            let retained = logging::scope(
+                product_name,
+                retained_assets(state, store, product_name, product_config, &mut budget),
+            );
+            match retained.await {
+                Ok(keep) => {
+                    let (removed, reclaimed) = store.retain(product_name, &keep);
+                    report.removed_assets += removed;
+                    report.reclaimed_bytes += reclaimed;
+                }
+                Err(e) => {
+                    warn!("Skipping stored assets of {}: {}", product_name, e);
+                    budget = budget.saturating_sub(store.usage(product_name));
+                    state
+                        .recent_errors
+                        .record(Some(product_name), "cache_gc", &e);
+                }
+            }
+        }
+    }
+
+    if report.expired_manifests > 0 || report.expired_metadata > 0 || report.removed_assets > 0 {
+        info!(
+            "Expired {} cached updates and {} metadata entries, and removed {} stored assets, reclaiming {} bytes",
+            report.expired_manifests,
+            report.expired_metadata,
+            report.removed_assets,
+            report.reclaimed_bytes
+        );
+    } else {
+        debug!("Nothing to collect in the caches");
+    }
+    state.gc_log.record(report);
+    report
+}
+
+/// Ids of the product's assets the store keeps.
+async fn retained_assets(
+    state: &AppState,
+    store: &LocalStore,
+    product_name: &str,
+    product_config: &ProductConfig,
+    budget: &mut u64,
+) -> Result<HashSet<u64>, ServerError> {
+    let github = state.github_client(product_name, product_config)?;
+    let releases = state
+        .release_cache
+        .releases(product_name, &github, product_config)
+        .await?;
+    Ok(
+        mirrored_assets(&releases, product_config, store.releases(), budget)
+            .into_iter()
+            .map(|asset| asset.id.0)
+            .collect(),
+    )
+}
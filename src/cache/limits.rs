@@ -0,0 +1,132 @@
+use std::env;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use crate::config::parse_size;
+
+/// Limits on what the caches hold, so the server's footprint stays
+/// predictable on small hosts. Unset limits are unlimited, but for the
+/// number of resolved updates.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheLimits {
+    /// Size of the local asset store.
+    pub max_disk_bytes: Option<u64>,
+    /// Resolved updates held in memory; 10,000 if unset.
+    pub max_manifest_entries: Option<usize>,
+    /// Size of the release lists, release notes, changelogs, package
+    /// metadata, resolved updates and delta patches held in memory,
+    /// together.
+    pub max_memory_bytes: Option<u64>,
+    /// How long release notes, changelogs, package metadata and delta
+    /// patches are kept without being used; a day if unset.
+    pub max_idle: Option<Duration>,
+}
+
+impl CacheLimits {
+    /// Reads `CACHE_MAX_DISK_BYTES` and `CACHE_MAX_MEMORY_BYTES`, with an
+    /// optional `K`, `M` or `G` suffix, `CACHE_MAX_MANIFEST_ENTRIES` and
+    /// `CACHE_MAX_IDLE_SECS`.
+    pub fn from_env() -> Result<Self, String> {
+        let bytes = |key: &str| match env::var(key) {
+            Ok(value) => parse_size(&value)
+                .map(Some)
+                .ok_or_else(|| format!("invalid {} {:?}", key, value)),
+            Err(_) => Ok(None),
+        };
+        let number = |key: &str| match env::var(key) {
+            Ok(value) => value
+                .trim()
+                .parse()
+                .map(Some)
+                .map_err(|_| format!("invalid {} {:?}", key, value)),
+            Err(_) => Ok(None),
+        };
+        Ok(CacheLimits {
+            max_disk_bytes: bytes("CACHE_MAX_DISK_BYTES")?,
+            max_manifest_entries: number("CACHE_MAX_MANIFEST_ENTRIES")?
+                .map(|entries| entries as usize),
+            max_memory_bytes: bytes("CACHE_MAX_MEMORY_BYTES")?,
+            max_idle: number("CACHE_MAX_IDLE_SECS")?.map(Duration::from_secs),
+        })
+    }
+}
+
+/// Bytes held by the in-memory caches. It's shared by all of them, so
+/// together they stay under the limit: entries that don't fit evict the
+/// least recently used ones of their cache. Release lists are always kept
+/// and only counted.
+#[derive(Debug, Default)]
+pub struct MemoryBudget {
+    limit: Option<u64>,
+    used: AtomicU64,
+}
+
+impl MemoryBudget {
+    pub fn new(limit: Option<u64>) -> Self {
+        MemoryBudget {
+            limit,
+            used: AtomicU64::new(0),
+        }
+    }
+
+    /// Reserves `bytes` for a new cache entry, unless that would exceed the
+    /// limit.
+    pub fn reserve(&self, bytes: u64) -> bool {
+        self.used
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+                used.checked_add(bytes)
+                    .filter(|total| self.limit.is_none_or(|limit| *total <= limit))
+            })
+            .is_ok()
+    }
+
+    /// Counts `bytes` that are held whatever the limit, such as release
+    /// lists, so the other caches make room for them.
+    pub fn charge(&self, bytes: u64) {
+        self.used.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Whether an entry of `bytes` could ever fit.
+    pub fn fits(&self, bytes: u64) -> bool {
+        self.limit.is_none_or(|limit| bytes <= limit)
+    }
+
+    /// Gives back the bytes of a dropped cache entry.
+    pub fn release(&self, bytes: u64) {
+        let _ = self
+            .used
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+                Some(used.saturating_sub(bytes))
+            });
+    }
+
+    /// Bytes currently reserved.
+    pub fn used(&self) -> u64 {
+        self.used.load(Ordering::Relaxed)
+    }
+
+    pub fn limit(&self) -> Option<u64> {
+        self.limit
+    }
+}
+
+#[test]
+fn test_memory_budget() {
+    let budget = MemoryBudget::new(Some(100));
+    assert!(budget.reserve(60));
+    assert!(!budget.reserve(50));
+    assert!(budget.reserve(40));
+    assert_eq!(budget.used(), 100);
+    budget.release(60);
+    assert!(budget.reserve(50));
+    assert_eq!(budget.used(), 90);
+    budget.charge(20);
+    assert_eq!(budget.used(), 110);
+    assert!(!budget.reserve(1));
+    assert!(budget.fits(100));
+    assert!(!budget.fits(101));
+
+    let unlimited = MemoryBudget::default();
+    assert!(unlimited.reserve(u64::MAX));
+    assert!(!unlimited.reserve(1));
+}
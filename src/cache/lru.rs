@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::cache::limits::MemoryBudget;
+
+/// How long metadata cache entries are kept without being used by default.
+pub const DEFAULT_MAX_IDLE: Duration = Duration::from_secs(24 * 3600);
+
+struct Entry<V> {
+    value: V,
+    size: u64,
+    last_used: Instant,
+}
+
+/// A cache whose entries count against the shared [`MemoryBudget`]. When a
+/// new entry doesn't fit, the least recently used ones make room for it;
+/// entries unused for `max_idle` are dropped by [`LruCache::expire`]. Empty
+/// entries, such as remembered failures, take no room and are only dropped
+/// when they expire.
+pub struct LruCache<K, V> {
+    entries: Mutex<HashMap<K, Entry<V>>>,
+    budget: Arc<MemoryBudget>,
+    max_idle: Duration,
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> Default for LruCache<K, V> {
+    fn default() -> Self {
+        LruCache::new(Arc::default(), DEFAULT_MAX_IDLE)
+    }
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> LruCache<K, V> {
+    pub fn new(budget: Arc<MemoryBudget>, max_idle: Duration) -> Self {
+        LruCache {
+            entries: Mutex::new(HashMap::new()),
+            budget,
+            max_idle,
+        }
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let entry = entries.get_mut(key)?;
+        entry.last_used = Instant::now();
+        Some(entry.value.clone())
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .contains_key(key)
+    }
+
+    /// Caches `value`, which takes up `size` bytes, evicting the least
+    /// recently used entries if it doesn't fit. Returns `false` if it
+    /// doesn't fit even then, in which case it isn't cached.
+    pub fn insert(&self, key: K, value: V, size: u64) -> bool {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(previous) = entries.remove(&key) {
+            self.budget.release(previous.size);
+        }
+        if !self.budget.fits(size) {
+            return false;
+        }
+        while !self.budget.reserve(size) {
+            let Some(oldest) = entries
+                .iter()
+                .filter(|(_, entry)| entry.size > 0)
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone())
+            else {
+                return false;
+            };
+            if let Some(evicted) = entries.remove(&oldest) {
+                self.budget.release(evicted.size);
+            }
+        }
+        entries.insert(
+            key,
+            Entry {
+                value,
+                size,
+                last_used: Instant::now(),
+            },
+        );
+        true
+    }
+
+    /// Drops the entries `keep` returns `false` for.
+    pub fn retain(&self, mut keep: impl FnMut(&K) -> bool) {
+        self.entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|key, entry| {
+                let kept = keep(key);
+                if !kept {
+                    self.budget.release(entry.size);
+                }
+                kept
+            });
+    }
+
+    /// Drops entries unused for `max_idle`. Returns how many were dropped.
+    pub fn expire(&self) -> usize {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let before = entries.len();
+        entries.retain(|_, entry| {
+            let fresh = entry.last_used.elapsed() < self.max_idle;
+            if !fresh {
+                self.budget.release(entry.size);
+            }
+            fresh
+        });
+        before - entries.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[test]
+fn test_lru_cache() {
+    let budget = Arc::new(MemoryBudget::new(Some(100)));
+    let cache = LruCache::new(Arc::clone(&budget), DEFAULT_MAX_IDLE);
+    assert!(cache.insert(1, "one", 40));
+    assert!(cache.insert(2, "two", 40));
+    assert_eq!(cache.get(&1), Some("one"));
+    assert!(cache.insert(0, "failed", 0));
+
+    // The budget is full, so the least recently used entry makes room
+    assert!(cache.insert(3, "three", 40));
+    assert_eq!(cache.get(&2), None);
+    assert_eq!(cache.get(&1), Some("one"));
+    assert_eq!(cache.get(&3), Some("three"));
+    assert_eq!(cache.get(&0), Some("failed"));
+    assert_eq!(budget.used(), 80);
+
+    // Caching keeps working once other caches fill the budget
+    assert!(budget.reserve(20));
+    assert!(cache.insert(4, "four", 40));
+    assert_eq!(cache.len(), 3);
+    assert_eq!(budget.used(), 100);
+    assert!(!cache.insert(5, "five", 200));
+    assert_eq!(cache.get(&5), None);
+    assert_eq!(cache.len(), 3);
+
+    cache.retain(|key| *key != 4);
+    assert_eq!(cache.get(&4), None);
+    assert_eq!(cache.expire(), 0);
+
+    let idle = LruCache::new(Arc::clone(&budget), Duration::ZERO);
+    idle.insert("notes", "notes", 10);
+    assert_eq!(idle.expire(), 1);
+    assert!(idle.is_empty());
+}
@@ -0,0 +1,11 @@
+pub mod changelog;
+pub mod deb;
+pub mod decision;
+pub mod delta;
+pub mod digest;
+pub mod gc;
+pub mod limits;
+pub mod lru;
+pub mod notes;
+pub mod release;
+pub mod rpm;
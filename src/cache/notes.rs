@@ -0,0 +1,96 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::{debug, warn};
+use octocrab::models::repos::Release;
+
+use crate::cache::limits::MemoryBudget;
+use crate::cache::lru::LruCache;
+use crate::config::ProductConfig;
+use crate::github::client::GitHubClient;
+use crate::notes::locale::{best_match, localized_notes, normalize_tag};
+
+/// The language of a release notes asset such as `notes.de.md`.
+fn notes_language(name: &str) -> Option<String> {
+    let name = name.to_lowercase();
+    let language = name.strip_prefix("notes.")?.strip_suffix(".md")?;
+    (!language.is_empty() && !language.contains('.')).then(|| normalize_tag(language))
+}
+
+/// Caches the contents of per-language release notes assets by asset id.
+/// GitHub gives a re-uploaded asset a new id, so entries never go stale.
+#[derive(Default)]
+pub struct NotesCache {
+    entries: LruCache<u64, String>,
+}
+
+impl NotesCache {
+    pub fn new(budget: Arc<MemoryBudget>, max_idle: Duration) -> Self {
+        NotesCache {
+            entries: LruCache::new(budget, max_idle),
+        }
+    }
+
+    /// Drops entries unused for the idle limit. Returns how many were
+    /// dropped.
+    pub fn expire(&self) -> usize {
+        self.entries.expire()
+    }
+
+    /// The release's notes in the client's preferred language: a
+    /// `notes.<language>.md` asset if one matches, otherwise the matching
+    /// section of `body`, the release's notes. Assets that can't be
+    /// downloaded are skipped in favor of the body.
+    pub async fn localized(
+        &self,
+        github: &GitHubClient,
+        product_config: &ProductConfig,
+        release: &Release,
+        body: &str,
+        languages: &[String],
+    ) -> String {
+        let (tags, assets): (Vec<String>, Vec<u64>) = release
+            .assets
+            .iter()
+            .filter_map(|asset| Some((notes_language(&asset.name)?, *asset.id)))
+            .unzip();
+        let Some(asset_id) = best_match(&tags, languages).map(|index| assets[index]) else {
+            return localized_notes(body, languages).to_string();
+        };
+
+        if let Some(notes) = self.entries.get(&asset_id) {
+            return notes;
+        }
+        debug!("Downloading release notes asset {}", asset_id);
+        let downloaded = github
+            .download_asset(
+                asset_id,
+                &product_config.repo_owner,
+                &product_config.repo_name,
+            )
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|bytes| String::from_utf8(bytes.to_vec()).map_err(|e| e.to_string()));
+        match downloaded {
+            Ok(notes) => {
+                let notes = notes.trim().to_string();
+                self.entries
+                    .insert(asset_id, notes.clone(), notes.len() as u64);
+                notes
+            }
+            Err(e) => {
+                warn!("Failed to read release notes asset {}: {}", asset_id, e);
+                localized_notes(body, languages).to_string()
+            }
+        }
+    }
+}
+
+#[test]
+fn test_notes_language() {
+    assert_eq!(notes_language("notes.de.md"), Some("de".to_string()));
+    assert_eq!(notes_language("Notes.pt_BR.md"), Some("pt-br".to_string()));
+    assert_eq!(notes_language("notes.md"), None);
+    assert_eq!(notes_language("notes.de.md.sig"), None);
+    assert_eq!(notes_language("MyApp_1.2.0_x64.msi"), None);
+}
@@ -0,0 +1,180 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+use log::{debug, info, warn};
+use octocrab::models::repos::Release;
+use tokio::sync::RwLock;
+
+use crate::cache::decision::DecisionCache;
+use crate::cache::limits::MemoryBudget;
+use crate::config::ProductConfig;
+use crate::error::ServerError;
+use crate::github::breaker::CircuitBreaker;
+use crate::github::client::GitHubClient;
+use crate::github::sync::SyncLog;
+use crate::local::store::LocalStore;
+
+struct CachedReleases {
+    fetched_at: Instant,
+    fingerprint: u64,
+    releases: Arc<Vec<Release>>,
+    /// Bytes counted against the memory budget.
+    size: u64,
+}
+
+/// Caches each product's release list for `ttl`, invalidating resolved
+/// decisions whenever a refresh observes a change.
+pub struct ReleaseCache {
+    ttl: Duration,
+    entries: RwLock<HashMap<String, CachedReleases>>,
+    decisions: Arc<DecisionCache>,
+    syncs: Arc<SyncLog>,
+    breakers: DashMap<String, Arc<CircuitBreaker>>,
+    /// Release lists stored by the mirror sync, for when GitHub fails before
+    /// anything is cached.
+    local_store: Option<Arc<LocalStore>>,
+    /// Release lists are always cached, but count against the memory limit,
+    /// so the other caches make room for them.
+    budget: Arc<MemoryBudget>,
+}
+
+impl ReleaseCache {
+    pub fn new(
+        ttl: Duration,
+        decisions: Arc<DecisionCache>,
+        syncs: Arc<SyncLog>,
+        local_store: Option<Arc<LocalStore>>,
+        budget: Arc<MemoryBudget>,
+    ) -> Self {
+        Self {
+            ttl,
+            entries: RwLock::new(HashMap::new()),
+            decisions,
+            syncs,
+            breakers: DashMap::new(),
+            local_store,
+            budget,
+        }
+    }
+
+    /// The circuit breaker guarding the product's release requests.
+    pub fn breaker(&self, product: &str) -> Arc<CircuitBreaker> {
+        Arc::clone(&self.breakers.entry(product.to_string()).or_default())
+    }
+
+    pub async fn releases(
+        &self,
+        product: &str,
+        github: &GitHubClient,
+        product_config: &ProductConfig,
+    ) -> Result<Arc<Vec<Release>>, ServerError> {
+        if let Some(cached) = self.entries.read().await.get(product) {
+            if cached.fetched_at.elapsed() < self.ttl {
+                return Ok(Arc::clone(&cached.releases));
+            }
+        }
+
+        let breaker = self.breaker(product);
+        if !breaker.allow() {
+            // Stale releases beat no releases while GitHub is down
+            if let Some(cached) = self.entries.read().await.get(product) {
+                debug!(
+                    "GitHub requests for {} are suspended, serving stale releases",
+                    product
+                );
+                return Ok(Arc::clone(&cached.releases));
+            }
+            if let Some(stored) = self.stored(product) {
+                return Ok(stored);
+            }
+            return Err(ServerError::Upstream(
+                "GitHub requests are suspended after repeated failures",
+            ));
+        }
+
+        debug!("Refreshing release cache for {}", product);
+        self.syncs.attempted(product);
+        let releases = match github
+            .list_releases(&product_config.repo_owner, &product_config.repo_name)
+            .await
+        {
+            Ok(releases) => {
+                breaker.succeeded();
+                Arc::new(releases)
+            }
+            Err(e) => {
+                breaker.failed(&e);
+                if !self.entries.read().await.contains_key(product) {
+                    if let Some(stored) = self.stored(product) {
+                        warn!("Failed to fetch releases of {}: {}", product, e);
+                        return Ok(stored);
+                    }
+                }
+                return Err(e);
+            }
+        };
+        let fingerprint = fingerprint(&releases);
+        let size = serde_json::to_vec(&*releases).map_or(0, |json| json.len() as u64);
+        self.budget.charge(size);
+
+        let previous = self.entries.write().await.insert(
+            product.to_string(),
+            CachedReleases {
+                fetched_at: Instant::now(),
+                fingerprint,
+                releases: Arc::clone(&releases),
+                size,
+            },
+        );
+        if let Some(previous) = &previous {
+            self.budget.release(previous.size);
+        }
+
+        let changed = previous.is_some_and(|p| p.fingerprint != fingerprint);
+        self.syncs.succeeded(product, releases.len(), changed);
+        if changed {
+            info!(
+                "Releases of {} changed, invalidating cached decisions",
+                product
+            );
+            self.decisions.invalidate_product(product).await;
+        }
+
+        Ok(releases)
+    }
+
+    /// The product's releases as of the last mirror sync.
+    fn stored(&self, product: &str) -> Option<Arc<Vec<Release>>> {
+        let releases = self.local_store.as_ref()?.load_releases(product)?;
+        debug!("Serving stored releases of {}", product);
+        Some(Arc::new(releases))
+    }
+
+    /// The product's releases as last fetched, without contacting GitHub.
+    pub async fn cached(&self, product: &str) -> Option<Arc<Vec<Release>>> {
+        let entries = self.entries.read().await;
+        entries
+            .get(product)
+            .map(|cached| Arc::clone(&cached.releases))
+    }
+}
+
+fn fingerprint(releases: &[Release]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for release in releases {
+        release.id.0.hash(&mut hasher);
+        release.tag_name.hash(&mut hasher);
+        release.draft.hash(&mut hasher);
+        release.prerelease.hash(&mut hasher);
+        release.published_at.hash(&mut hasher);
+        for asset in &release.assets {
+            asset.id.0.hash(&mut hasher);
+            asset.updated_at.hash(&mut hasher);
+        }
+    }
+    hasher.finish()
+}
@@ -0,0 +1,73 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::{debug, warn};
+
+use crate::cache::digest::sha256_hex;
+use crate::cache::limits::MemoryBudget;
+use crate::cache::lru::LruCache;
+use crate::config::ProductConfig;
+use crate::error::ServerError;
+use crate::formats::rpm::read_package;
+use crate::formats::yum::RepoPackage;
+use crate::github::client::GitHubClient;
+
+/// Caches the header data of `.rpm` assets by asset id, so repodata
+/// downloads each package only once. Assets that aren't valid packages are
+/// remembered as `None`.
+#[derive(Default)]
+pub struct RpmCache {
+    entries: LruCache<u64, Option<RepoPackage>>,
+}
+
+impl RpmCache {
+    pub fn new(budget: Arc<MemoryBudget>, max_idle: Duration) -> Self {
+        RpmCache {
+            entries: LruCache::new(budget, max_idle),
+        }
+    }
+
+    /// Drops entries unused for the idle limit. Returns how many were
+    /// dropped.
+    pub fn expire(&self) -> usize {
+        self.entries.expire()
+    }
+
+    /// Returns the package's repodata entry, downloading it the first time.
+    pub async fn package(
+        &self,
+        github: &GitHubClient,
+        product_config: &ProductConfig,
+        asset_id: u64,
+    ) -> Result<Option<RepoPackage>, ServerError> {
+        if let Some(package) = self.entries.get(&asset_id) {
+            return Ok(package);
+        }
+
+        debug!("Reading rpm header of asset {}", asset_id);
+        let rpm = github
+            .download_asset(
+                asset_id,
+                &product_config.repo_owner,
+                &product_config.repo_name,
+            )
+            .await?;
+
+        let package = match read_package(&rpm) {
+            Ok(header) => Some(RepoPackage {
+                rpm: header,
+                sha256: sha256_hex(&rpm),
+                size: rpm.len() as u64,
+            }),
+            Err(e) => {
+                warn!("Asset {} is not a valid .rpm: {}", asset_id, e);
+                None
+            }
+        };
+        let size = package
+            .as_ref()
+            .map_or(0, |p| p.rpm.header_range.1 - p.rpm.header_range.0);
+        self.entries.insert(asset_id, package.clone(), size as u64);
+        Ok(package)
+    }
+}
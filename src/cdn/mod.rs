@@ -0,0 +1,3 @@
+pub mod offload;
+pub mod s3;
+pub mod sync;
@@ -0,0 +1,91 @@
+use std::env;
+
+use dashmap::DashMap;
+
+use crate::cdn::s3::{encode_segment, Credentials, S3Bucket};
+
+/// Object storage that release assets are replicated to, and the public
+/// (CDN) URL it's served from.
+pub struct Cdn {
+    bucket: S3Bucket,
+    public_url: String,
+    /// Object keys of the assets known to be in the bucket, by asset id.
+    replicated: DashMap<u64, String>,
+}
+
+impl Cdn {
+    pub fn new(bucket: S3Bucket, public_url: impl Into<String>) -> Self {
+        Cdn {
+            bucket,
+            public_url: public_url.into().trim_end_matches('/').to_string(),
+            replicated: DashMap::new(),
+        }
+    }
+
+    /// Reads `CDN_S3_ENDPOINT`, `CDN_S3_BUCKET`, `CDN_S3_REGION` (`auto` by
+    /// default, as for R2), `CDN_S3_ACCESS_KEY_ID`, `CDN_S3_SECRET_ACCESS_KEY`
+    /// and `CDN_PUBLIC_URL`. Returns `None` when no bucket is configured.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let Ok(bucket) = env::var("CDN_S3_BUCKET") else {
+            return Ok(None);
+        };
+        let required = |key: &str| {
+            env::var(key).map_err(|_| format!("{} must be set with CDN_S3_BUCKET", key))
+        };
+        let credentials = Credentials {
+            access_key_id: required("CDN_S3_ACCESS_KEY_ID")?,
+            secret_access_key: required("CDN_S3_SECRET_ACCESS_KEY")?,
+        };
+        let region = env::var("CDN_S3_REGION").unwrap_or_else(|_| "auto".to_string());
+        let bucket = S3Bucket::new(&required("CDN_S3_ENDPOINT")?, &bucket, &region, credentials)
+            .map_err(|e| format!("invalid CDN_S3_ENDPOINT: {}", e))?;
+        Ok(Some(Cdn::new(bucket, required("CDN_PUBLIC_URL")?)))
+    }
+
+    pub fn bucket(&self) -> &S3Bucket {
+        &self.bucket
+    }
+
+    /// Where an asset is stored in the bucket.
+    pub fn object_key(product: &str, tag: &str, filename: &str) -> String {
+        format!("{}/{}/{}", product, tag, filename)
+    }
+
+    pub fn is_replicated(&self, asset_id: u64) -> bool {
+        self.replicated.contains_key(&asset_id)
+    }
+
+    pub fn mark_replicated(&self, asset_id: u64, key: String) {
+        self.replicated.insert(asset_id, key);
+    }
+
+    /// Public URL of an asset, once it's replicated.
+    pub fn url(&self, asset_id: u64) -> Option<String> {
+        let key = self.replicated.get(&asset_id)?;
+        let path: Vec<String> = key.split('/').map(encode_segment).collect();
+        Some(format!("{}/{}", self.public_url, path.join("/")))
+    }
+}
+
+#[test]
+fn test_cdn_urls() {
+    let bucket = S3Bucket::new(
+        "https://account.r2.cloudflarestorage.com",
+        "releases",
+        "auto",
+        Credentials {
+            access_key_id: "key".to_string(),
+            secret_access_key: "secret".to_string(),
+        },
+    )
+    .unwrap();
+    let cdn = Cdn::new(bucket, "https://cdn.example.com/");
+
+    assert_eq!(cdn.url(201), None);
+    cdn.mark_replicated(201, Cdn::object_key("myapp", "v1.2.0", "My App.msi"));
+    assert!(cdn.is_replicated(201));
+    assert_eq!(
+        cdn.url(201).as_deref(),
+        Some("https://cdn.example.com/myapp/v1.2.0/My%20App.msi")
+    );
+}
@@ -0,0 +1,119 @@
+use std::time::Duration;
+
+use actix_web::rt::time;
+use log::{debug, info, warn};
+use reqwest::Body;
+
+use crate::cdn::offload::Cdn;
+use crate::config::{AppState, ProductConfig};
+use crate::error::ServerError;
+use crate::logging;
+
+/// How often the sync task looks for new releases by default.
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Releases per product whose assets are replicated, newest first.
+const RELEASES_TO_SYNC: usize = 5;
+
+/// Replicates new release assets every `interval`, until the server shuts
+/// down. With several instances, only the one holding the lock uploads; the
+/// others just look up what's in the bucket.
+pub async fn run(state: AppState, interval: Duration) {
+    let mut ticker = time::interval(interval);
+    loop {
+        ticker.tick().await;
+        let upload = state.leadership.acquire("cdn-sync", interval * 2).await;
+        sync(&state, upload).await;
+    }
+}
+
+/// Copies the assets of the newest releases of every product with CDN
+/// offload to the bucket, unless they're already there.
+pub async fn refresh(state: &AppState) {
+    sync(state, true).await;
+}
+
+async fn sync(state: &AppState, upload: bool) {
+    let Some(cdn) = &state.cdn else {
+        return;
+    };
+    for (product_name, product_config) in state.products.load().iter() {
+        if !product_config.cdn_offload {
+            continue;
+        }
+        let refreshed = logging::scope(
+            product_name,
+            refresh_product(state, cdn, product_name, product_config, upload),
+        );
+        if let Err(e) = refreshed.await {
+            warn!("Failed to replicate assets of {}: {}", product_name, e);
+            state
+                .recent_errors
+                .record(Some(product_name), "cdn_replication", &e);
+        }
+    }
+}
+
+async fn refresh_product(
+    state: &AppState,
+    cdn: &Cdn,
+    product_name: &str,
+    product_config: &ProductConfig,
+    upload: bool,
+) -> Result<(), ServerError> {
+    let github = state.github_client(product_name, product_config)?;
+    let releases = state
+        .release_cache
+        .releases(product_name, &github, product_config)
+        .await?;
+
+    let newest = releases
+        .iter()
+        .filter(|r| !r.draft && product_config.tag_version(&r.tag_name).is_some())
+        .take(RELEASES_TO_SYNC);
+    for release in newest {
+        for asset in &release.assets {
+            if cdn.is_replicated(asset.id.0) {
+                continue;
+            }
+            let key = Cdn::object_key(product_name, &release.tag_name, &asset.name);
+            let size = u64::try_from(asset.size).unwrap_or_default();
+
+            match cdn.bucket().object_size(&key).await {
+                Ok(Some(existing)) if existing == size => {
+                    debug!("{} is already in the bucket", key);
+                    cdn.mark_replicated(asset.id.0, key);
+                    continue;
+                }
+                Ok(_) if !upload => continue,
+                Ok(_) => {}
+                Err(e) => {
+                    warn!("Failed to look up {}: {}", key, e);
+                    continue;
+                }
+            }
+
+            let upstream = github
+                .open_asset(
+                    asset.id.0,
+                    &product_config.repo_owner,
+                    &product_config.repo_name,
+                    None,
+                )
+                .await?;
+            let body = Body::wrap_stream(upstream.bytes_stream());
+            match cdn
+                .bucket()
+                .put_object(&key, body, size, &asset.content_type)
+                .await
+            {
+                Ok(()) => {
+                    info!("Replicated {} ({} bytes)", key, size);
+                    cdn.mark_replicated(asset.id.0, key);
+                }
+                Err(e) => warn!("Failed to upload {}: {}", key, e),
+            }
+        }
+    }
+    Ok(())
+}
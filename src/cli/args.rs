@@ -0,0 +1,66 @@
+use std::path::PathBuf;
+
+use clap::{Args, Parser, Subcommand};
+
+/// Multi-product update server for Tauri apps, serving releases from GitHub.
+#[derive(Debug, Parser)]
+#[command(version, about, args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    /// Load environment variables from this file instead of `.env`.
+    #[arg(long, global = true, value_name = "PATH")]
+    pub env_file: Option<PathBuf>,
+
+    /// Same as the `check-config` command.
+    #[arg(long)]
+    pub check_config: bool,
+
+    /// Same as the `sync` command.
+    #[arg(long, conflicts_with = "check_config")]
+    pub sync: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Arguments of `serve`, which runs when no subcommand is given.
+    #[command(flatten)]
+    pub serve: ServeArgs,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run the update server (the default).
+    Serve(ServeArgs),
+    /// Load the configuration without serving, report every product with its
+    /// settings (tokens redacted) and exit non-zero if any is invalid.
+    CheckConfig,
+    /// Request every product's `latest.json` from a running server to fill its caches.
+    WarmCache(WarmCacheArgs),
+    /// List the configured products.
+    ListProducts,
+    /// Download every asset of each product's newest releases to
+    /// `ASSET_CACHE_DIR` once, and exit non-zero if any product failed.
+    Sync,
+}
+
+#[derive(Debug, Args)]
+pub struct ServeArgs {
+    /// Address to listen on.
+    #[arg(long, env = "ADDRESS", default_value = "0.0.0.0")]
+    pub address: String,
+    /// Port to listen on.
+    #[arg(long, env = "PORT", default_value_t = 8080)]
+    pub port: u16,
+}
+
+#[derive(Debug, Args)]
+pub struct WarmCacheArgs {
+    /// Base URL of the running server.
+    #[arg(long, default_value = "http://127.0.0.1:8080")]
+    pub url: String,
+    /// Channels to warm; repeat for several.
+    #[arg(long = "channel", default_value = "stable")]
+    pub channels: Vec<String>,
+    /// Extra request header such as `X-Api-Key: secret`; repeat for several.
+    #[arg(long = "header", value_name = "NAME: VALUE")]
+    pub headers: Vec<String>,
+}
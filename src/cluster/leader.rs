@@ -0,0 +1,147 @@
+use std::env;
+use std::time::Duration;
+
+use log::{debug, error, warn};
+use ring::rand::{SecureRandom, SystemRandom};
+
+use crate::cache::digest::hex;
+use crate::cluster::redis::{RedisClient, Reply};
+use crate::config::parse_flag;
+
+/// Prefix of the lock keys in Redis.
+const KEY_PREFIX: &str = "tauri-update-server:lock:";
+
+/// Extends a lock, but only if this instance still holds it.
+const RENEW_SCRIPT: &str = "if redis.call('get', KEYS[1]) == ARGV[1] then \
+     return redis.call('pexpire', KEYS[1], ARGV[2]) else return 0 end";
+
+/// Decides which of several replicas runs a background job, through locks
+/// in a shared Redis. Without Redis, every instance runs every job.
+#[derive(Debug)]
+pub struct Leadership {
+    redis: Option<RedisClient>,
+    /// Redis was configured, but with an unusable URL.
+    misconfigured: bool,
+    /// Whether jobs run when Redis can't be reached or used, at the risk of
+    /// several instances running them at once.
+    fail_open: bool,
+    instance_id: String,
+}
+
+impl Default for Leadership {
+    fn default() -> Self {
+        Leadership::new(None)
+    }
+}
+
+impl Leadership {
+    pub fn new(redis: Option<RedisClient>) -> Self {
+        let mut id = [0u8; 8];
+        let _ = SystemRandom::new().fill(&mut id);
+        Leadership {
+            redis,
+            misconfigured: false,
+            fail_open: false,
+            instance_id: format!("{}-{}", std::process::id(), hex(&id)),
+        }
+    }
+
+    /// Runs jobs when Redis can't be reached, instead of skipping them.
+    pub fn with_fail_open(mut self, fail_open: bool) -> Self {
+        self.fail_open = fail_open;
+        self
+    }
+
+    /// Reads `COORDINATION_REDIS_URL` and `COORDINATION_FAIL_OPEN`. An
+    /// invalid URL is treated like an unreachable Redis.
+    pub fn from_env() -> Self {
+        let fail_open = env::var("COORDINATION_FAIL_OPEN").is_ok_and(|v| parse_flag(&v));
+        let Ok(url) = env::var("COORDINATION_REDIS_URL") else {
+            return Leadership::new(None);
+        };
+        match RedisClient::from_url(&url) {
+            Ok(client) => Leadership::new(Some(client)).with_fail_open(fail_open),
+            Err(e) => {
+                error!("Invalid COORDINATION_REDIS_URL: {}", e);
+                Leadership {
+                    misconfigured: true,
+                    ..Leadership::new(None).with_fail_open(fail_open)
+                }
+            }
+        }
+    }
+
+    /// Whether this instance should run `job` now. The lock is taken, or
+    /// renewed if already held, for `ttl`; the holder keeps it by asking
+    /// again before it expires, and another instance takes over once it
+    /// doesn't. When Redis can't be reached, the job is skipped, unless
+    /// `COORDINATION_FAIL_OPEN` is set.
+    pub async fn acquire(&self, job: &str, ttl: Duration) -> bool {
+        let Some(redis) = &self.redis else {
+            if self.misconfigured {
+                warn!(
+                    "Can't coordinate {} through a misconfigured Redis, {}",
+                    job,
+                    self.fallback()
+                );
+                return self.fail_open;
+            }
+            return true;
+        };
+        let key = format!("{}{}", KEY_PREFIX, job);
+        let ttl = ttl.as_millis().max(1).to_string();
+
+        let taken = redis
+            .query(&["SET", &key, &self.instance_id, "NX", "PX", &ttl])
+            .await;
+        let held = match taken {
+            Ok(Reply::Status(_)) => Ok(true),
+            Ok(_) => redis
+                .query(&["EVAL", RENEW_SCRIPT, "1", &key, &self.instance_id, &ttl])
+                .await
+                .map(|reply| reply == Reply::Integer(1)),
+            Err(e) => Err(e),
+        };
+        match held {
+            Ok(held) => {
+                if !held {
+                    debug!("Another instance runs {}", job);
+                }
+                held
+            }
+            Err(e) => {
+                warn!(
+                    "Failed to coordinate {} through Redis, {}: {}",
+                    job,
+                    self.fallback(),
+                    e
+                );
+                self.fail_open
+            }
+        }
+    }
+
+    fn fallback(&self) -> &'static str {
+        if self.fail_open {
+            "running it"
+        } else {
+            "skipping it"
+        }
+    }
+}
+
+#[actix_web::test]
+async fn test_leadership_without_redis() {
+    assert!(
+        Leadership::default()
+            .acquire("gc", Duration::from_secs(60))
+            .await
+    );
+
+    // Nothing listens on port 1
+    let unreachable = || Some(RedisClient::from_url("redis://127.0.0.1:1").unwrap());
+    let leadership = Leadership::new(unreachable());
+    assert!(!leadership.acquire("gc", Duration::from_secs(60)).await);
+    let leadership = Leadership::new(unreachable()).with_fail_open(true);
+    assert!(leadership.acquire("gc", Duration::from_secs(60)).await);
+}
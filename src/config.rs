@@ -1,47 +1,1415 @@
-use serde::Deserialize;
-use std::{collections::HashMap, env, sync::Arc};
-use tokio::sync::RwLock;
+use arc_swap::ArcSwap;
+use dashmap::DashMap;
+use log::{error, LevelFilter};
+use regex::Regex;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    env,
+    path::Path,
+    str::FromStr,
+    sync::{atomic::AtomicBool, Arc},
+    time::Duration,
+};
+use thiserror::Error;
+
+use crate::admin::audit::AuditLog;
+use crate::admin::experiments::Experiments;
+use crate::admin::kill_switch::KillSwitch;
+use crate::admin::maintenance::Maintenance;
+use crate::admin::maintenance_window::MaintenanceWindows;
+use crate::auth::authenticator::{secrets_match, AuthConfig};
+use crate::auth::rate_limit::RateLimiter;
+use crate::cache::changelog::ChangelogCache;
+use crate::cache::deb::DebCache;
+use crate::cache::decision::{self, DecisionCache};
+use crate::cache::delta::DeltaCache;
+use crate::cache::digest::DigestCache;
+use crate::cache::gc::GcLog;
+use crate::cache::limits::{CacheLimits, MemoryBudget};
+use crate::cache::lru;
+use crate::cache::notes::NotesCache;
+use crate::cache::release::ReleaseCache;
+use crate::cache::rpm::RpmCache;
+use crate::cdn::offload::Cdn;
+use crate::cluster::leader::Leadership;
+use crate::cors::policy::CorsConfig;
+use crate::error::ServerError;
+use crate::formats::minisign::PublicKey;
+use crate::formats::package::PackageMetadata;
+use crate::geo::mirror::Mirrors;
+use crate::github::client::{GitHubClient, RootCertificates, Timeouts};
+use crate::github::fallback::FallbackRepo;
+use crate::github::sync::SyncLog;
+use crate::handlers::feedback::DEFAULT_FEEDBACK_PER_MINUTE;
+use crate::license::entitlement::EntitlementVerifier;
+use crate::license::validator::{AllowAll, LicenseValidator};
+use crate::local::store::LocalStore;
+use crate::notes::sanitize::NotesSanitizer;
+use crate::release::compatibility::{parse_min_os_versions, MinOsVersion};
+use crate::release::rules::Rules;
+use crate::release::signing::SigningKeys;
+use crate::release::version::{scheme_by_name, Semver, VersionScheme};
+use crate::response::cache_control::CachePolicy;
+use crate::response::slots::DownloadSlots;
+use crate::response::throttle::Throttle;
+use crate::server::BackgroundJobs;
+use crate::telemetry::errors::RecentErrors;
+use crate::telemetry::halt::{HaltPolicy, RolloutHalts, DEFAULT_MIN_REPORTS};
+use crate::telemetry::store::{EventStore, DEFAULT_REPORTS_PER_MINUTE};
 
 #[derive(Clone, Debug, Deserialize)]
 pub struct ProductConfig {
     pub github_token: String,
     pub repo_owner: String,
     pub repo_name: String,
+    /// GitHub API endpoint, e.g. `https://ghe.example.com/api/v3` for GitHub
+    /// Enterprise; api.github.com when unset.
+    #[serde(default)]
+    pub api_base_url: Option<String>,
+    /// Repository that GitHub requests fail over to while this one is
+    /// unreachable.
+    #[serde(default)]
+    pub fallback: Option<FallbackRepo>,
+    /// HTTP or SOCKS proxy for GitHub requests, overriding `HTTPS_PROXY`/`ALL_PROXY`.
+    #[serde(default)]
+    pub proxy: Option<String>,
+    /// Extra root certificates trusted for GitHub requests.
+    #[serde(skip)]
+    pub root_certificates: RootCertificates,
+    /// Timeouts of GitHub requests.
+    #[serde(skip)]
+    pub timeouts: Timeouts,
+    /// Largest asset served, in bytes; larger ones are refused.
+    #[serde(default)]
+    pub max_asset_size: Option<u64>,
+    /// Concatenate the notes of every release newer than the client's version.
+    #[serde(default)]
+    pub aggregate_notes: bool,
+    /// Maximum number of releases whose notes are aggregated.
+    #[serde(default = "default_notes_max_releases")]
+    pub notes_max_releases: usize,
+    /// Path of a Markdown changelog in the repository, e.g. `CHANGELOG.md`.
+    /// When set, release notes are the changelog's section for the
+    /// release's version instead of the release body.
+    #[serde(default)]
+    pub changelog: Option<String>,
+    /// OS versions releases need per target platform. Clients reporting an
+    /// older OS are served the newest release they can run.
+    #[serde(default)]
+    pub min_os_versions: Vec<MinOsVersion>,
+    /// Skip releases whose publish date is in the future or predates an older version.
+    #[serde(default)]
+    pub freshness_guard: bool,
+    /// Tolerated clock skew, in seconds, for the freshness guard.
+    #[serde(default = "default_max_clock_skew_secs")]
+    pub max_clock_skew_secs: i64,
+    /// How release tags and client versions are numbered; semver by default.
+    #[serde(skip, default = "default_version_scheme")]
+    pub version_scheme: Arc<dyn VersionScheme>,
+    /// Treat versions differing only in build metadata (`+...`) as equal.
+    #[serde(default)]
+    pub ignore_build_metadata: bool,
+    /// Never offer a prerelease to a client running a stable version, even on
+    /// channels that serve prereleases.
+    #[serde(default)]
+    pub stable_skips_prereleases: bool,
+    /// Never offer a release newer than this version.
+    #[serde(default)]
+    pub max_version: Option<Version>,
+    #[serde(default)]
+    pub notes_sanitizer: NotesSanitizer,
+    /// Verifies entitlement tokens; when set, new major versions are only
+    /// offered to clients whose maintenance covers the release.
+    #[serde(skip)]
+    pub entitlement: Option<EntitlementVerifier>,
+    /// Decides which clients are served updates; everyone by default.
+    #[serde(skip, default = "default_license_validator")]
+    pub license_validator: Arc<dyn LicenseValidator>,
+    /// URL posted the client's identifiers on each update check; any answer
+    /// but `200 OK` refuses the update with `403`.
+    #[serde(default)]
+    pub entitlement_webhook: Option<String>,
+    /// Valid channels (`feature` segments) besides `stable`; any channel is
+    /// accepted when empty.
+    #[serde(default)]
+    pub channels: Vec<String>,
+    /// Legacy channel names mapped to the channel they're served as, e.g.
+    /// `prod` → `stable`, so older clients keep working after a rename.
+    #[serde(default)]
+    pub channel_aliases: HashMap<String, String>,
+    /// Channels (`feature` segments) on which GitHub prereleases are served.
+    #[serde(default)]
+    pub prerelease_channels: Vec<String>,
+    /// Only tags starting with this prefix belong to the product, e.g. `app-a-`
+    /// for `app-a-v1.2.3` in a monorepo.
+    #[serde(default)]
+    pub tag_prefix: Option<String>,
+    /// Only assets starting with this prefix (after the feature prefix) belong
+    /// to the product, so several products can share one repository.
+    #[serde(default)]
+    pub asset_prefix: Option<String>,
+    /// Accept feedback at `POST /{product}/feedback/{version}` and file it as
+    /// issues on the repository.
+    #[serde(default)]
+    pub feedback_issues: bool,
+    /// Labels applied to feedback issues.
+    #[serde(default)]
+    pub feedback_labels: Vec<String>,
+    /// Append a link to the release page to the notes, where users can react
+    /// or comment.
+    #[serde(default)]
+    pub feedback_link: bool,
+    /// Only assets whose name matches this regex belong to the product.
+    #[serde(skip)]
+    pub asset_pattern: Option<Regex>,
+    /// Name, publisher and other details shown in package manager manifests.
+    #[serde(default)]
+    pub package: PackageMetadata,
+    /// Build patches from the previous release's updater artifacts to the
+    /// newest ones in the background and offer them in update responses.
+    #[serde(default)]
+    pub delta_updates: bool,
+    /// GPG key (id or fingerprint) in the server's keyring that signs the
+    /// APT repository; the repository is unsigned when unset.
+    #[serde(default)]
+    pub apt_gpg_key: Option<String>,
+    /// Updater public keys the product's releases may be signed with, as in
+    /// `tauri.conf.json`. The first is the current key, served at
+    /// `/{product}/pubkey`; the others are retired keys that older releases
+    /// were signed with. Signatures by other keys are refused.
+    #[serde(default)]
+    pub pubkeys: Vec<String>,
+    /// Secret that update responses are signed with, in an
+    /// `X-Update-Signature` HMAC header.
+    #[serde(default)]
+    pub response_secret: Option<String>,
+    /// Seconds clients should accept a signed update response for.
+    #[serde(default = "default_response_max_age_secs")]
+    pub response_max_age_secs: u64,
+    /// Secret that download tokens are signed with. When set, download
+    /// links carry a short-lived token and the download route refuses
+    /// requests without one.
+    #[serde(default)]
+    pub download_token_secret: Option<String>,
+    /// Seconds a download token is valid for.
+    #[serde(default = "default_download_token_ttl_secs")]
+    pub download_token_ttl_secs: u64,
+    /// Tokens of a private product: when set, the product is only served to
+    /// clients presenting one of them and looks nonexistent to others.
+    #[serde(default)]
+    pub distribution_tokens: Vec<String>,
+    /// Canary values: clients sending one in the `X-Canary` header or
+    /// `canary` cookie always get the newest release, skipping rollouts,
+    /// experiments and publication schedules.
+    #[serde(default)]
+    pub canary_tokens: Vec<String>,
+    /// Offer the newest release only to this percentage of devices, chosen
+    /// by their device id; the others keep getting the release before it.
+    #[serde(default)]
+    pub rollout_percentage: Option<u8>,
+    /// Targeting rules that pick the release or edition served to clients
+    /// by channel, platform, locale, OS version or device bucket.
+    #[serde(default)]
+    pub rules: Rules,
+    /// Weekly windows, e.g. business hours, during which clients aren't
+    /// offered updates.
+    #[serde(default)]
+    pub maintenance_windows: MaintenanceWindows,
+    /// Stop offering a version once too many of its reported installs fail.
+    #[serde(default)]
+    pub halt_policy: Option<HaltPolicy>,
+    /// URL that is posted a JSON alert when a version's rollout is halted.
+    #[serde(default)]
+    pub halt_webhook: Option<String>,
+    /// Download mirrors by region; update checks point clients at the one
+    /// closest to them instead of this server.
+    #[serde(default)]
+    pub mirrors: Mirrors,
+    /// Replicate release assets to the CDN bucket and send clients there
+    /// instead of proxying downloads.
+    #[serde(default)]
+    pub cdn_offload: bool,
+    /// Maximum downloads of this product proxied at once.
+    #[serde(default)]
+    pub max_concurrent_downloads: Option<usize>,
+    /// Log level of the product's requests and background jobs, overriding
+    /// `RUST_LOG`.
+    #[serde(skip)]
+    pub log_level: Option<LevelFilter>,
+}
+
+impl ProductConfig {
+    /// A product served from `owner/repo` with every optional setting at its
+    /// default.
+    pub fn new(
+        github_token: impl Into<String>,
+        repo_owner: impl Into<String>,
+        repo_name: impl Into<String>,
+    ) -> Self {
+        let (repo_owner, repo_name) = (repo_owner.into(), repo_name.into());
+        ProductConfig {
+            github_token: github_token.into(),
+            package: PackageMetadata::for_repo(&repo_owner, &repo_name),
+            repo_owner,
+            repo_name,
+            api_base_url: None,
+            fallback: None,
+            proxy: None,
+            root_certificates: RootCertificates::default(),
+            timeouts: Timeouts::default(),
+            max_asset_size: None,
+            aggregate_notes: false,
+            changelog: None,
+            min_os_versions: Vec::new(),
+            notes_max_releases: default_notes_max_releases(),
+            freshness_guard: false,
+            max_clock_skew_secs: default_max_clock_skew_secs(),
+            version_scheme: default_version_scheme(),
+            ignore_build_metadata: false,
+            stable_skips_prereleases: false,
+            max_version: None,
+            notes_sanitizer: NotesSanitizer::default(),
+            entitlement: None,
+            license_validator: default_license_validator(),
+            entitlement_webhook: None,
+            channels: Vec::new(),
+            channel_aliases: HashMap::new(),
+            prerelease_channels: Vec::new(),
+            tag_prefix: None,
+            asset_prefix: None,
+            feedback_issues: false,
+            feedback_labels: Vec::new(),
+            feedback_link: false,
+            asset_pattern: None,
+            delta_updates: false,
+            apt_gpg_key: None,
+            pubkeys: Vec::new(),
+            response_secret: None,
+            response_max_age_secs: default_response_max_age_secs(),
+            download_token_secret: None,
+            download_token_ttl_secs: default_download_token_ttl_secs(),
+            distribution_tokens: Vec::new(),
+            canary_tokens: Vec::new(),
+            rollout_percentage: None,
+            rules: Rules::default(),
+            maintenance_windows: MaintenanceWindows::default(),
+            halt_policy: None,
+            halt_webhook: None,
+            mirrors: Mirrors::default(),
+            cdn_offload: false,
+            max_concurrent_downloads: None,
+            log_level: None,
+        }
+    }
+
+    /// Parses a release tag into a version, or `None` if the tag doesn't
+    /// belong to this product or isn't a version.
+    pub fn tag_version(&self, tag: &str) -> Option<Version> {
+        let tag = match &self.tag_prefix {
+            Some(prefix) => tag.strip_prefix(prefix.as_str())?,
+            None => tag,
+        };
+        self.version_scheme.parse(tag.trim_start_matches('v'))
+    }
+
+    /// Whether `latest` should be offered to a client running `current`.
+    pub fn is_update(&self, latest: &Version, current: &Version) -> bool {
+        if self.stable_skips_prereleases && current.pre.is_empty() && !latest.pre.is_empty() {
+            return false;
+        }
+        if self.ignore_build_metadata {
+            self.version_scheme.precedence(latest, current).is_gt()
+        } else {
+            latest > current
+        }
+    }
+
+    /// Builds a product from its `{PRODUCT}_{SUFFIX}` settings, returning a
+    /// description of the first invalid one.
+    fn from_settings<'a>(
+        github_token: &str,
+        setting: impl Fn(&str) -> Option<&'a String>,
+    ) -> Result<Self, String> {
+        let (Some(owner), Some(repo)) = (setting("OWNER"), setting("REPO")) else {
+            return Err("both OWNER and REPO must be set".to_string());
+        };
+
+        let version_scheme = match setting("VERSION_SCHEME") {
+            Some(name) => scheme_by_name(name)
+                .ok_or_else(|| format!("invalid VERSION_SCHEME: unknown scheme {}", name))?,
+            None => default_version_scheme(),
+        };
+
+        let max_version = setting("MAX_VERSION")
+            .map(|v| {
+                version_scheme
+                    .parse(v.trim().trim_start_matches('v'))
+                    .ok_or_else(|| {
+                        format!(
+                            "invalid MAX_VERSION: not a {} version",
+                            version_scheme.name()
+                        )
+                    })
+            })
+            .transpose()?;
+
+        let entitlement = match setting("ENTITLEMENT_PUBLIC_KEY") {
+            Some(pem) => Some(
+                EntitlementVerifier::from_rsa_pem(pem)
+                    .map_err(|e| format!("invalid ENTITLEMENT_PUBLIC_KEY: {}", e))?,
+            ),
+            None => setting("ENTITLEMENT_SECRET").map(|s| EntitlementVerifier::from_secret(s)),
+        };
+
+        let asset_pattern = setting("ASSET_PATTERN")
+            .map(|p| Regex::new(p))
+            .transpose()
+            .map_err(|e| format!("invalid ASSET_PATTERN: {}", e))?;
+
+        let channel_aliases = setting("CHANNEL_ALIASES")
+            .map(|v| parse_aliases(v))
+            .transpose()
+            .map_err(|e| format!("invalid CHANNEL_ALIASES: {}", e))?
+            .unwrap_or_default();
+
+        if let Some(Err(e)) = setting("PROXY").map(reqwest::Proxy::all) {
+            return Err(format!("invalid PROXY: {}", e));
+        }
+
+        // The product's own bundle replaces the global one
+        let root_certificates = match setting("CA_CERTS")
+            .cloned()
+            .or_else(|| env::var("CA_CERTS").ok())
+        {
+            Some(path) => RootCertificates::from_pem_file(Path::new(&path))
+                .map_err(|e| format!("invalid CA_CERTS: {}", e))?,
+            None => RootCertificates::default(),
+        };
+
+        // As with CA_CERTS, the product's own limits replace the global ones
+        let limit = |name: &str| setting(name).cloned().or_else(|| env::var(name).ok());
+        let timeout = |name: &str, default: Duration| match limit(name) {
+            Some(v) => v
+                .trim()
+                .parse::<u64>()
+                .ok()
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs)
+                .ok_or_else(|| format!("invalid {}: {} is not a number of seconds", name, v)),
+            None => Ok(default),
+        };
+        let default_timeouts = Timeouts::default();
+        let timeouts = Timeouts {
+            connect: timeout("UPSTREAM_CONNECT_TIMEOUT_SECS", default_timeouts.connect)?,
+            read: timeout("UPSTREAM_READ_TIMEOUT_SECS", default_timeouts.read)?,
+            request: timeout("UPSTREAM_TIMEOUT_SECS", default_timeouts.request)?,
+        };
+        let max_asset_size = limit("MAX_ASSET_SIZE")
+            .map(|v| parse_size(&v).ok_or_else(|| format!("invalid MAX_ASSET_SIZE: {}", v)))
+            .transpose()?;
+
+        let rollout_percentage = setting("ROLLOUT_PERCENTAGE")
+            .map(|v| match v.trim().trim_end_matches('%').parse::<u8>() {
+                Ok(percentage) if percentage <= 100 => Ok(percentage),
+                _ => Err(format!(
+                    "invalid ROLLOUT_PERCENTAGE: {} is not a percentage",
+                    v
+                )),
+            })
+            .transpose()?;
+
+        let halt_policy = setting("HALT_FAILURE_RATE")
+            .map(|v| {
+                let rate = match v.trim().strip_suffix('%') {
+                    Some(percent) => percent.trim().parse::<f64>().map(|p| p / 100.0),
+                    None => v.trim().parse::<f64>(),
+                };
+                match rate {
+                    Ok(rate) if (0.0..=1.0).contains(&rate) => Ok(rate),
+                    _ => Err(format!("invalid HALT_FAILURE_RATE: {} is not a rate", v)),
+                }
+            })
+            .transpose()?
+            .map(|max_failure_rate| HaltPolicy {
+                max_failure_rate,
+                min_reports: setting("HALT_MIN_REPORTS")
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(DEFAULT_MIN_REPORTS),
+            });
+
+        let mirrors = setting("MIRRORS")
+            .map(|v| Mirrors::parse(v))
+            .transpose()
+            .map_err(|e| format!("invalid MIRRORS: {}", e))?
+            .unwrap_or_default();
+
+        let max_concurrent_downloads = setting("MAX_CONCURRENT_DOWNLOADS")
+            .map(|v| match v.parse::<usize>() {
+                Ok(max) if max > 0 => Ok(max),
+                _ => Err(format!("invalid MAX_CONCURRENT_DOWNLOADS: {}", v)),
+            })
+            .transpose()?;
+
+        let pubkeys = setting("PUBKEY").map(|v| parse_list(v)).unwrap_or_default();
+        for (i, key) in pubkeys.iter().enumerate() {
+            PublicKey::parse(key).map_err(|e| format!("invalid PUBKEY: key {}: {}", i + 1, e))?;
+        }
+
+        let log_level = setting("LOG_LEVEL")
+            .map(|v| {
+                v.parse::<LevelFilter>()
+                    .map_err(|_| format!("invalid LOG_LEVEL: {}", v))
+            })
+            .transpose()?;
+
+        let defaults = PackageMetadata::for_repo(owner, repo);
+        let package = PackageMetadata {
+            name: setting("PACKAGE_NAME").cloned().unwrap_or(defaults.name),
+            publisher: setting("PACKAGE_PUBLISHER")
+                .cloned()
+                .unwrap_or(defaults.publisher),
+            description: setting("PACKAGE_DESCRIPTION").cloned(),
+            homepage: setting("PACKAGE_HOMEPAGE")
+                .cloned()
+                .unwrap_or(defaults.homepage),
+            license: setting("PACKAGE_LICENSE").cloned(),
+            winget_id: setting("WINGET_ID").cloned(),
+        };
+
+        Ok(ProductConfig {
+            github_token: github_token.to_string(),
+            repo_owner: owner.clone(),
+            repo_name: repo.clone(),
+            api_base_url: setting("API_BASE_URL").cloned(),
+            fallback: setting("FALLBACK_REPO")
+                .map(|v| {
+                    FallbackRepo::parse(
+                        v,
+                        setting("FALLBACK_API_BASE_URL").cloned(),
+                        setting("FALLBACK_TOKEN").cloned(),
+                    )
+                })
+                .transpose()
+                .map_err(|e| format!("invalid FALLBACK_REPO: {}", e))?,
+            proxy: setting("PROXY").cloned(),
+            root_certificates,
+            timeouts,
+            max_asset_size,
+            aggregate_notes: setting("AGGREGATE_NOTES").is_some_and(|v| parse_flag(v)),
+            changelog: setting("CHANGELOG_PATH").cloned(),
+            min_os_versions: setting("MIN_OS_VERSIONS")
+                .map(|v| parse_min_os_versions(v, &*version_scheme))
+                .transpose()
+                .map_err(|e| format!("invalid MIN_OS_VERSIONS: {}", e))?
+                .unwrap_or_default(),
+            notes_max_releases: setting("NOTES_MAX_RELEASES")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or_else(default_notes_max_releases),
+            freshness_guard: setting("FRESHNESS_GUARD").is_some_and(|v| parse_flag(v)),
+            max_clock_skew_secs: setting("MAX_CLOCK_SKEW_SECS")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or_else(default_max_clock_skew_secs),
+            version_scheme,
+            ignore_build_metadata: setting("IGNORE_BUILD_METADATA").is_some_and(|v| parse_flag(v)),
+            stable_skips_prereleases: setting("STABLE_SKIPS_PRERELEASES")
+                .is_some_and(|v| parse_flag(v)),
+            max_version,
+            notes_sanitizer: NotesSanitizer {
+                strip_comments: setting("NOTES_STRIP_COMMENTS").is_some_and(|v| parse_flag(v)),
+                strip_sections: setting("NOTES_STRIP_SECTIONS")
+                    .map(|v| parse_list(v))
+                    .unwrap_or_default(),
+                strip_link_hosts: setting("NOTES_STRIP_LINK_HOSTS")
+                    .map(|v| parse_list(v))
+                    .unwrap_or_default(),
+                max_length: setting("NOTES_MAX_LENGTH").and_then(|v| v.parse().ok()),
+            },
+            entitlement,
+            license_validator: default_license_validator(),
+            entitlement_webhook: setting("ENTITLEMENT_WEBHOOK").cloned(),
+            channels: setting("CHANNELS")
+                .map(|v| parse_list(v))
+                .unwrap_or_default(),
+            channel_aliases,
+            prerelease_channels: setting("PRERELEASE_CHANNELS")
+                .map(|v| parse_list(v))
+                .unwrap_or_default(),
+            tag_prefix: setting("TAG_PREFIX").cloned(),
+            asset_prefix: setting("ASSET_PREFIX").cloned(),
+            asset_pattern,
+            feedback_issues: setting("FEEDBACK_ISSUES").is_some_and(|v| parse_flag(v)),
+            feedback_labels: setting("FEEDBACK_LABELS")
+                .map(|v| parse_list(v))
+                .unwrap_or_default(),
+            feedback_link: setting("FEEDBACK_LINK").is_some_and(|v| parse_flag(v)),
+            package,
+            delta_updates: setting("DELTA_UPDATES").is_some_and(|v| parse_flag(v)),
+            apt_gpg_key: setting("APT_GPG_KEY").cloned(),
+            pubkeys,
+            response_secret: setting("RESPONSE_SECRET").cloned(),
+            response_max_age_secs: setting("RESPONSE_MAX_AGE_SECS")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or_else(default_response_max_age_secs),
+            download_token_secret: setting("DOWNLOAD_TOKEN_SECRET").cloned(),
+            download_token_ttl_secs: setting("DOWNLOAD_TOKEN_TTL_SECS")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or_else(default_download_token_ttl_secs),
+            distribution_tokens: setting("DISTRIBUTION_TOKENS")
+                .map(|v| parse_list(v))
+                .unwrap_or_default(),
+            canary_tokens: setting("CANARY_TOKENS")
+                .map(|v| parse_list(v))
+                .unwrap_or_default(),
+            rollout_percentage,
+            rules: setting("RULES")
+                .map(|v| Rules::parse(v))
+                .transpose()
+                .map_err(|e| format!("invalid RULES: {}", e))?
+                .unwrap_or_default(),
+            maintenance_windows: setting("MAINTENANCE_WINDOWS")
+                .map(|v| MaintenanceWindows::parse(v))
+                .transpose()
+                .map_err(|e| format!("invalid MAINTENANCE_WINDOWS: {}", e))?
+                .unwrap_or_default(),
+            halt_policy,
+            halt_webhook: setting("HALT_WEBHOOK").cloned(),
+            mirrors,
+            cdn_offload: setting("CDN_OFFLOAD").is_some_and(|v| parse_flag(v)),
+            max_concurrent_downloads,
+            log_level,
+        })
+    }
+
+    /// The product's updater public keys, the current one first.
+    pub fn public_keys(&self) -> Vec<PublicKey> {
+        self.pubkeys
+            .iter()
+            .filter_map(|key| PublicKey::parse(key).ok())
+            .collect()
+    }
+
+    /// Whether a client presenting the distribution token `token` may see
+    /// the product; public products are visible to everyone.
+    pub fn is_visible_to(&self, token: Option<&str>) -> bool {
+        // Check every token, so the time taken doesn't tell which one matched
+        self.distribution_tokens.is_empty()
+            || token.is_some_and(|token| {
+                self.distribution_tokens
+                    .iter()
+                    .fold(false, |matched, t| secrets_match(token, t) | matched)
+            })
+    }
+
+    /// Whether `canary` is one of the product's canary values.
+    pub fn is_canary(&self, canary: Option<&str>) -> bool {
+        canary.is_some_and(|canary| {
+            self.canary_tokens
+                .iter()
+                .fold(false, |matched, t| secrets_match(canary, t) | matched)
+        })
+    }
+
+    /// The channel that `channel` is served as, after resolving aliases.
+    pub fn resolve_channel(&self, channel: &str) -> String {
+        let channel = channel.to_lowercase();
+        self.channel_aliases
+            .get(&channel)
+            .cloned()
+            .unwrap_or(channel)
+    }
+
+    /// Whether clients may request `channel`; `stable` is always valid.
+    pub fn allows_channel(&self, channel: &str) -> bool {
+        self.channels.is_empty()
+            || channel.eq_ignore_ascii_case("stable")
+            || self
+                .channels
+                .iter()
+                .any(|c| c.eq_ignore_ascii_case(channel))
+    }
+
+    pub fn allows_prereleases(&self, channel: &str) -> bool {
+        self.prerelease_channels
+            .iter()
+            .any(|c| c.eq_ignore_ascii_case(channel))
+    }
+}
+
+fn default_version_scheme() -> Arc<dyn VersionScheme> {
+    Arc::new(Semver)
+}
+
+fn default_license_validator() -> Arc<dyn LicenseValidator> {
+    Arc::new(AllowAll)
 }
 
+fn default_notes_max_releases() -> usize {
+    10
+}
+
+fn default_max_clock_skew_secs() -> i64 {
+    300
+}
+
+fn default_response_max_age_secs() -> u64 {
+    3600
+}
+
+fn default_download_token_ttl_secs() -> u64 {
+    3600
+}
+
+pub(crate) fn parse_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+/// Parses `alias=channel` pairs such as `prod=stable,insider=beta`.
+fn parse_aliases(value: &str) -> Result<HashMap<String, String>, String> {
+    parse_list(value)
+        .into_iter()
+        .map(|pair| match pair.split_once('=') {
+            Some((alias, channel)) if !alias.trim().is_empty() && !channel.trim().is_empty() => {
+                Ok((alias.trim().to_lowercase(), channel.trim().to_lowercase()))
+            }
+            _ => Err(format!("expected alias=channel, got {}", pair)),
+        })
+        .collect()
+}
+
+/// Parses a number of bytes such as `500000`, `512K`, `10M` or `1G` (binary
+/// units).
+pub(crate) fn parse_size(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let (number, unit) = match value.char_indices().last()? {
+        (i, 'k' | 'K') => (&value[..i], 1 << 10),
+        (i, 'm' | 'M') => (&value[..i], 1 << 20),
+        (i, 'g' | 'G') => (&value[..i], 1 << 30),
+        _ => (value, 1),
+    };
+    number
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .filter(|n| *n > 0)
+        .and_then(|n| n.checked_mul(unit))
+}
+
+pub(crate) fn parse_flag(value: &str) -> bool {
+    matches!(
+        value.trim().to_lowercase().as_str(),
+        "1" | "true" | "yes" | "on"
+    )
+}
+
+/// Product settings whose names end in `_TOKEN` too.
+const TOKEN_SETTINGS: [&str; 1] = ["FALLBACK"];
+
+/// The prefix and token of each product. `MYAPP_FALLBACK_TOKEN` is a setting
+/// of `MYAPP`, not a product, even next to `MYAPP_FALLBACK_REPO`.
+fn product_tokens(env_vars: &HashMap<String, String>) -> Vec<(&str, &String)> {
+    env_vars
+        .iter()
+        .filter_map(|(key, value)| Some((key.strip_suffix("_TOKEN")?, value)))
+        .filter(|(prefix, _)| {
+            let setting = |suffix: &str| env_vars.contains_key(&format!("{}_{}", prefix, suffix));
+            // A lone `_TOKEN` is most likely not meant as a product
+            setting("OWNER") || setting("REPO")
+        })
+        .filter(|(prefix, _)| {
+            !TOKEN_SETTINGS.iter().any(|name| {
+                prefix
+                    .strip_suffix(name)
+                    .and_then(|product| product.strip_suffix('_'))
+                    .is_some_and(|product| env_vars.contains_key(&format!("{}_TOKEN", product)))
+            })
+        })
+        .collect()
+}
+
+/// Products with an `_OWNER` or `_REPO` but no `_TOKEN`, e.g. after a typo
+/// in its name, and what they're missing. Settings of configured products
+/// such as `_FALLBACK_REPO` aren't products of their own.
+fn incomplete_products(env_vars: &HashMap<String, String>) -> BTreeMap<String, String> {
+    let token_prefixes: Vec<&str> = env_vars
+        .keys()
+        .filter_map(|key| key.strip_suffix("_TOKEN"))
+        .collect();
+    let mut incomplete = BTreeMap::new();
+    for key in env_vars.keys() {
+        let Some(prefix) = key
+            .strip_suffix("_OWNER")
+            .or_else(|| key.strip_suffix("_REPO"))
+        else {
+            continue;
+        };
+        let is_setting = |token_prefix: &&str| {
+            *token_prefix == prefix
+                || prefix
+                    .strip_prefix(*token_prefix)
+                    .is_some_and(|rest| rest.starts_with('_'))
+        };
+        if token_prefixes.iter().any(is_setting) {
+            continue;
+        }
+        let missing: Vec<&str> = ["TOKEN", "OWNER", "REPO"]
+            .into_iter()
+            .filter(|suffix| !env_vars.contains_key(&format!("{}_{}", prefix, suffix)))
+            .collect();
+        let verb = if missing.len() > 1 { "are" } else { "is" };
+        incomplete.insert(
+            prefix.to_lowercase(),
+            format!("{} {} not set", missing.join(" and "), verb),
+        );
+    }
+    incomplete
+}
+
+/// What to do when a product's settings are invalid.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InvalidProductPolicy {
+    /// Serve the remaining products.
+    #[default]
+    Skip,
+    /// Refuse to start.
+    Fail,
+}
+
+impl InvalidProductPolicy {
+    /// Reads `ON_INVALID_PRODUCT`, skipping invalid products when it's unset.
+    pub fn from_env() -> Result<Self, ConfigError> {
+        match env::var("ON_INVALID_PRODUCT") {
+            Ok(value) => value.parse(),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+}
+
+impl FromStr for InvalidProductPolicy {
+    type Err = ConfigError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_lowercase().as_str() {
+            "skip" => Ok(Self::Skip),
+            "fail" => Ok(Self::Fail),
+            _ => Err(ConfigError::InvalidPolicy(value.to_string())),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ProductStatus {
+    Loaded,
+    Skipped { reason: String },
+}
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("invalid ON_INVALID_PRODUCT value {0:?}, expected \"skip\" or \"fail\"")]
+    InvalidPolicy(String),
+    #[error("{count} product(s) have invalid settings and ON_INVALID_PRODUCT=fail")]
+    InvalidProducts { count: usize },
+    #[error("invalid CDN settings: {0}")]
+    Cdn(String),
+    #[error("invalid cache limits: {0}")]
+    CacheLimits(String),
+}
+
+/// Shared server state. Request handlers only ever read it: the product map
+/// can only be swapped as a whole, so readers never wait on a lock.
 #[derive(Clone)]
 pub struct AppState {
-    pub products: Arc<RwLock<HashMap<String, ProductConfig>>>,
+    pub products: Arc<ArcSwap<HashMap<String, Arc<ProductConfig>>>>,
+    /// Outcome of loading each configured product, for diagnostics.
+    pub product_status: Arc<BTreeMap<String, ProductStatus>>,
+    pub auth: Arc<AuthConfig>,
+    pub release_cache: Arc<ReleaseCache>,
+    /// When each product's releases were last fetched from GitHub.
+    pub syncs: Arc<SyncLog>,
+    pub decision_cache: Arc<DecisionCache>,
+    /// What the last cache garbage collection freed.
+    pub gc_log: Arc<GcLog>,
+    /// Bytes held by the in-memory caches, and their limit.
+    pub memory_budget: Arc<MemoryBudget>,
+    /// Checksums of assets listed in package manifests.
+    pub digest_cache: Arc<DigestCache>,
+    /// Control data of `.deb` assets listed in APT indexes.
+    pub deb_cache: Arc<DebCache>,
+    /// Header data of `.rpm` assets listed in YUM repodata.
+    pub rpm_cache: Arc<RpmCache>,
+    /// Patches between consecutive releases, built by the delta worker.
+    pub delta_cache: Arc<DeltaCache>,
+    /// Contents of per-language release notes assets.
+    pub notes_cache: Arc<NotesCache>,
+    /// Changelogs that release notes are taken from, by tag.
+    pub changelog_cache: Arc<ChangelogCache>,
+    pub github_clients: Arc<DashMap<String, Arc<GitHubClient>>>,
+    /// Public base URL used in download links.
+    pub hostname: Arc<str>,
+    pub maintenance: Arc<Maintenance>,
+    pub kill_switch: Arc<KillSwitch>,
+    /// A/B experiments, started and stopped via the admin API.
+    pub experiments: Arc<Experiments>,
+    /// Update outcomes reported by clients.
+    pub events: Arc<EventStore>,
+    /// Limit on update outcomes reported per client address.
+    pub event_limiter: Arc<RateLimiter>,
+    /// Limit on feedback submitted per client address.
+    pub feedback_limiter: Arc<RateLimiter>,
+    /// Versions whose rollout was halted or resumed.
+    pub rollout_halts: Arc<RolloutHalts>,
+    /// Bucket that assets of products with CDN offload are replicated to.
+    pub cdn: Option<Arc<Cdn>>,
+    /// Local copies of the newest release assets, kept by the mirror sync.
+    pub local_store: Option<Arc<LocalStore>>,
+    /// Bandwidth limits of proxied downloads.
+    pub throttle: Throttle,
+    /// Downloads proxied at once.
+    pub download_slots: Arc<DownloadSlots>,
+    /// Which instance runs background jobs.
+    pub leadership: Arc<Leadership>,
+    /// Admin operations, with who made them.
+    pub audit: Arc<AuditLog>,
+    /// Failed requests and background jobs, shown on the dashboard.
+    pub recent_errors: Arc<RecentErrors>,
+    /// Which updater keys signed each release served.
+    pub signing_keys: Arc<SigningKeys>,
+    /// Cross-origin access for browser clients; disabled when `None`.
+    pub cors: Option<Arc<CorsConfig>>,
+    /// Whether responses are compressed for clients that accept it.
+    pub compression: bool,
+    /// Lifetimes advertised for update-check responses.
+    pub cache_policy: CachePolicy,
+    /// Background jobs that [`configure`](crate::server::configure) starts.
+    pub jobs: BackgroundJobs,
+    /// Whether the background jobs were started, so they run once however
+    /// many workers configure the app.
+    pub(crate) jobs_started: Arc<AtomicBool>,
 }
 
 impl AppState {
-    pub async fn load_config() -> Self {
+    /// Loads every product from the environment. Products with invalid
+    /// settings are skipped, or fail the whole load when `ON_INVALID_PRODUCT`
+    /// is `fail`.
+    pub async fn load_config() -> Result<Self, ConfigError> {
+        Self::load_config_with(InvalidProductPolicy::from_env()?).await
+    }
+
+    /// Like [`load_config`](Self::load_config), with `policy` in place of
+    /// `ON_INVALID_PRODUCT`.
+    pub async fn load_config_with(policy: InvalidProductPolicy) -> Result<Self, ConfigError> {
         let mut products = HashMap::new();
+        let mut product_status = BTreeMap::new();
+        let github_clients = DashMap::new();
         let env_vars: HashMap<String, String> = env::vars().collect();
 
-        for (key, value) in env_vars.iter() {
-            if key.ends_with("_TOKEN") {
-                let product_name = key.trim_end_matches("_TOKEN").to_lowercase();
-                let owner_key = format!("{}_OWNER", product_name.to_uppercase());
-                let repo_key = format!("{}_REPO", product_name.to_uppercase());
-
-                if let (Some(owner), Some(repo)) =
-                    (env_vars.get(&owner_key), env_vars.get(&repo_key))
-                {
-                    products.insert(
-                        product_name,
-                        ProductConfig {
-                            github_token: value.clone(),
-                            repo_owner: owner.clone(),
-                            repo_name: repo.clone(),
-                        },
-                    );
+        for (prefix, value) in product_tokens(&env_vars) {
+            let product_name = prefix.to_lowercase();
+            let setting = |suffix: &str| env_vars.get(&format!("{}_{}", prefix, suffix));
+
+            let loaded = ProductConfig::from_settings(value, setting).and_then(|config| {
+                let client = GitHubClient::new(&config).map_err(|e| e.to_string())?;
+                Ok((config, client))
+            });
+
+            match loaded {
+                Ok((config, client)) => {
+                    github_clients.insert(product_name.clone(), Arc::new(client));
+                    products.insert(product_name.clone(), Arc::new(config));
+                    product_status.insert(product_name, ProductStatus::Loaded);
+                }
+                Err(reason) => {
+                    error!("Skipping product {}: {}", product_name, reason);
+                    product_status.insert(product_name, ProductStatus::Skipped { reason });
                 }
             }
         }
 
+        for (product_name, reason) in incomplete_products(&env_vars) {
+            error!("Skipping product {}: {}", product_name, reason);
+            product_status.insert(product_name, ProductStatus::Skipped { reason });
+        }
+
+        let invalid = product_status
+            .values()
+            .filter(|status| matches!(status, ProductStatus::Skipped { .. }))
+            .count();
+        if invalid > 0 && policy == InvalidProductPolicy::Fail {
+            return Err(ConfigError::InvalidProducts { count: invalid });
+        }
+
+        let mut builder = AppState::builder()
+            .auth(AuthConfig::from_env())
+            .maintenance(Maintenance::from_env())
+            .kill_switch(KillSwitch::from_env())
+            .events(EventStore::from_env())
+            .event_limiter(RateLimiter::from_env(
+                "EVENTS_RATE_LIMIT",
+                DEFAULT_REPORTS_PER_MINUTE,
+            ))
+            .feedback_limiter(RateLimiter::from_env(
+                "FEEDBACK_RATE_LIMIT",
+                DEFAULT_FEEDBACK_PER_MINUTE,
+            ))
+            .throttle(Throttle::from_env())
+            .download_slots(DownloadSlots::from_env())
+            .leadership(Leadership::from_env())
+            .audit(AuditLog::from_env())
+            .cache_policy(CachePolicy::from_env())
+            .compression(
+                env::var("RESPONSE_COMPRESSION")
+                    .map(|v| parse_flag(&v))
+                    .unwrap_or(true),
+            )
+            .cache_limits(CacheLimits::from_env().map_err(ConfigError::CacheLimits)?);
+        if let Some(cdn) = Cdn::from_env().map_err(ConfigError::Cdn)? {
+            builder = builder.cdn(cdn);
+        }
+        if let Some(local_store) = LocalStore::from_env() {
+            builder = builder.local_store(local_store);
+        }
+        if let Some(cors) = CorsConfig::from_env() {
+            builder = builder.cors(cors);
+        }
+        if let Some(ttl) = env::var("RELEASE_CACHE_TTL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+        {
+            builder = builder.release_cache_ttl(Duration::from_secs(ttl));
+        }
+        if let Some(ttl) = env::var("MANIFEST_CACHE_TTL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+        {
+            builder = builder.manifest_cache_ttl(Duration::from_secs(ttl));
+        }
+        if let Ok(hostname) = env::var("HOSTNAME") {
+            builder = builder.hostname(hostname);
+        }
+
+        builder.products = products;
+        builder.product_status = product_status;
+        builder.github_clients = github_clients;
+        Ok(builder.build())
+    }
+
+    pub fn builder() -> AppStateBuilder {
+        AppStateBuilder::default()
+    }
+
+    /// Looks up a product by its lowercase name.
+    pub fn product(&self, name: &str) -> Option<Arc<ProductConfig>> {
+        self.products.load().get(name).cloned()
+    }
+
+    /// Returns the product's GitHub client, creating it on first use.
+    pub fn github_client(
+        &self,
+        name: &str,
+        product_config: &ProductConfig,
+    ) -> Result<Arc<GitHubClient>, ServerError> {
+        if let Some(client) = self.github_clients.get(name) {
+            return Ok(Arc::clone(&client));
+        }
+
+        let client = Arc::new(GitHubClient::new(product_config)?);
+        self.github_clients
+            .insert(name.to_string(), Arc::clone(&client));
+        Ok(client)
+    }
+}
+
+/// Configures an [`AppState`] in code instead of from the environment, e.g.
+/// when embedding the server in another application.
+pub struct AppStateBuilder {
+    products: HashMap<String, Arc<ProductConfig>>,
+    product_status: BTreeMap<String, ProductStatus>,
+    github_clients: DashMap<String, Arc<GitHubClient>>,
+    auth: AuthConfig,
+    hostname: String,
+    release_cache_ttl: Duration,
+    manifest_cache_ttl: Duration,
+    cache_limits: CacheLimits,
+    maintenance: Maintenance,
+    kill_switch: KillSwitch,
+    events: EventStore,
+    event_limiter: RateLimiter,
+    feedback_limiter: RateLimiter,
+    cdn: Option<Cdn>,
+    local_store: Option<LocalStore>,
+    throttle: Throttle,
+    download_slots: DownloadSlots,
+    leadership: Leadership,
+    audit: AuditLog,
+    cors: Option<CorsConfig>,
+    compression: bool,
+    cache_policy: CachePolicy,
+    jobs: BackgroundJobs,
+}
+
+impl Default for AppStateBuilder {
+    fn default() -> Self {
+        AppStateBuilder {
+            products: HashMap::new(),
+            product_status: BTreeMap::new(),
+            github_clients: DashMap::new(),
+            auth: AuthConfig::default(),
+            hostname: "localhost".to_string(),
+            release_cache_ttl: Duration::from_secs(60),
+            manifest_cache_ttl: decision::DEFAULT_TTL,
+            cache_limits: CacheLimits::default(),
+            maintenance: Maintenance::default(),
+            kill_switch: KillSwitch::default(),
+            events: EventStore::default(),
+            event_limiter: RateLimiter::default(),
+            feedback_limiter: RateLimiter::default(),
+            cdn: None,
+            local_store: None,
+            throttle: Throttle::default(),
+            download_slots: DownloadSlots::default(),
+            leadership: Leadership::default(),
+            audit: AuditLog::default(),
+            cors: None,
+            compression: true,
+            cache_policy: CachePolicy::default(),
+            jobs: BackgroundJobs::default(),
+        }
+    }
+}
+
+impl AppStateBuilder {
+    /// Serves `config` under `name`, which is matched case-insensitively.
+    pub fn product(mut self, name: &str, config: ProductConfig) -> Self {
+        let name = name.to_lowercase();
+        self.product_status
+            .insert(name.clone(), ProductStatus::Loaded);
+        self.products.insert(name, Arc::new(config));
+        self
+    }
+
+    pub fn auth(mut self, auth: AuthConfig) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    /// Public base URL used in download links, e.g. `https://updates.example.com`.
+    pub fn hostname(mut self, hostname: impl Into<String>) -> Self {
+        self.hostname = hostname.into();
+        self
+    }
+
+    /// How long release lists are cached; 60 seconds by default.
+    pub fn release_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.release_cache_ttl = ttl;
+        self
+    }
+
+    /// How long resolved updates are cached; an hour by default. Expired
+    /// ones are dropped by the cache garbage collection.
+    pub fn manifest_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.manifest_cache_ttl = ttl;
+        self
+    }
+
+    /// Limits on the local asset store, resolved updates and the memory
+    /// the caches take up; unlimited by default.
+    pub fn cache_limits(mut self, cache_limits: CacheLimits) -> Self {
+        self.cache_limits = cache_limits;
+        self
+    }
+
+    /// Initial maintenance mode; it can be switched at runtime via the admin API.
+    pub fn maintenance(mut self, maintenance: Maintenance) -> Self {
+        self.maintenance = maintenance;
+        self
+    }
+
+    /// Products whose updates start out paused.
+    pub fn kill_switch(mut self, kill_switch: KillSwitch) -> Self {
+        self.kill_switch = kill_switch;
+        self
+    }
+
+    /// Where reported update outcomes are kept; in memory by default.
+    pub fn events(mut self, events: EventStore) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// Limit on update outcomes reported per client address; unlimited by
+    /// default.
+    pub fn event_limiter(mut self, event_limiter: RateLimiter) -> Self {
+        self.event_limiter = event_limiter;
+        self
+    }
+
+    /// Limit on feedback submitted per client address; unlimited by
+    /// default.
+    pub fn feedback_limiter(mut self, feedback_limiter: RateLimiter) -> Self {
+        self.feedback_limiter = feedback_limiter;
+        self
+    }
+
+    /// Bucket and public URL that assets are offloaded to.
+    pub fn cdn(mut self, cdn: Cdn) -> Self {
+        self.cdn = Some(cdn);
+        self
+    }
+
+    /// Directory that the newest release assets are mirrored to, so
+    /// updates are served from it when GitHub is slow or down.
+    pub fn local_store(mut self, local_store: LocalStore) -> Self {
+        self.local_store = Some(local_store);
+        self
+    }
+
+    /// Bandwidth limits of proxied downloads; unlimited by default.
+    pub fn throttle(mut self, throttle: Throttle) -> Self {
+        self.throttle = throttle;
+        self
+    }
+
+    /// Limit on downloads proxied at once; unlimited by default.
+    pub fn download_slots(mut self, download_slots: DownloadSlots) -> Self {
+        self.download_slots = download_slots;
+        self
+    }
+
+    /// Coordination of background jobs between instances; without it,
+    /// every instance runs them.
+    pub fn leadership(mut self, leadership: Leadership) -> Self {
+        self.leadership = leadership;
+        self
+    }
+
+    /// Where admin operations are recorded; in memory by default.
+    pub fn audit(mut self, audit: AuditLog) -> Self {
+        self.audit = audit;
+        self
+    }
+
+    /// Lets browsers on other origins call the server; disabled by default.
+    pub fn cors(mut self, cors: CorsConfig) -> Self {
+        self.cors = Some(cors);
+        self
+    }
+
+    /// Whether responses are compressed for clients that accept it; on by
+    /// default.
+    pub fn compression(mut self, enabled: bool) -> Self {
+        self.compression = enabled;
+        self
+    }
+
+    /// `Cache-Control` lifetimes of update-check responses; none by default.
+    pub fn cache_policy(mut self, cache_policy: CachePolicy) -> Self {
+        self.cache_policy = cache_policy;
+        self
+    }
+
+    /// Which background jobs run, and how often.
+    pub fn jobs(mut self, jobs: BackgroundJobs) -> Self {
+        self.jobs = jobs;
+        self
+    }
+
+    pub fn build(self) -> AppState {
+        let limits = self.cache_limits;
+        let memory_budget = Arc::new(MemoryBudget::new(limits.max_memory_bytes));
+        let max_idle = limits.max_idle.unwrap_or(lru::DEFAULT_MAX_IDLE);
+        let decision_cache = Arc::new(DecisionCache::new(
+            self.manifest_cache_ttl,
+            limits
+                .max_manifest_entries
+                .unwrap_or(decision::DEFAULT_MAX_ENTRIES),
+            Arc::clone(&memory_budget),
+        ));
+        let syncs = Arc::new(SyncLog::default());
+        let local_store = self
+            .local_store
+            .map(|store| Arc::new(store.with_max_bytes(limits.max_disk_bytes)));
+
         AppState {
-            products: Arc::new(RwLock::new(products)),
+            products: Arc::new(ArcSwap::from_pointee(self.products)),
+            product_status: Arc::new(self.product_status),
+            auth: Arc::new(self.auth),
+            release_cache: Arc::new(ReleaseCache::new(
+                self.release_cache_ttl,
+                Arc::clone(&decision_cache),
+                Arc::clone(&syncs),
+                local_store.clone(),
+                Arc::clone(&memory_budget),
+            )),
+            syncs,
+            decision_cache,
+            gc_log: Arc::new(GcLog::default()),
+            digest_cache: Arc::new(DigestCache::new(Arc::clone(&memory_budget), max_idle)),
+            deb_cache: Arc::new(DebCache::new(Arc::clone(&memory_budget), max_idle)),
+            rpm_cache: Arc::new(RpmCache::new(Arc::clone(&memory_budget), max_idle)),
+            delta_cache: Arc::new(DeltaCache::new(Arc::clone(&memory_budget), max_idle)),
+            notes_cache: Arc::new(NotesCache::new(Arc::clone(&memory_budget), max_idle)),
+            changelog_cache: Arc::new(ChangelogCache::new(Arc::clone(&memory_budget), max_idle)),
+            memory_budget,
+            github_clients: Arc::new(self.github_clients),
+            hostname: self.hostname.into(),
+            maintenance: Arc::new(self.maintenance),
+            kill_switch: Arc::new(self.kill_switch),
+            experiments: Arc::new(Experiments::default()),
+            events: Arc::new(self.events),
+            event_limiter: Arc::new(self.event_limiter),
+            feedback_limiter: Arc::new(self.feedback_limiter),
+            rollout_halts: Arc::new(RolloutHalts::default()),
+            cdn: self.cdn.map(Arc::new),
+            local_store,
+            throttle: self.throttle,
+            download_slots: Arc::new(self.download_slots),
+            leadership: Arc::new(self.leadership),
+            audit: Arc::new(self.audit),
+            recent_errors: Arc::new(RecentErrors::default()),
+            signing_keys: Arc::new(SigningKeys::default()),
+            cors: self.cors.map(Arc::new),
+            compression: self.compression,
+            cache_policy: self.cache_policy,
+            jobs: self.jobs,
+            jobs_started: Arc::new(AtomicBool::new(false)),
         }
     }
 }
+
+#[cfg(test)]
+fn string_map(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    pairs
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+#[test]
+fn test_missing_owner_or_repo() {
+    let settings = string_map(&[("OWNER", "acme")]);
+    let error = ProductConfig::from_settings("token", |s| settings.get(s)).unwrap_err();
+    assert_eq!(error, "both OWNER and REPO must be set");
+}
+
+#[test]
+fn test_invalid_max_version() {
+    let settings = string_map(&[("OWNER", "acme"), ("REPO", "app"), ("MAX_VERSION", "2.x")]);
+    let error = ProductConfig::from_settings("token", |s| settings.get(s)).unwrap_err();
+    assert!(error.starts_with("invalid MAX_VERSION"));
+}
+
+#[test]
+fn test_invalid_upstream_timeout() {
+    let settings = string_map(&[
+        ("OWNER", "acme"),
+        ("REPO", "app"),
+        ("UPSTREAM_TIMEOUT_SECS", "0"),
+    ]);
+    let error = ProductConfig::from_settings("token", |s| settings.get(s)).unwrap_err();
+    assert!(error.starts_with("invalid UPSTREAM_TIMEOUT_SECS"));
+}
+
+#[test]
+fn test_parse_size() {
+    assert_eq!(parse_size("10M"), Some(10 << 20));
+    assert_eq!(parse_size(" 512k "), Some(512 << 10));
+    assert_eq!(parse_size("1000"), Some(1000));
+    assert_eq!(parse_size("0"), None);
+    assert_eq!(parse_size("fast"), None);
+}
+
+#[test]
+fn test_is_update() {
+    let mut product = ProductConfig::new("token", "acme", "app");
+    let version = |v| Version::parse(v).unwrap();
+    assert!(product.is_update(&version("1.2.0+2"), &version("1.2.0+1")));
+    assert!(product.is_update(&version("1.3.0-beta.1"), &version("1.2.0")));
+
+    product.ignore_build_metadata = true;
+    product.stable_skips_prereleases = true;
+    assert!(!product.is_update(&version("1.2.0+2"), &version("1.2.0+1")));
+    assert!(!product.is_update(&version("1.3.0-beta.1"), &version("1.2.0")));
+    assert!(product.is_update(&version("1.3.0-beta.2"), &version("1.3.0-beta.1")));
+}
+
+#[test]
+fn test_allowed_channels() {
+    let mut product = ProductConfig::new("token", "acme", "app");
+    assert!(product.allows_channel("nightly"));
+
+    product.channels = vec!["beta".to_string()];
+    assert!(product.allows_channel("Beta"));
+    assert!(product.allows_channel("stable"));
+    assert!(!product.allows_channel("nightly"));
+}
+
+#[test]
+fn test_channel_aliases() {
+    let mut product = ProductConfig::new("token", "acme", "app");
+    product.channel_aliases = parse_aliases("prod=stable, Insider=beta").unwrap();
+    assert_eq!(product.resolve_channel("PROD"), "stable");
+    assert_eq!(product.resolve_channel("insider"), "beta");
+    assert_eq!(product.resolve_channel("beta"), "beta");
+    assert!(parse_aliases("prod").is_err());
+}
+
+#[test]
+fn test_invalid_product_policy() {
+    assert_eq!(
+        "FAIL".parse::<InvalidProductPolicy>().ok(),
+        Some(InvalidProductPolicy::Fail)
+    );
+    assert!("ignore".parse::<InvalidProductPolicy>().is_err());
+}
+
+#[test]
+fn test_incomplete_products() {
+    let env_vars = string_map(&[
+        ("MYAPP_TOKEN", "token"),
+        ("MYAPP_OWNER", "acme"),
+        ("MYAPP_REPO", "myapp"),
+        ("MYAPP_FALLBACK_REPO", "acme/mirror"),
+        ("TYPO_TOKN", "token"),
+        ("TYPO_OWNER", "acme"),
+        ("TYPO_REPO", "typo"),
+        ("OTHER_REPO", "other"),
+    ]);
+
+    let incomplete = incomplete_products(&env_vars);
+    assert_eq!(incomplete.len(), 2);
+    assert_eq!(incomplete["typo"], "TOKEN is not set");
+    assert_eq!(incomplete["other"], "TOKEN and OWNER are not set");
+}
+
+#[test]
+fn test_fallback_token_is_not_a_product() {
+    let env_vars = string_map(&[
+        ("MYAPP_TOKEN", "token"),
+        ("MYAPP_OWNER", "acme"),
+        ("MYAPP_REPO", "myapp"),
+        ("MYAPP_FALLBACK_REPO", "acme/mirror"),
+        ("MYAPP_FALLBACK_TOKEN", "mirror-token"),
+        ("MYAPP_BETA_TOKEN", "token"),
+        ("MYAPP_BETA_REPO", "acme/myapp-beta"),
+    ]);
+
+    let mut prefixes: Vec<&str> = product_tokens(&env_vars)
+        .into_iter()
+        .map(|(prefix, _)| prefix)
+        .collect();
+    prefixes.sort();
+    assert_eq!(prefixes, ["MYAPP", "MYAPP_BETA"]);
+    assert!(incomplete_products(&env_vars).is_empty());
+
+    let config =
+        ProductConfig::from_settings("token", |s| env_vars.get(&format!("MYAPP_{}", s))).unwrap();
+    let fallback = config.fallback.unwrap();
+    assert_eq!(fallback.repo, "mirror");
+    assert_eq!(fallback.token.as_deref(), Some("mirror-token"));
+}
@@ -0,0 +1,233 @@
+use std::cell::OnceCell;
+use std::env;
+use std::future::{ready, Ready};
+use std::rc::Rc;
+
+use actix_cors::{Cors, CorsMiddleware};
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::guard::{self, GuardContext};
+use actix_web::http::header::ACCESS_CONTROL_REQUEST_METHOD;
+use actix_web::{web, Error, HttpResponse};
+use futures_util::future::{FutureExt, LocalBoxFuture};
+use log::{error, info};
+
+use crate::auth::authenticator::RouteGroup;
+use crate::config::{parse_list, AppState};
+
+const DEFAULT_METHODS: &str = "GET,POST";
+const DEFAULT_MAX_AGE_SECS: usize = 3600;
+
+/// Paths of the server's own routes that aren't under a product's name.
+const SERVER_PREFIXES: [&str; 3] = ["/admin/", "/batch/", "/scoop/"];
+const SERVER_PATHS: [&str; 2] = ["/status", "/version"];
+
+/// Cross-origin access for browser clients such as web dashboards.
+#[derive(Debug, Clone)]
+pub struct CorsConfig {
+    /// Allowed origins; `*` allows any origin.
+    pub allowed_origins: Vec<String>,
+    pub allowed_methods: Vec<String>,
+    /// Allowed request headers; any header when empty.
+    pub allowed_headers: Vec<String>,
+    pub max_age_secs: usize,
+    /// Route groups whose responses carry CORS headers.
+    pub groups: Vec<RouteGroup>,
+}
+
+impl CorsConfig {
+    /// Allows `allowed_origins` to call update routes with `GET` and `POST`
+    /// and any header.
+    pub fn new(allowed_origins: Vec<String>) -> Self {
+        CorsConfig {
+            allowed_origins,
+            allowed_methods: parse_list(DEFAULT_METHODS),
+            allowed_headers: Vec::new(),
+            max_age_secs: DEFAULT_MAX_AGE_SECS,
+            groups: vec![RouteGroup::Update],
+        }
+    }
+
+    /// Reads the `CORS_*` settings; CORS stays disabled unless
+    /// `CORS_ALLOWED_ORIGINS` names at least one valid origin.
+    pub fn from_env() -> Option<Self> {
+        let allowed_origins: Vec<String> = parse_list(&env::var("CORS_ALLOWED_ORIGINS").ok()?)
+            .into_iter()
+            .filter(|origin| {
+                let valid = origin == "*" || reqwest::Url::parse(origin).is_ok();
+                if !valid {
+                    error!("Ignoring invalid CORS origin {}", origin);
+                }
+                valid
+            })
+            .collect();
+        if allowed_origins.is_empty() {
+            return None;
+        }
+
+        let groups = parse_list(&env::var("CORS_ROUTES").unwrap_or_else(|_| "update".to_string()))
+            .into_iter()
+            .filter_map(|name| {
+                let group = RouteGroup::ALL
+                    .into_iter()
+                    .find(|group| group.to_string().eq_ignore_ascii_case(&name));
+                if group.is_none() {
+                    error!("Ignoring unknown CORS route group {}", name);
+                }
+                group
+            })
+            .collect();
+
+        let config = CorsConfig {
+            allowed_origins,
+            allowed_methods: parse_list(
+                &env::var("CORS_ALLOWED_METHODS").unwrap_or_else(|_| DEFAULT_METHODS.to_string()),
+            ),
+            allowed_headers: env::var("CORS_ALLOWED_HEADERS")
+                .map(|v| parse_list(&v))
+                .unwrap_or_default(),
+            max_age_secs: env::var("CORS_MAX_AGE_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_MAX_AGE_SECS),
+            groups,
+        };
+        info!("CORS enabled: {:?}", config);
+        Some(config)
+    }
+
+    fn build(&self) -> Cors {
+        let mut cors = Cors::default()
+            .allowed_methods(self.allowed_methods.iter().map(String::as_str))
+            .max_age(self.max_age_secs);
+
+        for origin in &self.allowed_origins {
+            cors = if origin == "*" {
+                cors.allow_any_origin()
+            } else {
+                cors.allowed_origin(origin)
+            };
+        }
+
+        if self.allowed_headers.is_empty() {
+            cors.allow_any_header()
+        } else {
+            cors.allowed_headers(self.allowed_headers.iter().map(String::as_str))
+        }
+    }
+}
+
+/// CORS middleware for a route group, active when the group is listed in
+/// the [`CorsConfig`] of the app's state.
+pub fn for_group(group: RouteGroup) -> GroupCors {
+    GroupCors { group }
+}
+
+pub struct GroupCors {
+    group: RouteGroup,
+}
+
+impl<S, B> Transform<S, ServiceRequest> for GroupCors
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = GroupCorsMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(GroupCorsMiddleware {
+            group: self.group,
+            service: Rc::new(service),
+            cors: OnceCell::new(),
+        }))
+    }
+}
+
+/// Routes aren't registered with the app's state at hand, so the CORS
+/// middleware is built from it on the first request.
+pub struct GroupCorsMiddleware<S> {
+    group: RouteGroup,
+    service: Rc<S>,
+    cors: OnceCell<Option<CorsMiddleware<Rc<S>>>>,
+}
+
+impl<S, B> Service<ServiceRequest> for GroupCorsMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    actix_web::dev::forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let cors = self.cors.get_or_init(|| {
+            let config = req
+                .app_data::<web::Data<AppState>>()
+                .and_then(|data| data.cors.clone())
+                .filter(|config| config.groups.contains(&self.group))?;
+            match config
+                .build()
+                .new_transform(Rc::clone(&self.service))
+                .now_or_never()
+            {
+                Some(Ok(cors)) => Some(cors),
+                _ => {
+                    error!("Invalid CORS settings for {} routes", self.group);
+                    None
+                }
+            }
+        });
+        match cors {
+            Some(cors) => cors.call(req),
+            None => {
+                let res = self.service.call(req);
+                async move { res.await.map(ServiceResponse::map_into_left_body) }.boxed_local()
+            }
+        }
+    }
+}
+
+/// Answers preflight requests, which don't reach method-guarded routes. Only
+/// the selected route groups add CORS headers to actual responses, so
+/// browsers still refuse to expose any other route to foreign origins.
+/// Preflights for paths the server doesn't serve, e.g. routes of an app it
+/// is embedded in, are left to the app.
+pub fn configure(cfg: &mut web::ServiceConfig, state: &AppState) {
+    let Some(config) = &state.cors else {
+        return;
+    };
+    let state = state.clone();
+    cfg.service(
+        web::resource("/{path:.*}")
+            .guard(guard::Options())
+            .guard(guard::fn_guard(move |ctx| is_own_preflight(ctx, &state)))
+            .wrap(config.build())
+            .to(HttpResponse::NoContent),
+    );
+}
+
+fn is_own_preflight(ctx: &GuardContext, state: &AppState) -> bool {
+    if ctx
+        .head()
+        .headers()
+        .get(ACCESS_CONTROL_REQUEST_METHOD)
+        .is_none()
+    {
+        return false;
+    }
+    let path = ctx.head().uri.path();
+    if SERVER_PATHS.contains(&path) || SERVER_PREFIXES.iter().any(|p| path.starts_with(p)) {
+        return true;
+    }
+    path.trim_start_matches('/')
+        .split('/')
+        .next()
+        .is_some_and(|product| state.product(&product.to_lowercase()).is_some())
+}
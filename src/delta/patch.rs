@@ -0,0 +1,53 @@
+use std::io::{self, Read};
+
+use zstd::stream::{read::Decoder, write::Encoder};
+
+/// Compression level of patches. They're built once in the background, so
+/// size matters more than speed.
+const LEVEL: i32 = 19;
+
+/// Largest window zstd supports on 64-bit targets.
+const MAX_WINDOW_LOG: u32 = 31;
+
+/// Window needed to reference all of `old` while producing `new_len` bytes.
+fn window_log(old: &[u8], new_len: u64) -> u32 {
+    let span = (old.len() as u64 + new_len).max(1).next_power_of_two();
+    span.trailing_zeros().clamp(10, MAX_WINDOW_LOG)
+}
+
+/// Builds a patch that turns `old` into the `new_len` bytes read from `new`:
+/// the new version compressed with `old` as reference, the same format as
+/// `zstd --patch-from`. Clients apply it with
+/// `zstd -d --patch-from=<old> <patch>`.
+pub fn create_patch(old: &[u8], mut new: impl Read, new_len: u64) -> io::Result<Vec<u8>> {
+    let mut encoder = Encoder::with_ref_prefix(Vec::new(), LEVEL, old)?;
+    encoder.window_log(window_log(old, new_len))?;
+    encoder.long_distance_matching(true)?;
+    encoder.set_pledged_src_size(Some(new_len))?;
+    encoder.include_checksum(true)?;
+    io::copy(&mut new, &mut encoder)?;
+    encoder.finish()
+}
+
+/// Rebuilds the new artifact from `old` and a patch made by [`create_patch`].
+pub fn apply_patch(old: &[u8], patch: &[u8]) -> io::Result<Vec<u8>> {
+    let mut decoder = Decoder::with_ref_prefix(patch, old)?;
+    decoder.window_log_max(MAX_WINDOW_LOG)?;
+    let mut new = Vec::new();
+    decoder.read_to_end(&mut new)?;
+    Ok(new)
+}
+
+#[test]
+fn test_patch_roundtrip() {
+    let old: Vec<u8> = (0..200_000u32)
+        .flat_map(|n| (n * 7).to_le_bytes())
+        .collect();
+    let mut new = old.clone();
+    new[1000..1010].copy_from_slice(b"new build!");
+    new.extend_from_slice(b"appended section");
+
+    let patch = create_patch(&old, new.as_slice(), new.len() as u64).unwrap();
+    assert!(patch.len() < new.len() / 100);
+    assert_eq!(apply_patch(&old, &patch).unwrap(), new);
+}
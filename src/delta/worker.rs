@@ -0,0 +1,213 @@
+use std::collections::HashSet;
+use std::env;
+use std::fmt::Display;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use actix_web::rt::time;
+use actix_web::web;
+use bytes::Bytes;
+use futures_util::StreamExt;
+use log::{debug, info, warn};
+use octocrab::models::repos::Asset;
+
+use crate::cache::delta::DeltaPatch;
+use crate::cache::digest::sha256_hex;
+use crate::config::{AppState, ProductConfig};
+use crate::delta::patch::create_patch;
+use crate::error::ServerError;
+use crate::github::client::GitHubClient;
+use crate::logging;
+use crate::platform::matcher::{Platform, PlatformMatcher};
+use crate::release::selection::eligible_releases;
+
+/// How often the worker looks for new releases by default.
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Artifacts above this size are served without a patch; the previous
+/// version is the patch's reference and held in memory while it's built.
+const MAX_ARTIFACT_SIZE: i64 = 512 * 1024 * 1024;
+
+/// Builds patches for every product with delta updates every `interval`,
+/// until the server shuts down. With several instances, only the one
+/// holding the lock builds them; while no product has delta updates, the
+/// lock isn't taken.
+pub async fn run(state: AppState, interval: Duration) {
+    let mut ticker = time::interval(interval);
+    loop {
+        ticker.tick().await;
+        let wanted = state.products.load().values().any(|p| p.delta_updates);
+        if wanted
+            && state
+                .leadership
+                .acquire("delta-patches", interval * 2)
+                .await
+        {
+            refresh(&state).await;
+        }
+    }
+}
+
+/// Builds the patches that are missing for the newest release of each
+/// product and channel, and drops those to artifacts that were superseded.
+pub async fn refresh(state: &AppState) {
+    let mut current = HashSet::new();
+    for (product_name, product_config) in state.products.load().iter() {
+        if !product_config.delta_updates {
+            continue;
+        }
+        let refreshed = logging::scope(
+            product_name,
+            refresh_product(state, product_name, product_config, &mut current),
+        );
+        if let Err(e) = refreshed.await {
+            warn!("Failed to build delta patches for {}: {}", product_name, e);
+            state
+                .recent_errors
+                .record(Some(product_name), "delta_patches", &e);
+        }
+    }
+    state.delta_cache.retain(&current);
+}
+
+async fn refresh_product(
+    state: &AppState,
+    product_name: &str,
+    product_config: &ProductConfig,
+    current: &mut HashSet<u64>,
+) -> Result<(), ServerError> {
+    let github = state.github_client(product_name, product_config)?;
+    let releases = state
+        .release_cache
+        .releases(product_name, &github, product_config)
+        .await?;
+    let matcher = PlatformMatcher::new()
+        .with_asset_prefix(product_config.asset_prefix.clone())
+        .with_asset_pattern(product_config.asset_pattern.clone());
+
+    let channels =
+        std::iter::once("stable").chain(product_config.channels.iter().map(String::as_str));
+    for channel in channels {
+        let eligible = eligible_releases(&releases, product_config, channel);
+        let [(_, newest), (from_version, previous), ..] = eligible.as_slice() else {
+            continue;
+        };
+        let (newest, previous) = (&releases[*newest], &releases[*previous]);
+
+        for platform in Platform::supported() {
+            let (Some(to), Some(from)) = (
+                updater_artifact(&matcher, &platform, &newest.assets, channel),
+                updater_artifact(&matcher, &platform, &previous.assets, channel),
+            ) else {
+                continue;
+            };
+            current.insert(to.id.0);
+            if state.delta_cache.contains(to.id.0) {
+                continue;
+            }
+
+            let patch = if to.size > MAX_ARTIFACT_SIZE || from.size > MAX_ARTIFACT_SIZE {
+                debug!("Not building a patch to {}: too large", to.name);
+                None
+            } else {
+                let old = download(&github, product_config, from).await?;
+                let new = download(&github, product_config, to).await?;
+                let built = web::block(move || {
+                    let old_data = fs::read(&old.0)?;
+                    let new_len = new.0.metadata()?.len();
+                    create_patch(&old_data, File::open(&new.0)?, new_len)
+                })
+                .await
+                .map_err(|e| e.to_string())
+                .and_then(|patch| patch.map_err(|e| e.to_string()));
+                match built {
+                    Ok(data) => {
+                        info!(
+                            "Built patch from {} to {} ({} of {} bytes)",
+                            from.name,
+                            to.name,
+                            data.len(),
+                            to.size
+                        );
+                        Some(DeltaPatch {
+                            from_version: from_version.clone(),
+                            from_asset_id: from.id.0,
+                            to_asset_id: to.id.0,
+                            sha256: sha256_hex(&data),
+                            data: Bytes::from(data),
+                        })
+                    }
+                    Err(e) => {
+                        warn!("Failed to build patch to {}: {}", to.name, e);
+                        None
+                    }
+                }
+            };
+            state.delta_cache.insert(to.id.0, patch);
+        }
+    }
+    Ok(())
+}
+
+/// An artifact downloaded for building a patch, deleted when dropped.
+struct TempArtifact(PathBuf);
+
+impl Drop for TempArtifact {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+/// Streams an artifact to a temporary file, so only the patch's reference
+/// is held in memory while it's built.
+async fn download(
+    github: &GitHubClient,
+    product_config: &ProductConfig,
+    asset: &Asset,
+) -> Result<TempArtifact, ServerError> {
+    let path = env::temp_dir().join(format!("delta-{}-{}", std::process::id(), asset.id.0));
+    let artifact = TempArtifact(path.clone());
+    let mut file = web::block(move || File::create(path))
+        .await
+        .map_err(temp_dir_error)?
+        .map_err(temp_dir_error)?;
+
+    let response = github
+        .open_asset(
+            asset.id.0,
+            &product_config.repo_owner,
+            &product_config.repo_name,
+            None,
+        )
+        .await?;
+    let mut chunks = response.bytes_stream();
+    while let Some(chunk) = chunks.next().await {
+        let chunk = chunk?;
+        file = web::block(move || file.write_all(&chunk).map(|_| file))
+            .await
+            .map_err(temp_dir_error)?
+            .map_err(temp_dir_error)?;
+    }
+    Ok(artifact)
+}
+
+fn temp_dir_error(cause: impl Display) -> ServerError {
+    ServerError::config("Failed to write to the temporary directory", cause)
+}
+
+/// The updater artifact of a release for `platform`, as served in update
+/// responses.
+fn updater_artifact<'a>(
+    matcher: &PlatformMatcher,
+    platform: &Platform,
+    assets: &'a [Asset],
+    channel: &str,
+) -> Option<&'a Asset> {
+    let names: Vec<&str> = assets.iter().map(|a| a.name.as_str()).collect();
+    let found = matcher
+        .find_matching_asset(platform, &names, Some(channel), None)
+        .ok()?;
+    assets.iter().find(|a| a.name == found.filename)
+}
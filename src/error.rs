@@ -0,0 +1,161 @@
+use std::fmt::Display;
+
+use actix_web::http::header::RETRY_AFTER;
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, ResponseError};
+use log::error;
+
+use crate::response::error::ErrorEnvelope;
+
+/// Failures while serving a request, each with its own status and error code.
+#[derive(Debug, thiserror::Error)]
+pub enum ServerError {
+    #[error("Product {0} not found")]
+    ProductNotFound(String),
+    #[error("Unknown channel {channel} for product {product}")]
+    UnknownChannel { product: String, channel: String },
+    #[error("Feedback is not enabled for product {0}")]
+    FeedbackDisabled(String),
+    #[error("No experiment is running for product {0}")]
+    ExperimentNotFound(String),
+    #[error("No updater public key is configured for product {0}")]
+    NoPublicKey(String),
+    #[error("No eligible release found")]
+    NoEligibleRelease,
+    #[error("Release {0} not found")]
+    ReleaseNotFound(String),
+    #[error("Asset {0} not found")]
+    AssetNotFound(String),
+    #[error("Asset {0} exceeds the maximum size served")]
+    AssetTooLarge(u64),
+    #[error("Download link is invalid or expired")]
+    InvalidDownloadToken,
+    #[error("Not licensed for updates: {0}")]
+    LicenseDenied(String),
+    /// The release's signature file isn't an updater signature; the details
+    /// are logged.
+    #[error("Signature {0} is not a valid updater signature")]
+    InvalidSignature(String),
+    #[error("Invalid version in release tag {0}")]
+    VersionParse(String),
+    /// A product or client setting that can't be used; the details are logged.
+    #[error("{0}")]
+    Config(&'static str),
+    /// GitHub failed or couldn't be reached; the details are logged.
+    #[error("{0}")]
+    Upstream(&'static str),
+    #[error("{0}: not found on GitHub")]
+    UpstreamNotFound(&'static str),
+    #[error("GitHub rate limit exceeded")]
+    RateLimited { retry_after_secs: u64 },
+    #[error("Update server is in maintenance mode")]
+    Maintenance { retry_after_secs: u64 },
+    #[error("Updates of product {0} are paused")]
+    Paused(String),
+    #[error("Too many downloads in progress")]
+    Overloaded { retry_after_secs: u64 },
+    #[error("Too many requests from this address")]
+    TooManyRequests { retry_after_secs: u64 },
+}
+
+impl ServerError {
+    /// Logs `cause` and returns a [`ServerError::Config`] that doesn't expose it.
+    pub fn config(message: &'static str, cause: impl Display) -> Self {
+        error!("{}: {}", message, cause);
+        ServerError::Config(message)
+    }
+
+    /// Logs `cause` and returns a [`ServerError::Upstream`] that doesn't expose it.
+    pub fn upstream(message: &'static str, cause: impl Display) -> Self {
+        error!("{}: {}", message, cause);
+        ServerError::Upstream(message)
+    }
+
+    /// Stable, machine-readable code reported in error responses.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ServerError::ProductNotFound(_) => "product_not_found",
+            ServerError::UnknownChannel { .. } => "unknown_channel",
+            ServerError::FeedbackDisabled(_) => "feedback_disabled",
+            ServerError::ExperimentNotFound(_) => "experiment_not_found",
+            ServerError::NoPublicKey(_) => "no_public_key",
+            ServerError::NoEligibleRelease => "no_eligible_release",
+            ServerError::ReleaseNotFound(_) => "release_not_found",
+            ServerError::AssetNotFound(_) => "asset_not_found",
+            ServerError::AssetTooLarge(_) => "asset_too_large",
+            ServerError::InvalidDownloadToken => "invalid_download_token",
+            ServerError::LicenseDenied(_) => "license_denied",
+            ServerError::InvalidSignature(_) => "invalid_signature",
+            ServerError::VersionParse(_) => "invalid_release_version",
+            ServerError::Config(_) => "config_error",
+            ServerError::Upstream(_) => "upstream_error",
+            ServerError::UpstreamNotFound(_) => "upstream_not_found",
+            ServerError::RateLimited { .. } => "rate_limited",
+            ServerError::Maintenance { .. } => "maintenance",
+            ServerError::Paused(_) => "paused",
+            ServerError::Overloaded { .. } => "overloaded",
+            ServerError::TooManyRequests { .. } => "too_many_requests",
+        }
+    }
+}
+
+impl ResponseError for ServerError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ServerError::ProductNotFound(_)
+            | ServerError::UnknownChannel { .. }
+            | ServerError::FeedbackDisabled(_)
+            | ServerError::ExperimentNotFound(_)
+            | ServerError::NoPublicKey(_)
+            | ServerError::NoEligibleRelease
+            | ServerError::ReleaseNotFound(_)
+            | ServerError::AssetNotFound(_)
+            | ServerError::UpstreamNotFound(_) => StatusCode::NOT_FOUND,
+            ServerError::VersionParse(_) | ServerError::Config(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+            ServerError::Upstream(_)
+            | ServerError::AssetTooLarge(_)
+            | ServerError::InvalidSignature(_) => StatusCode::BAD_GATEWAY,
+            ServerError::InvalidDownloadToken | ServerError::LicenseDenied(_) => {
+                StatusCode::FORBIDDEN
+            }
+            ServerError::RateLimited { .. } | ServerError::TooManyRequests { .. } => {
+                StatusCode::TOO_MANY_REQUESTS
+            }
+            ServerError::Maintenance { .. }
+            | ServerError::Paused(_)
+            | ServerError::Overloaded { .. } => StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut res = HttpResponse::build(self.status_code());
+        if let ServerError::Maintenance { retry_after_secs }
+        | ServerError::RateLimited { retry_after_secs }
+        | ServerError::Overloaded { retry_after_secs }
+        | ServerError::TooManyRequests { retry_after_secs } = self
+        {
+            res.insert_header((RETRY_AFTER, retry_after_secs.to_string()));
+        }
+        res.json(ErrorEnvelope {
+            code: self.code().into(),
+            message: self.to_string().into(),
+            request_id: None,
+        })
+    }
+}
+
+#[test]
+fn test_server_error_response() {
+    let res = ServerError::Maintenance {
+        retry_after_secs: 120,
+    }
+    .error_response();
+    assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(res.headers().get(RETRY_AFTER).unwrap(), "120");
+
+    let error = ServerError::Upstream("Failed to list releases");
+    assert_eq!(error.status_code(), StatusCode::BAD_GATEWAY);
+    assert_eq!(error.code(), "upstream_error");
+}
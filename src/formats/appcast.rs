@@ -0,0 +1,137 @@
+use std::fmt::Write;
+
+use chrono::{DateTime, Utc};
+
+use crate::formats::package::escape_xml;
+use crate::platform::matcher::{contains_ignore_case, ends_with_ignore_case};
+
+/// One release in a Sparkle appcast.
+#[derive(Debug, Clone)]
+pub struct AppcastItem {
+    pub title: String,
+    pub version: String,
+    pub pub_date: Option<DateTime<Utc>>,
+    /// Release notes in HTML or Markdown, embedded as CDATA.
+    pub notes: Option<String>,
+    /// Download URL of the archive.
+    pub url: String,
+    pub length: u64,
+}
+
+/// Whether `name` is an archive Sparkle can install from.
+pub fn is_macos_archive(name: &str) -> bool {
+    ends_with_ignore_case(name, ".dmg")
+        || ends_with_ignore_case(name, ".app.tar.gz")
+        || ends_with_ignore_case(name, ".app.zip")
+        || (ends_with_ignore_case(name, ".zip")
+            && ["mac", "darwin", "osx"]
+                .iter()
+                .any(|marker| contains_ignore_case(name, marker)))
+}
+
+/// Whether `name` is marked as a build for `arch` (`x86_64`, `aarch64` or `universal`).
+pub fn has_arch_marker(name: &str, arch: &str) -> bool {
+    let markers: &[&str] = match arch {
+        "x86_64" => &["_x64", "x86_64", "intel"],
+        "aarch64" => &["aarch64", "arm64"],
+        "universal" => &["universal"],
+        _ => &[],
+    };
+    markers
+        .iter()
+        .any(|marker| contains_ignore_case(name, marker))
+}
+
+/// Picks the archive for `arch` among `archives`, falling back to a
+/// universal build. Without an arch, universal builds are preferred.
+pub fn pick_archive<'a>(archives: &[&'a str], arch: Option<&str>) -> Option<&'a str> {
+    let find = |arch: &str| {
+        archives
+            .iter()
+            .copied()
+            .find(|name| has_arch_marker(name, arch))
+    };
+    match arch {
+        Some(arch) => find(arch).or_else(|| find("universal")),
+        None => find("universal").or_else(|| archives.first().copied()),
+    }
+}
+
+/// Renders a Sparkle appcast feed with the given items, newest first.
+pub fn render_appcast(title: &str, link: &str, items: &[AppcastItem]) -> String {
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+         <rss version=\"2.0\" xmlns:sparkle=\"http://www.andymatuschak.org/xml-namespaces/sparkle\">\n\
+         <channel>\n",
+    );
+    let _ = writeln!(xml, "<title>{}</title>", escape_xml(title));
+    let _ = writeln!(xml, "<link>{}</link>", escape_xml(link));
+
+    for item in items {
+        xml.push_str("<item>\n");
+        let _ = writeln!(xml, "<title>{}</title>", escape_xml(&item.title));
+        if let Some(pub_date) = item.pub_date {
+            let _ = writeln!(xml, "<pubDate>{}</pubDate>", pub_date.to_rfc2822());
+        }
+        let version = escape_xml(&item.version);
+        let _ = writeln!(xml, "<sparkle:version>{}</sparkle:version>", version);
+        let _ = writeln!(
+            xml,
+            "<sparkle:shortVersionString>{}</sparkle:shortVersionString>",
+            version
+        );
+        if let Some(notes) = &item.notes {
+            let _ = writeln!(
+                xml,
+                "<description><![CDATA[{}]]></description>",
+                notes.replace("]]>", "]]]]><![CDATA[>")
+            );
+        }
+        let _ = writeln!(
+            xml,
+            "<enclosure url=\"{}\" length=\"{}\" type=\"application/octet-stream\"/>",
+            escape_xml(&item.url),
+            item.length
+        );
+        xml.push_str("</item>\n");
+    }
+
+    xml.push_str("</channel>\n</rss>\n");
+    xml
+}
+
+#[test]
+fn test_appcast_rendering() {
+    let archives = ["App_x64.app.tar.gz", "App_universal.app.tar.gz"];
+    assert_eq!(
+        pick_archive(&archives, None),
+        Some("App_universal.app.tar.gz")
+    );
+    assert_eq!(
+        pick_archive(&archives, Some("x86_64")),
+        Some("App_x64.app.tar.gz")
+    );
+    assert_eq!(
+        pick_archive(&archives, Some("aarch64")),
+        Some("App_universal.app.tar.gz")
+    );
+    assert!(is_macos_archive("App_1.0.0_aarch64.dmg"));
+    assert!(!is_macos_archive("App_1.0.0_x64.app.tar.gz.sig"));
+
+    let xml = render_appcast(
+        "myapp",
+        "https://updates.example.com/myapp/appcast.xml",
+        &[AppcastItem {
+            title: "Version 1.2.0".to_string(),
+            version: "1.2.0".to_string(),
+            pub_date: None,
+            notes: Some("Fixes <b>&</b> ]]> more".to_string()),
+            url: "https://updates.example.com/myapp/download/7/App & Co.dmg".to_string(),
+            length: 42,
+        }],
+    );
+    assert!(xml.contains("<sparkle:version>1.2.0</sparkle:version>"));
+    assert!(xml.contains("url=\"https://updates.example.com/myapp/download/7/App &amp; Co.dmg\""));
+    assert!(xml.contains("length=\"42\""));
+    assert!(xml.contains("]]]]><![CDATA[> more"));
+}
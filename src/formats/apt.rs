@@ -0,0 +1,115 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use chrono::{DateTime, Utc};
+
+use crate::cache::digest::sha256_hex;
+
+/// The only component of the generated repositories.
+pub const COMPONENT: &str = "main";
+
+/// A `.deb` package as listed in a `Packages` index.
+#[derive(Debug, Clone)]
+pub struct DebPackage {
+    /// The package's `control` paragraph.
+    pub control: String,
+    /// [`sha256_hex`] of the `.deb`.
+    pub sha256: String,
+    pub size: u64,
+}
+
+impl DebPackage {
+    /// The value of a `control` field, e.g. `Architecture`.
+    pub fn field(&self, name: &str) -> Option<&str> {
+        self.control.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            key.eq_ignore_ascii_case(name).then(|| value.trim())
+        })
+    }
+
+    /// The package's paragraph in a `Packages` index, pointing at `filename`
+    /// relative to the repository root.
+    pub fn index_entry(&self, filename: &str) -> String {
+        let mut entry = self.control.trim_end().to_string();
+        let _ = write!(
+            entry,
+            "\nFilename: {}\nSize: {}\nSHA256: {}\n",
+            filename, self.size, self.sha256
+        );
+        entry
+    }
+}
+
+/// The `Packages` indexes of one suite, by architecture.
+pub type PackageIndexes = BTreeMap<String, String>;
+
+/// Path of an architecture's `Packages` index within the suite.
+pub fn index_path(arch: &str) -> String {
+    format!("{}/binary-{}/Packages", COMPONENT, arch)
+}
+
+/// Metadata of a suite, rendered as its `Release` file.
+pub struct AptRelease<'a> {
+    pub suite: &'a str,
+    pub origin: &'a str,
+    pub label: &'a str,
+    pub description: &'a str,
+    pub date: DateTime<Utc>,
+    pub indexes: &'a PackageIndexes,
+}
+
+impl AptRelease<'_> {
+    pub fn render(&self) -> String {
+        let architectures: Vec<&str> = self.indexes.keys().map(String::as_str).collect();
+        let mut release = String::new();
+        let _ = writeln!(release, "Origin: {}", self.origin);
+        let _ = writeln!(release, "Label: {}", self.label);
+        let _ = writeln!(release, "Suite: {}", self.suite);
+        let _ = writeln!(release, "Codename: {}", self.suite);
+        let _ = writeln!(
+            release,
+            "Date: {}",
+            self.date.format("%a, %d %b %Y %H:%M:%S UTC")
+        );
+        let _ = writeln!(release, "Architectures: {}", architectures.join(" "));
+        let _ = writeln!(release, "Components: {}", COMPONENT);
+        let _ = writeln!(release, "Description: {}", self.description);
+        release.push_str("SHA256:\n");
+        for (arch, index) in self.indexes {
+            let hash = sha256_hex(index.as_bytes());
+            let _ = writeln!(release, " {} {} {}", hash, index.len(), index_path(arch));
+        }
+        release
+    }
+}
+
+#[test]
+fn test_apt_indexes() {
+    let package = DebPackage {
+        control: "Package: myapp\nVersion: 1.2.0\nArchitecture: amd64\nDescription: My app\n"
+            .to_string(),
+        sha256: "ab12".to_string(),
+        size: 42,
+    };
+    assert_eq!(package.field("architecture"), Some("amd64"));
+    assert_eq!(
+        package.index_entry("pool/7/myapp_1.2.0_amd64.deb"),
+        "Package: myapp\nVersion: 1.2.0\nArchitecture: amd64\nDescription: My app\n\
+         Filename: pool/7/myapp_1.2.0_amd64.deb\nSize: 42\nSHA256: ab12\n"
+    );
+
+    let indexes = PackageIndexes::from([("amd64".to_string(), String::new())]);
+    let release = AptRelease {
+        suite: "stable",
+        origin: "acme",
+        label: "myapp",
+        description: "My app",
+        date: DateTime::from_timestamp(0, 0).unwrap(),
+        indexes: &indexes,
+    }
+    .render();
+    assert!(release.contains("Date: Thu, 01 Jan 1970 00:00:00 UTC\n"));
+    assert!(release.ends_with(
+        "SHA256:\n e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855 0 main/binary-amd64/Packages\n"
+    ));
+}
@@ -0,0 +1,161 @@
+use std::io::Read;
+
+/// Largest control archive unpacked. Control archives hold a few small text
+/// files; anything bigger is most likely a decompression bomb.
+const MAX_CONTROL_ARCHIVE_SIZE: u64 = 1024 * 1024;
+
+/// Reads the `control` file of a `.deb` package: the package's name,
+/// version, architecture, dependencies and description.
+pub fn read_control(deb: &[u8]) -> Result<String, String> {
+    let (name, member) = ar_members(deb)?
+        .into_iter()
+        .find(|(name, _)| name.starts_with("control.tar"))
+        .ok_or("no control archive")?;
+
+    let tar = match name.strip_prefix("control.tar") {
+        Some("") => unpack(&name, member)?,
+        Some(".gz") => unpack(&name, flate2::read::GzDecoder::new(member))?,
+        Some(".zst") => unpack(
+            &name,
+            zstd::Decoder::new(member).map_err(|e| format!("invalid {}: {}", name, e))?,
+        )?,
+        _ => return Err(format!("unsupported control archive {}", name)),
+    };
+
+    let control = tar_file(&tar, "control").ok_or("no control file")?;
+    String::from_utf8(control.to_vec()).map_err(|_| "control file is not UTF-8".to_string())
+}
+
+/// Reads the control archive `name`, up to [`MAX_CONTROL_ARCHIVE_SIZE`].
+fn unpack(name: &str, reader: impl Read) -> Result<Vec<u8>, String> {
+    let mut tar = Vec::new();
+    reader
+        .take(MAX_CONTROL_ARCHIVE_SIZE + 1)
+        .read_to_end(&mut tar)
+        .map_err(|e| format!("invalid {}: {}", name, e))?;
+    if tar.len() as u64 > MAX_CONTROL_ARCHIVE_SIZE {
+        return Err(format!(
+            "{} unpacks to more than {} bytes",
+            name, MAX_CONTROL_ARCHIVE_SIZE
+        ));
+    }
+    Ok(tar)
+}
+
+/// Members of an `ar` archive, the container format of `.deb` files.
+fn ar_members(archive: &[u8]) -> Result<Vec<(String, &[u8])>, String> {
+    let mut rest = archive
+        .strip_prefix(b"!<arch>\n")
+        .ok_or("not a deb archive")?;
+    let mut members = Vec::new();
+
+    while rest.len() >= 60 {
+        let header = &rest[..60];
+        let name = String::from_utf8_lossy(&header[..16])
+            .trim_end()
+            .trim_end_matches('/')
+            .to_string();
+        let size: usize = std::str::from_utf8(&header[48..58])
+            .ok()
+            .and_then(|s| s.trim().parse().ok())
+            .ok_or("invalid ar member size")?;
+        let data = rest.get(60..60 + size).ok_or("truncated ar member")?;
+        members.push((name, data));
+        // Members are padded to an even length
+        rest = rest.get(60 + size + size % 2..).unwrap_or_default();
+    }
+
+    Ok(members)
+}
+
+/// The contents of `path` in a tar archive, ignoring any leading `./`.
+fn tar_file<'a>(tar: &'a [u8], path: &str) -> Option<&'a [u8]> {
+    let field = |bytes: &[u8]| {
+        let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+        String::from_utf8_lossy(&bytes[..end]).to_string()
+    };
+
+    let mut offset = 0;
+    while offset + 512 <= tar.len() {
+        let header = &tar[offset..offset + 512];
+        if header.iter().all(|b| *b == 0) {
+            break;
+        }
+        let prefix = field(&header[345..500]);
+        let name = match field(&header[..100]) {
+            name if prefix.is_empty() => name,
+            name => format!("{}/{}", prefix, name),
+        };
+        let size = usize::from_str_radix(field(&header[124..136]).trim(), 8).ok()?;
+        let data = offset + 512;
+        if name.trim_start_matches("./") == path {
+            return tar.get(data..data + size);
+        }
+        offset = data + size.div_ceil(512) * 512;
+    }
+    None
+}
+
+#[cfg(test)]
+fn tar_entry(name: &str, data: &[u8]) -> Vec<u8> {
+    let mut header = [0u8; 512];
+    header[..name.len()].copy_from_slice(name.as_bytes());
+    let size = format!("{:011o}\0", data.len());
+    header[124..136].copy_from_slice(size.as_bytes());
+    let mut entry = header.to_vec();
+    entry.extend_from_slice(data);
+    entry.resize(512 + data.len().div_ceil(512) * 512, 0);
+    entry
+}
+
+#[cfg(test)]
+fn deb_with(control_archive: &[u8]) -> Vec<u8> {
+    let mut deb = b"!<arch>\n".to_vec();
+    for (name, data) in [
+        ("debian-binary", &b"2.0\n"[..]),
+        ("control.tar.gz", control_archive),
+    ] {
+        deb.extend(
+            format!(
+                "{:<16}{:<12}{:<6}{:<6}{:<8}{:<10}`\n",
+                name,
+                0,
+                0,
+                0,
+                100644,
+                data.len()
+            )
+            .as_bytes(),
+        );
+        deb.extend_from_slice(data);
+        if data.len() % 2 == 1 {
+            deb.push(b'\n');
+        }
+    }
+    deb
+}
+
+#[cfg(test)]
+fn gzip(data: &[u8]) -> Vec<u8> {
+    use std::io::Write;
+
+    let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+    gz.write_all(data).unwrap();
+    gz.finish().unwrap()
+}
+
+#[test]
+fn test_read_control() {
+    let control = "Package: myapp\nVersion: 1.2.0\nArchitecture: amd64\n";
+    let mut tar = tar_entry("./", b"");
+    tar.extend(tar_entry("./control", control.as_bytes()));
+    tar.extend([0; 1024]);
+
+    assert_eq!(read_control(&deb_with(&gzip(&tar))).unwrap(), control);
+    assert!(read_control(b"not a deb").is_err());
+
+    // A small archive that unpacks to gigabytes is refused early
+    let bomb = gzip(&vec![0; 2 * MAX_CONTROL_ARCHIVE_SIZE as usize]);
+    let err = read_control(&deb_with(&bomb)).unwrap_err();
+    assert!(err.contains("unpacks to more than"), "{}", err);
+}
@@ -0,0 +1,129 @@
+use std::fmt::Write;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use crate::formats::appcast::is_macos_archive;
+use crate::formats::package::yaml_string;
+use crate::platform::matcher::ends_with_ignore_case;
+
+/// Platform of an electron-updater feed, named by its file suffix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ElectronPlatform {
+    /// `{channel}.yml`, NSIS installers.
+    Windows,
+    /// `{channel}-mac.yml`, zip archives and disk images.
+    Mac,
+    /// `{channel}-linux.yml`, AppImages.
+    Linux,
+}
+
+impl ElectronPlatform {
+    /// The matching update-check target, e.g. `darwin`.
+    pub fn target(self) -> &'static str {
+        match self {
+            ElectronPlatform::Windows => "windows",
+            ElectronPlatform::Mac => "darwin",
+            ElectronPlatform::Linux => "linux",
+        }
+    }
+
+    /// Whether `name` is an artifact electron-updater installs on this platform.
+    pub fn accepts(self, name: &str) -> bool {
+        match self {
+            ElectronPlatform::Windows => ends_with_ignore_case(name, ".exe"),
+            // electron-updater can't install the `.app.tar.gz` Tauri produces
+            ElectronPlatform::Mac => {
+                is_macos_archive(name) && !ends_with_ignore_case(name, ".app.tar.gz")
+            }
+            ElectronPlatform::Linux => ends_with_ignore_case(name, ".appimage"),
+        }
+    }
+}
+
+/// Splits a feed file name such as `beta-mac.yml` into its channel and
+/// platform. electron-updater calls the stable channel `latest`.
+pub fn parse_feed_name(file: &str) -> Option<(String, ElectronPlatform)> {
+    let name = file.strip_suffix(".yml")?;
+    let (channel, platform) = if let Some(channel) = name.strip_suffix("-mac") {
+        (channel, ElectronPlatform::Mac)
+    } else if let Some(channel) = name.strip_suffix("-linux") {
+        (channel, ElectronPlatform::Linux)
+    } else {
+        (name, ElectronPlatform::Windows)
+    };
+
+    match channel {
+        "" => None,
+        "latest" => Some(("stable".to_string(), platform)),
+        channel => Some((channel.to_string(), platform)),
+    }
+}
+
+/// One downloadable file of an electron-updater feed.
+#[derive(Debug, Clone)]
+pub struct ElectronFile {
+    pub url: String,
+    /// Base64 SHA-512 of the file.
+    pub sha512: String,
+    pub size: u64,
+}
+
+/// Renders an electron-updater `latest.yml`. The first file is also
+/// listed as the legacy top-level `path`.
+pub fn render_latest_yml(
+    version: &str,
+    files: &[ElectronFile],
+    release_date: Option<DateTime<Utc>>,
+    notes: Option<&str>,
+) -> String {
+    let mut yml = format!("version: {}\nfiles:\n", yaml_string(version));
+    for file in files {
+        let _ = writeln!(yml, "  - url: {}", yaml_string(&file.url));
+        let _ = writeln!(yml, "    sha512: {}", yaml_string(&file.sha512));
+        let _ = writeln!(yml, "    size: {}", file.size);
+    }
+    if let Some(file) = files.first() {
+        let _ = writeln!(yml, "path: {}", yaml_string(&file.url));
+        let _ = writeln!(yml, "sha512: {}", yaml_string(&file.sha512));
+    }
+    if let Some(date) = release_date {
+        let _ = writeln!(
+            yml,
+            "releaseDate: {}",
+            yaml_string(&date.to_rfc3339_opts(SecondsFormat::Millis, true))
+        );
+    }
+    if let Some(notes) = notes {
+        let _ = writeln!(yml, "releaseNotes: {}", yaml_string(notes));
+    }
+    yml
+}
+
+#[test]
+fn test_electron_feed() {
+    assert_eq!(
+        parse_feed_name("latest.yml"),
+        Some(("stable".to_string(), ElectronPlatform::Windows))
+    );
+    assert_eq!(
+        parse_feed_name("beta-mac.yml"),
+        Some(("beta".to_string(), ElectronPlatform::Mac))
+    );
+    assert_eq!(parse_feed_name("-linux.yml"), None);
+    assert!(ElectronPlatform::Mac.accepts("App-1.2.0-arm64-mac.zip"));
+    assert!(!ElectronPlatform::Windows.accepts("App_1.2.0_x64-setup.exe.sig"));
+
+    let yml = render_latest_yml(
+        "1.2.0",
+        &[ElectronFile {
+            url: "https://updates.example.com/myapp/download/7/App-Setup.exe".to_string(),
+            sha512: "c2hh".to_string(),
+            size: 42,
+        }],
+        None,
+        Some("Line one\n\"quoted\""),
+    );
+    assert!(yml.starts_with("version: \"1.2.0\"\nfiles:\n  - url: \"https://"));
+    assert!(yml.contains("    size: 42\npath: \"https://"));
+    assert!(yml.contains("releaseNotes: \"Line one\\n\\\"quoted\\\"\""));
+}
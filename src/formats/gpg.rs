@@ -0,0 +1,47 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Runs `gpg` with `args`, feeding it `input`, and returns its output.
+/// Signing keys live in the keyring of the user running the server.
+fn gpg(args: &[&str], input: &[u8]) -> Result<Vec<u8>, String> {
+    let mut child = Command::new("gpg")
+        .args(["--batch", "--yes", "--armor"])
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("failed to run gpg: {}", e))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(input)
+            .map_err(|e| format!("failed to write to gpg: {}", e))?;
+    }
+
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("failed to run gpg: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "gpg failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(output.stdout)
+}
+
+/// `data` with an inline signature by `key`, as in an APT `InRelease` file.
+pub fn clearsign(key: &str, data: &[u8]) -> Result<Vec<u8>, String> {
+    gpg(&["--local-user", key, "--clearsign"], data)
+}
+
+/// An armored detached signature of `data` by `key`.
+pub fn detach_sign(key: &str, data: &[u8]) -> Result<Vec<u8>, String> {
+    gpg(&["--local-user", key, "--detach-sign"], data)
+}
+
+/// The armored public key of `key`.
+pub fn export_public_key(key: &str) -> Result<Vec<u8>, String> {
+    gpg(&["--export", key], b"")
+}
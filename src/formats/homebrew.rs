@@ -0,0 +1,176 @@
+use std::fmt::Write;
+
+use crate::formats::appcast::has_arch_marker;
+use crate::platform::matcher::ends_with_ignore_case;
+
+/// Which Macs a cask artifact is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaskArch {
+    /// Any Mac, e.g. a universal build.
+    Any,
+    Arm,
+    Intel,
+}
+
+/// The archives a cask should install, preferring disk images: a universal
+/// build if there is one, otherwise one per architecture.
+pub fn pick_cask_archives<'a>(archives: &[&'a str]) -> Vec<(CaskArch, &'a str)> {
+    let mut sorted = archives.to_vec();
+    sorted.sort_by_key(|name| !ends_with_ignore_case(name, ".dmg"));
+    let find = |arch: &str| {
+        sorted
+            .iter()
+            .copied()
+            .find(|name| has_arch_marker(name, arch))
+    };
+
+    if let Some(universal) = find("universal") {
+        return vec![(CaskArch::Any, universal)];
+    }
+    let builds: Vec<(CaskArch, &str)> = [
+        (CaskArch::Arm, find("aarch64")),
+        (CaskArch::Intel, find("x86_64")),
+    ]
+    .into_iter()
+    .filter_map(|(arch, name)| Some((arch, name?)))
+    .collect();
+    if !builds.is_empty() {
+        return builds;
+    }
+    sorted
+        .first()
+        .map(|name| vec![(CaskArch::Any, *name)])
+        .unwrap_or_default()
+}
+
+/// The `.app` bundle inside a Tauri archive, e.g. `MyApp.app` for
+/// `MyApp_1.2.0_aarch64.dmg`.
+pub fn app_bundle(archive: &str) -> String {
+    let stem = archive.split(['_', '.']).next().unwrap_or(archive);
+    format!("{}.app", stem)
+}
+
+/// One downloadable archive of a cask.
+#[derive(Debug, Clone)]
+pub struct CaskArtifact {
+    pub arch: CaskArch,
+    pub url: String,
+    /// Checked by Homebrew after downloading the archive.
+    pub sha256: String,
+}
+
+/// A Homebrew cask definition.
+#[derive(Debug, Clone)]
+pub struct Cask {
+    pub token: String,
+    pub version: String,
+    pub name: String,
+    pub desc: String,
+    pub homepage: String,
+    pub app: String,
+    pub artifacts: Vec<CaskArtifact>,
+    /// Appcast that `brew livecheck` can read new versions from.
+    pub livecheck_url: Option<String>,
+}
+
+/// Quotes text as a Ruby string literal without interpolation.
+fn ruby_string(text: &str) -> String {
+    let escaped = text
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace("#{", "\\#{");
+    format!("\"{}\"", escaped)
+}
+
+impl Cask {
+    pub fn render(&self) -> String {
+        let mut rb = format!("cask {} do\n", ruby_string(&self.token));
+        let _ = writeln!(rb, "  version {}", ruby_string(&self.version));
+
+        match self.artifacts.as_slice() {
+            [artifact] => {
+                let _ = writeln!(rb, "  sha256 {}", ruby_string(&artifact.sha256));
+                let _ = writeln!(rb, "\n  url {}", ruby_string(&artifact.url));
+            }
+            artifacts => {
+                for artifact in artifacts {
+                    let block = match artifact.arch {
+                        CaskArch::Arm => "on_arm",
+                        CaskArch::Intel => "on_intel",
+                        CaskArch::Any => continue,
+                    };
+                    let _ = writeln!(rb, "\n  {} do", block);
+                    let _ = writeln!(rb, "    sha256 {}", ruby_string(&artifact.sha256));
+                    let _ = writeln!(rb, "    url {}", ruby_string(&artifact.url));
+                    rb.push_str("  end\n");
+                }
+            }
+        }
+
+        let _ = writeln!(rb, "\n  name {}", ruby_string(&self.name));
+        let _ = writeln!(rb, "  desc {}", ruby_string(&self.desc));
+        let _ = writeln!(rb, "  homepage {}", ruby_string(&self.homepage));
+
+        if let Some(url) = &self.livecheck_url {
+            let _ = write!(
+                rb,
+                "\n  livecheck do\n    url {}\n    strategy :sparkle\n  end\n",
+                ruby_string(url)
+            );
+        }
+
+        // An arch-specific build that has no counterpart
+        match self.artifacts.as_slice() {
+            [CaskArtifact {
+                arch: CaskArch::Arm,
+                ..
+            }] => rb.push_str("\n  depends_on arch: :arm64\n"),
+            [CaskArtifact {
+                arch: CaskArch::Intel,
+                ..
+            }] => rb.push_str("\n  depends_on arch: :x86_64\n"),
+            _ => {}
+        }
+
+        let _ = writeln!(rb, "\n  app {}", ruby_string(&self.app));
+        rb.push_str("end\n");
+        rb
+    }
+}
+
+#[test]
+fn test_homebrew_cask() {
+    let archives = [
+        "MyApp_x64.app.tar.gz",
+        "MyApp_1.2.0_x64.dmg",
+        "MyApp_1.2.0_aarch64.dmg",
+    ];
+    assert_eq!(
+        pick_cask_archives(&archives),
+        vec![
+            (CaskArch::Arm, "MyApp_1.2.0_aarch64.dmg"),
+            (CaskArch::Intel, "MyApp_1.2.0_x64.dmg"),
+        ]
+    );
+    assert_eq!(app_bundle("MyApp_1.2.0_x64.dmg"), "MyApp.app");
+
+    let cask = Cask {
+        token: "myapp".to_string(),
+        version: "1.2.0".to_string(),
+        name: "My \"App\"".to_string(),
+        desc: "Does #{things}".to_string(),
+        homepage: "https://example.com".to_string(),
+        app: "MyApp.app".to_string(),
+        artifacts: vec![CaskArtifact {
+            arch: CaskArch::Arm,
+            url: "https://updates.example.com/myapp/download/1/MyApp.dmg".to_string(),
+            sha256: "ab12".to_string(),
+        }],
+        livecheck_url: None,
+    };
+    let rb = cask.render();
+    assert!(rb.starts_with("cask \"myapp\" do\n  version \"1.2.0\"\n  sha256 \"ab12\"\n"));
+    assert!(rb.contains("  name \"My \\\"App\\\"\"\n  desc \"Does \\#{things}\"\n"));
+    assert!(rb.contains("depends_on arch: :arm64"));
+    assert!(rb.ends_with("  app \"MyApp.app\"\nend\n"));
+}
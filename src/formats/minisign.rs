@@ -0,0 +1,166 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ring::signature::{UnparsedPublicKey, ED25519};
+
+/// An updater signature, as `tauri signer sign` writes it to `.sig` files: a
+/// minisign signature file, base64 encoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature {
+    /// Id of the key that made the signature.
+    pub key_id: [u8; 8],
+    /// Signature of the file, or of its BLAKE2b hash if `prehashed`.
+    pub signature: [u8; 64],
+    pub prehashed: bool,
+    pub trusted_comment: String,
+    /// Signature of `signature` followed by `trusted_comment`.
+    pub global_signature: [u8; 64],
+}
+
+impl Signature {
+    /// Parses the contents of a `.sig` file, so e.g. an HTML error page or a
+    /// truncated download isn't passed on as a signature.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let decoded = STANDARD
+            .decode(text.trim())
+            .map_err(|e| format!("not base64: {}", e))?;
+        let file = String::from_utf8(decoded).map_err(|_| "not text".to_string())?;
+
+        let mut lines = file.lines();
+        let mut line = |name: &str, prefix: &str| {
+            lines
+                .next()
+                .and_then(|line| line.strip_prefix(prefix))
+                .ok_or_else(|| format!("missing {}", name))
+        };
+        line("untrusted comment", "untrusted comment:")?;
+        let signature = decode::<74>(line("signature", "")?)?;
+        let trusted_comment = line("trusted comment", "trusted comment: ")?.to_string();
+        let global_signature = decode::<64>(line("global signature", "")?)?;
+
+        let prehashed = match &signature[..2] {
+            b"Ed" => false,
+            b"ED" => true,
+            _ => return Err("unknown signature algorithm".to_string()),
+        };
+        let mut key_id = [0; 8];
+        key_id.copy_from_slice(&signature[2..10]);
+        let mut signature_bytes = [0; 64];
+        signature_bytes.copy_from_slice(&signature[10..]);
+
+        Ok(Signature {
+            key_id,
+            signature: signature_bytes,
+            prehashed,
+            trusted_comment,
+            global_signature,
+        })
+    }
+
+    /// The key id as minisign and `tauri signer` print it.
+    pub fn key_id_hex(&self) -> String {
+        format!("{:016X}", u64::from_le_bytes(self.key_id))
+    }
+
+    /// Whether `key` made this signature. Only the global signature over
+    /// the file's signature and trusted comment is checked, which doesn't
+    /// need the signed file.
+    pub fn verify(&self, key: &PublicKey) -> bool {
+        let message = [self.signature.as_slice(), self.trusted_comment.as_bytes()].concat();
+        self.key_id == key.key_id
+            && UnparsedPublicKey::new(&ED25519, key.key)
+                .verify(&message, &self.global_signature)
+                .is_ok()
+    }
+}
+
+/// An updater public key, as `tauri signer generate` writes it and
+/// `tauri.conf.json` expects it: a minisign public key file, base64 encoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublicKey {
+    pub key_id: [u8; 8],
+    pub key: [u8; 32],
+}
+
+impl PublicKey {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let decoded = STANDARD
+            .decode(text.trim())
+            .map_err(|e| format!("not base64: {}", e))?;
+        let file = String::from_utf8(decoded).map_err(|_| "not text".to_string())?;
+
+        let mut lines = file.lines();
+        if !lines
+            .next()
+            .is_some_and(|line| line.starts_with("untrusted comment:"))
+        {
+            return Err("missing untrusted comment".to_string());
+        }
+        let key = decode::<42>(lines.next().ok_or("missing key")?)?;
+        if &key[..2] != b"Ed" {
+            return Err("unknown key algorithm".to_string());
+        }
+
+        let mut key_id = [0; 8];
+        key_id.copy_from_slice(&key[2..10]);
+        let mut key_bytes = [0; 32];
+        key_bytes.copy_from_slice(&key[10..]);
+        Ok(PublicKey {
+            key_id,
+            key: key_bytes,
+        })
+    }
+
+    /// The key id as minisign and `tauri signer` print it.
+    pub fn key_id_hex(&self) -> String {
+        format!("{:016X}", u64::from_le_bytes(self.key_id))
+    }
+}
+
+/// Decodes a base64 line of exactly `N` bytes.
+fn decode<const N: usize>(line: &str) -> Result<[u8; N], String> {
+    STANDARD
+        .decode(line.trim())
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| format!("expected {} bytes of base64", N))
+}
+
+#[test]
+fn test_parse_signature() {
+    let file = format!(
+        "untrusted comment: signature from tauri secret key\n{}\ntrusted comment: timestamp:1700000000\tfile:MyApp.msi\n{}\n",
+        STANDARD.encode([b"ED".as_slice(), &[1, 2, 3, 4, 5, 6, 7, 8], &[9; 64]].concat()),
+        STANDARD.encode([7; 64]),
+    );
+    let signature = Signature::parse(&format!("{}\n", STANDARD.encode(&file))).unwrap();
+    assert!(signature.prehashed);
+    assert_eq!(signature.key_id_hex(), "0807060504030201");
+    assert_eq!(signature.signature, [9; 64]);
+    assert_eq!(
+        signature.trusted_comment,
+        "timestamp:1700000000\tfile:MyApp.msi"
+    );
+
+    assert!(Signature::parse("<!DOCTYPE html><html>").is_err());
+    assert!(Signature::parse(&STANDARD.encode("<!DOCTYPE html>")).is_err());
+    let truncated = file.lines().take(3).collect::<Vec<_>>().join("\n");
+    assert!(Signature::parse(&STANDARD.encode(truncated)).is_err());
+}
+
+#[test]
+fn test_parse_public_key() {
+    let file = |algorithm: &[u8]| {
+        let key = [algorithm, &[1, 2, 3, 4, 5, 6, 7, 8], &[3; 32]].concat();
+        let file = format!(
+            "untrusted comment: minisign public key: 0807060504030201\n{}\n",
+            STANDARD.encode(key)
+        );
+        STANDARD.encode(file)
+    };
+    let key = PublicKey::parse(&file(b"Ed")).unwrap();
+    assert_eq!(key.key_id_hex(), "0807060504030201");
+    assert_eq!(key.key, [3; 32]);
+
+    assert!(PublicKey::parse(&file(b"XX")).is_err());
+    assert!(PublicKey::parse("RWQBAgMEBQYHCAMDAwMDAwMD").is_err());
+}
@@ -0,0 +1,14 @@
+pub mod appcast;
+pub mod apt;
+pub mod deb;
+pub mod electron;
+pub mod gpg;
+pub mod homebrew;
+pub mod minisign;
+pub mod nuget;
+pub mod package;
+pub mod rpm;
+pub mod scoop;
+pub mod winget;
+pub mod yum;
+pub mod zip;
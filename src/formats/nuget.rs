@@ -0,0 +1,283 @@
+use std::fmt::Write;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::{DateTime, SecondsFormat, Utc};
+use ring::digest::{digest, SHA512};
+use semver::Version;
+
+use crate::formats::package::escape_xml;
+use crate::formats::zip::stored_zip;
+
+/// A NuGet version for `version`. NuGet ignores build metadata, so
+/// Windows-style `1.2.3+4` is written as `1.2.3.4`.
+pub fn nuget_version(version: &Version) -> String {
+    let revision = version.build.as_str();
+    if version.pre.is_empty()
+        && !revision.is_empty()
+        && revision.bytes().all(|b| b.is_ascii_digit())
+    {
+        format!(
+            "{}.{}.{}.{}",
+            version.major, version.minor, version.patch, revision
+        )
+    } else {
+        let mut version = version.clone();
+        version.build = semver::BuildMetadata::EMPTY;
+        version.to_string()
+    }
+}
+
+/// A Windows installer run by the Chocolatey install script.
+#[derive(Debug, Clone)]
+pub struct ChocolateyInstaller {
+    pub url: String,
+    /// Checked by the install script before running the installer.
+    pub sha256: String,
+    /// `msi` or `exe` (NSIS).
+    pub file_type: &'static str,
+}
+
+/// A Chocolatey package wrapping a release's installers.
+#[derive(Debug, Clone)]
+pub struct NugetPackage {
+    pub id: String,
+    pub version: String,
+    pub title: String,
+    pub authors: String,
+    pub description: String,
+    pub project_url: String,
+    pub published: Option<DateTime<Utc>>,
+    pub installer_x64: Option<ChocolateyInstaller>,
+    pub installer_x86: Option<ChocolateyInstaller>,
+}
+
+impl NugetPackage {
+    pub fn nuspec(&self) -> String {
+        format!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+             <package xmlns=\"http://schemas.microsoft.com/packaging/2015/06/nuspec.xsd\">\n\
+             <metadata>\n\
+             <id>{}</id>\n\
+             <version>{}</version>\n\
+             <title>{}</title>\n\
+             <authors>{}</authors>\n\
+             <projectUrl>{}</projectUrl>\n\
+             <description>{}</description>\n\
+             </metadata>\n\
+             </package>\n",
+            escape_xml(&self.id),
+            escape_xml(&self.version),
+            escape_xml(&self.title),
+            escape_xml(&self.authors),
+            escape_xml(&self.project_url),
+            escape_xml(&self.description),
+        )
+    }
+
+    /// `tools/chocolateyInstall.ps1`, which downloads and verifies the
+    /// installer from this server and runs it silently.
+    pub fn install_script(&self) -> String {
+        let quote = |s: &str| format!("'{}'", s.replace('\'', "''"));
+        let primary = self.installer_x64.as_ref().or(self.installer_x86.as_ref());
+        let file_type = primary.map_or("msi", |i| i.file_type);
+
+        let mut ps1 = String::from("$ErrorActionPreference = 'Stop'\n\n$packageArgs = @{\n");
+        ps1.push_str("  packageName    = $env:ChocolateyPackageName\n");
+        let _ = writeln!(ps1, "  fileType       = {}", quote(file_type));
+        if let Some(installer) = &self.installer_x86 {
+            let _ = writeln!(ps1, "  url            = {}", quote(&installer.url));
+            let _ = writeln!(ps1, "  checksum       = {}", quote(&installer.sha256));
+            ps1.push_str("  checksumType   = 'sha256'\n");
+        }
+        if let Some(installer) = &self.installer_x64 {
+            let _ = writeln!(ps1, "  url64bit       = {}", quote(&installer.url));
+            let _ = writeln!(ps1, "  checksum64     = {}", quote(&installer.sha256));
+            ps1.push_str("  checksumType64 = 'sha256'\n");
+        }
+        let silent_args = if file_type == "msi" {
+            "/qn /norestart"
+        } else {
+            "/S"
+        };
+        let _ = writeln!(ps1, "  silentArgs     = {}", quote(silent_args));
+        ps1.push_str(
+            "  validExitCodes = @(0, 3010, 1641)\n}\n\nInstall-ChocolateyPackage @packageArgs\n",
+        );
+        ps1
+    }
+
+    /// The `.nupkg` archive. It is deterministic, so its hash is stable.
+    pub fn nupkg(&self) -> Vec<u8> {
+        let nuspec = self.nuspec();
+        let script = self.install_script();
+        let nuspec_name = format!("{}.nuspec", self.id);
+        let content_types = "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+             <Types xmlns=\"http://schemas.openxmlformats.org/package/2006/content-types\">\n\
+             <Default Extension=\"rels\" ContentType=\"application/vnd.openxmlformats-package.relationships+xml\"/>\n\
+             <Default Extension=\"nuspec\" ContentType=\"application/octet\"/>\n\
+             <Default Extension=\"ps1\" ContentType=\"application/octet\"/>\n\
+             </Types>\n";
+        let rels = format!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+             <Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">\n\
+             <Relationship Type=\"http://schemas.microsoft.com/packaging/2010/07/manifest\" Target=\"/{}\" Id=\"R1\"/>\n\
+             </Relationships>\n",
+            escape_xml(&nuspec_name)
+        );
+
+        stored_zip(&[
+            ("[Content_Types].xml", content_types.as_bytes()),
+            ("_rels/.rels", rels.as_bytes()),
+            (&nuspec_name, nuspec.as_bytes()),
+            ("tools/chocolateyInstall.ps1", script.as_bytes()),
+        ])
+    }
+}
+
+/// The feed's service document, listing its `Packages` collection.
+pub fn service_document(base: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+         <service xml:base=\"{}/\" xmlns=\"http://www.w3.org/2007/app\" xmlns:atom=\"http://www.w3.org/2005/Atom\">\n\
+         <workspace><atom:title>Default</atom:title>\
+         <collection href=\"Packages\"><atom:title>Packages</atom:title></collection></workspace>\n\
+         </service>\n",
+        escape_xml(base)
+    )
+}
+
+/// The feed's `$metadata`, describing the package properties it serves.
+pub const METADATA: &str = "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+<edmx:Edmx Version=\"1.0\" xmlns:edmx=\"http://schemas.microsoft.com/ado/2007/06/edmx\">\n\
+<edmx:DataServices m:DataServiceVersion=\"2.0\" xmlns:m=\"http://schemas.microsoft.com/ado/2007/08/dataservices/metadata\">\n\
+<Schema Namespace=\"NuGetGallery\" xmlns=\"http://schemas.microsoft.com/ado/2006/04/edm\">\n\
+<EntityType Name=\"V2FeedPackage\" m:HasStream=\"true\">\n\
+<Key><PropertyRef Name=\"Id\"/><PropertyRef Name=\"Version\"/></Key>\n\
+<Property Name=\"Id\" Type=\"Edm.String\" Nullable=\"false\"/>\n\
+<Property Name=\"Version\" Type=\"Edm.String\" Nullable=\"false\"/>\n\
+<Property Name=\"NormalizedVersion\" Type=\"Edm.String\"/>\n\
+<Property Name=\"Title\" Type=\"Edm.String\"/>\n\
+<Property Name=\"Authors\" Type=\"Edm.String\"/>\n\
+<Property Name=\"Description\" Type=\"Edm.String\"/>\n\
+<Property Name=\"ProjectUrl\" Type=\"Edm.String\"/>\n\
+<Property Name=\"IsLatestVersion\" Type=\"Edm.Boolean\" Nullable=\"false\"/>\n\
+<Property Name=\"IsAbsoluteLatestVersion\" Type=\"Edm.Boolean\" Nullable=\"false\"/>\n\
+<Property Name=\"IsPrerelease\" Type=\"Edm.Boolean\" Nullable=\"false\"/>\n\
+<Property Name=\"Published\" Type=\"Edm.DateTime\" Nullable=\"false\"/>\n\
+<Property Name=\"PackageHash\" Type=\"Edm.String\"/>\n\
+<Property Name=\"PackageHashAlgorithm\" Type=\"Edm.String\"/>\n\
+<Property Name=\"PackageSize\" Type=\"Edm.Int64\" Nullable=\"false\"/>\n\
+</EntityType>\n\
+<EntityContainer Name=\"FeedContext_x0060_1\" m:IsDefaultEntityContainer=\"true\">\n\
+<EntitySet Name=\"Packages\" EntityType=\"NuGetGallery.V2FeedPackage\"/>\n\
+</EntityContainer>\n\
+</Schema>\n\
+</edmx:DataServices>\n\
+</edmx:Edmx>\n";
+
+/// An Atom feed of `packages`, whose `.nupkg` files are served at
+/// `{base}/package/{id}/{version}`.
+pub fn render_feed(base: &str, packages: &[NugetPackage]) -> String {
+    let mut xml = format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+         <feed xml:base=\"{base}/\" xmlns=\"http://www.w3.org/2005/Atom\" \
+         xmlns:d=\"http://schemas.microsoft.com/ado/2007/08/dataservices\" \
+         xmlns:m=\"http://schemas.microsoft.com/ado/2007/08/dataservices/metadata\">\n\
+         <id>{base}/Packages</id>\n\
+         <title type=\"text\">Packages</title>\n\
+         <updated>{updated}</updated>\n\
+         <link rel=\"self\" title=\"Packages\" href=\"Packages\"/>\n",
+        base = escape_xml(base),
+        updated = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+    );
+
+    for package in packages {
+        let nupkg = package.nupkg();
+        let hash = STANDARD.encode(digest(&SHA512, &nupkg));
+        let published = package
+            .published
+            .unwrap_or_default()
+            .to_rfc3339_opts(SecondsFormat::Secs, true);
+        let id = escape_xml(&package.id);
+        let version = escape_xml(&package.version);
+        let prerelease = package.version.contains('-');
+
+        let _ = write!(
+            xml,
+            "<entry>\n\
+             <id>{base}/Packages(Id='{id}',Version='{version}')</id>\n\
+             <title type=\"text\">{id}</title>\n\
+             <updated>{published}</updated>\n\
+             <author><name>{authors}</name></author>\n\
+             <content type=\"application/zip\" src=\"{base}/package/{id}/{version}\"/>\n\
+             <m:properties>\n\
+             <d:Id>{id}</d:Id>\n\
+             <d:Version>{version}</d:Version>\n\
+             <d:NormalizedVersion>{version}</d:NormalizedVersion>\n\
+             <d:Title>{title}</d:Title>\n\
+             <d:Authors>{authors}</d:Authors>\n\
+             <d:Description>{description}</d:Description>\n\
+             <d:ProjectUrl>{project_url}</d:ProjectUrl>\n\
+             <d:IsLatestVersion m:type=\"Edm.Boolean\">{latest}</d:IsLatestVersion>\n\
+             <d:IsAbsoluteLatestVersion m:type=\"Edm.Boolean\">true</d:IsAbsoluteLatestVersion>\n\
+             <d:IsPrerelease m:type=\"Edm.Boolean\">{prerelease}</d:IsPrerelease>\n\
+             <d:Published m:type=\"Edm.DateTime\">{published}</d:Published>\n\
+             <d:PackageHash>{hash}</d:PackageHash>\n\
+             <d:PackageHashAlgorithm>SHA512</d:PackageHashAlgorithm>\n\
+             <d:PackageSize m:type=\"Edm.Int64\">{size}</d:PackageSize>\n\
+             </m:properties>\n\
+             </entry>\n",
+            base = escape_xml(base),
+            title = escape_xml(&package.title),
+            authors = escape_xml(&package.authors),
+            description = escape_xml(&package.description),
+            project_url = escape_xml(&package.project_url),
+            latest = !prerelease,
+            size = nupkg.len(),
+        );
+    }
+
+    xml.push_str("</feed>\n");
+    xml
+}
+
+#[test]
+fn test_nuget_package() {
+    assert_eq!(
+        nuget_version(&Version::parse("1.2.3+4").unwrap()),
+        "1.2.3.4"
+    );
+    assert_eq!(
+        nuget_version(&Version::parse("1.2.3-beta.1+sha.5").unwrap()),
+        "1.2.3-beta.1"
+    );
+
+    let package = NugetPackage {
+        id: "myapp".to_string(),
+        version: "1.2.0".to_string(),
+        title: "MyApp".to_string(),
+        authors: "acme".to_string(),
+        description: "My app".to_string(),
+        project_url: "https://github.com/acme/myapp".to_string(),
+        published: None,
+        installer_x64: Some(ChocolateyInstaller {
+            url: "https://updates.example.com/myapp/download/1/MyApp_x64.msi".to_string(),
+            sha256: "ab12".to_string(),
+            file_type: "msi",
+        }),
+        installer_x86: None,
+    };
+
+    let script = package.install_script();
+    assert!(script.contains(
+        "  url64bit       = 'https://updates.example.com/myapp/download/1/MyApp_x64.msi'\n"
+    ));
+    assert!(script.contains("  silentArgs     = '/qn /norestart'\n"));
+    assert!(!script.contains("  url            ="));
+
+    assert_eq!(package.nupkg(), package.nupkg());
+    let feed = render_feed("https://updates.example.com/myapp/nuget", &[package]);
+    assert!(feed.contains("src=\"https://updates.example.com/myapp/nuget/package/myapp/1.2.0\""));
+    assert!(feed.contains("<d:PackageHashAlgorithm>SHA512</d:PackageHashAlgorithm>"));
+}
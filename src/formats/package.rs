@@ -0,0 +1,99 @@
+use octocrab::models::repos::Asset;
+use serde::Deserialize;
+
+use crate::auth::download_token::with_download_token;
+use crate::config::ProductConfig;
+
+/// How a product is presented in package manager manifests.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct PackageMetadata {
+    /// Display name; the repository name by default.
+    pub name: String,
+    /// The repository owner by default.
+    pub publisher: String,
+    pub description: Option<String>,
+    /// The GitHub repository page by default.
+    pub homepage: String,
+    /// SPDX identifier or license name.
+    pub license: Option<String>,
+    /// winget package identifier; `Publisher.Name` by default.
+    pub winget_id: Option<String>,
+}
+
+impl PackageMetadata {
+    /// Metadata derived from the repository alone.
+    pub fn for_repo(owner: &str, repo: &str) -> Self {
+        PackageMetadata {
+            name: repo.to_string(),
+            publisher: owner.to_string(),
+            description: None,
+            homepage: format!("https://github.com/{}/{}", owner, repo),
+            license: None,
+            winget_id: None,
+        }
+    }
+
+    pub fn description(&self) -> &str {
+        self.description.as_deref().unwrap_or(&self.name)
+    }
+
+    pub fn license(&self) -> &str {
+        self.license.as_deref().unwrap_or("Proprietary")
+    }
+
+    pub fn winget_id(&self) -> String {
+        self.winget_id.clone().unwrap_or_else(|| {
+            let part = |s: &str| {
+                s.chars()
+                    .filter(|c| c.is_ascii_alphanumeric())
+                    .collect::<String>()
+            };
+            format!("{}.{}", part(&self.publisher), part(&self.name))
+        })
+    }
+}
+
+/// URL of `asset` through the server's download route.
+pub fn download_url(hostname: &str, product: &str, asset: &Asset) -> String {
+    format!(
+        "{}/{}/download/{}/{}",
+        hostname, product, asset.id.0, asset.name
+    )
+}
+
+/// [`download_url`] with a download token, if the product requires one.
+pub fn download_url_with_token(
+    hostname: &str,
+    product_config: &ProductConfig,
+    product: &str,
+    asset: &Asset,
+) -> String {
+    with_download_token(
+        download_url(hostname, product, asset),
+        product_config,
+        product,
+        asset.id.0,
+    )
+}
+
+/// Escapes text for use in XML content and attribute values.
+pub fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Quotes text as a YAML scalar. JSON strings are valid double-quoted YAML,
+/// which keeps newlines and special characters intact.
+pub fn yaml_string(text: &str) -> String {
+    serde_json::Value::from(text).to_string()
+}
@@ -0,0 +1,354 @@
+/// Size of the obsolete lead preceding an RPM's headers.
+const LEAD_SIZE: usize = 96;
+const HEADER_MAGIC: &[u8] = &[0x8e, 0xad, 0xe8, 0x01];
+
+const TAG_NAME: u32 = 1000;
+const TAG_VERSION: u32 = 1001;
+const TAG_RELEASE: u32 = 1002;
+const TAG_EPOCH: u32 = 1003;
+const TAG_SUMMARY: u32 = 1004;
+const TAG_DESCRIPTION: u32 = 1005;
+const TAG_BUILDTIME: u32 = 1006;
+const TAG_BUILDHOST: u32 = 1007;
+const TAG_SIZE: u32 = 1009;
+const TAG_VENDOR: u32 = 1011;
+const TAG_LICENSE: u32 = 1014;
+const TAG_PACKAGER: u32 = 1015;
+const TAG_GROUP: u32 = 1016;
+const TAG_URL: u32 = 1020;
+const TAG_ARCH: u32 = 1022;
+const TAG_SOURCERPM: u32 = 1044;
+const TAG_ARCHIVESIZE: u32 = 1046;
+const TAG_PROVIDENAME: u32 = 1047;
+const TAG_REQUIREFLAGS: u32 = 1048;
+const TAG_REQUIRENAME: u32 = 1049;
+const TAG_REQUIREVERSION: u32 = 1050;
+const TAG_PROVIDEFLAGS: u32 = 1112;
+const TAG_PROVIDEVERSION: u32 = 1113;
+const TAG_DIRINDEXES: u32 = 1116;
+const TAG_BASENAMES: u32 = 1117;
+const TAG_DIRNAMES: u32 = 1118;
+
+/// `rpmlib(...)` requirements, which repodata leaves out.
+const SENSE_RPMLIB: u32 = 1 << 24;
+
+/// A `provides` or `requires` entry of a package.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RpmDependency {
+    pub name: String,
+    /// `EQ`, `LT`, `GE`, ... when the dependency is versioned.
+    pub flags: Option<&'static str>,
+    pub epoch: Option<String>,
+    pub version: Option<String>,
+    pub release: Option<String>,
+}
+
+/// The header data of an `.rpm` package that repodata lists.
+#[derive(Debug, Clone, Default)]
+pub struct RpmPackage {
+    pub name: String,
+    pub epoch: u32,
+    pub version: String,
+    pub release: String,
+    pub arch: String,
+    pub summary: String,
+    pub description: String,
+    pub packager: String,
+    pub url: String,
+    pub license: String,
+    pub vendor: String,
+    pub group: String,
+    pub buildhost: String,
+    pub sourcerpm: String,
+    pub build_time: u64,
+    pub installed_size: u64,
+    pub archive_size: u64,
+    /// Byte range of the main header within the file.
+    pub header_range: (usize, usize),
+    pub provides: Vec<RpmDependency>,
+    pub requires: Vec<RpmDependency>,
+    pub files: Vec<String>,
+}
+
+/// The index entries and data store of an RPM header.
+struct Header<'a> {
+    entries: Vec<(u32, u32, usize, usize)>,
+    store: &'a [u8],
+}
+
+impl<'a> Header<'a> {
+    /// Parses the header at the start of `data`, returning it and its length.
+    fn parse(data: &'a [u8]) -> Result<(Self, usize), String> {
+        if !data.starts_with(HEADER_MAGIC) || data.len() < 16 {
+            return Err("invalid rpm header".to_string());
+        }
+        let be32 =
+            |at: usize| u32::from_be_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]]);
+        let count = be32(8) as usize;
+        let store_size = be32(12) as usize;
+        let store_start = 16 + count * 16;
+        let end = store_start + store_size;
+        if data.len() < end {
+            return Err("truncated rpm header".to_string());
+        }
+
+        let entries = (0..count)
+            .map(|i| {
+                let at = 16 + i * 16;
+                (
+                    be32(at),
+                    be32(at + 4),
+                    be32(at + 8) as usize,
+                    be32(at + 12) as usize,
+                )
+            })
+            .collect();
+        Ok((
+            Header {
+                entries,
+                store: &data[store_start..end],
+            },
+            end,
+        ))
+    }
+
+    fn entry(&self, tag: u32) -> Option<(u32, usize, usize)> {
+        self.entries
+            .iter()
+            .find(|(t, ..)| *t == tag)
+            .map(|(_, kind, offset, count)| (*kind, *offset, *count))
+    }
+
+    /// The strings of a string, string array or i18n string entry; only the
+    /// first translation of the latter.
+    fn strings(&self, tag: u32) -> Vec<String> {
+        let Some((kind, offset, count)) = self.entry(tag) else {
+            return Vec::new();
+        };
+        let count = match kind {
+            6 | 9 => 1,
+            8 => count,
+            _ => return Vec::new(),
+        };
+        self.store
+            .get(offset..)
+            .unwrap_or_default()
+            .split(|b| *b == 0)
+            .take(count)
+            .map(|s| String::from_utf8_lossy(s).into_owned())
+            .collect()
+    }
+
+    fn string(&self, tag: u32) -> String {
+        self.strings(tag).into_iter().next().unwrap_or_default()
+    }
+
+    /// The values of an int32 or int64 entry.
+    fn ints(&self, tag: u32) -> Vec<u64> {
+        let Some((kind, offset, count)) = self.entry(tag) else {
+            return Vec::new();
+        };
+        let width = match kind {
+            4 => 4,
+            5 => 8,
+            _ => return Vec::new(),
+        };
+        self.store
+            .get(offset..offset + width * count)
+            .unwrap_or_default()
+            .chunks(width)
+            .map(|bytes| bytes.iter().fold(0, |n, b| n << 8 | u64::from(*b)))
+            .collect()
+    }
+
+    fn int(&self, tag: u32) -> u64 {
+        self.ints(tag).first().copied().unwrap_or_default()
+    }
+
+    fn dependencies(&self, names: u32, flags: u32, versions: u32) -> Vec<RpmDependency> {
+        let flags = self.ints(flags);
+        let versions = self.strings(versions);
+        self.strings(names)
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| flags.get(*i).is_none_or(|f| *f as u32 & SENSE_RPMLIB == 0))
+            .map(|(i, name)| {
+                let flags = flags.get(i).and_then(|f| sense_flags(*f as u32));
+                let (epoch, version, release) = match versions.get(i) {
+                    Some(evr) if flags.is_some() && !evr.is_empty() => split_evr(evr),
+                    _ => (None, None, None),
+                };
+                RpmDependency {
+                    name,
+                    flags,
+                    epoch,
+                    version,
+                    release,
+                }
+            })
+            .collect()
+    }
+}
+
+/// The comparison of a versioned dependency, from its `RPMSENSE_*` bits.
+fn sense_flags(flags: u32) -> Option<&'static str> {
+    match flags & 0b1110 {
+        0b0010 => Some("LT"),
+        0b0100 => Some("GT"),
+        0b1000 => Some("EQ"),
+        0b1010 => Some("LE"),
+        0b1100 => Some("GE"),
+        _ => None,
+    }
+}
+
+/// Splits `epoch:version-release`, where epoch and release are optional.
+fn split_evr(evr: &str) -> (Option<String>, Option<String>, Option<String>) {
+    let (epoch, rest) = match evr.split_once(':') {
+        Some((epoch, rest)) => (epoch.to_string(), rest),
+        None => ("0".to_string(), evr),
+    };
+    let (version, release) = match rest.rsplit_once('-') {
+        Some((version, release)) => (version, Some(release.to_string())),
+        None => (rest, None),
+    };
+    (Some(epoch), Some(version.to_string()), release)
+}
+
+/// Whether a file is listed in primary metadata rather than only in
+/// filelists, as createrepo does for executables and configuration.
+fn is_primary_file(path: &str) -> bool {
+    path.starts_with("/etc/") || path.contains("bin/") || path == "/usr/lib/sendmail"
+}
+
+/// Reads the header of an `.rpm` package.
+pub fn read_package(rpm: &[u8]) -> Result<RpmPackage, String> {
+    if !rpm.starts_with(&[0xed, 0xab, 0xee, 0xdb]) || rpm.len() < LEAD_SIZE {
+        return Err("not an rpm package".to_string());
+    }
+    let (_, signature_len) = Header::parse(&rpm[LEAD_SIZE..])?;
+    // The signature header is padded to a multiple of 8 bytes
+    let start = LEAD_SIZE + signature_len.div_ceil(8) * 8;
+    let (header, header_len) = Header::parse(rpm.get(start..).unwrap_or_default())?;
+
+    let dirnames = header.strings(TAG_DIRNAMES);
+    let files = header
+        .strings(TAG_BASENAMES)
+        .into_iter()
+        .zip(header.ints(TAG_DIRINDEXES))
+        .filter_map(|(base, dir)| Some(format!("{}{}", dirnames.get(dir as usize)?, base)))
+        .filter(|path| is_primary_file(path))
+        .collect();
+
+    let package = RpmPackage {
+        name: header.string(TAG_NAME),
+        epoch: header.int(TAG_EPOCH) as u32,
+        version: header.string(TAG_VERSION),
+        release: header.string(TAG_RELEASE),
+        arch: header.string(TAG_ARCH),
+        summary: header.string(TAG_SUMMARY),
+        description: header.string(TAG_DESCRIPTION),
+        packager: header.string(TAG_PACKAGER),
+        url: header.string(TAG_URL),
+        license: header.string(TAG_LICENSE),
+        vendor: header.string(TAG_VENDOR),
+        group: header.string(TAG_GROUP),
+        buildhost: header.string(TAG_BUILDHOST),
+        sourcerpm: header.string(TAG_SOURCERPM),
+        build_time: header.int(TAG_BUILDTIME),
+        installed_size: header.int(TAG_SIZE),
+        archive_size: header.int(TAG_ARCHIVESIZE),
+        header_range: (start, start + header_len),
+        provides: header.dependencies(TAG_PROVIDENAME, TAG_PROVIDEFLAGS, TAG_PROVIDEVERSION),
+        requires: header.dependencies(TAG_REQUIRENAME, TAG_REQUIREFLAGS, TAG_REQUIREVERSION),
+        files,
+    };
+    if package.name.is_empty() || package.version.is_empty() {
+        return Err("rpm header has no name or version".to_string());
+    }
+    Ok(package)
+}
+
+#[cfg(test)]
+fn header(entries: &[(u32, u32, u32, &[u8])]) -> Vec<u8> {
+    let mut index = Vec::new();
+    let mut store = Vec::new();
+    for (tag, kind, count, data) in entries {
+        // Integers are aligned to their width
+        while *kind == 4 && store.len() % 4 != 0 {
+            store.push(0);
+        }
+        for n in [*tag, *kind, store.len() as u32, *count] {
+            index.extend(n.to_be_bytes());
+        }
+        store.extend_from_slice(data);
+    }
+    let mut header = HEADER_MAGIC.to_vec();
+    header.extend([0; 4]);
+    header.extend((entries.len() as u32).to_be_bytes());
+    header.extend((store.len() as u32).to_be_bytes());
+    header.extend(index);
+    header.extend(store);
+    header
+}
+
+#[test]
+fn test_read_package() {
+    let mut rpm = vec![0xed, 0xab, 0xee, 0xdb];
+    rpm.resize(LEAD_SIZE, 0);
+    rpm.extend(header(&[(1000, 7, 3, b"abc")]));
+    rpm.resize(rpm.len().div_ceil(8) * 8, 0);
+    let start = rpm.len();
+    rpm.extend(header(&[
+        (TAG_NAME, 6, 1, b"myapp\0"),
+        (TAG_VERSION, 6, 1, b"1.2.0\0"),
+        (TAG_RELEASE, 6, 1, b"1\0"),
+        (TAG_ARCH, 6, 1, b"x86_64\0"),
+        (TAG_SUMMARY, 9, 1, b"My app\0"),
+        (TAG_SIZE, 4, 1, &1234u32.to_be_bytes()),
+        (
+            TAG_REQUIRENAME,
+            8,
+            2,
+            b"libc.so.6()(64bit)\0rpmlib(PayloadIsZstd)\0",
+        ),
+        (TAG_REQUIREFLAGS, 4, 2, &[0, 0, 0, 0, 1, 0, 0, 8]),
+        (TAG_REQUIREVERSION, 8, 2, b"\x005.4.18-1\0"),
+        (TAG_PROVIDENAME, 8, 1, b"myapp\0"),
+        (TAG_PROVIDEFLAGS, 4, 1, &8u32.to_be_bytes()),
+        (TAG_PROVIDEVERSION, 8, 1, b"1.2.0-1\0"),
+        (TAG_BASENAMES, 8, 2, b"myapp\0icon.png\0"),
+        (TAG_DIRINDEXES, 4, 2, &[0, 0, 0, 0, 0, 0, 0, 1]),
+        (TAG_DIRNAMES, 8, 2, b"/usr/bin/\0/usr/share/icons/\0"),
+    ]));
+    let end = rpm.len();
+    rpm.extend(b"payload");
+
+    let package = read_package(&rpm).unwrap();
+    assert_eq!(
+        (
+            package.name.as_str(),
+            package.version.as_str(),
+            package.release.as_str()
+        ),
+        ("myapp", "1.2.0", "1")
+    );
+    assert_eq!(package.arch, "x86_64");
+    assert_eq!(package.summary, "My app");
+    assert_eq!(package.installed_size, 1234);
+    assert_eq!(package.header_range, (start, end));
+    assert_eq!(package.requires.len(), 1);
+    assert_eq!(package.requires[0].flags, None);
+    assert_eq!(
+        package.provides[0],
+        RpmDependency {
+            name: "myapp".to_string(),
+            flags: Some("EQ"),
+            epoch: Some("0".to_string()),
+            version: Some("1.2.0".to_string()),
+            release: Some("1".to_string()),
+        }
+    );
+    assert_eq!(package.files, ["/usr/bin/myapp"]);
+    assert!(read_package(b"not an rpm").is_err());
+}
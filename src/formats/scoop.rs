@@ -0,0 +1,137 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::formats::package::PackageMetadata;
+use crate::formats::winget::classify_installer;
+
+/// Scoop's name for the architecture of an MSI installer, e.g. `64bit`.
+pub fn scoop_arch(name: &str) -> Option<&'static str> {
+    match classify_installer(name)? {
+        (arch, "wix") => Some(match arch {
+            "x64" => "64bit",
+            "x86" => "32bit",
+            _ => "arm64",
+        }),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScoopDownload {
+    pub url: String,
+    /// SHA-256 that Scoop checks the download against.
+    pub hash: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScoopCheckver {
+    pub url: String,
+    pub jsonpath: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScoopHashSource {
+    pub url: String,
+    pub jsonpath: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScoopAutoupdateArch {
+    pub url: String,
+    pub hash: ScoopHashSource,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScoopAutoupdate {
+    pub architecture: BTreeMap<&'static str, ScoopAutoupdateArch>,
+}
+
+/// A Scoop app manifest.
+#[derive(Debug, Clone, Serialize)]
+pub struct ScoopManifest {
+    pub version: String,
+    pub description: String,
+    pub homepage: String,
+    pub license: String,
+    pub architecture: BTreeMap<&'static str, ScoopDownload>,
+    pub checkver: ScoopCheckver,
+    pub autoupdate: ScoopAutoupdate,
+}
+
+impl ScoopManifest {
+    /// A manifest served at `manifest_url`. Autoupdate downloads go through
+    /// `{versioned_base}/$version/{arch}`, and their hashes are read back
+    /// from the manifest once it lists the new version.
+    pub fn new(
+        version: String,
+        metadata: &PackageMetadata,
+        architecture: BTreeMap<&'static str, ScoopDownload>,
+        manifest_url: &str,
+        versioned_base: &str,
+    ) -> Self {
+        let autoupdate = architecture
+            .keys()
+            .map(|arch| {
+                (
+                    *arch,
+                    ScoopAutoupdateArch {
+                        url: format!("{}/$version/{}#/dl.msi", versioned_base, arch),
+                        hash: ScoopHashSource {
+                            url: manifest_url.to_string(),
+                            jsonpath: format!("$.architecture.{}.hash", arch),
+                        },
+                    },
+                )
+            })
+            .collect();
+
+        ScoopManifest {
+            version,
+            description: metadata.description().to_string(),
+            homepage: metadata.homepage.clone(),
+            license: metadata.license().to_string(),
+            architecture,
+            checkver: ScoopCheckver {
+                url: manifest_url.to_string(),
+                jsonpath: "$.version".to_string(),
+            },
+            autoupdate: ScoopAutoupdate {
+                architecture: autoupdate,
+            },
+        }
+    }
+}
+
+#[test]
+fn test_scoop_manifest() {
+    assert_eq!(scoop_arch("MyApp_1.2.0_x64_en-US.msi"), Some("64bit"));
+    assert_eq!(scoop_arch("MyApp_1.2.0_x86_en-US.msi"), Some("32bit"));
+    assert_eq!(scoop_arch("MyApp_1.2.0_x64-setup.exe"), None);
+
+    let metadata = PackageMetadata::for_repo("acme", "myapp");
+    let architecture = BTreeMap::from([(
+        "64bit",
+        ScoopDownload {
+            url: "https://updates.example.com/myapp/download/1/MyApp.msi".to_string(),
+            hash: "ab12".to_string(),
+        },
+    )]);
+    let manifest = ScoopManifest::new(
+        "1.2.0".to_string(),
+        &metadata,
+        architecture,
+        "https://updates.example.com/scoop/myapp.json",
+        "https://updates.example.com/scoop/myapp",
+    );
+    let json = serde_json::to_value(&manifest).unwrap();
+    assert_eq!(json["checkver"]["jsonpath"], "$.version");
+    assert_eq!(
+        json["autoupdate"]["architecture"]["64bit"]["url"],
+        "https://updates.example.com/scoop/myapp/$version/64bit#/dl.msi"
+    );
+    assert_eq!(
+        json["autoupdate"]["architecture"]["64bit"]["hash"]["jsonpath"],
+        "$.architecture.64bit.hash"
+    );
+}
@@ -0,0 +1,182 @@
+use std::fmt::Write;
+
+use crate::formats::package::{yaml_string, PackageMetadata};
+use crate::platform::matcher::{contains_ignore_case, ends_with_ignore_case};
+
+const MANIFEST_VERSION: &str = "1.6.0";
+const LOCALE: &str = "en-US";
+
+/// One installer of a winget package.
+#[derive(Debug, Clone)]
+pub struct WingetInstaller {
+    /// `x64`, `x86` or `arm64`.
+    pub architecture: &'static str,
+    /// `wix` for MSI installers, `nullsoft` for NSIS setups.
+    pub installer_type: &'static str,
+    pub url: String,
+    /// Uppercase hex SHA-256.
+    pub sha256: String,
+}
+
+/// Architecture and installer type of a Windows installer, or `None` if
+/// `name` isn't one winget can use.
+pub fn classify_installer(name: &str) -> Option<(&'static str, &'static str)> {
+    let installer_type = if ends_with_ignore_case(name, ".msi") {
+        "wix"
+    } else if ends_with_ignore_case(name, "-setup.exe") {
+        "nullsoft"
+    } else {
+        return None;
+    };
+
+    let architecture = if contains_ignore_case(name, "_x64") || contains_ignore_case(name, "x86_64")
+    {
+        "x64"
+    } else if contains_ignore_case(name, "arm64") || contains_ignore_case(name, "aarch64") {
+        "arm64"
+    } else if contains_ignore_case(name, "_x86") || contains_ignore_case(name, "i686") {
+        "x86"
+    } else {
+        return None;
+    };
+
+    Some((architecture, installer_type))
+}
+
+/// The three manifests winget-pkgs expects for one package version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ManifestPart {
+    Version,
+    Installer,
+    DefaultLocale,
+}
+
+impl ManifestPart {
+    pub const ALL: [ManifestPart; 3] = [
+        ManifestPart::Version,
+        ManifestPart::Installer,
+        ManifestPart::DefaultLocale,
+    ];
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "version" => Some(ManifestPart::Version),
+            "installer" => Some(ManifestPart::Installer),
+            "locale" | "defaultlocale" => Some(ManifestPart::DefaultLocale),
+            _ => None,
+        }
+    }
+
+    /// File name in winget-pkgs, e.g. `Acme.MyApp.installer.yaml`.
+    pub fn file_name(self, id: &str) -> String {
+        match self {
+            ManifestPart::Version => format!("{}.yaml", id),
+            ManifestPart::Installer => format!("{}.installer.yaml", id),
+            ManifestPart::DefaultLocale => format!("{}.locale.{}.yaml", id, LOCALE),
+        }
+    }
+}
+
+/// A package version as described by winget manifests.
+pub struct WingetPackage<'a> {
+    pub id: String,
+    pub version: String,
+    pub metadata: &'a PackageMetadata,
+    pub release_notes_url: Option<String>,
+    pub installers: Vec<WingetInstaller>,
+}
+
+impl WingetPackage<'_> {
+    pub fn render(&self, part: ManifestPart) -> String {
+        let mut yaml = format!(
+            "PackageIdentifier: {}\nPackageVersion: {}\n",
+            yaml_string(&self.id),
+            yaml_string(&self.version)
+        );
+        let manifest_type = match part {
+            ManifestPart::Version => {
+                let _ = writeln!(yaml, "DefaultLocale: {}", LOCALE);
+                "version"
+            }
+            ManifestPart::Installer => {
+                yaml.push_str("Installers:\n");
+                for installer in &self.installers {
+                    let _ = writeln!(yaml, "  - Architecture: {}", installer.architecture);
+                    let _ = writeln!(yaml, "    InstallerType: {}", installer.installer_type);
+                    let _ = writeln!(yaml, "    InstallerUrl: {}", yaml_string(&installer.url));
+                    let _ = writeln!(yaml, "    InstallerSha256: {}", installer.sha256);
+                }
+                "installer"
+            }
+            ManifestPart::DefaultLocale => {
+                let metadata = self.metadata;
+                let _ = writeln!(yaml, "PackageLocale: {}", LOCALE);
+                let _ = writeln!(yaml, "Publisher: {}", yaml_string(&metadata.publisher));
+                let _ = writeln!(yaml, "PackageName: {}", yaml_string(&metadata.name));
+                let _ = writeln!(yaml, "PackageUrl: {}", yaml_string(&metadata.homepage));
+                let _ = writeln!(yaml, "License: {}", yaml_string(metadata.license()));
+                let _ = writeln!(
+                    yaml,
+                    "ShortDescription: {}",
+                    yaml_string(metadata.description())
+                );
+                if let Some(url) = &self.release_notes_url {
+                    let _ = writeln!(yaml, "ReleaseNotesUrl: {}", yaml_string(url));
+                }
+                "defaultLocale"
+            }
+        };
+        let _ = write!(
+            yaml,
+            "ManifestType: {}\nManifestVersion: {}\n",
+            manifest_type, MANIFEST_VERSION
+        );
+        yaml
+    }
+
+    /// Every manifest as one YAML stream, each document headed by its file name.
+    pub fn render_all(&self) -> String {
+        ManifestPart::ALL
+            .iter()
+            .map(|part| format!("# {}\n{}", part.file_name(&self.id), self.render(*part)))
+            .collect::<Vec<_>>()
+            .join("---\n")
+    }
+}
+
+#[test]
+fn test_winget_manifests() {
+    assert_eq!(
+        classify_installer("MyApp_1.2.0_x64_en-US.msi"),
+        Some(("x64", "wix"))
+    );
+    assert_eq!(
+        classify_installer("MyApp_1.2.0_arm64-setup.exe"),
+        Some(("arm64", "nullsoft"))
+    );
+    assert_eq!(classify_installer("MyApp_1.2.0_x64.dmg"), None);
+
+    let metadata = PackageMetadata::for_repo("acme", "my-app");
+    let package = WingetPackage {
+        id: metadata.winget_id(),
+        version: "1.2.0".to_string(),
+        metadata: &metadata,
+        release_notes_url: None,
+        installers: vec![WingetInstaller {
+            architecture: "x64",
+            installer_type: "wix",
+            url: "https://updates.example.com/myapp/download/1/MyApp.msi".to_string(),
+            sha256: "AB12".to_string(),
+        }],
+    };
+    assert_eq!(package.id, "acme.myapp");
+
+    let installer = package.render(ManifestPart::Installer);
+    assert!(installer.contains("  - Architecture: x64\n    InstallerType: wix\n"));
+    assert!(installer.ends_with("ManifestType: installer\nManifestVersion: 1.6.0\n"));
+
+    let all = package.render_all();
+    assert!(all.starts_with("# acme.myapp.yaml\n"));
+    assert!(all.contains("---\n# acme.myapp.locale.en-US.yaml\n"));
+    assert!(all.contains("License: \"Proprietary\"\n"));
+}
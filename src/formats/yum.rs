@@ -0,0 +1,196 @@
+use std::fmt::Write as _;
+use std::io::Write as _;
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use crate::cache::digest::sha256_hex;
+use crate::formats::package::escape_xml;
+use crate::formats::rpm::{RpmDependency, RpmPackage};
+
+/// Path of the compressed primary metadata, relative to the repository root.
+pub const PRIMARY_PATH: &str = "repodata/primary.xml.gz";
+
+/// An `.rpm` package as listed in primary metadata.
+#[derive(Debug, Clone)]
+pub struct RepoPackage {
+    pub rpm: RpmPackage,
+    /// [`sha256_hex`] of the `.rpm`.
+    pub sha256: String,
+    pub size: u64,
+}
+
+fn render_dependencies(xml: &mut String, element: &str, dependencies: &[RpmDependency]) {
+    if dependencies.is_empty() {
+        return;
+    }
+    let _ = writeln!(xml, "    <rpm:{}>", element);
+    for dependency in dependencies {
+        let _ = write!(
+            xml,
+            "      <rpm:entry name=\"{}\"",
+            escape_xml(&dependency.name)
+        );
+        if let Some(flags) = dependency.flags {
+            let _ = write!(xml, " flags=\"{}\"", flags);
+        }
+        for (attribute, value) in [
+            ("epoch", &dependency.epoch),
+            ("ver", &dependency.version),
+            ("rel", &dependency.release),
+        ] {
+            if let Some(value) = value {
+                let _ = write!(xml, " {}=\"{}\"", attribute, escape_xml(value));
+            }
+        }
+        xml.push_str("/>\n");
+    }
+    let _ = writeln!(xml, "    </rpm:{}>", element);
+}
+
+/// Renders `primary.xml`, listing each package with its location relative
+/// to the repository root.
+pub fn render_primary(packages: &[(RepoPackage, String)]) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        xml,
+        "<metadata xmlns=\"http://linux.duke.edu/metadata/common\" \
+         xmlns:rpm=\"http://linux.duke.edu/metadata/rpm\" packages=\"{}\">",
+        packages.len()
+    );
+    for (package, location) in packages {
+        let rpm = &package.rpm;
+        xml.push_str("<package type=\"rpm\">\n");
+        let _ = writeln!(xml, "  <name>{}</name>", escape_xml(&rpm.name));
+        let _ = writeln!(xml, "  <arch>{}</arch>", escape_xml(&rpm.arch));
+        let _ = writeln!(
+            xml,
+            "  <version epoch=\"{}\" ver=\"{}\" rel=\"{}\"/>",
+            rpm.epoch,
+            escape_xml(&rpm.version),
+            escape_xml(&rpm.release)
+        );
+        let _ = writeln!(
+            xml,
+            "  <checksum type=\"sha256\" pkgid=\"YES\">{}</checksum>",
+            package.sha256
+        );
+        for (element, value) in [
+            ("summary", &rpm.summary),
+            ("description", &rpm.description),
+            ("packager", &rpm.packager),
+            ("url", &rpm.url),
+        ] {
+            let _ = writeln!(xml, "  <{0}>{1}</{0}>", element, escape_xml(value));
+        }
+        let _ = writeln!(xml, "  <time file=\"{0}\" build=\"{0}\"/>", rpm.build_time);
+        let _ = writeln!(
+            xml,
+            "  <size package=\"{}\" installed=\"{}\" archive=\"{}\"/>",
+            package.size, rpm.installed_size, rpm.archive_size
+        );
+        let _ = writeln!(xml, "  <location href=\"{}\"/>", escape_xml(location));
+
+        xml.push_str("  <format>\n");
+        for (element, value) in [
+            ("license", &rpm.license),
+            ("vendor", &rpm.vendor),
+            ("group", &rpm.group),
+            ("buildhost", &rpm.buildhost),
+            ("sourcerpm", &rpm.sourcerpm),
+        ] {
+            let _ = writeln!(
+                xml,
+                "    <rpm:{0}>{1}</rpm:{0}>",
+                element,
+                escape_xml(value)
+            );
+        }
+        let _ = writeln!(
+            xml,
+            "    <rpm:header-range start=\"{}\" end=\"{}\"/>",
+            rpm.header_range.0, rpm.header_range.1
+        );
+        render_dependencies(&mut xml, "provides", &rpm.provides);
+        render_dependencies(&mut xml, "requires", &rpm.requires);
+        for file in &rpm.files {
+            let _ = writeln!(xml, "    <file>{}</file>", escape_xml(file));
+        }
+        xml.push_str("  </format>\n</package>\n");
+    }
+    xml.push_str("</metadata>\n");
+    xml
+}
+
+/// Gzips metadata. The header carries no timestamp, so the output and its
+/// checksum only change with the content.
+pub fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    let _ = encoder.write_all(data);
+    encoder.finish().unwrap_or_default()
+}
+
+/// Renders `repomd.xml`, which points dnf at the primary metadata.
+/// `revision` is a Unix timestamp that increases with every change.
+pub fn render_repomd(revision: i64, primary_xml: &[u8], primary_gz: &[u8]) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <repomd xmlns=\"http://linux.duke.edu/metadata/repo\" xmlns:rpm=\"http://linux.duke.edu/metadata/rpm\">\n\
+         \x20 <revision>{revision}</revision>\n\
+         \x20 <data type=\"primary\">\n\
+         \x20   <checksum type=\"sha256\">{}</checksum>\n\
+         \x20   <open-checksum type=\"sha256\">{}</open-checksum>\n\
+         \x20   <location href=\"{PRIMARY_PATH}\"/>\n\
+         \x20   <timestamp>{revision}</timestamp>\n\
+         \x20   <size>{}</size>\n\
+         \x20   <open-size>{}</open-size>\n\
+         \x20 </data>\n\
+         </repomd>\n",
+        sha256_hex(primary_gz),
+        sha256_hex(primary_xml),
+        primary_gz.len(),
+        primary_xml.len(),
+    )
+}
+
+#[test]
+fn test_yum_metadata() {
+    let package = RepoPackage {
+        rpm: RpmPackage {
+            name: "myapp".to_string(),
+            version: "1.2.0".to_string(),
+            release: "1".to_string(),
+            arch: "x86_64".to_string(),
+            summary: "My <app>".to_string(),
+            provides: vec![RpmDependency {
+                name: "myapp".to_string(),
+                flags: Some("EQ"),
+                epoch: Some("0".to_string()),
+                version: Some("1.2.0".to_string()),
+                release: Some("1".to_string()),
+            }],
+            ..RpmPackage::default()
+        },
+        sha256: "ab12".to_string(),
+        size: 42,
+    };
+    let primary = render_primary(&[(package, "packages/7/myapp-1.2.0-1.x86_64.rpm".to_string())]);
+    assert!(primary.contains("packages=\"1\">"));
+    assert!(primary.contains("  <version epoch=\"0\" ver=\"1.2.0\" rel=\"1\"/>\n"));
+    assert!(primary.contains("  <summary>My &lt;app&gt;</summary>\n"));
+    assert!(primary.contains("  <location href=\"packages/7/myapp-1.2.0-1.x86_64.rpm\"/>\n"));
+    assert!(primary.contains(
+        "      <rpm:entry name=\"myapp\" flags=\"EQ\" epoch=\"0\" ver=\"1.2.0\" rel=\"1\"/>\n"
+    ));
+    assert!(!primary.contains("<rpm:requires>"));
+
+    let gz = gzip(primary.as_bytes());
+    assert_eq!(gz, gzip(primary.as_bytes()));
+    let repomd = render_repomd(1700000000, primary.as_bytes(), &gz);
+    assert!(repomd.contains("  <revision>1700000000</revision>\n"));
+    assert!(repomd.contains(&format!("    <size>{}</size>\n", gz.len())));
+    assert!(repomd.contains(&format!(
+        "    <open-checksum type=\"sha256\">{}</open-checksum>\n",
+        sha256_hex(primary.as_bytes())
+    )));
+}
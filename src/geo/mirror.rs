@@ -0,0 +1,122 @@
+use std::collections::BTreeMap;
+
+use semver::Version;
+use serde::{Deserialize, Serialize};
+
+use crate::config::parse_list;
+use crate::geo::region::ClientLocation;
+
+/// Download mirrors of a product by region. Regions are ISO country codes
+/// (`jp`), continents (`europe`, `north-america`, `south-america`, `asia`,
+/// `africa`, `oceania`, `antarctica`) or `default`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Mirrors {
+    regions: BTreeMap<String, String>,
+}
+
+impl Mirrors {
+    /// Parses `region=url` pairs such as
+    /// `europe=https://eu.example.com/myapp,jp=https://jp.example.com/myapp`.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let regions = parse_list(value)
+            .into_iter()
+            .map(|pair| match pair.split_once('=') {
+                Some((region, url))
+                    if !region.trim().is_empty()
+                        && (url.trim().starts_with("https://")
+                            || url.trim().starts_with("http://")) =>
+                {
+                    Ok((region.trim().to_lowercase(), url.trim().to_string()))
+                }
+                _ => Err(format!("expected region=url, got {}", pair)),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Mirrors { regions })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+    }
+
+    /// The mirror closest to `location`: the country's, else the
+    /// continent's, else the `default` one.
+    pub fn select(&self, location: &ClientLocation) -> Option<&str> {
+        let country = location.country.as_ref().map(|c| c.to_lowercase());
+        let mirror = [country.as_deref(), location.continent, Some("default")]
+            .into_iter()
+            .flatten()
+            .find_map(|region| self.regions.get(region));
+        mirror.map(String::as_str)
+    }
+
+    /// Region and URL pairs, for diagnostics.
+    pub fn regions(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.regions.iter().map(|(r, u)| (r.as_str(), u.as_str()))
+    }
+}
+
+/// Download URL of an asset on a mirror. `{tag}`, `{version}` and
+/// `{filename}` in `template` are replaced; without a `{filename}` the file
+/// name is appended as the last path segment.
+pub fn mirror_url(template: &str, tag: &str, version: &Version, filename: &str) -> String {
+    let url = template
+        .replace("{tag}", tag)
+        .replace("{version}", &version.to_string());
+    if url.contains("{filename}") {
+        url.replace("{filename}", filename)
+    } else {
+        format!("{}/{}", url.trim_end_matches('/'), filename)
+    }
+}
+
+#[test]
+fn test_mirror_selection() {
+    let mirrors = Mirrors::parse(
+        "Europe=https://eu.example.com/myapp/{tag}, jp=https://jp.example.com/{version}/{filename}",
+    )
+    .unwrap();
+    let location = |country: &str| ClientLocation {
+        country: Some(country.to_string()),
+        continent: crate::geo::region::continent_of(country),
+    };
+    assert_eq!(
+        mirrors.select(&location("DE")),
+        Some("https://eu.example.com/myapp/{tag}")
+    );
+    assert_eq!(
+        mirrors.select(&location("JP")),
+        Some("https://jp.example.com/{version}/{filename}")
+    );
+    assert_eq!(mirrors.select(&location("US")), None);
+    assert_eq!(mirrors.select(&ClientLocation::default()), None);
+
+    let version = Version::new(1, 2, 0);
+    assert_eq!(
+        mirror_url(
+            "https://eu.example.com/myapp/{tag}/",
+            "v1.2.0",
+            &version,
+            "MyApp.msi"
+        ),
+        "https://eu.example.com/myapp/v1.2.0/MyApp.msi"
+    );
+    assert_eq!(
+        mirror_url(
+            "https://jp.example.com/{version}/{filename}",
+            "v1.2.0",
+            &version,
+            "MyApp.msi"
+        ),
+        "https://jp.example.com/1.2.0/MyApp.msi"
+    );
+
+    let with_default = Mirrors::parse("default=https://cdn.example.com").unwrap();
+    assert_eq!(
+        with_default.select(&location("US")),
+        Some("https://cdn.example.com")
+    );
+    assert_eq!(
+        Mirrors::parse("europe=eu.example.com").unwrap_err(),
+        "expected region=url, got europe=eu.example.com"
+    );
+}
@@ -0,0 +1,103 @@
+use actix_web::http::header::HeaderMap;
+
+/// Headers carrying the client's ISO country code, as set by Cloudflare,
+/// CloudFront or a reverse proxy with a GeoIP database (e.g. nginx's geoip2
+/// module), in order of preference.
+pub(crate) const COUNTRY_HEADERS: [&str; 3] = [
+    "CF-IPCountry",
+    "CloudFront-Viewer-Country",
+    "X-Country-Code",
+];
+
+/// Continents by name, with the ISO codes of their countries.
+const CONTINENTS: [(&str, &str); 7] = [
+    (
+        "africa",
+        "AO BF BI BJ BW CD CF CG CI CM CV DJ DZ EG EH ER ET GA GH GM GN GQ GW KE KM LR LS LY \
+         MA MG ML MR MU MW MZ NA NE NG RE RW SC SD SH SL SN SO SS ST SZ TD TG TN TZ UG YT ZA \
+         ZM ZW",
+    ),
+    ("antarctica", "AQ BV GS HM TF"),
+    (
+        "asia",
+        "AE AF AM AZ BD BH BN BT CC CN CX GE HK ID IL IN IO IQ IR JO JP KG KH KP KR KW KZ LA \
+         LB LK MM MN MO MV MY NP OM PH PK PS QA SA SG SY TH TJ TL TM TR TW UZ VN YE",
+    ),
+    (
+        "europe",
+        "AD AL AT AX BA BE BG BY CH CY CZ DE DK EE ES FI FO FR GB GG GI GR HR HU IE IM IS IT \
+         JE LI LT LU LV MC MD ME MK MT NL NO PL PT RO RS RU SE SI SJ SK SM UA VA XK",
+    ),
+    (
+        "north-america",
+        "AG AI AW BB BL BM BQ BS BZ CA CR CU CW DM DO GD GL GP GT HN HT JM KN KY LC MF MQ MS \
+         MX NI PA PM PR SV SX TC TT US VC VG VI",
+    ),
+    (
+        "oceania",
+        "AS AU CK FJ FM GU KI MH MP NC NF NR NU NZ PF PG PN PW SB TK TO TV UM VU WF WS",
+    ),
+    ("south-america", "AR BO BR CL CO EC FK GF GY PE PY SR UY VE"),
+];
+
+/// The continent a country belongs to, e.g. `europe` for `DE`.
+pub fn continent_of(country: &str) -> Option<&'static str> {
+    let country = country.to_uppercase();
+    CONTINENTS
+        .iter()
+        .find(|(_, countries)| countries.split_whitespace().any(|c| c == country))
+        .map(|(continent, _)| *continent)
+}
+
+/// Where a client is, as far as the request headers tell.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClientLocation {
+    /// Uppercase ISO 3166-1 alpha-2 code.
+    pub country: Option<String>,
+    pub continent: Option<&'static str>,
+}
+
+impl ClientLocation {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        // Cloudflare reports `XX` for unknown and `T1` for Tor clients
+        let country = COUNTRY_HEADERS
+            .iter()
+            .filter_map(|name| headers.get(*name)?.to_str().ok())
+            .map(|value| value.trim().to_uppercase())
+            .find(|value| value.len() == 2 && value != "XX" && value != "T1");
+        ClientLocation {
+            continent: country.as_deref().and_then(continent_of),
+            country,
+        }
+    }
+}
+
+#[test]
+fn test_client_location() {
+    assert_eq!(continent_of("de"), Some("europe"));
+    assert_eq!(continent_of("NA"), Some("africa"));
+    assert_eq!(continent_of("BR"), Some("south-america"));
+    assert_eq!(continent_of("ZZ"), None);
+
+    let mut headers = HeaderMap::new();
+    assert_eq!(
+        ClientLocation::from_headers(&headers),
+        ClientLocation::default()
+    );
+
+    headers.insert(
+        actix_web::http::header::HeaderName::from_static("cf-ipcountry"),
+        "XX".parse().unwrap(),
+    );
+    headers.insert(
+        actix_web::http::header::HeaderName::from_static("x-country-code"),
+        "jp".parse().unwrap(),
+    );
+    assert_eq!(
+        ClientLocation::from_headers(&headers),
+        ClientLocation {
+            country: Some("JP".to_string()),
+            continent: Some("asia"),
+        }
+    );
+}
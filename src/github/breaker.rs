@@ -0,0 +1,146 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::error::ServerError;
+
+/// Consecutive outages after which requests to GitHub are suspended.
+pub const DEFAULT_THRESHOLD: u32 = 5;
+/// How long requests stay suspended before one is let through to probe.
+pub const DEFAULT_COOLDOWN: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    /// Requests go through.
+    Closed,
+    /// GitHub failed repeatedly; requests are suspended.
+    Open,
+    /// The cooldown is over; the next request decides whether to close.
+    HalfOpen,
+}
+
+/// The last request to GitHub that failed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UpstreamFailure {
+    pub at: DateTime<Utc>,
+    pub code: &'static str,
+    pub message: String,
+}
+
+#[derive(Debug, Default)]
+struct Breaker {
+    failures: u32,
+    opened_at: Option<Instant>,
+    last_failure: Option<UpstreamFailure>,
+}
+
+/// Stops hammering GitHub while it's down: after `threshold` consecutive
+/// outages, requests are suspended for `cooldown`, then a single request
+/// probes whether it's back. Errors that GitHub answered deliberately, such
+/// as a missing repository, don't count as outages.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    state: Mutex<Breaker>,
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        CircuitBreaker::new(DEFAULT_THRESHOLD, DEFAULT_COOLDOWN)
+    }
+}
+
+impl CircuitBreaker {
+    pub fn new(threshold: u32, cooldown: Duration) -> Self {
+        CircuitBreaker {
+            threshold: threshold.max(1),
+            cooldown,
+            state: Mutex::new(Breaker::default()),
+        }
+    }
+
+    pub fn state(&self) -> BreakerState {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        match state.opened_at {
+            None => BreakerState::Closed,
+            Some(opened_at) if opened_at.elapsed() < self.cooldown => BreakerState::Open,
+            Some(_) => BreakerState::HalfOpen,
+        }
+    }
+
+    /// Whether a request may be sent. Once the cooldown is over, one request
+    /// is let through and the cooldown restarts until it reports back.
+    pub fn allow(&self) -> bool {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        match state.opened_at {
+            None => true,
+            Some(opened_at) if opened_at.elapsed() < self.cooldown => false,
+            Some(_) => {
+                state.opened_at = Some(Instant::now());
+                true
+            }
+        }
+    }
+
+    pub fn succeeded(&self) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.failures = 0;
+        state.opened_at = None;
+    }
+
+    pub fn failed(&self, error: &ServerError) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.last_failure = Some(UpstreamFailure {
+            at: Utc::now(),
+            code: error.code(),
+            message: error.to_string(),
+        });
+        if !matches!(
+            error,
+            ServerError::Upstream(_) | ServerError::RateLimited { .. }
+        ) {
+            return;
+        }
+        state.failures += 1;
+        if state.failures >= self.threshold {
+            state.opened_at = Some(Instant::now());
+        }
+    }
+
+    pub fn last_failure(&self) -> Option<UpstreamFailure> {
+        self.state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .last_failure
+            .clone()
+    }
+}
+
+#[test]
+fn test_circuit_breaker() {
+    let breaker = CircuitBreaker::new(2, Duration::from_millis(20));
+    breaker.failed(&ServerError::UpstreamNotFound("Failed to list releases"));
+    breaker.failed(&ServerError::Upstream("Failed to list releases"));
+    assert_eq!(breaker.state(), BreakerState::Closed);
+    assert_eq!(breaker.last_failure().unwrap().code, "upstream_error");
+
+    breaker.failed(&ServerError::RateLimited {
+        retry_after_secs: 60,
+    });
+    assert_eq!(breaker.state(), BreakerState::Open);
+    assert!(!breaker.allow());
+
+    std::thread::sleep(Duration::from_millis(25));
+    assert_eq!(breaker.state(), BreakerState::HalfOpen);
+    assert!(breaker.allow());
+    assert!(!breaker.allow());
+
+    breaker.succeeded();
+    assert_eq!(breaker.state(), BreakerState::Closed);
+    assert!(breaker.allow());
+    assert_eq!(breaker.last_failure().unwrap().code, "rate_limited");
+}
@@ -1,44 +1,425 @@
-use actix_web::Error;
-use bytes::Bytes;
-use log::{debug, error};
-use octocrab::Octocrab;
-use reqwest;
+use bytes::{Bytes, BytesMut};
+use futures_util::{Stream, StreamExt, TryStreamExt};
+use http::{HeaderValue, Uri};
+use log::{debug, error, warn};
+use octocrab::service::middleware::auth_header::AuthHeaderLayer;
+use octocrab::service::middleware::base_uri::BaseUriLayer;
+use octocrab::{AuthState, Octocrab, OctocrabBuilder};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use std::{fmt, fs, path::Path};
+
+use actix_web::rt::time::timeout;
+
+use crate::auth::authenticator::fingerprint;
+use crate::config::ProductConfig;
+use crate::error::ServerError;
+use crate::github::breaker::{BreakerState, CircuitBreaker, DEFAULT_COOLDOWN};
+use crate::github::quota::{QuotaTracker, RateLimit};
+use crate::github::status;
+use crate::github::transport::ReqwestService;
+
+const MAX_RELEASE_PAGES: usize = 10;
+
+const USER_AGENT: &str = "Multi-Product-Update-Server";
+
+pub const DEFAULT_API_BASE_URL: &str = "https://api.github.com";
+
+/// Root certificates trusted in addition to the system's, e.g. of a
+/// TLS-inspecting corporate proxy or a GitHub Enterprise server's private CA.
+#[derive(Clone, Default)]
+pub struct RootCertificates(Vec<reqwest::Certificate>);
+
+impl RootCertificates {
+    /// Loads every certificate of a PEM bundle.
+    pub fn from_pem_file(path: &Path) -> Result<Self, String> {
+        let pem = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let certificates = reqwest::Certificate::from_pem_bundle(&pem)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        if certificates.is_empty() {
+            return Err(format!("{}: no certificates found", path.display()));
+        }
+        Ok(Self(certificates))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &reqwest::Certificate> {
+        self.0.iter()
+    }
+}
+
+impl fmt::Debug for RootCertificates {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "RootCertificates({})", self.0.len())
+    }
+}
+
+/// Limits on how long requests to GitHub may take.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Timeouts {
+    /// Establishing a connection.
+    pub connect: Duration,
+    /// Waiting for the next bytes of a response, which also bounds how long
+    /// a stalled asset download holds on.
+    pub read: Duration,
+    /// A whole API call or buffered asset download, or the response headers
+    /// of a streamed download.
+    pub request: Duration,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Timeouts {
+            connect: Duration::from_secs(10),
+            read: Duration::from_secs(30),
+            request: Duration::from_secs(60),
+        }
+    }
+}
+
+/// The `Range` (and optional `If-Range`) header of a resumed download.
+#[derive(Clone, Copy, Debug)]
+pub struct AssetRange<'a> {
+    pub range: &'a str,
+    pub if_range: Option<&'a str>,
+}
 
 pub struct GitHubClient {
     octocrab: Octocrab,
+    http: reqwest::Client,
     github_token: String,
+    api_base_url: String,
+    timeouts: Timeouts,
+    max_asset_size: Option<u64>,
+    quota: Arc<QuotaTracker>,
+    fallback: Option<Fallback>,
+}
+
+/// The product's fallback repository and the state of its primary one.
+struct Fallback {
+    client: Box<GitHubClient>,
+    owner: String,
+    repo: String,
+    primary_owner: String,
+    primary_repo: String,
+    /// Opens on the primary's first outage, so requests go straight to
+    /// the fallback until the cooldown is over.
+    primary: CircuitBreaker,
 }
 
 impl GitHubClient {
-    pub fn new(github_token: String) -> Result<Self, Error> {
-        let octocrab = Octocrab::builder()
-            .personal_token(github_token.clone())
+    /// Creates a client for the product's API endpoint, e.g.
+    /// `https://ghe.example.com/api/v3` for GitHub Enterprise, or api.github.com
+    /// when unset. Requests go through the product's proxy when given,
+    /// otherwise through the proxy named by the `HTTPS_PROXY`/`ALL_PROXY`
+    /// environment variables, if any.
+    pub fn new(product_config: &ProductConfig) -> Result<Self, ServerError> {
+        let github_token = product_config.github_token.clone();
+        let client_error =
+            |cause: String| ServerError::config("Failed to create GitHub client", cause);
+
+        let api_base_url = product_config
+            .api_base_url
+            .as_deref()
+            .unwrap_or(DEFAULT_API_BASE_URL)
+            .trim_end_matches('/')
+            .to_string();
+        let base_uri: Uri = api_base_url.parse().map_err(|e| {
+            client_error(format!(
+                "Invalid GitHub API base URL {}: {}",
+                api_base_url, e
+            ))
+        })?;
+
+        let timeouts = product_config.timeouts;
+        let mut http = reqwest::Client::builder()
+            .user_agent(USER_AGENT)
+            .connect_timeout(timeouts.connect)
+            .read_timeout(timeouts.read);
+        for certificate in product_config.root_certificates.iter() {
+            http = http.add_root_certificate(certificate.clone());
+        }
+        if let Some(proxy) = &product_config.proxy {
+            let proxy = reqwest::Proxy::all(proxy)
+                .map_err(|e| client_error(format!("Invalid proxy URL: {}", e)))?;
+            http = http.proxy(proxy);
+        }
+        let http = http
+            .build()
+            .map_err(|e| client_error(format!("Failed to build HTTP client: {}", e)))?;
+
+        let auth_header = HeaderValue::from_str(&format!("Bearer {}", github_token))
+            .map_err(|e| client_error(format!("Invalid GitHub token: {}", e)))?;
+
+        let quota = Arc::new(QuotaTracker::default());
+        let octocrab = OctocrabBuilder::new_empty()
+            .with_service(ReqwestService::new(
+                http.clone(),
+                timeouts.request,
+                Arc::clone(&quota),
+            ))
+            .with_layer(&BaseUriLayer::new(base_uri.clone()))
+            .with_layer(&AuthHeaderLayer::new(
+                Some(auth_header),
+                base_uri.clone(),
+                base_uri,
+            ))
+            .with_auth(AuthState::None)
             .build()
-            .map_err(|e| {
-                error!("Failed to build Octocrab instance: {}", e);
-                actix_web::error::ErrorInternalServerError("Failed to create GitHub client")
-            })?;
+            .unwrap_or_else(|never| match never {});
+
+        let fallback = match &product_config.fallback {
+            Some(fallback) => {
+                let mirror = ProductConfig {
+                    github_token: fallback
+                        .token
+                        .clone()
+                        .unwrap_or_else(|| github_token.clone()),
+                    repo_owner: fallback.owner.clone(),
+                    repo_name: fallback.repo.clone(),
+                    api_base_url: fallback
+                        .api_base_url
+                        .clone()
+                        .or_else(|| product_config.api_base_url.clone()),
+                    fallback: None,
+                    ..product_config.clone()
+                };
+                Some(Fallback {
+                    client: Box::new(GitHubClient::new(&mirror)?),
+                    owner: fallback.owner.clone(),
+                    repo: fallback.repo.clone(),
+                    primary_owner: product_config.repo_owner.clone(),
+                    primary_repo: product_config.repo_name.clone(),
+                    primary: CircuitBreaker::new(1, DEFAULT_COOLDOWN),
+                })
+            }
+            None => None,
+        };
 
         Ok(Self {
             octocrab,
+            http,
             github_token,
+            api_base_url,
+            timeouts,
+            max_asset_size: product_config.max_asset_size,
+            quota,
+            fallback,
         })
     }
 
-    pub async fn get_latest_release(
+    /// The HTTP client requests to GitHub go through, with the product's
+    /// proxy and root certificates, for other requests made on the product's
+    /// behalf.
+    pub fn http(&self) -> &reqwest::Client {
+        &self.http
+    }
+
+    /// The state of the primary repository when a fallback is configured:
+    /// `open` while requests go to the fallback.
+    pub fn failover_state(&self) -> Option<BreakerState> {
+        self.fallback.as_ref().map(|f| f.primary.state())
+    }
+
+    /// The fallback of `owner/repo`, if it's the product's repository and
+    /// has one.
+    fn fallback_for(&self, owner: &str, repo: &str) -> Option<&Fallback> {
+        self.fallback
+            .as_ref()
+            .filter(|f| f.primary_owner == owner && f.primary_repo == repo)
+    }
+
+    /// Runs `primary`, or `fallback` instead while the primary repository
+    /// is unreachable. `fallback` also runs when `primary` fails with an
+    /// error `fail_over` accepts, e.g. an asset id only the fallback knows.
+    async fn failover<T>(
+        &self,
+        primary: impl Future<Output = Result<T, ServerError>>,
+        fallback: Option<impl Future<Output = Result<T, ServerError>>>,
+        fail_over: fn(&ServerError) -> bool,
+    ) -> Result<T, ServerError> {
+        let (Some(fallback), Some(mirror)) = (fallback, &self.fallback) else {
+            return primary.await;
+        };
+        if !mirror.primary.allow() {
+            debug!(
+                "Primary repository unreachable, using {}/{}",
+                mirror.owner, mirror.repo
+            );
+            return fallback.await;
+        }
+
+        match primary.await {
+            Err(e) if is_outage(&e) || fail_over(&e) => {
+                mirror.primary.failed(&e);
+                warn!("{}, failing over to {}/{}", e, mirror.owner, mirror.repo);
+                fallback.await
+            }
+            result => {
+                mirror.primary.succeeded();
+                result
+            }
+        }
+    }
+
+    /// The token's API budget as of the latest response, if GitHub reported
+    /// one yet.
+    pub fn rate_limit(&self) -> Option<RateLimit> {
+        self.quota.latest()
+    }
+
+    /// Identifies the token without revealing it.
+    pub fn token_fingerprint(&self) -> String {
+        fingerprint(&self.github_token)
+    }
+
+    /// Lists releases newest first, following pagination up to
+    /// `MAX_RELEASE_PAGES` pages.
+    pub async fn list_releases(
+        &self,
+        owner: &str,
+        repo: &str,
+    ) -> Result<Vec<octocrab::models::repos::Release>, ServerError> {
+        let fallback = self
+            .fallback_for(owner, repo)
+            .map(|f| f.client.fetch_releases(&f.owner, &f.repo));
+        self.failover(self.fetch_releases(owner, repo), fallback, never)
+            .await
+    }
+
+    async fn fetch_releases(
+        &self,
+        owner: &str,
+        repo: &str,
+    ) -> Result<Vec<octocrab::models::repos::Release>, ServerError> {
+        let map_err = |e| status::from_octocrab("Failed to list releases", e);
+
+        let mut page = self
+            .octocrab
+            .repos(owner, repo)
+            .releases()
+            .list()
+            .per_page(100)
+            .send()
+            .await
+            .map_err(map_err)?;
+
+        let mut releases = std::mem::take(&mut page.items);
+        for _ in 1..MAX_RELEASE_PAGES {
+            match self
+                .octocrab
+                .get_page::<octocrab::models::repos::Release>(&page.next)
+                .await
+                .map_err(map_err)?
+            {
+                Some(mut next) => {
+                    releases.append(&mut next.items);
+                    page = next;
+                }
+                None => break,
+            }
+        }
+
+        Ok(releases)
+    }
+
+    /// Opens an issue and returns its number.
+    pub async fn create_issue(
         &self,
         owner: &str,
         repo: &str,
-    ) -> Result<octocrab::models::repos::Release, Error> {
+        title: &str,
+        body: &str,
+        labels: &[String],
+    ) -> Result<u64, ServerError> {
+        let issues = self.octocrab.issues(owner, repo);
+        let mut request = issues.create(title).body(body);
+        if !labels.is_empty() {
+            request = request.labels(labels.to_vec());
+        }
+
+        let issue = request.send().await.map_err(|e| {
+            error!("Failed to create issue in {}/{}", owner, repo);
+            status::from_octocrab("Failed to record feedback", e)
+        })?;
+        Ok(issue.number)
+    }
+
+    pub async fn get_release_by_tag(
+        &self,
+        owner: &str,
+        repo: &str,
+        tag: &str,
+    ) -> Result<octocrab::models::repos::Release, ServerError> {
+        let fallback = self
+            .fallback_for(owner, repo)
+            .map(|f| f.client.fetch_release_by_tag(&f.owner, &f.repo, tag));
+        self.failover(self.fetch_release_by_tag(owner, repo, tag), fallback, never)
+            .await
+    }
+
+    async fn fetch_release_by_tag(
+        &self,
+        owner: &str,
+        repo: &str,
+        tag: &str,
+    ) -> Result<octocrab::models::repos::Release, ServerError> {
         self.octocrab
             .repos(owner, repo)
             .releases()
-            .get_latest()
+            .get_by_tag(tag)
             .await
-            .map_err(|e| {
-                error!("Failed to fetch latest release: {}", e);
-                actix_web::error::ErrorInternalServerError("Failed to fetch release")
-            })
+            .map_err(
+                |e| match status::from_octocrab("Failed to fetch release", e) {
+                    ServerError::UpstreamNotFound(_) => {
+                        ServerError::ReleaseNotFound(tag.to_string())
+                    }
+                    error => error,
+                },
+            )
+    }
+
+    /// The text of the file at `path` as of `reference`, e.g. a tag.
+    pub async fn get_file(
+        &self,
+        owner: &str,
+        repo: &str,
+        path: &str,
+        reference: &str,
+    ) -> Result<String, ServerError> {
+        let fallback = self
+            .fallback_for(owner, repo)
+            .map(|f| f.client.fetch_file(&f.owner, &f.repo, path, reference));
+        self.failover(
+            self.fetch_file(owner, repo, path, reference),
+            fallback,
+            never,
+        )
+        .await
+    }
+
+    async fn fetch_file(
+        &self,
+        owner: &str,
+        repo: &str,
+        path: &str,
+        reference: &str,
+    ) -> Result<String, ServerError> {
+        let mut contents = self
+            .octocrab
+            .repos(owner, repo)
+            .get_content()
+            .path(path)
+            .r#ref(reference)
+            .send()
+            .await
+            .map_err(|e| status::from_octocrab("Failed to fetch file", e))?;
+        contents
+            .take_items()
+            .into_iter()
+            .next()
+            .and_then(|file| file.decoded_content())
+            .ok_or(ServerError::UpstreamNotFound("Failed to fetch file"))
     }
 
     pub async fn download_asset(
@@ -46,41 +427,164 @@ impl GitHubClient {
         asset_id: u64,
         owner: &str,
         repo: &str,
-    ) -> Result<Bytes, Error> {
-        let client = reqwest::Client::new();
+    ) -> Result<Bytes, ServerError> {
+        let download = async {
+            let response = self.open_asset(asset_id, owner, repo, None).await?;
+            response.bytes().await
+        };
+
+        timeout(self.timeouts.request, download)
+            .await
+            .map_err(|e| ServerError::upstream("Failed to read asset", e))?
+    }
+
+    /// Requests an asset and returns the response once its headers arrived,
+    /// leaving the body to be streamed by the caller. With `range`, GitHub may
+    /// answer `206 Partial Content` or `416 Range Not Satisfiable`. Assets
+    /// above the product's maximum size are refused, or cut off once they
+    /// grow past it if GitHub doesn't tell their length. Asset ids unknown to
+    /// the primary repository are looked up in the fallback, which served
+    /// them while the primary was unreachable.
+    pub async fn open_asset(
+        &self,
+        asset_id: u64,
+        owner: &str,
+        repo: &str,
+        range: Option<AssetRange<'_>>,
+    ) -> Result<AssetResponse, ServerError> {
+        let fallback = self
+            .fallback_for(owner, repo)
+            .map(|f| f.client.request_asset(asset_id, &f.owner, &f.repo, range));
+        self.failover(
+            self.request_asset(asset_id, owner, repo, range),
+            fallback,
+            |e| matches!(e, ServerError::UpstreamNotFound(_)),
+        )
+        .await
+    }
+
+    async fn request_asset(
+        &self,
+        asset_id: u64,
+        owner: &str,
+        repo: &str,
+        range: Option<AssetRange<'_>>,
+    ) -> Result<AssetResponse, ServerError> {
         let url = format!(
-            "https://api.github.com/repos/{}/{}/releases/assets/{}",
-            owner, repo, asset_id
+            "{}/repos/{}/{}/releases/assets/{}",
+            self.api_base_url, owner, repo, asset_id
         );
 
         debug!("Downloading asset from GitHub API URL: {}", url);
 
-        let response = client
+        let mut request = self
+            .http
             .get(url)
             .header("Authorization", format!("Bearer {}", self.github_token))
-            .header("Accept", "application/octet-stream")
-            .header("User-Agent", "Multi-Product-Update-Server")
-            .send()
+            .header("Accept", "application/octet-stream");
+        if let Some(range) = range {
+            request = request.header("Range", range.range);
+            if let Some(if_range) = range.if_range {
+                request = request.header("If-Range", if_range);
+            }
+        }
+
+        let response = timeout(self.timeouts.request, request.send())
             .await
-            .map_err(|e| {
-                error!("Failed to send request to GitHub: {}", e);
-                actix_web::error::ErrorInternalServerError("Failed to download asset")
-            })?;
-
-        if !response.status().is_success() {
-            error!(
-                "GitHub API returned error status: {} for asset ID: {}",
-                response.status(),
-                asset_id
-            );
-            return Err(actix_web::error::ErrorInternalServerError(
-                "GitHub API error",
-            ));
+            .map_err(|e| ServerError::upstream("Failed to download asset", e))?
+            .map_err(|e| ServerError::upstream("Failed to download asset", e))?;
+        self.quota.observe(response.headers());
+
+        if !response.status().is_success()
+            && response.status() != reqwest::StatusCode::RANGE_NOT_SATISFIABLE
+        {
+            error!("Failed to download asset ID {}", asset_id);
+            return Err(status::from_response("Failed to download asset", &response));
         }
 
-        response.bytes().await.map_err(|e| {
-            error!("Failed to read response from GitHub: {}", e);
-            actix_web::error::ErrorInternalServerError("Failed to read asset")
+        if let (Some(size), Some(max)) = (response.content_length(), self.max_asset_size) {
+            if size > max {
+                error!(
+                    "Asset ID {} is {} bytes, above the limit of {}",
+                    asset_id, size, max
+                );
+                return Err(ServerError::AssetTooLarge(asset_id));
+            }
+        }
+
+        Ok(AssetResponse {
+            response,
+            asset_id,
+            max_size: self.max_asset_size,
+        })
+    }
+}
+
+/// GitHub's response to an asset request, whose body is cut off with
+/// [`ServerError::AssetTooLarge`] once it exceeds the product's maximum
+/// asset size.
+pub struct AssetResponse {
+    response: reqwest::Response,
+    asset_id: u64,
+    max_size: Option<u64>,
+}
+
+impl AssetResponse {
+    pub fn status(&self) -> reqwest::StatusCode {
+        self.response.status()
+    }
+
+    pub fn headers(&self) -> &reqwest::header::HeaderMap {
+        self.response.headers()
+    }
+
+    pub fn content_length(&self) -> Option<u64> {
+        self.response.content_length()
+    }
+
+    /// The body, chunk by chunk as it arrives.
+    pub fn bytes_stream(self) -> impl Stream<Item = Result<Bytes, ServerError>> + Send + 'static {
+        let AssetResponse {
+            response,
+            asset_id,
+            max_size,
+        } = self;
+        let mut received = 0u64;
+        response.bytes_stream().map(move |chunk| {
+            let chunk = chunk.map_err(|e| ServerError::upstream("Failed to read asset", e))?;
+            received += chunk.len() as u64;
+            match max_size {
+                Some(max) if received > max => {
+                    error!("Asset ID {} is above the limit of {} bytes", asset_id, max);
+                    Err(ServerError::AssetTooLarge(asset_id))
+                }
+                _ => Ok(chunk),
+            }
         })
     }
+
+    /// The whole body.
+    pub async fn bytes(self) -> Result<Bytes, ServerError> {
+        let body = self
+            .bytes_stream()
+            .try_fold(BytesMut::new(), |mut body, chunk| async move {
+                body.extend_from_slice(&chunk);
+                Ok(body)
+            })
+            .await?;
+        Ok(body.freeze())
+    }
+}
+
+/// Whether GitHub is unreachable or refused the request for its rate limit,
+/// as opposed to answering it.
+fn is_outage(error: &ServerError) -> bool {
+    matches!(
+        error,
+        ServerError::Upstream(_) | ServerError::RateLimited { .. }
+    )
+}
+
+fn never(_: &ServerError) -> bool {
+    false
 }
@@ -0,0 +1,46 @@
+use serde::Deserialize;
+
+/// A mirror of a product's repository that GitHub requests fail over to
+/// while the primary one is unreachable, e.g. on a GitHub Enterprise server.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct FallbackRepo {
+    pub owner: String,
+    pub repo: String,
+    /// API endpoint of the mirror; the product's own when unset.
+    #[serde(default)]
+    pub api_base_url: Option<String>,
+    /// Token for the mirror; the product's own when unset.
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+impl FallbackRepo {
+    /// Parses `owner/repo`, taking the endpoint and token from the
+    /// product's other settings.
+    pub fn parse(
+        value: &str,
+        api_base_url: Option<String>,
+        token: Option<String>,
+    ) -> Result<Self, String> {
+        match value.trim().split_once('/') {
+            Some((owner, repo)) if !owner.is_empty() && !repo.is_empty() && !repo.contains('/') => {
+                Ok(FallbackRepo {
+                    owner: owner.to_string(),
+                    repo: repo.to_string(),
+                    api_base_url,
+                    token,
+                })
+            }
+            _ => Err(format!("expected owner/repo, got {}", value)),
+        }
+    }
+}
+
+#[test]
+fn test_parse_fallback_repo() {
+    let fallback = FallbackRepo::parse("acme-mirror/myapp", None, None).unwrap();
+    assert_eq!(fallback.owner, "acme-mirror");
+    assert_eq!(fallback.repo, "myapp");
+    assert!(FallbackRepo::parse("myapp", None, None).is_err());
+    assert!(FallbackRepo::parse("acme/myapp/extra", None, None).is_err());
+}
@@ -1 +1,7 @@
+pub mod breaker;
 pub mod client;
+pub mod fallback;
+pub mod quota;
+pub mod status;
+pub mod sync;
+pub mod transport;
@@ -0,0 +1,98 @@
+use std::cmp::Ordering;
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use reqwest::header::HeaderMap;
+use serde::Serialize;
+
+/// A token's REST API budget, as GitHub reported it in the `X-RateLimit-*`
+/// headers of a response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct RateLimit {
+    pub limit: u64,
+    pub remaining: u64,
+    /// When the budget is refilled.
+    pub reset: DateTime<Utc>,
+    pub observed_at: DateTime<Utc>,
+}
+
+impl RateLimit {
+    /// Reads the headers of a response counted against the `core` budget,
+    /// which release listings and asset downloads draw from.
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let header = |name: &str| headers.get(name)?.to_str().ok();
+        if header("x-ratelimit-resource").is_some_and(|resource| resource != "core") {
+            return None;
+        }
+        let number = |name: &str| header(name)?.parse::<u64>().ok();
+        Some(RateLimit {
+            limit: number("x-ratelimit-limit")?,
+            remaining: number("x-ratelimit-remaining")?,
+            reset: DateTime::from_timestamp(number("x-ratelimit-reset")? as i64, 0)?,
+            observed_at: Utc::now(),
+        })
+    }
+
+    /// The more current of two observations of the same budget. Responses
+    /// may arrive out of order, so within one window the lowest remaining
+    /// count wins.
+    pub fn latest(self, other: RateLimit) -> RateLimit {
+        match self.reset.cmp(&other.reset) {
+            Ordering::Less => other,
+            Ordering::Greater => self,
+            Ordering::Equal if other.remaining < self.remaining => other,
+            Ordering::Equal => self,
+        }
+    }
+}
+
+/// The latest rate limit seen in a client's responses.
+#[derive(Debug, Default)]
+pub struct QuotaTracker {
+    latest: Mutex<Option<RateLimit>>,
+}
+
+impl QuotaTracker {
+    pub fn observe(&self, headers: &HeaderMap) {
+        let Some(observed) = RateLimit::from_headers(headers) else {
+            return;
+        };
+        let mut latest = self.latest.lock().unwrap_or_else(|e| e.into_inner());
+        *latest = Some(match *latest {
+            Some(previous) => previous.latest(observed),
+            None => observed,
+        });
+    }
+
+    pub fn latest(&self) -> Option<RateLimit> {
+        *self.latest.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[test]
+fn test_quota_tracker() {
+    let headers = |remaining: &str, reset: &str| {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-limit", "5000".parse().unwrap());
+        headers.insert("x-ratelimit-remaining", remaining.parse().unwrap());
+        headers.insert("x-ratelimit-reset", reset.parse().unwrap());
+        headers
+    };
+
+    let tracker = QuotaTracker::default();
+    tracker.observe(&HeaderMap::new());
+    assert_eq!(tracker.latest(), None);
+
+    tracker.observe(&headers("4990", "1700000000"));
+    tracker.observe(&headers("4995", "1700000000"));
+    let latest = tracker.latest().unwrap();
+    assert_eq!((latest.limit, latest.remaining), (5000, 4990));
+
+    tracker.observe(&headers("4999", "1700003600"));
+    assert_eq!(tracker.latest().unwrap().remaining, 4999);
+
+    let mut search = headers("1", "1700003600");
+    search.insert("x-ratelimit-resource", "search".parse().unwrap());
+    tracker.observe(&search);
+    assert_eq!(tracker.latest().unwrap().remaining, 4999);
+}
@@ -0,0 +1,93 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::error;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::StatusCode;
+
+use crate::error::ServerError;
+
+/// Seconds clients are asked to wait when GitHub doesn't say how long.
+const DEFAULT_RETRY_AFTER_SECS: u64 = 60;
+
+/// Maps a failed octocrab request to the error reported to clients: `404`
+/// when GitHub has no such resource, `429` when rate limited and `502`
+/// otherwise.
+pub fn from_octocrab(message: &'static str, error: octocrab::Error) -> ServerError {
+    match &error {
+        octocrab::Error::GitHub { source, .. } => {
+            let rate_limited = source.message.to_lowercase().contains("rate limit");
+            error!("{}: GitHub answered {}", message, source.status_code);
+            classify(message, source.status_code.as_u16(), rate_limited, None)
+        }
+        _ => ServerError::upstream(message, error),
+    }
+}
+
+/// Like [`from_octocrab`], for an unsuccessful response to a direct request.
+pub fn from_response(message: &'static str, response: &reqwest::Response) -> ServerError {
+    let headers = response.headers();
+    let rate_limited = headers
+        .get("x-ratelimit-remaining")
+        .is_some_and(|v| v.as_bytes() == b"0");
+    error!("{}: GitHub answered {}", message, response.status());
+    classify(
+        message,
+        response.status().as_u16(),
+        rate_limited,
+        retry_after(headers),
+    )
+}
+
+fn classify(
+    message: &'static str,
+    status: u16,
+    rate_limited: bool,
+    retry_after_secs: Option<u64>,
+) -> ServerError {
+    match StatusCode::from_u16(status) {
+        Ok(StatusCode::NOT_FOUND) => ServerError::UpstreamNotFound(message),
+        Ok(StatusCode::TOO_MANY_REQUESTS) => ServerError::RateLimited {
+            retry_after_secs: retry_after_secs.unwrap_or(DEFAULT_RETRY_AFTER_SECS),
+        },
+        Ok(StatusCode::FORBIDDEN) if rate_limited => ServerError::RateLimited {
+            retry_after_secs: retry_after_secs.unwrap_or(DEFAULT_RETRY_AFTER_SECS),
+        },
+        _ => ServerError::Upstream(message),
+    }
+}
+
+/// Seconds until GitHub accepts requests again, from `Retry-After` or the
+/// `X-RateLimit-Reset` timestamp.
+fn retry_after(headers: &HeaderMap) -> Option<u64> {
+    let header = |name| headers.get(name)?.to_str().ok()?.parse::<u64>().ok();
+
+    header(RETRY_AFTER).or_else(|| {
+        let reset = header(reqwest::header::HeaderName::from_static(
+            "x-ratelimit-reset",
+        ))?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+        Some(reset.saturating_sub(now).max(1))
+    })
+}
+
+#[test]
+fn test_classify_upstream_status() {
+    assert!(matches!(
+        classify("Failed to list releases", 404, false, None),
+        ServerError::UpstreamNotFound(_)
+    ));
+    assert!(matches!(
+        classify("Failed to list releases", 403, true, Some(30)),
+        ServerError::RateLimited {
+            retry_after_secs: 30
+        }
+    ));
+    assert!(matches!(
+        classify("Failed to list releases", 403, false, None),
+        ServerError::Upstream(_)
+    ));
+    assert!(matches!(
+        classify("Failed to list releases", 503, false, None),
+        ServerError::Upstream(_)
+    ));
+}
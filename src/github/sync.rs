@@ -0,0 +1,86 @@
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use serde::Serialize;
+
+/// A release list fetched from GitHub.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct SyncOutcome {
+    pub at: DateTime<Utc>,
+    /// Releases GitHub listed.
+    pub releases: usize,
+    /// Whether they differ from the previous fetch.
+    pub changed: bool,
+}
+
+/// How fetching a product's releases went lately.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ProductSync {
+    pub last_attempt: Option<DateTime<Utc>>,
+    pub last_success: Option<SyncOutcome>,
+}
+
+impl ProductSync {
+    /// Whether the last fetch failed, so the cached releases may be out of
+    /// date, e.g. because the product's token was revoked.
+    pub fn is_stale(&self) -> bool {
+        match (self.last_attempt, self.last_success) {
+            (Some(attempt), Some(success)) => attempt > success.at,
+            (Some(_), None) => true,
+            (None, _) => false,
+        }
+    }
+}
+
+/// When each product's releases were last fetched from GitHub, and with
+/// what outcome.
+#[derive(Debug, Default)]
+pub struct SyncLog {
+    products: DashMap<String, ProductSync>,
+}
+
+impl SyncLog {
+    pub fn attempted(&self, product: &str) {
+        self.products
+            .entry(product.to_string())
+            .or_default()
+            .last_attempt = Some(Utc::now());
+    }
+
+    pub fn succeeded(&self, product: &str, releases: usize, changed: bool) {
+        let mut sync = self.products.entry(product.to_string()).or_default();
+        let at = Utc::now();
+        sync.last_attempt = Some(at);
+        sync.last_success = Some(SyncOutcome {
+            at,
+            releases,
+            changed,
+        });
+    }
+
+    pub fn get(&self, product: &str) -> ProductSync {
+        self.products
+            .get(product)
+            .map(|sync| *sync)
+            .unwrap_or_default()
+    }
+}
+
+#[test]
+fn test_sync_log() {
+    let log = SyncLog::default();
+    assert_eq!(log.get("myapp"), ProductSync::default());
+    assert!(!log.get("myapp").is_stale());
+
+    log.attempted("myapp");
+    assert!(log.get("myapp").is_stale());
+
+    log.succeeded("myapp", 3, true);
+    let sync = log.get("myapp");
+    assert!(!sync.is_stale());
+    assert_eq!(sync.last_success.unwrap().releases, 3);
+
+    std::thread::sleep(std::time::Duration::from_millis(2));
+    log.attempted("myapp");
+    assert!(log.get("myapp").is_stale());
+    assert_eq!(log.get("myapp").last_success, sync.last_success);
+}
@@ -0,0 +1,76 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use bytes::Bytes;
+use http_body_util::BodyExt;
+use log::debug;
+use tower::{BoxError, Service};
+
+use crate::github::quota::QuotaTracker;
+
+/// Sends octocrab's requests through a `reqwest::Client`, so API calls and
+/// asset downloads share one connection pool and one proxy configuration.
+/// Each request is abandoned after `timeout`; the rate limit reported in
+/// responses is recorded in `quota`. Calls are logged at debug level with
+/// the id of the request they're made for, as octocrab polls the returned
+/// future on the caller's task.
+#[derive(Clone)]
+pub struct ReqwestService {
+    client: reqwest::Client,
+    timeout: Duration,
+    quota: Arc<QuotaTracker>,
+}
+
+impl ReqwestService {
+    pub fn new(client: reqwest::Client, timeout: Duration, quota: Arc<QuotaTracker>) -> Self {
+        Self {
+            client,
+            timeout,
+            quota,
+        }
+    }
+}
+
+impl<B> Service<http::Request<B>> for ReqwestService
+where
+    B: http_body::Body<Data = Bytes> + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<BoxError>,
+{
+    type Response = http::Response<reqwest::Body>;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<B>) -> Self::Future {
+        let client = self.client.clone();
+        let timeout = self.timeout;
+        let quota = Arc::clone(&self.quota);
+        Box::pin(async move {
+            let (parts, body) = request.into_parts();
+            let body = body.collect().await.map_err(Into::into)?.to_bytes();
+            let mut request = reqwest::Request::try_from(http::Request::from_parts(parts, body))?;
+            *request.timeout_mut() = Some(timeout);
+            let (method, url) = (request.method().clone(), request.url().clone());
+            let started = Instant::now();
+            let response = client.execute(request).await.inspect_err(|e| {
+                debug!("GitHub {} {} failed: {}", method, url, e);
+            })?;
+            debug!(
+                "GitHub {} {} answered {} in {}ms",
+                method,
+                url,
+                response.status(),
+                started.elapsed().as_millis()
+            );
+            quota.observe(response.headers());
+            Ok(http::Response::from(response))
+        })
+    }
+}
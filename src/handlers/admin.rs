@@ -0,0 +1,547 @@
+use actix_web::middleware::from_fn;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use actix_web::{delete, get, put, web, Error, HttpResponse};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::admin::audit::{AuditEntry, AuditQuery};
+use crate::admin::dashboard::{self, ProductOverview};
+use crate::admin::experiments::Experiment;
+use crate::auth::authenticator::{Actor, RouteGroup};
+use crate::auth::middleware::RequireAuth;
+use crate::config::{AppState, ProductConfig};
+use crate::error::ServerError;
+use crate::github::quota::RateLimit;
+use crate::handlers::query::QueryError;
+use crate::logging::{self, log_context, LogLevels};
+use crate::release::selection::eligible_releases;
+use crate::response::error::json_errors;
+use crate::response::json::json_ok;
+use crate::telemetry::store::{InstallBase, VersionHealth};
+
+#[derive(Serialize)]
+struct MaintenanceStatus {
+    enabled: bool,
+    retry_after_secs: u64,
+}
+
+fn maintenance_status(data: &AppState) -> HttpResponse {
+    json_ok(&MaintenanceStatus {
+        enabled: data.maintenance.is_enabled(),
+        retry_after_secs: data.maintenance.retry_after_secs(),
+    })
+}
+
+#[get(
+    "/admin/maintenance",
+    wrap = "RequireAuth::new(RouteGroup::Admin)",
+    wrap = "from_fn(json_errors)",
+    wrap = "from_fn(log_context)"
+)]
+pub async fn get_maintenance(data: web::Data<AppState>) -> HttpResponse {
+    maintenance_status(&data)
+}
+
+#[derive(Deserialize)]
+pub struct MaintenanceUpdate {
+    pub enabled: bool,
+}
+
+/// Switches maintenance mode on or off without restarting the server.
+#[put(
+    "/admin/maintenance",
+    wrap = "RequireAuth::new(RouteGroup::Admin)",
+    wrap = "from_fn(json_errors)",
+    wrap = "from_fn(log_context)"
+)]
+pub async fn set_maintenance(
+    actor: Actor,
+    update: web::Json<MaintenanceUpdate>,
+    data: web::Data<AppState>,
+) -> HttpResponse {
+    let was_enabled = data.maintenance.set_enabled(update.enabled);
+    data.audit
+        .record(&actor, "maintenance.set", None, was_enabled, update.enabled);
+    if was_enabled != update.enabled {
+        warn!(
+            "Maintenance mode {}",
+            if update.enabled {
+                "enabled"
+            } else {
+                "disabled"
+            }
+        );
+    }
+    maintenance_status(&data)
+}
+
+#[derive(Serialize)]
+struct PausedProducts {
+    paused: Vec<String>,
+}
+
+/// Lists the products whose updates are paused.
+#[get(
+    "/admin/paused",
+    wrap = "RequireAuth::new(RouteGroup::Admin)",
+    wrap = "from_fn(json_errors)",
+    wrap = "from_fn(log_context)"
+)]
+pub async fn get_paused(data: web::Data<AppState>) -> HttpResponse {
+    json_ok(&PausedProducts {
+        paused: data.kill_switch.paused(),
+    })
+}
+
+#[derive(Deserialize)]
+pub struct PauseUpdate {
+    pub paused: bool,
+}
+
+/// Pauses or resumes updates of a single product.
+#[put(
+    "/admin/products/{product_name}/paused",
+    wrap = "RequireAuth::new(RouteGroup::Admin)",
+    wrap = "from_fn(json_errors)",
+    wrap = "from_fn(log_context)"
+)]
+pub async fn set_paused(
+    actor: Actor,
+    path: web::Path<String>,
+    update: web::Json<PauseUpdate>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    let product_name = path.into_inner().to_lowercase();
+    if data.product(&product_name).is_none() {
+        return Err(ServerError::ProductNotFound(product_name).into());
+    }
+
+    let was_paused = data.kill_switch.set_paused(&product_name, update.paused);
+    data.audit.record(
+        &actor,
+        "product.paused",
+        Some(&product_name),
+        was_paused,
+        update.paused,
+    );
+    if was_paused != update.paused {
+        warn!(
+            "Updates of {} {}",
+            product_name,
+            if update.paused { "paused" } else { "resumed" }
+        );
+    }
+
+    Ok(json_ok(&PausedProducts {
+        paused: data.kill_switch.paused(),
+    }))
+}
+
+#[derive(Serialize)]
+struct RunningExperiments {
+    experiments: BTreeMap<String, Experiment>,
+}
+
+/// Lists the running experiments by product.
+#[get(
+    "/admin/experiments",
+    wrap = "RequireAuth::new(RouteGroup::Admin)",
+    wrap = "from_fn(json_errors)",
+    wrap = "from_fn(log_context)"
+)]
+pub async fn get_experiments(data: web::Data<AppState>) -> HttpResponse {
+    json_ok(&RunningExperiments {
+        experiments: data.experiments.running(),
+    })
+}
+
+/// Starts an experiment on one channel of a product, replacing the
+/// product's running experiment. Every group's tag must be a release.
+#[put(
+    "/admin/products/{product_name}/experiment",
+    wrap = "RequireAuth::new(RouteGroup::Admin)",
+    wrap = "from_fn(json_errors)",
+    wrap = "from_fn(log_context)"
+)]
+pub async fn start_experiment(
+    actor: Actor,
+    path: web::Path<String>,
+    experiment: web::Json<Experiment>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    let product_name = path.into_inner().to_lowercase();
+    let Some(product_config) = data.product(&product_name) else {
+        return Err(ServerError::ProductNotFound(product_name).into());
+    };
+
+    let mut experiment = experiment.into_inner();
+    experiment.channel = product_config.resolve_channel(&experiment.channel);
+    if !product_config.allows_channel(&experiment.channel) {
+        return Err(ServerError::UnknownChannel {
+            product: product_name,
+            channel: experiment.channel,
+        }
+        .into());
+    }
+    if let Err(reason) = experiment.validate() {
+        return Err(QueryError::Invalid {
+            field: "experiment",
+            value: experiment.name,
+            reason,
+        }
+        .into());
+    }
+
+    let github = data.github_client(&product_name, &product_config)?;
+    for tag in experiment.groups.iter().filter_map(|g| g.tag.as_deref()) {
+        github
+            .get_release_by_tag(&product_config.repo_owner, &product_config.repo_name, tag)
+            .await?;
+    }
+
+    warn!(
+        "Experiment {} started on {} {}",
+        experiment.name, product_name, experiment.channel
+    );
+    let (experiment, replaced) = data.experiments.start(&product_name, experiment);
+    data.audit.record(
+        &actor,
+        "experiment.started",
+        Some(&product_name),
+        replaced,
+        &experiment,
+    );
+    Ok(json_ok(&experiment))
+}
+
+/// Stops the product's experiment; its devices go back to the channel's
+/// regular release.
+#[delete(
+    "/admin/products/{product_name}/experiment",
+    wrap = "RequireAuth::new(RouteGroup::Admin)",
+    wrap = "from_fn(json_errors)",
+    wrap = "from_fn(log_context)"
+)]
+pub async fn stop_experiment(
+    actor: Actor,
+    path: web::Path<String>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    let product_name = path.into_inner().to_lowercase();
+    let Some(experiment) = data.experiments.stop(&product_name) else {
+        return Err(ServerError::ExperimentNotFound(product_name).into());
+    };
+
+    warn!("Experiment {} on {} stopped", experiment.name, product_name);
+    data.audit.record(
+        &actor,
+        "experiment.stopped",
+        Some(&product_name),
+        &experiment,
+        (),
+    );
+    Ok(json_ok(&experiment))
+}
+
+#[derive(Serialize)]
+struct VersionReport {
+    #[serde(flatten)]
+    health: VersionHealth,
+    failure_rate: Option<f64>,
+    /// The outcomes that count toward the halt policy.
+    verified: VersionHealth,
+    halted: bool,
+}
+
+#[derive(Serialize)]
+struct ProductEvents {
+    versions: BTreeMap<String, VersionReport>,
+    /// What the product's installations reported in their update checks.
+    install_base: InstallBase,
+}
+
+fn product_events(
+    data: &AppState,
+    product_name: &str,
+    product_config: &ProductConfig,
+) -> HttpResponse {
+    let mut health = data.events.health(product_name);
+    for (version, _) in data.rollout_halts.overrides(product_name) {
+        health.entry(version).or_default();
+    }
+
+    let versions = health
+        .into_iter()
+        .map(|(version, health)| {
+            let verified = data.events.verified_health(product_name, &version);
+            let report = VersionReport {
+                health,
+                failure_rate: health.failure_rate(),
+                verified,
+                halted: data.rollout_halts.is_halted(
+                    product_name,
+                    &version,
+                    product_config.halt_policy.as_ref(),
+                    &verified,
+                ),
+            };
+            (version, report)
+        })
+        .collect();
+    json_ok(&ProductEvents {
+        versions,
+        install_base: data.events.install_base(product_name),
+    })
+}
+
+/// Reports the update outcomes clients sent for each version of a product,
+/// and whether its rollout is halted.
+#[get(
+    "/admin/products/{product_name}/events",
+    wrap = "RequireAuth::new(RouteGroup::Admin)",
+    wrap = "from_fn(json_errors)",
+    wrap = "from_fn(log_context)"
+)]
+pub async fn get_events(
+    path: web::Path<String>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    let product_name = path.into_inner().to_lowercase();
+    let Some(product_config) = data.product(&product_name) else {
+        return Err(ServerError::ProductNotFound(product_name).into());
+    };
+    Ok(product_events(&data, &product_name, &product_config))
+}
+
+#[derive(Deserialize)]
+pub struct HaltUpdate {
+    pub halted: bool,
+}
+
+/// Halts or resumes the rollout of a version by hand, overriding its
+/// failure rate.
+#[put(
+    "/admin/products/{product_name}/versions/{version}/halted",
+    wrap = "RequireAuth::new(RouteGroup::Admin)",
+    wrap = "from_fn(json_errors)",
+    wrap = "from_fn(log_context)"
+)]
+pub async fn set_halted(
+    actor: Actor,
+    path: web::Path<(String, String)>,
+    update: web::Json<HaltUpdate>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    let (product_name, version) = path.into_inner();
+    let product_name = product_name.to_lowercase();
+    let Some(product_config) = data.product(&product_name) else {
+        return Err(ServerError::ProductNotFound(product_name).into());
+    };
+    let version = product_config
+        .version_scheme
+        .parse(version.trim_start_matches('v'))
+        .ok_or_else(|| QueryError::Invalid {
+            field: "version",
+            value: version.clone(),
+            reason: format!("not a {} version", product_config.version_scheme.name()),
+        })?
+        .to_string();
+
+    let was_halted = data.rollout_halts.is_halted(
+        &product_name,
+        &version,
+        product_config.halt_policy.as_ref(),
+        &data.events.verified_health(&product_name, &version),
+    );
+    data.rollout_halts
+        .set_halted(&product_name, &version, update.halted);
+    data.audit.record(
+        &actor,
+        "version.halted",
+        Some(&product_name),
+        json!({ "version": version, "halted": was_halted }),
+        json!({ "version": version, "halted": update.halted }),
+    );
+    warn!(
+        "Rollout of {} {} {}",
+        product_name,
+        version,
+        if update.halted { "halted" } else { "resumed" }
+    );
+    Ok(product_events(&data, &product_name, &product_config))
+}
+
+#[derive(Serialize)]
+struct AuditEntries {
+    entries: Vec<AuditEntry>,
+}
+
+/// Lists recorded admin operations, newest first, optionally filtered by
+/// product, action, actor and time.
+#[get(
+    "/admin/audit",
+    wrap = "RequireAuth::new(RouteGroup::Admin)",
+    wrap = "from_fn(json_errors)",
+    wrap = "from_fn(log_context)"
+)]
+pub async fn get_audit(query: web::Query<AuditQuery>, data: web::Data<AppState>) -> HttpResponse {
+    json_ok(&AuditEntries {
+        entries: data.audit.query(&query),
+    })
+}
+
+/// Reports the log levels in effect.
+#[get(
+    "/admin/log-levels",
+    wrap = "RequireAuth::new(RouteGroup::Admin)",
+    wrap = "from_fn(json_errors)",
+    wrap = "from_fn(log_context)"
+)]
+pub async fn get_log_levels() -> HttpResponse {
+    json_ok(&*logging::levels())
+}
+
+/// Replaces the log levels without restarting the server, e.g. to debug a
+/// single product. They last until the server restarts.
+#[put(
+    "/admin/log-levels",
+    wrap = "RequireAuth::new(RouteGroup::Admin)",
+    wrap = "from_fn(json_errors)",
+    wrap = "from_fn(log_context)"
+)]
+pub async fn set_log_levels(
+    actor: Actor,
+    levels: web::Json<LogLevels>,
+    data: web::Data<AppState>,
+) -> HttpResponse {
+    let levels = levels.into_inner();
+    let previous = logging::levels();
+    data.audit
+        .record(&actor, "log_levels.set", None, &*previous, &levels);
+    warn!("Log levels changed by {}", actor.0);
+    logging::set_levels(levels);
+    json_ok(&*logging::levels())
+}
+
+#[derive(Serialize)]
+struct TokenQuota {
+    /// Products using the token; they share its budget.
+    products: Vec<String>,
+    /// As of the latest response; `None` until GitHub was first asked.
+    rate_limit: Option<RateLimit>,
+}
+
+#[derive(Serialize)]
+struct Quotas {
+    tokens: BTreeMap<String, TokenQuota>,
+}
+
+/// Reports how much of each GitHub token's API budget is left, by token
+/// fingerprint, as of the latest response GitHub sent. Nothing is requested
+/// from GitHub to find out.
+#[get(
+    "/admin/quota",
+    wrap = "RequireAuth::new(RouteGroup::Admin)",
+    wrap = "from_fn(json_errors)",
+    wrap = "from_fn(log_context)"
+)]
+pub async fn get_quota(data: web::Data<AppState>) -> HttpResponse {
+    let products: BTreeMap<String, _> = data
+        .products
+        .load()
+        .iter()
+        .map(|(name, config)| (name.clone(), Arc::clone(config)))
+        .collect();
+
+    let mut tokens: BTreeMap<String, TokenQuota> = BTreeMap::new();
+    for (name, product_config) in products {
+        let Ok(github) = data.github_client(&name, &product_config) else {
+            continue;
+        };
+        let rate_limit = github.rate_limit();
+        let quota = tokens
+            .entry(github.token_fingerprint())
+            .or_insert(TokenQuota {
+                products: Vec::new(),
+                rate_limit: None,
+            });
+        quota.products.push(name);
+        quota.rate_limit = match (quota.rate_limit, rate_limit) {
+            (Some(a), Some(b)) => Some(a.latest(b)),
+            (a, b) => a.or(b),
+        };
+    }
+    json_ok(&Quotas { tokens })
+}
+
+/// Errors listed per product on the dashboard.
+const DASHBOARD_ERRORS: usize = 5;
+
+/// Versions of a product whose rollout is halted, by hand or by its policy.
+fn halted_versions(
+    data: &AppState,
+    product_name: &str,
+    product_config: &ProductConfig,
+) -> Vec<String> {
+    let mut health = data.events.health(product_name);
+    for (version, _) in data.rollout_halts.overrides(product_name) {
+        health.entry(version).or_default();
+    }
+    health
+        .into_iter()
+        .filter(|(version, _)| {
+            data.rollout_halts.is_halted(
+                product_name,
+                version,
+                product_config.halt_policy.as_ref(),
+                &data.events.verified_health(product_name, version),
+            )
+        })
+        .map(|(version, _)| version)
+        .collect()
+}
+
+/// A page summarizing every product for operators: its newest cached
+/// release, when releases were last synced, its rollout state and recent
+/// errors. Only cached releases are shown, so it never waits on GitHub.
+#[get(
+    "/admin/ui",
+    wrap = "RequireAuth::new(RouteGroup::Admin)",
+    wrap = "from_fn(json_errors)",
+    wrap = "from_fn(log_context)"
+)]
+pub async fn admin_dashboard(data: web::Data<AppState>) -> HttpResponse {
+    let products: BTreeMap<String, _> = data
+        .products
+        .load()
+        .iter()
+        .map(|(name, config)| (name.clone(), Arc::clone(config)))
+        .collect();
+    let mut experiments = data.experiments.running();
+
+    let mut overviews = Vec::with_capacity(products.len());
+    for (name, product_config) in products {
+        let latest_version = data.release_cache.cached(&name).await.and_then(|releases| {
+            eligible_releases(&releases, &product_config, "stable")
+                .first()
+                .map(|(version, _)| version.to_string())
+        });
+        overviews.push(ProductOverview {
+            latest_version,
+            synced_at: data.syncs.get(&name).last_success.map(|sync| sync.at),
+            paused: data.kill_switch.is_paused(&name),
+            rollout_percentage: product_config.rollout_percentage,
+            experiment: experiments.remove(&name),
+            halted_versions: halted_versions(&data, &name, &product_config),
+            recent_errors: data.recent_errors.recent(&name, DASHBOARD_ERRORS),
+            name,
+        });
+    }
+
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(dashboard::render(&overviews, data.maintenance.is_enabled()))
+}
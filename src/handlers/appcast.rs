@@ -0,0 +1,137 @@
+use actix_web::middleware::from_fn;
+use actix_web::{get, web, Error, HttpResponse};
+use log::{debug, error};
+use serde::Deserialize;
+
+use crate::auth::authenticator::RouteGroup;
+use crate::auth::middleware::RequireAuth;
+use crate::config::AppState;
+use crate::cors::policy as cors;
+use crate::error::ServerError;
+use crate::formats::appcast::{is_macos_archive, pick_archive, render_appcast, AppcastItem};
+use crate::formats::package::download_url_with_token;
+use crate::handlers::query::{slug, QueryError};
+use crate::logging::log_context;
+use crate::notes::locale::localized_notes;
+use crate::platform::matcher::PlatformMatcher;
+use crate::release::selection::eligible_releases;
+use crate::response::cache_control::cache_control;
+use crate::response::compression::compress;
+use crate::response::error::json_errors;
+
+/// Releases listed in an appcast; Sparkle only needs the newest ones.
+const MAX_ITEMS: usize = 10;
+
+#[derive(Debug, Deserialize)]
+pub struct AppcastQuery {
+    /// Release channel, `stable` by default.
+    pub channel: Option<String>,
+    /// `x86_64`, `aarch64` or `universal`; universal builds are preferred when unset.
+    pub arch: Option<String>,
+}
+
+fn parse_arch(arch: &str) -> Result<&'static str, QueryError> {
+    match arch.to_lowercase().as_str() {
+        "x86_64" | "x64" | "intel" => Ok("x86_64"),
+        "aarch64" | "arm64" => Ok("aarch64"),
+        "universal" => Ok("universal"),
+        _ => Err(QueryError::Invalid {
+            field: "arch",
+            value: arch.to_string(),
+            reason: "expected one of x86_64, aarch64, universal".to_string(),
+        }),
+    }
+}
+
+/// Sparkle appcast of the product's macOS archives, for apps that update
+/// with Sparkle rather than the Tauri updater.
+#[get(
+    "/{product_name}/appcast.xml",
+    wrap = "RequireAuth::new(RouteGroup::Update)",
+    wrap = "from_fn(cache_control)",
+    wrap = "compress()",
+    wrap = "from_fn(json_errors)",
+    wrap = "cors::for_group(RouteGroup::Update)",
+    wrap = "from_fn(log_context)"
+)]
+pub async fn appcast(
+    path: web::Path<String>,
+    query: web::Query<AppcastQuery>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    let product_name = slug("product", &path.into_inner())?;
+    let channel = slug("channel", query.channel.as_deref().unwrap_or("stable"))?;
+    let arch = query.arch.as_deref().map(parse_arch).transpose()?;
+
+    let Some(product_config) = data.product(&product_name) else {
+        error!("Product {} not found in configuration", product_name);
+        return Err(ServerError::ProductNotFound(product_name).into());
+    };
+
+    let channel = product_config.resolve_channel(&channel);
+    if !product_config.allows_channel(&channel) {
+        return Err(ServerError::UnknownChannel {
+            product: product_name,
+            channel,
+        }
+        .into());
+    }
+
+    let link = format!("{}/{}/appcast.xml", data.hostname, product_name);
+
+    // An empty feed tells Sparkle there's nothing to install
+    if data.maintenance.is_enabled() || data.kill_switch.is_paused(&product_name) {
+        debug!(
+            "Updates of {} are unavailable, serving an empty appcast",
+            product_name
+        );
+        return Ok(xml_ok(render_appcast(&product_name, &link, &[])));
+    }
+
+    let github = data.github_client(&product_name, &product_config)?;
+    let releases = data
+        .release_cache
+        .releases(&product_name, &github, &product_config)
+        .await?;
+
+    let matcher = PlatformMatcher::new()
+        .with_asset_prefix(product_config.asset_prefix.clone())
+        .with_asset_pattern(product_config.asset_pattern.clone());
+
+    let items: Vec<AppcastItem> = eligible_releases(&releases, &product_config, &channel)
+        .into_iter()
+        .filter_map(|(version, index)| {
+            let release = &releases[index];
+            let names: Vec<&str> = release.assets.iter().map(|a| a.name.as_str()).collect();
+            let archives = matcher.find_packages(&names, Some(&channel), is_macos_archive);
+            let archive = pick_archive(&archives, arch)?;
+            let asset = release.assets.iter().find(|a| a.name == archive)?;
+
+            Some(AppcastItem {
+                title: release
+                    .name
+                    .clone()
+                    .filter(|name| !name.is_empty())
+                    .unwrap_or_else(|| format!("Version {}", version)),
+                version: version.to_string(),
+                pub_date: release.published_at,
+                notes: release.body.as_deref().map(|body| {
+                    product_config
+                        .notes_sanitizer
+                        .sanitize(localized_notes(body, &[]))
+                }),
+                url: download_url_with_token(&data.hostname, &product_config, &product_name, asset),
+                length: asset.size.max(0) as u64,
+            })
+        })
+        .take(MAX_ITEMS)
+        .collect();
+
+    Ok(xml_ok(render_appcast(&product_name, &link, &items)))
+}
+
+fn xml_ok(body: String) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("application/xml; charset=utf-8")
+        .body(body)
+}
@@ -0,0 +1,326 @@
+use std::sync::Arc;
+
+use actix_web::body::MessageBody;
+use actix_web::http::header::LOCATION;
+use actix_web::middleware::from_fn;
+use actix_web::{get, web, Error, HttpResponse};
+use chrono::Utc;
+use log::{debug, error};
+
+use crate::auth::authenticator::RouteGroup;
+use crate::auth::middleware::RequireAuth;
+use crate::config::{AppState, ProductConfig};
+use crate::cors::policy as cors;
+use crate::error::ServerError;
+use crate::formats::apt::{AptRelease, PackageIndexes};
+use crate::formats::gpg;
+use crate::formats::package::download_url_with_token;
+use crate::handlers::query::slug;
+use crate::logging::log_context;
+use crate::platform::matcher::PlatformMatcher;
+use crate::release::selection::eligible_releases;
+use crate::response::cache_control::cache_control;
+use crate::response::compression::compress;
+use crate::response::error::json_errors;
+
+/// Releases whose packages are listed; `apt` only installs the newest, older
+/// ones just allow pinning and downgrades.
+const MAX_RELEASES: usize = 10;
+
+/// A suite of the product's APT repository: one per channel.
+struct Suite {
+    product_config: Arc<ProductConfig>,
+    indexes: PackageIndexes,
+    release: String,
+}
+
+/// Builds the `Packages` indexes and `Release` file of the channel's suite
+/// from the `.deb` assets of its newest releases. While the product's
+/// updates are unavailable, the suite is answered with `503`: `apt` then
+/// keeps the indexes it has, where a freshly signed empty suite would
+/// replace them.
+async fn suite(data: &AppState, product_name: &str, channel: &str) -> Result<Suite, Error> {
+    if data.maintenance.is_enabled() {
+        debug!("Maintenance mode, not serving the APT suite");
+        return Err(data.maintenance.unavailable().into());
+    }
+    let Some(product_config) = data.product(product_name) else {
+        error!("Product {} not found in configuration", product_name);
+        return Err(ServerError::ProductNotFound(product_name.to_string()).into());
+    };
+
+    let channel = product_config.resolve_channel(channel);
+    if !product_config.allows_channel(&channel) {
+        return Err(ServerError::UnknownChannel {
+            product: product_name.to_string(),
+            channel,
+        }
+        .into());
+    }
+
+    if data.kill_switch.is_paused(product_name) {
+        debug!("Updates of {} are paused", product_name);
+        return Err(ServerError::Paused(product_name.to_string()).into());
+    }
+
+    let github = data.github_client(product_name, &product_config)?;
+    let releases = data
+        .release_cache
+        .releases(product_name, &github, &product_config)
+        .await?;
+    let matcher = PlatformMatcher::new()
+        .with_asset_prefix(product_config.asset_prefix.clone())
+        .with_asset_pattern(product_config.asset_pattern.clone());
+
+    let mut entries = Vec::new();
+    let mut date = None;
+    for (_, index) in eligible_releases(&releases, &product_config, &channel)
+        .into_iter()
+        .take(MAX_RELEASES)
+    {
+        let release = &releases[index];
+        date = date.max(release.published_at);
+        let names: Vec<&str> = release.assets.iter().map(|a| a.name.as_str()).collect();
+        let debs = matcher.find_packages(&names, Some(&channel), |name| {
+            name.to_lowercase().ends_with(".deb")
+        });
+        for asset in release
+            .assets
+            .iter()
+            .filter(|a| debs.contains(&a.name.as_str()))
+        {
+            if let Some(package) = data
+                .deb_cache
+                .package(&github, &product_config, asset.id.0)
+                .await?
+            {
+                let filename = format!("pool/{}/{}", asset.id.0, asset.name);
+                let arch = package.field("Architecture").unwrap_or("all").to_string();
+                entries.push((arch, package.index_entry(&filename)));
+            }
+        }
+    }
+
+    // Architecture-independent packages belong in every index
+    let mut indexes = PackageIndexes::new();
+    for (arch, _) in &entries {
+        if arch != "all" {
+            indexes.entry(arch.clone()).or_default();
+        }
+    }
+    if indexes.is_empty() && !entries.is_empty() {
+        indexes.insert("all".to_string(), String::new());
+    }
+    for (arch, index) in indexes.iter_mut() {
+        for (package_arch, entry) in &entries {
+            if package_arch == arch || package_arch == "all" {
+                if !index.is_empty() {
+                    index.push('\n');
+                }
+                index.push_str(entry);
+            }
+        }
+    }
+
+    let metadata = &product_config.package;
+    let release = AptRelease {
+        suite: &channel,
+        origin: &metadata.publisher,
+        label: &metadata.name,
+        description: metadata.description(),
+        date: date.unwrap_or_else(Utc::now),
+        indexes: &indexes,
+    }
+    .render();
+
+    Ok(Suite {
+        product_config,
+        indexes,
+        release,
+    })
+}
+
+/// Runs a GPG operation with the product's APT key, off the async runtime.
+async fn sign(
+    product_config: &ProductConfig,
+    operation: fn(&str, &[u8]) -> Result<Vec<u8>, String>,
+    data: String,
+) -> Result<Vec<u8>, Error> {
+    let Some(key) = product_config.apt_gpg_key.clone() else {
+        return Err(ServerError::AssetNotFound("signature".to_string()).into());
+    };
+    web::block(move || operation(&key, data.as_bytes()))
+        .await?
+        .map_err(|e| ServerError::config("Failed to sign the APT repository", e).into())
+}
+
+fn text_ok(body: impl MessageBody + 'static) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/plain; charset=utf-8")
+        .body(body)
+}
+
+/// The suite's `Release` file, unsigned.
+#[get(
+    "/{product_name}/apt/dists/{suite}/Release",
+    wrap = "RequireAuth::new(RouteGroup::Update)",
+    wrap = "from_fn(cache_control)",
+    wrap = "compress()",
+    wrap = "from_fn(json_errors)",
+    wrap = "cors::for_group(RouteGroup::Update)",
+    wrap = "from_fn(log_context)"
+)]
+pub async fn apt_release(
+    path: web::Path<(String, String)>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    let (product_name, channel) = path.into_inner();
+    let product_name = slug("product", &product_name)?;
+    let channel = slug("channel", &channel)?;
+
+    Ok(text_ok(
+        suite(&data, &product_name, &channel).await?.release,
+    ))
+}
+
+/// The suite's clearsigned `Release` file; not found when the repository
+/// isn't signed, so `apt` falls back to `Release`.
+#[get(
+    "/{product_name}/apt/dists/{suite}/InRelease",
+    wrap = "RequireAuth::new(RouteGroup::Update)",
+    wrap = "from_fn(cache_control)",
+    wrap = "from_fn(json_errors)",
+    wrap = "cors::for_group(RouteGroup::Update)",
+    wrap = "from_fn(log_context)"
+)]
+pub async fn apt_in_release(
+    path: web::Path<(String, String)>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    let (product_name, channel) = path.into_inner();
+    let product_name = slug("product", &product_name)?;
+    let channel = slug("channel", &channel)?;
+
+    let suite = suite(&data, &product_name, &channel).await?;
+    let signed = sign(&suite.product_config, gpg::clearsign, suite.release).await?;
+    Ok(text_ok(signed))
+}
+
+/// Detached signature of the suite's `Release` file.
+#[get(
+    "/{product_name}/apt/dists/{suite}/Release.gpg",
+    wrap = "RequireAuth::new(RouteGroup::Update)",
+    wrap = "from_fn(cache_control)",
+    wrap = "from_fn(json_errors)",
+    wrap = "cors::for_group(RouteGroup::Update)",
+    wrap = "from_fn(log_context)"
+)]
+pub async fn apt_release_signature(
+    path: web::Path<(String, String)>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    let (product_name, channel) = path.into_inner();
+    let product_name = slug("product", &product_name)?;
+    let channel = slug("channel", &channel)?;
+
+    let suite = suite(&data, &product_name, &channel).await?;
+    let signature = sign(&suite.product_config, gpg::detach_sign, suite.release).await?;
+    Ok(text_ok(signature))
+}
+
+/// The `Packages` index of one architecture; empty for architectures
+/// without packages.
+#[get(
+    "/{product_name}/apt/dists/{suite}/main/binary-{arch}/Packages",
+    wrap = "RequireAuth::new(RouteGroup::Update)",
+    wrap = "from_fn(cache_control)",
+    wrap = "compress()",
+    wrap = "from_fn(json_errors)",
+    wrap = "cors::for_group(RouteGroup::Update)",
+    wrap = "from_fn(log_context)"
+)]
+pub async fn apt_packages(
+    path: web::Path<(String, String, String)>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    let (product_name, channel, arch) = path.into_inner();
+    let product_name = slug("product", &product_name)?;
+    let channel = slug("channel", &channel)?;
+
+    let mut suite = suite(&data, &product_name, &channel).await?;
+    Ok(text_ok(suite.indexes.remove(&arch).unwrap_or_default()))
+}
+
+/// The public key that signs the repository, for `/etc/apt/keyrings`.
+#[get(
+    "/{product_name}/apt/key.asc",
+    wrap = "RequireAuth::new(RouteGroup::Update)",
+    wrap = "from_fn(json_errors)",
+    wrap = "cors::for_group(RouteGroup::Update)",
+    wrap = "from_fn(log_context)"
+)]
+pub async fn apt_key(
+    path: web::Path<String>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    let product_name = slug("product", &path.into_inner())?;
+    let Some(product_config) = data.product(&product_name) else {
+        error!("Product {} not found in configuration", product_name);
+        return Err(ServerError::ProductNotFound(product_name).into());
+    };
+
+    let key = sign(
+        &product_config,
+        |key, _| gpg::export_public_key(key),
+        String::new(),
+    )
+    .await?;
+    if key.is_empty() {
+        return Err(
+            ServerError::config("Failed to export the APT key", "key not in keyring").into(),
+        );
+    }
+    Ok(HttpResponse::Ok()
+        .content_type("application/pgp-keys")
+        .body(key))
+}
+
+/// A package file listed in the `Packages` indexes, redirected to the
+/// asset's download URL.
+#[get(
+    "/{product_name}/apt/pool/{asset_id}/{filename}",
+    wrap = "RequireAuth::new(RouteGroup::Download)",
+    wrap = "from_fn(json_errors)",
+    wrap = "cors::for_group(RouteGroup::Download)",
+    wrap = "from_fn(log_context)"
+)]
+pub async fn apt_pool(
+    path: web::Path<(String, u64, String)>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    let (product_name, asset_id, filename) = path.into_inner();
+    let product_name = slug("product", &product_name)?;
+
+    let Some(product_config) = data.product(&product_name) else {
+        error!("Product {} not found in configuration", product_name);
+        return Err(ServerError::ProductNotFound(product_name).into());
+    };
+
+    let github = data.github_client(&product_name, &product_config)?;
+    let releases = data
+        .release_cache
+        .releases(&product_name, &github, &product_config)
+        .await?;
+    let asset = releases
+        .iter()
+        .flat_map(|r| &r.assets)
+        .find(|a| a.id.0 == asset_id && a.name == filename)
+        .ok_or(ServerError::AssetNotFound(filename))?;
+
+    Ok(HttpResponse::Found()
+        .insert_header((
+            LOCATION,
+            download_url_with_token(&data.hostname, &product_config, &product_name, asset),
+        ))
+        .finish())
+}
@@ -0,0 +1,127 @@
+use actix_web::middleware::from_fn;
+use actix_web::{post, web, Error, HttpRequest, HttpResponse};
+use futures_util::future::join_all;
+use serde::{Deserialize, Serialize};
+
+use crate::auth::authenticator::RouteGroup;
+use crate::auth::middleware::RequireAuth;
+use crate::config::AppState;
+use crate::cors::policy as cors;
+use crate::handlers::query::{QueryError, UpdateOptions, UpdateQuery};
+use crate::handlers::update::find_update;
+use crate::logging::log_context;
+use crate::response::builder::DynamicResponse;
+use crate::response::compression::compress;
+use crate::response::error::{error_code, json_errors};
+use crate::response::json::json_ok;
+
+/// Checks answered per request; larger batches are rejected.
+const MAX_CHECKS: usize = 50;
+
+/// One update check of a batch, with the segments of the single-check URL.
+#[derive(Debug, Deserialize)]
+pub struct BatchCheck {
+    pub product: String,
+    #[serde(default = "default_feature")]
+    pub feature: String,
+    pub target: String,
+    pub arch: String,
+    pub current_version: String,
+}
+
+fn default_feature() -> String {
+    "stable".to_string()
+}
+
+/// The outcome of one check, with the status the single-check endpoint
+/// would have answered.
+#[derive(Debug, Serialize)]
+pub struct BatchResult {
+    pub product: String,
+    pub status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub update: Option<DynamicResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<BatchError>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BatchError {
+    pub code: &'static str,
+    pub message: String,
+}
+
+async fn check(data: &AppState, check: &BatchCheck, options: &UpdateOptions) -> BatchResult {
+    let outcome = match UpdateQuery::parse(
+        Some(data),
+        &check.product,
+        &check.feature,
+        &check.target,
+        &check.arch,
+        &check.current_version,
+    ) {
+        Ok(query) => find_update(data, query, options).await,
+        Err(e) => Err(e.into()),
+    };
+
+    let product = check.product.trim().to_lowercase();
+    match outcome {
+        Ok(Some(update)) => BatchResult {
+            product,
+            status: 200,
+            update: Some(update),
+            error: None,
+        },
+        Ok(None) => BatchResult {
+            product,
+            status: 204,
+            update: None,
+            error: None,
+        },
+        Err(e) => {
+            let status = e.as_response_error().status_code();
+            BatchResult {
+                product,
+                status: status.as_u16(),
+                update: None,
+                error: Some(BatchError {
+                    code: error_code(&e, status),
+                    message: e.to_string(),
+                }),
+            }
+        }
+    }
+}
+
+/// Runs several update checks in one round trip, e.g. for a launcher that
+/// manages companion apps. Results come back in request order; query
+/// options and the entitlement and device id headers apply to every check.
+#[post(
+    "/batch/check",
+    wrap = "RequireAuth::new(RouteGroup::Update)",
+    wrap = "compress()",
+    wrap = "from_fn(json_errors)",
+    wrap = "cors::for_group(RouteGroup::Update)",
+    wrap = "from_fn(log_context)"
+)]
+pub async fn batch_check(
+    req: HttpRequest,
+    checks: web::Json<Vec<BatchCheck>>,
+    options: web::Query<UpdateOptions>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    if checks.len() > MAX_CHECKS {
+        return Err(QueryError::Invalid {
+            field: "checks",
+            value: checks.len().to_string(),
+            reason: format!("at most {} checks per batch", MAX_CHECKS),
+        }
+        .into());
+    }
+
+    let options = options.into_inner().with_headers(&req);
+    let results: Vec<BatchResult> =
+        join_all(checks.iter().map(|c| check(&data, c, &options))).await;
+
+    Ok(json_ok(&results))
+}
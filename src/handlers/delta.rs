@@ -0,0 +1,53 @@
+use actix_web::middleware::from_fn;
+use actix_web::{get, web, Error, HttpResponse};
+
+use crate::auth::authenticator::RouteGroup;
+use crate::auth::middleware::RequireAuth;
+use crate::config::AppState;
+use crate::cors::policy as cors;
+use crate::error::ServerError;
+use crate::handlers::query::slug;
+use crate::logging::log_context;
+use crate::response::error::json_errors;
+
+/// A patch built by the delta worker, as advertised in update responses.
+#[get(
+    "/{product_name}/delta/{from_asset_id}/{to_asset_id}",
+    wrap = "RequireAuth::new(RouteGroup::Download)",
+    wrap = "from_fn(json_errors)",
+    wrap = "cors::for_group(RouteGroup::Download)",
+    wrap = "from_fn(log_context)"
+)]
+pub async fn delta_patch(
+    path: web::Path<(String, u64, u64)>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    let (product_name, from_asset_id, to_asset_id) = path.into_inner();
+    let product_name = slug("product", &product_name)?;
+
+    if data.maintenance.is_enabled() {
+        return Err(data.maintenance.unavailable().into());
+    }
+    if !data.product(&product_name).is_some_and(|p| p.delta_updates) {
+        return Err(ServerError::ProductNotFound(product_name).into());
+    }
+
+    let patch = data
+        .delta_cache
+        .get(to_asset_id)
+        .filter(|patch| patch.from_asset_id == from_asset_id)
+        .ok_or_else(|| {
+            ServerError::AssetNotFound(format!("{}-{}.patch", from_asset_id, to_asset_id))
+        })?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/zstd")
+        .insert_header((
+            "Content-Disposition",
+            format!(
+                "attachment; filename={}-{}.patch",
+                from_asset_id, to_asset_id
+            ),
+        ))
+        .body(patch.data.clone()))
+}
@@ -1,46 +1,149 @@
-use actix_web::{get, web, Error, HttpResponse};
+use actix_web::body::SizedStream;
+use actix_web::http::header::{
+    ACCEPT_RANGES, CONTENT_RANGE, CONTENT_TYPE, ETAG, IF_RANGE, LAST_MODIFIED, LOCATION, RANGE,
+};
+use actix_web::http::StatusCode;
+use actix_web::middleware::from_fn;
+use actix_web::{get, web, Error, HttpRequest, HttpResponse};
+use chrono::Utc;
+use futures_util::StreamExt;
 use log::error;
+use serde::Deserialize;
 
+use crate::auth::authenticator::RouteGroup;
+use crate::auth::download_token;
+use crate::auth::middleware::RequireAuth;
 use crate::config::AppState;
-use crate::github::client::GitHubClient;
+use crate::cors::policy as cors;
+use crate::error::ServerError;
+use crate::github::client::AssetRange;
+use crate::handlers::update::offloaded_url;
+use crate::logging::log_context;
+use crate::response::error::json_errors;
 
-#[get("/{product_name}/download/{asset_id}/{filename}")]
+#[derive(Deserialize)]
+pub struct DownloadQuery {
+    /// Download token, for products that require one.
+    token: Option<String>,
+}
+
+#[get(
+    "/{product_name}/download/{asset_id}/{filename}",
+    wrap = "RequireAuth::new(RouteGroup::Download)",
+    wrap = "from_fn(json_errors)",
+    wrap = "cors::for_group(RouteGroup::Download)",
+    wrap = "from_fn(log_context)"
+)]
 pub async fn download_asset(
+    req: HttpRequest,
     path: web::Path<(String, u64, String)>,
+    query: web::Query<DownloadQuery>,
     data: web::Data<AppState>,
 ) -> Result<HttpResponse, Error> {
     let (product_name, asset_id, filename) = path.into_inner();
 
-    let products = data.products.read().await;
-    let product_config = match products.get(&product_name.to_lowercase()) {
-        Some(config) => config.clone(),
-        None => {
-            error!("Product {} not found in configuration", product_name);
-            return Ok(HttpResponse::NotFound().finish());
+    if data.maintenance.is_enabled() {
+        return Err(data.maintenance.unavailable().into());
+    }
+
+    let product_name = product_name.to_lowercase();
+    let Some(product_config) = data.product(&product_name) else {
+        error!("Product {} not found in configuration", product_name);
+        return Err(ServerError::ProductNotFound(product_name).into());
+    };
+
+    if let Some(secret) = &product_config.download_token_secret {
+        let valid = query.token.as_deref().is_some_and(|token| {
+            download_token::verify(
+                secret,
+                &product_name,
+                asset_id,
+                token,
+                Utc::now().timestamp(),
+            )
+        });
+        if !valid {
+            return Err(ServerError::InvalidDownloadToken.into());
         }
+    }
+
+    if let Some(url) = offloaded_url(&data, &product_config, asset_id) {
+        return Ok(HttpResponse::Found()
+            .insert_header((LOCATION, url))
+            .finish());
+    }
+
+    let slot = data
+        .download_slots
+        .acquire(&product_name, product_config.max_concurrent_downloads)?;
+    let header = |name| req.headers().get(name).and_then(|v| v.to_str().ok());
+    let disposition = format!("attachment; filename={}", filename);
+
+    // Mirrored assets are served from disk; ranges are left to GitHub
+    let stored = match &data.local_store {
+        Some(store) if header(RANGE).is_none() => store.open(&product_name, asset_id),
+        _ => None,
     };
+    if let Some((length, chunks)) = stored {
+        let body = Box::pin(
+            data.throttle
+                .wrap(req.peer_addr(), chunks)
+                .map(move |chunk| {
+                    let _ = &slot;
+                    chunk
+                }),
+        );
+        return Ok(HttpResponse::Ok()
+            .insert_header((ACCEPT_RANGES, "bytes"))
+            .insert_header((CONTENT_TYPE, "application/octet-stream"))
+            .append_header(("Content-Disposition", disposition))
+            .body(SizedStream::new(length, body)));
+    }
 
-    let github = GitHubClient::new(product_config.github_token)?;
+    let github = data.github_client(&product_name, &product_config)?;
+    let range = header(RANGE).map(|range| AssetRange {
+        range,
+        if_range: header(IF_RANGE),
+    });
 
-    match github
-        .download_asset(
+    let upstream = github
+        .open_asset(
             asset_id,
             &product_config.repo_owner,
             &product_config.repo_name,
+            range,
         )
-        .await
-    {
-        Ok(bytes) => Ok(HttpResponse::Ok()
-            .append_header((
-                "Content-Disposition",
-                format!("attachment; filename={}", filename),
-            ))
-            .body(bytes)),
-        Err(e) => {
-            error!("Failed to download asset: {}", e);
-            Err(actix_web::error::ErrorInternalServerError(
-                "Failed to download asset",
-            ))
+        .await?;
+
+    let status = match upstream.status().as_u16() {
+        206 => StatusCode::PARTIAL_CONTENT,
+        416 => StatusCode::RANGE_NOT_SATISFIABLE,
+        _ => StatusCode::OK,
+    };
+
+    let mut response = HttpResponse::build(status);
+    response.insert_header((ACCEPT_RANGES, "bytes"));
+    response.append_header(("Content-Disposition", disposition));
+    for name in [CONTENT_TYPE, CONTENT_RANGE, LAST_MODIFIED, ETAG] {
+        if let Some(value) = upstream.headers().get(name.as_str()) {
+            response.insert_header((name, value.as_bytes()));
         }
     }
+
+    let length = upstream.content_length();
+    // The slot is held until the body is sent or the client goes away
+    let body = Box::pin(
+        data.throttle
+            .wrap(req.peer_addr(), upstream.bytes_stream())
+            .map(move |chunk| {
+                let _ = &slot;
+                chunk
+            }),
+    );
+
+    // Without a known length the body is sent chunked
+    Ok(match length {
+        Some(length) => response.body(SizedStream::new(length, body)),
+        None => response.streaming(body),
+    })
 }
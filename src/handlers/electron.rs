@@ -0,0 +1,94 @@
+use actix_web::middleware::from_fn;
+use actix_web::{get, web, Error, HttpResponse};
+
+use crate::auth::authenticator::RouteGroup;
+use crate::auth::middleware::RequireAuth;
+use crate::config::AppState;
+use crate::cors::policy as cors;
+use crate::formats::electron::{parse_feed_name, render_latest_yml, ElectronFile};
+use crate::formats::package::download_url_with_token;
+use crate::handlers::package::package_release;
+use crate::handlers::query::{slug, QueryError};
+use crate::logging::log_context;
+use crate::notes::locale::localized_notes;
+use crate::platform::matcher::MatchError;
+use crate::response::cache_control::cache_control;
+use crate::response::compression::compress;
+use crate::response::error::json_errors;
+
+/// electron-updater feed (`latest.yml`, `beta-mac.yml`, …) of the newest
+/// release, for Electron apps sharing the server with Tauri apps.
+#[get(
+    "/{product_name}/{file:[^/]+\\.yml}",
+    wrap = "RequireAuth::new(RouteGroup::Update)",
+    wrap = "from_fn(cache_control)",
+    wrap = "compress()",
+    wrap = "from_fn(json_errors)",
+    wrap = "cors::for_group(RouteGroup::Update)",
+    wrap = "from_fn(log_context)"
+)]
+pub async fn electron_feed(
+    path: web::Path<(String, String)>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    let (product_name, file) = path.into_inner();
+    let product_name = slug("product", &product_name)?;
+    let Some((channel, platform)) = parse_feed_name(&file) else {
+        return Err(QueryError::Invalid {
+            field: "file",
+            value: file,
+            reason: "expected {channel}.yml, {channel}-mac.yml or {channel}-linux.yml".to_string(),
+        }
+        .into());
+    };
+    let channel = slug("channel", &channel)?;
+
+    let Some(package) = package_release(&data, &product_name, &channel).await? else {
+        return Ok(HttpResponse::NoContent().finish());
+    };
+
+    let packages = package.packages(|name| platform.accepts(name));
+    if packages.is_empty() {
+        return Err(MatchError::NoMatch {
+            target: platform.target().to_string(),
+            arch: "any".to_string(),
+        }
+        .into());
+    }
+
+    let mut files = Vec::with_capacity(packages.len());
+    for asset in packages {
+        let digests = data
+            .digest_cache
+            .digests(&package.github, &package.product_config, asset.id.0)
+            .await?;
+        files.push(ElectronFile {
+            url: download_url_with_token(
+                &data.hostname,
+                &package.product_config,
+                &product_name,
+                asset,
+            ),
+            sha512: digests.sha512,
+            size: asset.size.max(0) as u64,
+        });
+    }
+
+    let release = &package.release;
+    let notes = release.body.as_deref().map(|body| {
+        package
+            .product_config
+            .notes_sanitizer
+            .sanitize(localized_notes(body, &[]))
+    });
+    let yml = render_latest_yml(
+        &package.version.to_string(),
+        &files,
+        release.published_at,
+        notes.as_deref(),
+    );
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/yaml; charset=utf-8")
+        .body(yml))
+}
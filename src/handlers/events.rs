@@ -0,0 +1,179 @@
+use actix_web::middleware::from_fn;
+use actix_web::{post, web, Error, HttpRequest, HttpResponse};
+use chrono::Utc;
+use log::{debug, error, warn};
+use serde::Deserialize;
+
+use crate::auth::authenticator::{Actor, RouteGroup};
+use crate::auth::middleware::RequireAuth;
+use crate::config::{AppState, ProductConfig};
+use crate::cors::policy as cors;
+use crate::error::ServerError;
+use crate::handlers::query::QueryError;
+use crate::logging::log_context;
+use crate::response::error::json_errors;
+use crate::telemetry::halt::{send_alert, HaltAlert};
+use crate::telemetry::store::{EventKind, UpdateEvent};
+
+const MAX_ERROR_LENGTH: usize = 1000;
+
+/// An update outcome as reported by a client.
+#[derive(Debug, Deserialize)]
+pub struct EventReport {
+    pub event: EventKind,
+    /// The version being updated to.
+    pub version: String,
+    #[serde(default)]
+    pub from_version: Option<String>,
+    /// What went wrong, for `update_failed`.
+    #[serde(default)]
+    pub error: Option<String>,
+    /// The reporting installation, unless sent in the `X-Installation-Id`
+    /// header.
+    #[serde(default)]
+    pub installation_id: Option<String>,
+}
+
+fn parse_version(
+    product_config: &ProductConfig,
+    field: &'static str,
+    version: &str,
+) -> Result<String, QueryError> {
+    product_config
+        .version_scheme
+        .parse(version.trim().trim_start_matches('v'))
+        .map(|v| v.to_string())
+        .ok_or_else(|| QueryError::Invalid {
+            field,
+            value: version.to_string(),
+            reason: format!("not a {} version", product_config.version_scheme.name()),
+        })
+}
+
+/// Records an update outcome, so the health of a rollout is visible on the
+/// server. Reports must come from an identified installation or an
+/// authenticated client, and are limited per address by
+/// `EVENTS_RATE_LIMIT`.
+#[post(
+    "/{product_name}/events",
+    wrap = "RequireAuth::new(RouteGroup::Update)",
+    wrap = "from_fn(json_errors)",
+    wrap = "cors::for_group(RouteGroup::Update)",
+    wrap = "from_fn(log_context)"
+)]
+pub async fn report_event(
+    req: HttpRequest,
+    path: web::Path<String>,
+    report: web::Json<EventReport>,
+    actor: Actor,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    data.event_limiter.check(&req)?;
+    let product_name = path.into_inner().to_lowercase();
+    let Some(product_config) = data.product(&product_name) else {
+        error!("Product {} not found in configuration", product_name);
+        return Err(ServerError::ProductNotFound(product_name).into());
+    };
+
+    let report = report.into_inner();
+    let installation_id = report
+        .installation_id
+        .or_else(|| {
+            req.headers()
+                .get("X-Installation-Id")
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+        })
+        .filter(|id| !id.trim().is_empty());
+    let actor = (actor != Actor::anonymous()).then_some(actor.0);
+    if installation_id.is_none() && actor.is_none() {
+        return Err(QueryError::Missing {
+            field: "installation_id",
+        }
+        .into());
+    }
+
+    let version = parse_version(&product_config, "version", &report.version)?;
+    let from_version = report
+        .from_version
+        .map(|v| parse_version(&product_config, "from_version", &v))
+        .transpose()?;
+    if let Some(message) = &report.error {
+        if message.chars().count() > MAX_ERROR_LENGTH {
+            return Err(QueryError::Invalid {
+                field: "error",
+                value: format!("{}…", message.chars().take(20).collect::<String>()),
+                reason: format!("must be at most {} characters", MAX_ERROR_LENGTH),
+            }
+            .into());
+        }
+    }
+
+    if !is_released(&data, &product_name, &product_config, &version).await? {
+        return Err(QueryError::Invalid {
+            field: "version",
+            value: report.version,
+            reason: "no such release".to_string(),
+        }
+        .into());
+    }
+
+    let Some(health) = data.events.record(&UpdateEvent {
+        product: product_name.clone(),
+        event: report.event,
+        version: version.clone(),
+        from_version,
+        error: report.error,
+        installation_id,
+        actor,
+        received_at: Utc::now(),
+    }) else {
+        debug!(
+            "Ignored {:?} for {} {}",
+            report.event, product_name, version
+        );
+        return Ok(HttpResponse::NoContent().finish());
+    };
+    debug!(
+        "Recorded {:?} for {}: {} installed, {} failed",
+        report.event, product_name, health.installed, health.failed
+    );
+
+    // Alert once when the failure rate halts the version, unless it was
+    // resumed by hand. Only reports that are authenticated and name an
+    // installation count toward it.
+    let health = data.events.verified_health(&product_name, &version);
+    let policy = product_config.halt_policy.as_ref();
+    if policy.is_some_and(|policy| policy.exceeded(&health))
+        && data
+            .rollout_halts
+            .is_halted(&product_name, &version, policy, &health)
+        && data.rollout_halts.first_alert(&product_name, &version)
+    {
+        let alert = HaltAlert::new(&product_name, &version, &health);
+        warn!("{}", alert.text);
+        if let Some(url) = product_config.halt_webhook.clone() {
+            actix_web::rt::spawn(async move { send_alert(&url, &alert).await });
+        }
+    }
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Whether `version` is that of a published release of the product.
+async fn is_released(
+    data: &AppState,
+    product_name: &str,
+    product_config: &ProductConfig,
+    version: &str,
+) -> Result<bool, ServerError> {
+    let github = data.github_client(product_name, product_config)?;
+    let releases = data
+        .release_cache
+        .releases(product_name, &github, product_config)
+        .await?;
+    Ok(releases.iter().filter(|r| !r.draft).any(|r| {
+        product_config
+            .tag_version(&r.tag_name)
+            .is_some_and(|v| v.to_string() == version)
+    }))
+}
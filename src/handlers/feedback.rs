@@ -0,0 +1,171 @@
+use actix_web::middleware::from_fn;
+use actix_web::{post, web, Error, HttpRequest, HttpResponse};
+use log::{error, info};
+use semver::Version;
+use serde::Deserialize;
+
+use crate::auth::authenticator::RouteGroup;
+use crate::auth::middleware::RequireAuth;
+use crate::config::AppState;
+use crate::cors::policy as cors;
+use crate::error::ServerError;
+use crate::handlers::query::QueryError;
+use crate::logging::log_context;
+use crate::response::error::json_errors;
+
+const MAX_MESSAGE_LENGTH: usize = 4000;
+
+/// Feedback submissions allowed per client address and minute by default.
+pub const DEFAULT_FEEDBACK_PER_MINUTE: u32 = 10;
+
+/// User feedback on an installed version.
+#[derive(Debug, Deserialize)]
+pub struct FeedbackRequest {
+    /// Rating from 1 to 5.
+    pub rating: u8,
+    #[serde(default)]
+    pub message: String,
+}
+
+impl FeedbackRequest {
+    fn validate(&self) -> Result<(), QueryError> {
+        if !(1..=5).contains(&self.rating) {
+            return Err(QueryError::Invalid {
+                field: "rating",
+                value: self.rating.to_string(),
+                reason: "must be between 1 and 5".to_string(),
+            });
+        }
+        if self.message.chars().count() > MAX_MESSAGE_LENGTH {
+            return Err(QueryError::Invalid {
+                field: "message",
+                value: format!("{}…", self.message.chars().take(20).collect::<String>()),
+                reason: format!("must be at most {} characters", MAX_MESSAGE_LENGTH),
+            });
+        }
+        Ok(())
+    }
+
+    fn issue_body(&self, version: &Version) -> String {
+        let mut body = format!(
+            "**Version:** {}\n**Rating:** {}{} ({}/5)\n",
+            version,
+            "★".repeat(self.rating.into()),
+            "☆".repeat((5 - self.rating).into()),
+            self.rating
+        );
+        let message = self.message.trim();
+        if !message.is_empty() {
+            body.push('\n');
+            for line in message.lines() {
+                body.push_str("> ");
+                body.push_str(&neutralize(line));
+                body.push('\n');
+            }
+        }
+        body
+    }
+}
+
+/// Escapes what would make a message act on GitHub rather than just read:
+/// `@` mentions and `#` references that notify people or link issues,
+/// images that load remote content, and HTML.
+fn neutralize(line: &str) -> String {
+    let mut neutral = String::with_capacity(line.len());
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '&' => neutral.push_str("&amp;"),
+            '<' => neutral.push_str("&lt;"),
+            '>' => neutral.push_str("&gt;"),
+            '@' => neutral.push_str("&#64;"),
+            '#' => neutral.push_str("&#35;"),
+            '!' if chars.peek() == Some(&'[') => neutral.push_str("!\\"),
+            c => neutral.push(c),
+        }
+    }
+    neutral
+}
+
+/// Relays feedback into an issue on the product's repository. Submissions
+/// are limited per address by `FEEDBACK_RATE_LIMIT`.
+#[post(
+    "/{product_name}/feedback/{version}",
+    wrap = "RequireAuth::new(RouteGroup::Update)",
+    wrap = "from_fn(json_errors)",
+    wrap = "cors::for_group(RouteGroup::Update)",
+    wrap = "from_fn(log_context)"
+)]
+pub async fn submit_feedback(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    feedback: web::Json<FeedbackRequest>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    data.feedback_limiter.check(&req)?;
+    let (product_name, version) = path.into_inner();
+    let product_name = product_name.to_lowercase();
+
+    let Some(product_config) = data.product(&product_name) else {
+        error!("Product {} not found in configuration", product_name);
+        return Err(ServerError::ProductNotFound(product_name).into());
+    };
+    if !product_config.feedback_issues {
+        return Err(ServerError::FeedbackDisabled(product_name).into());
+    }
+
+    let version = product_config
+        .version_scheme
+        .parse(version.trim_start_matches('v'))
+        .ok_or_else(|| QueryError::Invalid {
+            field: "version",
+            value: version.clone(),
+            reason: format!("not a {} version", product_config.version_scheme.name()),
+        })?;
+    feedback.validate()?;
+
+    let github = data.github_client(&product_name, &product_config)?;
+    let issue = github
+        .create_issue(
+            &product_config.repo_owner,
+            &product_config.repo_name,
+            &format!("Feedback on v{}: {}/5", version, feedback.rating),
+            &feedback.issue_body(&version),
+            &product_config.feedback_labels,
+        )
+        .await?;
+
+    info!(
+        "Recorded feedback on {} v{} as issue #{}",
+        product_name, version, issue
+    );
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[test]
+fn test_feedback_issue_body() {
+    let feedback = FeedbackRequest {
+        rating: 4,
+        message: "Works great.\nStartup is slow though.".to_string(),
+    };
+    assert!(feedback.validate().is_ok());
+    assert_eq!(
+        feedback.issue_body(&Version::new(1, 2, 0)),
+        "**Version:** 1.2.0\n**Rating:** ★★★★☆ (4/5)\n\n> Works great.\n> Startup is slow though.\n"
+    );
+
+    let hostile = FeedbackRequest {
+        rating: 1,
+        message: "@octocat see #12 ![x](https://evil.example/p.png) <img src=x>".to_string(),
+    };
+    assert_eq!(
+        hostile.issue_body(&Version::new(1, 2, 0)).lines().last(),
+        Some("> &#64;octocat see &#35;12 !\\[x](https://evil.example/p.png) &lt;img src=x&gt;")
+    );
+
+    let invalid = FeedbackRequest {
+        rating: 6,
+        message: String::new(),
+    };
+    assert!(invalid.validate().is_err());
+}
@@ -0,0 +1,88 @@
+use actix_web::middleware::from_fn;
+use actix_web::{get, web, Error, HttpResponse};
+
+use crate::auth::authenticator::RouteGroup;
+use crate::auth::middleware::RequireAuth;
+use crate::config::AppState;
+use crate::cors::policy as cors;
+use crate::formats::appcast::is_macos_archive;
+use crate::formats::homebrew::{app_bundle, pick_cask_archives, Cask, CaskArtifact};
+use crate::formats::package::download_url_with_token;
+use crate::handlers::package::package_release;
+use crate::handlers::query::slug;
+use crate::logging::log_context;
+use crate::platform::matcher::MatchError;
+use crate::response::cache_control::cache_control;
+use crate::response::compression::compress;
+use crate::response::error::json_errors;
+
+/// Homebrew cask of the newest stable macOS release, for a tap that
+/// follows this server.
+#[get(
+    "/{product_name}/homebrew/cask.rb",
+    wrap = "RequireAuth::new(RouteGroup::Update)",
+    wrap = "from_fn(cache_control)",
+    wrap = "compress()",
+    wrap = "from_fn(json_errors)",
+    wrap = "cors::for_group(RouteGroup::Update)",
+    wrap = "from_fn(log_context)"
+)]
+pub async fn homebrew_cask(
+    path: web::Path<String>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    let product_name = slug("product", &path.into_inner())?;
+
+    let Some(package) = package_release(&data, &product_name, "stable").await? else {
+        return Ok(HttpResponse::NoContent().finish());
+    };
+
+    let assets = package.packages(is_macos_archive);
+    let names: Vec<&str> = assets.iter().map(|a| a.name.as_str()).collect();
+    let picked = pick_cask_archives(&names);
+    let Some((_, first)) = picked.first() else {
+        return Err(MatchError::NoMatch {
+            target: "darwin".to_string(),
+            arch: "any".to_string(),
+        }
+        .into());
+    };
+    let app = app_bundle(first);
+
+    let mut artifacts = Vec::with_capacity(picked.len());
+    for (arch, name) in picked {
+        let Some(asset) = assets.iter().find(|a| a.name == name) else {
+            continue;
+        };
+        let digests = data
+            .digest_cache
+            .digests(&package.github, &package.product_config, asset.id.0)
+            .await?;
+        artifacts.push(CaskArtifact {
+            arch,
+            url: download_url_with_token(
+                &data.hostname,
+                &package.product_config,
+                &product_name,
+                asset,
+            ),
+            sha256: digests.sha256,
+        });
+    }
+
+    let metadata = &package.product_config.package;
+    let cask = Cask {
+        token: product_name.clone(),
+        version: package.version.to_string(),
+        name: metadata.name.clone(),
+        desc: metadata.description().to_string(),
+        homepage: metadata.homepage.clone(),
+        app,
+        artifacts,
+        livecheck_url: Some(format!("{}/{}/appcast.xml", data.hostname, product_name)),
+    };
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/x-ruby; charset=utf-8")
+        .body(cask.render()))
+}
@@ -0,0 +1,138 @@
+use std::collections::BTreeMap;
+
+use actix_web::http::header::ACCEPT_LANGUAGE;
+use actix_web::middleware::from_fn;
+use actix_web::{get, web, Error, HttpRequest, HttpResponse};
+use log::{debug, error};
+use serde::Deserialize;
+
+use crate::auth::authenticator::RouteGroup;
+use crate::auth::download_token::with_download_token;
+use crate::auth::middleware::RequireAuth;
+use crate::config::AppState;
+use crate::cors::policy as cors;
+use crate::error::ServerError;
+use crate::handlers::query::slug;
+use crate::handlers::update::{parse_release_version, release_notes, resolve_update, AssetRequest};
+use crate::logging::log_context;
+use crate::notes::feedback::append_feedback_link;
+use crate::notes::locale::{accepted_languages, normalize_tag};
+use crate::platform::matcher::Platform;
+use crate::release::selection::latest_release;
+use crate::response::builder::UpdateResponseBuilder;
+use crate::response::cache_control::cache_control;
+use crate::response::compression::compress;
+use crate::response::error::json_errors;
+use crate::response::hmac::signed_json_ok;
+
+#[derive(Debug, Deserialize)]
+pub struct ManifestQuery {
+    /// Language of the release notes, e.g. `de`; takes precedence over the
+    /// `Accept-Language` header.
+    pub locale: Option<String>,
+}
+
+/// Static `latest.json` manifest covering every platform with a signed asset.
+#[get(
+    "/{product_name}/{feature}/latest.json",
+    wrap = "RequireAuth::new(RouteGroup::Update)",
+    wrap = "from_fn(cache_control)",
+    wrap = "compress()",
+    wrap = "from_fn(json_errors)",
+    wrap = "cors::for_group(RouteGroup::Update)",
+    wrap = "from_fn(log_context)"
+)]
+pub async fn latest_manifest(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    query: web::Query<ManifestQuery>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    let (product_name, feature) = path.into_inner();
+    let product_name = slug("product", &product_name)?;
+    let feature = slug("feature", &feature)?;
+
+    if data.maintenance.is_enabled() {
+        debug!("Maintenance mode, not serving the manifest");
+        return Ok(HttpResponse::NoContent().finish());
+    }
+
+    let Some(product_config) = data.product(&product_name) else {
+        error!("Product {} not found in configuration", product_name);
+        return Err(ServerError::ProductNotFound(product_name).into());
+    };
+
+    let feature = product_config.resolve_channel(&feature);
+    if !product_config.allows_channel(&feature) {
+        return Err(ServerError::UnknownChannel {
+            product: product_name,
+            channel: feature,
+        }
+        .into());
+    }
+
+    if data.kill_switch.is_paused(&product_name) {
+        debug!("Updates of {} are paused", product_name);
+        return Ok(HttpResponse::NoContent().finish());
+    }
+
+    let github = data.github_client(&product_name, &product_config)?;
+    let release = latest_release(&data, &github, &product_name, &product_config, &feature).await?;
+    let version = parse_release_version(&product_config, &release)?;
+
+    let mut platforms = BTreeMap::new();
+    for platform in Platform::supported() {
+        let request = AssetRequest {
+            product: &product_name,
+            platform: &platform,
+            feature: &feature,
+            edition: None,
+        };
+        match resolve_update(&data, &github, &product_config, &release, &request).await {
+            Ok(mut update) => {
+                update.url = with_download_token(
+                    update.url,
+                    &product_config,
+                    &product_name,
+                    update.asset_id,
+                );
+                platforms.insert(platform.key(), update);
+            }
+            Err(e) => debug!("Skipping {} in manifest: {}", platform, e),
+        }
+    }
+
+    let mut languages: Vec<String> = query.locale.iter().map(|l| normalize_tag(l)).collect();
+    languages.extend(
+        req.headers()
+            .get(ACCEPT_LANGUAGE)
+            .and_then(|v| v.to_str().ok())
+            .map(accepted_languages)
+            .unwrap_or_default(),
+    );
+    let localized = release_notes(
+        &data,
+        &github,
+        &product_config,
+        &release,
+        &version,
+        &languages,
+    )
+    .await;
+    let mut notes = (release.body.is_some() || !localized.is_empty())
+        .then(|| product_config.notes_sanitizer.sanitize(&localized));
+    if product_config.feedback_link {
+        notes = Some(append_feedback_link(
+            notes.unwrap_or_default(),
+            release.html_url.as_str(),
+            product_config.notes_sanitizer.max_length,
+        ));
+    }
+
+    let manifest = UpdateResponseBuilder::new(&version)
+        .pub_date(release.published_at.map(|d| d.to_rfc3339()))
+        .notes(notes)
+        .static_manifest(platforms);
+
+    Ok(signed_json_ok(&manifest, &product_config))
+}
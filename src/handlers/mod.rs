@@ -1,2 +1,20 @@
+pub mod admin;
+pub mod appcast;
+pub mod apt;
+pub mod batch;
+pub mod delta;
 pub mod download;
+pub mod electron;
+pub mod events;
+pub mod feedback;
+pub mod homebrew;
+pub mod manifest;
+pub mod nuget;
+pub mod package;
+pub mod pubkey;
+pub mod query;
+pub mod scoop;
+pub mod status;
 pub mod update;
+pub mod winget;
+pub mod yum;
@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+
+use actix_web::middleware::from_fn;
+use actix_web::{get, web, Error, HttpResponse};
+
+use crate::auth::authenticator::RouteGroup;
+use crate::auth::middleware::RequireAuth;
+use crate::config::AppState;
+use crate::cors::policy as cors;
+use crate::error::ServerError;
+use crate::formats::nuget::{
+    nuget_version, render_feed, service_document, ChocolateyInstaller, NugetPackage, METADATA,
+};
+use crate::formats::package::download_url_with_token;
+use crate::formats::winget::classify_installer;
+use crate::handlers::package::{package_release, PackageRelease};
+use crate::handlers::query::slug;
+use crate::logging::log_context;
+use crate::platform::matcher::MatchError;
+use crate::response::cache_control::cache_control;
+use crate::response::compression::compress;
+use crate::response::error::json_errors;
+
+/// Wraps the release's installers, MSI preferred, into a Chocolatey package.
+async fn chocolatey_package(
+    data: &AppState,
+    product_name: &str,
+    package: &PackageRelease,
+) -> Result<NugetPackage, Error> {
+    let mut installers: HashMap<&str, (&str, ChocolateyInstaller)> = HashMap::new();
+    for asset in package.packages(|name| classify_installer(name).is_some()) {
+        let Some((arch @ ("x64" | "x86"), installer_type)) = classify_installer(&asset.name) else {
+            continue;
+        };
+        if installers
+            .get(arch)
+            .is_some_and(|(existing, _)| *existing == "wix" || installer_type != "wix")
+        {
+            continue;
+        }
+        let digests = data
+            .digest_cache
+            .digests(&package.github, &package.product_config, asset.id.0)
+            .await?;
+        let installer = ChocolateyInstaller {
+            url: download_url_with_token(
+                &data.hostname,
+                &package.product_config,
+                product_name,
+                asset,
+            ),
+            sha256: digests.sha256,
+            file_type: if installer_type == "wix" {
+                "msi"
+            } else {
+                "exe"
+            },
+        };
+        installers.insert(arch, (installer_type, installer));
+    }
+    if installers.is_empty() {
+        return Err(MatchError::NoMatch {
+            target: "windows".to_string(),
+            arch: "any".to_string(),
+        }
+        .into());
+    }
+
+    let metadata = &package.product_config.package;
+    Ok(NugetPackage {
+        id: product_name.to_string(),
+        version: nuget_version(&package.version),
+        title: metadata.name.clone(),
+        authors: metadata.publisher.clone(),
+        description: metadata.description().to_string(),
+        project_url: metadata.homepage.clone(),
+        published: package.release.published_at,
+        installer_x64: installers.remove("x64").map(|(_, installer)| installer),
+        installer_x86: installers.remove("x86").map(|(_, installer)| installer),
+    })
+}
+
+fn xml_ok(content_type: &str, body: String) -> HttpResponse {
+    HttpResponse::Ok().content_type(content_type).body(body)
+}
+
+fn feed_base(data: &AppState, product_name: &str) -> String {
+    format!("{}/{}/nuget", data.hostname, product_name)
+}
+
+/// Service document of the product's NuGet v2 feed.
+#[get(
+    "/{product_name}/nuget{slash:/?}",
+    wrap = "RequireAuth::new(RouteGroup::Update)",
+    wrap = "from_fn(json_errors)",
+    wrap = "cors::for_group(RouteGroup::Update)",
+    wrap = "from_fn(log_context)"
+)]
+pub async fn nuget_service(
+    path: web::Path<(String, String)>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    let product_name = slug("product", &path.into_inner().0)?;
+    if data.product(&product_name).is_none() {
+        return Err(ServerError::ProductNotFound(product_name).into());
+    }
+    Ok(xml_ok(
+        "application/xml; charset=utf-8",
+        service_document(&feed_base(&data, &product_name)),
+    ))
+}
+
+#[get(
+    "/{product_name}/nuget/$metadata",
+    wrap = "RequireAuth::new(RouteGroup::Update)",
+    wrap = "from_fn(json_errors)",
+    wrap = "cors::for_group(RouteGroup::Update)",
+    wrap = "from_fn(log_context)"
+)]
+pub async fn nuget_metadata() -> HttpResponse {
+    xml_ok("application/xml; charset=utf-8", METADATA.to_string())
+}
+
+/// The feed's package queries. The feed lists only the newest stable
+/// release, so `Packages`, `FindPackagesById`, `Search` and `GetUpdates`
+/// all answer with that entry, filtered by `id` when given.
+#[get(
+    "/{product_name}/nuget/{query:(Packages|FindPackagesById|Search|GetUpdates)(\\(.*\\))?}",
+    wrap = "RequireAuth::new(RouteGroup::Update)",
+    wrap = "from_fn(cache_control)",
+    wrap = "compress()",
+    wrap = "from_fn(json_errors)",
+    wrap = "cors::for_group(RouteGroup::Update)",
+    wrap = "from_fn(log_context)"
+)]
+pub async fn nuget_packages(
+    path: web::Path<(String, String)>,
+    params: web::Query<HashMap<String, String>>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    let product_name = slug("product", &path.into_inner().0)?;
+    let base = feed_base(&data, &product_name);
+
+    let requested_id = params
+        .get("id")
+        .or_else(|| params.get("packageIds"))
+        .map(|id| id.trim_matches('\'').to_lowercase());
+    let mut packages = Vec::new();
+    if let Some(package) = package_release(&data, &product_name, "stable").await? {
+        if requested_id.is_none_or(|id| id.split('|').any(|id| id == product_name)) {
+            packages.push(chocolatey_package(&data, &product_name, &package).await?);
+        }
+    }
+
+    Ok(xml_ok(
+        "application/atom+xml; charset=utf-8",
+        render_feed(&base, &packages),
+    ))
+}
+
+/// The `.nupkg` of the newest stable release.
+#[get(
+    "/{product_name}/nuget/package/{id}/{version}",
+    wrap = "RequireAuth::new(RouteGroup::Update)",
+    wrap = "from_fn(json_errors)",
+    wrap = "cors::for_group(RouteGroup::Update)",
+    wrap = "from_fn(log_context)"
+)]
+pub async fn nuget_package(
+    path: web::Path<(String, String, String)>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    let (product_name, id, version) = path.into_inner();
+    let product_name = slug("product", &product_name)?;
+
+    let Some(package) = package_release(&data, &product_name, "stable").await? else {
+        if data.maintenance.is_enabled() {
+            return Err(data.maintenance.unavailable().into());
+        }
+        return Err(ServerError::NoEligibleRelease.into());
+    };
+    let nupkg = chocolatey_package(&data, &product_name, &package).await?;
+    if !id.eq_ignore_ascii_case(&nupkg.id) || !version.eq_ignore_ascii_case(&nupkg.version) {
+        return Err(ServerError::ReleaseNotFound(version).into());
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/zip")
+        .insert_header((
+            "Content-Disposition",
+            format!(
+                "attachment; filename=\"{}.{}.nupkg\"",
+                nupkg.id, nupkg.version
+            ),
+        ))
+        .body(nupkg.nupkg()))
+}
@@ -0,0 +1,83 @@
+use std::sync::Arc;
+
+use actix_web::Error;
+use log::{debug, error};
+use octocrab::models::repos::Asset;
+use semver::Version;
+
+use crate::config::{AppState, ProductConfig};
+use crate::error::ServerError;
+use crate::github::client::GitHubClient;
+use crate::handlers::update::parse_release_version;
+use crate::platform::matcher::PlatformMatcher;
+use crate::release::selection::{latest_release, ReleaseRef};
+
+/// The newest release of a product, as listed in package manifests.
+pub(crate) struct PackageRelease {
+    pub product_config: Arc<ProductConfig>,
+    pub github: Arc<GitHubClient>,
+    pub release: ReleaseRef,
+    pub version: Version,
+    pub channel: String,
+}
+
+impl PackageRelease {
+    /// The release's assets for this product and channel that `accept` recognises.
+    pub fn packages(&self, accept: impl Fn(&str) -> bool) -> Vec<&Asset> {
+        let matcher = PlatformMatcher::new()
+            .with_asset_prefix(self.product_config.asset_prefix.clone())
+            .with_asset_pattern(self.product_config.asset_pattern.clone());
+        let names: Vec<&str> = self
+            .release
+            .assets
+            .iter()
+            .map(|a| a.name.as_str())
+            .collect();
+        let packages = matcher.find_packages(&names, Some(&self.channel), accept);
+
+        self.release
+            .assets
+            .iter()
+            .filter(|asset| packages.contains(&asset.name.as_str()))
+            .collect()
+    }
+}
+
+/// Looks up the product's newest release on `channel`, or `None` while its
+/// updates are unavailable (maintenance mode or a paused product).
+pub(crate) async fn package_release(
+    data: &AppState,
+    product_name: &str,
+    channel: &str,
+) -> Result<Option<PackageRelease>, Error> {
+    let Some(product_config) = data.product(product_name) else {
+        error!("Product {} not found in configuration", product_name);
+        return Err(ServerError::ProductNotFound(product_name.to_string()).into());
+    };
+
+    let channel = product_config.resolve_channel(channel);
+    if !product_config.allows_channel(&channel) {
+        return Err(ServerError::UnknownChannel {
+            product: product_name.to_string(),
+            channel,
+        }
+        .into());
+    }
+
+    if data.maintenance.is_enabled() || data.kill_switch.is_paused(product_name) {
+        debug!("Updates of {} are unavailable", product_name);
+        return Ok(None);
+    }
+
+    let github = data.github_client(product_name, &product_config)?;
+    let release = latest_release(data, &github, product_name, &product_config, &channel).await?;
+    let version = parse_release_version(&product_config, &release)?;
+
+    Ok(Some(PackageRelease {
+        product_config,
+        github,
+        release,
+        version,
+        channel,
+    }))
+}
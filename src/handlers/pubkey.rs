@@ -0,0 +1,39 @@
+use actix_web::middleware::from_fn;
+use actix_web::{get, web, Error, HttpResponse};
+use log::error;
+
+use crate::auth::authenticator::RouteGroup;
+use crate::auth::middleware::RequireAuth;
+use crate::config::AppState;
+use crate::cors::policy as cors;
+use crate::error::ServerError;
+use crate::handlers::query::slug;
+use crate::logging::log_context;
+use crate::response::error::json_errors;
+
+/// The product's current updater public key, in the form `tauri.conf.json`
+/// expects, so installers and CI can fetch it from the update server.
+#[get(
+    "/{product_name}/pubkey",
+    wrap = "RequireAuth::new(RouteGroup::Update)",
+    wrap = "from_fn(json_errors)",
+    wrap = "cors::for_group(RouteGroup::Update)",
+    wrap = "from_fn(log_context)"
+)]
+pub async fn updater_pubkey(
+    path: web::Path<String>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    let product_name = slug("product", &path.into_inner())?;
+    let Some(product_config) = data.product(&product_name) else {
+        error!("Product {} not found in configuration", product_name);
+        return Err(ServerError::ProductNotFound(product_name).into());
+    };
+    let Some(pubkey) = product_config.pubkeys.first().cloned() else {
+        return Err(ServerError::NoPublicKey(product_name).into());
+    };
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/plain; charset=utf-8")
+        .body(pubkey))
+}
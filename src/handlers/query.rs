@@ -0,0 +1,349 @@
+use std::future::{ready, Ready};
+use std::sync::Arc;
+
+use actix_web::dev::Payload;
+use actix_web::http::StatusCode;
+use actix_web::{web, FromRequest, HttpMessage, HttpRequest, HttpResponse, ResponseError};
+use semver::Version;
+use serde::{Deserialize, Serialize};
+
+use crate::auth::authenticator::Actor;
+use crate::config::AppState;
+use crate::geo::region::ClientLocation;
+use crate::notes::locale::{accepted_languages, normalize_tag};
+use crate::notes::render::NotesFormat;
+use crate::platform::matcher::Platform;
+use crate::release::version::{Semver, VersionScheme};
+use crate::response::builder::ResponseFormat;
+use crate::telemetry::store::ClientMetadata;
+
+/// Normalized and validated parameters of an update check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpdateQuery {
+    pub product: String,
+    pub feature: String,
+    pub platform: Platform,
+    pub current_version: Version,
+}
+
+/// Optional query-string parameters of an update check.
+#[derive(Debug, Default, Deserialize)]
+pub struct UpdateOptions {
+    /// Serve this release tag instead of the latest release.
+    pub to: Option<String>,
+    /// Response shape, `v1` or `v2` (default).
+    #[serde(default)]
+    pub format: ResponseFormat,
+    /// Release notes format, `markdown` (default) or sanitized `html`.
+    #[serde(default)]
+    pub notes: NotesFormat,
+    /// Entitlement token from the `X-Entitlement-Token` header; never read
+    /// from the query string, which ends up in access logs.
+    #[serde(skip)]
+    pub entitlement: Option<String>,
+    /// Installer edition, e.g. `trial` or `full`. Only honored for
+    /// authenticated clients; an edition claim in the entitlement token
+    /// takes precedence.
+    pub edition: Option<String>,
+    /// Stable identifier of the device, alternatively sent in the
+    /// `X-Device-Id` header; places the device in staged rollouts.
+    pub device_id: Option<String>,
+    /// Distribution token of a private product, alternatively sent in the
+    /// `X-Distribution-Token` header.
+    pub distribution_token: Option<String>,
+    /// Version of the client's operating system, alternatively sent in the
+    /// `X-OS-Version` header.
+    pub os_version: Option<String>,
+    /// Build number of the installed app, alternatively sent in the
+    /// `X-App-Build` header.
+    pub app_build: Option<String>,
+    /// Identifier of the installation, alternatively sent in the
+    /// `X-Installation-Id` header.
+    pub installation_id: Option<String>,
+    /// Language of the release notes, e.g. `de`; takes precedence over the
+    /// `Accept-Language` header.
+    pub locale: Option<String>,
+    /// Languages the client prefers for release notes, from `locale` and
+    /// its `Accept-Language` header.
+    #[serde(skip)]
+    pub languages: Vec<String>,
+    /// Where the client is, from headers set by a CDN or proxy; selects the
+    /// download mirror.
+    #[serde(skip)]
+    pub location: ClientLocation,
+    /// Canary value from the `X-Canary` header or `canary` cookie.
+    #[serde(skip)]
+    pub canary: Option<String>,
+    /// Whether the update routes' authenticators accepted the client.
+    #[serde(skip)]
+    pub authenticated: bool,
+}
+
+impl UpdateOptions {
+    /// Fills in the device id, distribution token and client metadata from
+    /// the request headers where the query string doesn't set them, and the
+    /// client's entitlement token, languages, location, canary value and
+    /// whether it's authenticated.
+    pub fn with_headers(mut self, req: &HttpRequest) -> Self {
+        let header = |name: &str| {
+            req.headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+        };
+        self.entitlement = header("X-Entitlement-Token");
+        self.device_id = self.device_id.or_else(|| header("X-Device-Id"));
+        self.distribution_token = self
+            .distribution_token
+            .or_else(|| header("X-Distribution-Token"));
+        self.os_version = self.os_version.or_else(|| header("X-OS-Version"));
+        self.app_build = self.app_build.or_else(|| header("X-App-Build"));
+        self.installation_id = self.installation_id.or_else(|| header("X-Installation-Id"));
+        self.languages = self.locale.iter().map(|l| normalize_tag(l)).collect();
+        self.languages.extend(
+            header("Accept-Language")
+                .map(|v| accepted_languages(&v))
+                .unwrap_or_default(),
+        );
+        self.location = ClientLocation::from_headers(req.headers());
+        self.canary = header("X-Canary").or_else(|| {
+            req.cookie("canary")
+                .map(|cookie| cookie.value().to_string())
+        });
+        self.authenticated = req
+            .extensions()
+            .get::<Actor>()
+            .is_some_and(|actor| *actor != Actor::anonymous());
+        self
+    }
+
+    /// What the client told about itself.
+    pub fn client_metadata(&self) -> ClientMetadata {
+        ClientMetadata {
+            os_version: self.os_version.clone(),
+            app_build: self.app_build.clone(),
+            installation_id: self.installation_id.clone(),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error, Serialize)]
+#[serde(tag = "error", rename_all = "snake_case")]
+pub enum QueryError {
+    #[error("Missing parameter {field}")]
+    Missing { field: &'static str },
+    #[error("Invalid {field} '{value}': {reason}")]
+    Invalid {
+        field: &'static str,
+        value: String,
+        reason: String,
+    },
+}
+
+impl ResponseError for QueryError {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::BAD_REQUEST
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status_code()).json(self)
+    }
+}
+
+impl UpdateQuery {
+    /// Validates an update check; the current version must follow the
+    /// product's version scheme, or semver for unknown products.
+    pub fn parse(
+        data: Option<&AppState>,
+        product: &str,
+        feature: &str,
+        target: &str,
+        arch: &str,
+        current_version: &str,
+    ) -> Result<Self, QueryError> {
+        let product = slug("product", product)?;
+        let scheme = data
+            .and_then(|data| data.product(&product))
+            .map(|product| Arc::clone(&product.version_scheme));
+        Ok(UpdateQuery {
+            feature: slug("feature", feature)?,
+            platform: Platform {
+                target: one_of("target", normalize_target(target), &TARGETS)?,
+                arch: one_of("arch", normalize_arch(arch), &ARCHES)?,
+            },
+            current_version: parse_version(scheme.as_deref().unwrap_or(&Semver), current_version)?,
+            product,
+        })
+    }
+}
+
+impl FromRequest for UpdateQuery {
+    type Error = QueryError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let param = |field: &'static str| {
+            req.match_info()
+                .get(field)
+                .ok_or(QueryError::Missing { field })
+        };
+
+        let data = req.app_data::<web::Data<AppState>>();
+        ready((|| {
+            UpdateQuery::parse(
+                data.map(|data| data.get_ref()),
+                param("product_name")?,
+                param("feature")?,
+                param("target")?,
+                param("arch")?,
+                param("current_version")?,
+            )
+        })())
+    }
+}
+
+/// Operating systems Tauri reports as `{{target}}`.
+const TARGETS: [&str; 3] = ["windows", "darwin", "linux"];
+
+/// Architectures Tauri reports as `{{arch}}`.
+const ARCHES: [&str; 4] = ["x86_64", "i686", "aarch64", "armv7"];
+
+pub(crate) fn slug(field: &'static str, value: &str) -> Result<String, QueryError> {
+    let value = value.trim().to_lowercase();
+    if value.is_empty() {
+        return Err(QueryError::Missing { field });
+    }
+    if !value
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(QueryError::Invalid {
+            field,
+            value,
+            reason: "only letters, digits, '-' and '_' are allowed".to_string(),
+        });
+    }
+    Ok(value)
+}
+
+fn one_of(field: &'static str, value: String, allowed: &[&str]) -> Result<String, QueryError> {
+    if allowed.contains(&value.as_str()) {
+        Ok(value)
+    } else {
+        Err(QueryError::Invalid {
+            field,
+            value,
+            reason: format!("expected one of {}", allowed.join(", ")),
+        })
+    }
+}
+
+fn normalize_target(target: &str) -> String {
+    let target = target.trim().to_lowercase();
+    match target.as_str() {
+        "macos" | "osx" | "mac" => "darwin".to_string(),
+        "win" | "win32" | "win64" => "windows".to_string(),
+        _ => target,
+    }
+}
+
+fn normalize_arch(arch: &str) -> String {
+    let arch = arch.trim().to_lowercase();
+    match arch.as_str() {
+        "x64" | "amd64" => "x86_64".to_string(),
+        "arm64" => "aarch64".to_string(),
+        "armhf" | "arm" => "armv7".to_string(),
+        "x86" | "i386" => "i686".to_string(),
+        _ => arch,
+    }
+}
+
+fn parse_version(scheme: &dyn VersionScheme, version: &str) -> Result<Version, QueryError> {
+    let trimmed = version.trim();
+    scheme
+        .parse(trimmed.trim_start_matches('v'))
+        .ok_or_else(|| QueryError::Invalid {
+            field: "current_version",
+            value: trimmed.to_string(),
+            reason: format!("not a {} version", scheme.name()),
+        })
+}
+
+#[test]
+fn test_update_query_normalization() {
+    let query = UpdateQuery::parse(None, "MyApp", "Beta", "macos", "arm64", "v1.2.3").unwrap();
+    assert_eq!(query.product, "myapp");
+    assert_eq!(query.feature, "beta");
+    assert_eq!(query.platform.target, "darwin");
+    assert_eq!(query.platform.arch, "aarch64");
+    assert_eq!(query.current_version, Version::new(1, 2, 3));
+}
+
+#[test]
+fn test_update_query_rejects_unknown_platform() {
+    let err = UpdateQuery::parse(None, "myapp", "stable", "beos", "x86_64", "1.0.0").unwrap_err();
+    assert!(matches!(
+        err,
+        QueryError::Invalid {
+            field: "target",
+            ..
+        }
+    ));
+
+    let err = UpdateQuery::parse(None, "myapp", "stable", "linux", "sparc", "1.0.0").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Invalid arch 'sparc': expected one of x86_64, i686, aarch64, armv7"
+    );
+}
+
+#[test]
+fn test_update_query_rejects_bad_version() {
+    let err =
+        UpdateQuery::parse(None, "myapp", "stable", "windows", "x86_64", "latest").unwrap_err();
+    assert!(matches!(
+        err,
+        QueryError::Invalid {
+            field: "current_version",
+            ..
+        }
+    ));
+}
+
+#[test]
+fn test_update_query_uses_product_scheme() {
+    use crate::config::ProductConfig;
+    use crate::release::version::FourPart;
+
+    let mut product = ProductConfig::new("token", "acme", "winapp");
+    product.version_scheme = Arc::new(FourPart);
+    let data = AppState::builder()
+        .product("winapp", product)
+        .product("myapp", ProductConfig::new("token", "acme", "myapp"))
+        .build();
+
+    let query = UpdateQuery::parse(
+        Some(&data),
+        "winapp",
+        "stable",
+        "windows",
+        "x86_64",
+        "1.2.3.10",
+    )
+    .unwrap();
+    assert!(query.current_version > FourPart.parse("1.2.3.9").unwrap());
+
+    let err = UpdateQuery::parse(
+        Some(&data),
+        "myapp",
+        "stable",
+        "windows",
+        "x86_64",
+        "1.2.3.10",
+    )
+    .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Invalid current_version '1.2.3.10': not a semver version"
+    );
+}
@@ -0,0 +1,145 @@
+use std::collections::BTreeMap;
+
+use actix_web::http::header::LOCATION;
+use actix_web::middleware::from_fn;
+use actix_web::{get, web, Error, HttpResponse};
+use log::error;
+
+use crate::auth::authenticator::RouteGroup;
+use crate::auth::middleware::RequireAuth;
+use crate::config::AppState;
+use crate::cors::policy as cors;
+use crate::error::ServerError;
+use crate::formats::package::download_url_with_token;
+use crate::formats::scoop::{scoop_arch, ScoopDownload, ScoopManifest};
+use crate::handlers::package::package_release;
+use crate::handlers::query::slug;
+use crate::logging::log_context;
+use crate::platform::matcher::{MatchError, PlatformMatcher};
+use crate::response::cache_control::cache_control;
+use crate::response::compression::compress;
+use crate::response::error::json_errors;
+use crate::response::json::json_ok;
+
+/// Scoop manifest of the newest stable release's MSI installers, with
+/// `checkver` and `autoupdate` pointing back at this server.
+#[get(
+    "/scoop/{product_name}.json",
+    wrap = "RequireAuth::new(RouteGroup::Update)",
+    wrap = "from_fn(cache_control)",
+    wrap = "compress()",
+    wrap = "from_fn(json_errors)",
+    wrap = "cors::for_group(RouteGroup::Update)",
+    wrap = "from_fn(log_context)"
+)]
+pub async fn scoop_manifest(
+    path: web::Path<String>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    let product_name = slug("product", &path.into_inner())?;
+
+    let Some(package) = package_release(&data, &product_name, "stable").await? else {
+        return Ok(HttpResponse::NoContent().finish());
+    };
+
+    let mut architecture = BTreeMap::new();
+    for asset in package.packages(|name| scoop_arch(name).is_some()) {
+        let Some(arch) = scoop_arch(&asset.name) else {
+            continue;
+        };
+        if architecture.contains_key(arch) {
+            continue;
+        }
+        let digests = data
+            .digest_cache
+            .digests(&package.github, &package.product_config, asset.id.0)
+            .await?;
+        architecture.insert(
+            arch,
+            ScoopDownload {
+                url: download_url_with_token(
+                    &data.hostname,
+                    &package.product_config,
+                    &product_name,
+                    asset,
+                ),
+                hash: digests.sha256,
+            },
+        );
+    }
+    if architecture.is_empty() {
+        return Err(MatchError::NoMatch {
+            target: "windows".to_string(),
+            arch: "any".to_string(),
+        }
+        .into());
+    }
+
+    let versioned_base = format!("{}/scoop/{}", data.hostname, product_name);
+    let manifest = ScoopManifest::new(
+        package.version.to_string(),
+        &package.product_config.package,
+        architecture,
+        &format!("{}.json", versioned_base),
+        &versioned_base,
+    );
+
+    Ok(json_ok(&manifest))
+}
+
+/// Redirects to the MSI installer of a given version, so Scoop's
+/// `autoupdate` can build download URLs from the version alone.
+#[get(
+    "/scoop/{product_name}/{version}/{arch}",
+    wrap = "RequireAuth::new(RouteGroup::Download)",
+    wrap = "from_fn(json_errors)",
+    wrap = "cors::for_group(RouteGroup::Download)",
+    wrap = "from_fn(log_context)"
+)]
+pub async fn scoop_download(
+    path: web::Path<(String, String, String)>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    let (product_name, version, arch) = path.into_inner();
+    let product_name = slug("product", &product_name)?;
+
+    let Some(product_config) = data.product(&product_name) else {
+        error!("Product {} not found in configuration", product_name);
+        return Err(ServerError::ProductNotFound(product_name).into());
+    };
+
+    let github = data.github_client(&product_name, &product_config)?;
+    let releases = data
+        .release_cache
+        .releases(&product_name, &github, &product_config)
+        .await?;
+
+    let wanted = product_config
+        .version_scheme
+        .parse(version.trim_start_matches('v'));
+    let release = releases
+        .iter()
+        .filter(|r| !r.draft)
+        .find(|r| wanted.is_some() && product_config.tag_version(&r.tag_name) == wanted)
+        .ok_or_else(|| ServerError::ReleaseNotFound(version.clone()))?;
+
+    let names: Vec<&str> = release.assets.iter().map(|a| a.name.as_str()).collect();
+    let matcher = PlatformMatcher::new()
+        .with_asset_prefix(product_config.asset_prefix.clone())
+        .with_asset_pattern(product_config.asset_pattern.clone());
+    let installer = matcher
+        .find_packages(&names, None, |name| scoop_arch(name) == Some(arch.as_str()))
+        .first()
+        .and_then(|name| release.assets.iter().find(|a| a.name == *name))
+        .ok_or_else(|| MatchError::NoMatch {
+            target: "windows".to_string(),
+            arch: arch.clone(),
+        })?;
+
+    Ok(HttpResponse::Found()
+        .insert_header((
+            LOCATION,
+            download_url_with_token(&data.hostname, &product_config, &product_name, installer),
+        ))
+        .finish())
+}
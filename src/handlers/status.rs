@@ -0,0 +1,161 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use actix_web::middleware::from_fn;
+use actix_web::{get, web, HttpResponse};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::auth::authenticator::RouteGroup;
+use crate::auth::middleware::RequireAuth;
+use crate::build_info::BuildInfo;
+use crate::cache::gc::GcReport;
+use crate::config::{AppState, ProductStatus};
+use crate::formats::minisign::PublicKey;
+use crate::github::breaker::{BreakerState, UpstreamFailure};
+use crate::github::sync::SyncOutcome;
+use crate::logging::log_context;
+use crate::release::selection::eligible_releases;
+use crate::response::error::json_errors;
+use crate::response::json::json_ok;
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum OverallStatus {
+    Ok,
+    /// Some products can't reach GitHub or weren't loaded.
+    Degraded,
+}
+
+#[derive(Serialize)]
+struct ProductHealth {
+    #[serde(flatten)]
+    status: ProductStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    latest_version: Option<String>,
+    /// The last successful fetch of the product's releases.
+    last_sync: Option<SyncOutcome>,
+    last_sync_attempt: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cache_age_secs: Option<i64>,
+    /// Whether the last fetch failed, so the releases may be out of date.
+    stale: bool,
+    paused: bool,
+    circuit: BreakerState,
+    /// The primary repository's state, for products with a fallback one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    failover: Option<BreakerState>,
+    last_error: Option<UpstreamFailure>,
+    /// Ids of the configured updater public keys, the current one first.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    public_keys: Vec<String>,
+    /// Ids of the keys that signed each release served so far.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    signed_with: BTreeMap<String, BTreeSet<String>>,
+}
+
+#[derive(Serialize)]
+struct ServerStatus {
+    status: OverallStatus,
+    maintenance: bool,
+    products: BTreeMap<String, ProductHealth>,
+    /// Bytes held by the in-memory caches.
+    cache_memory_bytes: u64,
+    /// What the last cache garbage collection freed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_gc: Option<GcReport>,
+}
+
+/// Summarizes the health of every configured product for monitoring:
+/// its newest cached release, when its releases were last fetched, the last
+/// error GitHub returned, whether requests to GitHub are suspended and which
+/// keys signed its releases, plus how much memory the caches take up and
+/// what the last cache garbage collection freed. Only cached data is reported, so checking it never costs GitHub
+/// requests.
+#[get(
+    "/status",
+    wrap = "RequireAuth::new(RouteGroup::Admin)",
+    wrap = "from_fn(json_errors)",
+    wrap = "from_fn(log_context)"
+)]
+pub async fn server_status(data: web::Data<AppState>) -> HttpResponse {
+    let now = Utc::now();
+    let mut degraded = false;
+    let mut products = BTreeMap::new();
+
+    // Products skipped at startup, plus whichever are served now
+    let mut statuses: BTreeMap<String, ProductStatus> = data
+        .product_status
+        .iter()
+        .filter(|(_, status)| matches!(status, ProductStatus::Skipped { .. }))
+        .map(|(name, status)| (name.clone(), status.clone()))
+        .collect();
+    for name in data.products.load().keys() {
+        statuses.insert(name.clone(), ProductStatus::Loaded);
+    }
+
+    for (name, product_status) in statuses {
+        let breaker = data.release_cache.breaker(&name);
+        let sync = data.syncs.get(&name);
+        let mut health = ProductHealth {
+            status: product_status,
+            latest_version: None,
+            last_sync: sync.last_success,
+            last_sync_attempt: sync.last_attempt,
+            cache_age_secs: sync
+                .last_success
+                .map(|success| (now - success.at).num_seconds().max(0)),
+            stale: sync.is_stale(),
+            paused: data.kill_switch.is_paused(&name),
+            circuit: breaker.state(),
+            failover: data
+                .github_clients
+                .get(&name)
+                .and_then(|client| client.failover_state()),
+            last_error: breaker.last_failure(),
+            public_keys: Vec::new(),
+            signed_with: data.signing_keys.get(&name),
+        };
+        degraded |= health.circuit != BreakerState::Closed
+            || health
+                .failover
+                .is_some_and(|state| state != BreakerState::Closed)
+            || matches!(health.status, ProductStatus::Skipped { .. });
+
+        if let Some(product_config) = data.product(&name) {
+            health.public_keys = product_config
+                .public_keys()
+                .iter()
+                .map(PublicKey::key_id_hex)
+                .collect();
+            if let Some(releases) = data.release_cache.cached(&name).await {
+                health.latest_version = eligible_releases(&releases, &product_config, "stable")
+                    .first()
+                    .map(|(version, _)| version.to_string());
+            }
+        }
+        products.insert(name, health);
+    }
+
+    json_ok(&ServerStatus {
+        status: if degraded {
+            OverallStatus::Degraded
+        } else {
+            OverallStatus::Ok
+        },
+        maintenance: data.maintenance.is_enabled(),
+        products,
+        cache_memory_bytes: data.memory_budget.used(),
+        last_gc: data.gc_log.last(),
+    })
+}
+
+/// Reports which build is running: its version, git commit, build time and
+/// enabled features.
+#[get(
+    "/version",
+    wrap = "from_fn(json_errors)",
+    wrap = "from_fn(log_context)"
+)]
+pub async fn build_version() -> HttpResponse {
+    json_ok(&BuildInfo::current())
+}
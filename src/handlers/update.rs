@@ -1,124 +1,713 @@
-use actix_web::{get, web, Error, HttpResponse};
+use actix_web::middleware::from_fn;
+use actix_web::{get, web, Error, HttpRequest, HttpResponse};
+use chrono::Utc;
 use log::{debug, error};
+use octocrab::models::repos::Release;
 use semver::Version;
-use serde::Serialize;
 
-use crate::config::AppState;
+use crate::auth::authenticator::RouteGroup;
+use crate::auth::download_token::with_download_token;
+use crate::auth::middleware::RequireAuth;
+use crate::cache::decision::DecisionKey;
+use crate::config::{AppState, ProductConfig};
+use crate::cors::policy as cors;
+use crate::error::ServerError;
+use crate::formats::minisign::Signature;
+use crate::formats::package::download_url;
+use crate::geo::mirror::mirror_url;
 use crate::github::client::GitHubClient;
-use crate::platform::matcher::{Platform, PlatformMatcher};
-
-#[derive(Serialize)]
-pub struct UpdateResponse {
-    version: String,
-    pub_date: String,
-    url: String,
-    signature: String,
-    notes: String,
-}
+use crate::handlers::query::{UpdateOptions, UpdateQuery};
+use crate::license::validator::{LicenseError, LicenseRequest, LicenseValidator};
+use crate::license::webhook::EntitlementWebhook;
+use crate::local::store::LocalStore;
+use crate::logging::log_context;
+use crate::notes::aggregate::aggregate_notes;
+use crate::notes::changelog::changelog_section;
+use crate::notes::feedback::append_feedback_link;
+use crate::notes::locale::localized_notes;
+use crate::platform::matcher::{MatchError, Platform, PlatformMatcher};
+use crate::release::compatibility::{required_os, OsVersion};
+use crate::release::rollout::in_rollout;
+use crate::release::rules::RuleContext;
+use crate::release::selection::{
+    latest_release, latest_release_before, latest_release_in_major, latest_stable_release,
+    newest_release, ReleaseRef,
+};
+use crate::response::builder::{
+    DynamicResponse, PatchUpdate, PlatformUpdate, UpdateResponseBuilder,
+};
+use crate::response::cache_control::cache_control;
+use crate::response::compression::compress;
+use crate::response::error::json_errors;
+use crate::response::hmac::signed_json_ok;
+use crate::response::json::json_ok;
 
-#[get("/{product_name}/{feature}/{target}/{arch}/{current_version}")]
+#[get(
+    "/{product_name}/{feature}/{target}/{arch}/{current_version}",
+    wrap = "RequireAuth::new(RouteGroup::Update)",
+    wrap = "from_fn(cache_control)",
+    wrap = "compress()",
+    wrap = "from_fn(json_errors)",
+    wrap = "cors::for_group(RouteGroup::Update)",
+    wrap = "from_fn(log_context)"
+)]
 pub async fn check_update(
-    path: web::Path<(String, String, String, String, String)>,
+    req: HttpRequest,
+    query: UpdateQuery,
+    options: web::Query<UpdateOptions>,
     data: web::Data<AppState>,
 ) -> Result<HttpResponse, Error> {
-    let (product_name, feature, target, arch, current_version) = path.into_inner();
+    let options = options.into_inner().with_headers(&req);
+    let product = query.product.clone();
+    match find_update(&data, query, &options).await? {
+        Some(update) => match data.product(&product) {
+            Some(product_config) => Ok(signed_json_ok(&update, &product_config)),
+            None => Ok(json_ok(&update)),
+        },
+        None => Ok(HttpResponse::NoContent().finish()),
+    }
+}
+
+/// Asks the product's license validator, then its entitlement webhook,
+/// whether the client may be served updates.
+async fn check_license(
+    product_config: &ProductConfig,
+    github: &GitHubClient,
+    request: LicenseRequest<'_>,
+) -> Result<(), ServerError> {
+    let webhook = product_config
+        .entitlement_webhook
+        .as_deref()
+        .map(|url| EntitlementWebhook::new(url, github.http().clone()));
+    let mut result = product_config.license_validator.validate(request).await;
+    if let (Ok(()), Some(webhook)) = (&result, &webhook) {
+        result = webhook.validate(request).await;
+    }
+    result.map_err(|e| match e {
+        LicenseError::Denied(reason) => ServerError::LicenseDenied(reason),
+        LicenseError::Unavailable(cause) => {
+            ServerError::upstream("Licensing service unavailable", cause)
+        }
+    })
+}
 
+/// Runs an update check, returning the response body or `None` when the
+/// client is up to date (or updates are unavailable).
+pub(crate) async fn find_update(
+    data: &AppState,
+    mut query: UpdateQuery,
+    options: &UpdateOptions,
+) -> Result<Option<DynamicResponse>, Error> {
     debug!(
-        "Checking for update for product {}, feature {}, target {}, arch {}, current version {}",
-        product_name, feature, target, arch, current_version
+        "Checking for update for product {}, feature {}, platform {}, current version {}",
+        query.product, query.feature, query.platform, query.current_version
     );
 
-    // Get product configuration
-    let products = data.products.read().await;
-    let product_config = match products.get(&product_name.to_lowercase()) {
-        Some(config) => config.clone(),
+    if data.maintenance.is_enabled() {
+        debug!("Maintenance mode, not offering updates");
+        return Ok(None);
+    }
+
+    // Get product configuration; private products look nonexistent to
+    // clients without their distribution token
+    let Some(product_config) = data
+        .product(&query.product)
+        .filter(|product| product.is_visible_to(options.distribution_token.as_deref()))
+    else {
+        error!("Product {} not found in configuration", query.product);
+        return Err(ServerError::ProductNotFound(query.product.clone()).into());
+    };
+
+    query.feature = product_config.resolve_channel(&query.feature);
+    if !product_config.allows_channel(&query.feature) {
+        return Err(ServerError::UnknownChannel {
+            product: query.product,
+            channel: query.feature,
+        }
+        .into());
+    }
+
+    if data.kill_switch.is_paused(&query.product) {
+        debug!("Updates of {} are paused", query.product);
+        return Ok(None);
+    }
+
+    if product_config.maintenance_windows.is_open(Utc::now()) {
+        debug!("Maintenance window of {} is open", query.product);
+        return Ok(None);
+    }
+
+    let client = options.client_metadata();
+    data.events.record_check(
+        &query.product,
+        &query.current_version.to_string(),
+        &query.platform.key(),
+        &client,
+        options.device_id.as_deref(),
+    );
+
+    let github = data.github_client(&query.product, &product_config)?;
+
+    check_license(
+        &product_config,
+        &github,
+        LicenseRequest {
+            product: &query.product,
+            channel: &query.feature,
+            platform: &query.platform,
+            current_version: &query.current_version,
+            entitlement: options.entitlement.as_deref(),
+            device_id: options.device_id.as_deref(),
+            client: &client,
+        },
+    )
+    .await?;
+
+    // Devices in an experiment group with a tag are served that release
+    let experiment_tag = match (
+        &options.to,
+        data.experiments.get(&query.product, &query.feature),
+    ) {
+        (None, Some(experiment)) => experiment
+            .assign(&query.product, options.device_id.as_deref())
+            .and_then(|group| {
+                debug!(
+                    "Device in group {} of experiment {}",
+                    group.name, experiment.name
+                );
+                group.tag.clone()
+            }),
+        _ => None,
+    };
+    // Targeting rules take precedence over experiments
+    let client_os = options.os_version.as_deref().and_then(OsVersion::parse);
+    let decision = product_config
+        .rules
+        .decide(&RuleContext {
+            product: &query.product,
+            channel: &query.feature,
+            target: &query.platform.target,
+            arch: &query.platform.arch,
+            languages: &options.languages,
+            os_version: client_os.as_ref(),
+            device_id: options.device_id.as_deref(),
+        })
+        .cloned()
+        .unwrap_or_default();
+    // Canaries get the newest release, whatever rules and experiments say
+    let canary = options.to.is_none() && product_config.is_canary(options.canary.as_deref());
+    if decision.hold && options.to.is_none() && !canary {
+        debug!("Targeting rule holds back updates of {}", query.product);
+        return Ok(None);
+    }
+    let target_tag = if canary {
+        None
+    } else {
+        options
+            .to
+            .clone()
+            .or(decision.tag.clone())
+            .or(experiment_tag)
+    };
+
+    // Fetch the requested release, or the latest one
+    let mut release: ReleaseRef = match &target_tag {
+        Some(tag) => github
+            .get_release_by_tag(&product_config.repo_owner, &product_config.repo_name, tag)
+            .await?
+            .into(),
+        None if canary => {
+            debug!("Canary client, serving the newest release");
+            newest_release(
+                data,
+                &github,
+                &query.product,
+                &product_config,
+                &query.feature,
+            )
+            .await?
+        }
         None => {
-            error!("Product {} not found in configuration", product_name);
-            return Ok(HttpResponse::NotFound().finish());
+            latest_release(
+                data,
+                &github,
+                &query.product,
+                &product_config,
+                &query.feature,
+            )
+            .await?
         }
     };
 
-    // Create GitHub client
-    let github = GitHubClient::new(product_config.github_token)?;
+    // Parse versions and compare
+    let mut latest_version = parse_release_version(&product_config, &release)?;
 
-    // Fetch latest release
-    let release = github
-        .get_latest_release(&product_config.repo_owner, &product_config.repo_name)
-        .await?;
+    // Hold the newest release back from devices outside the staged rollout
+    if let Some(percentage) = product_config.rollout_percentage {
+        if target_tag.is_none()
+            && !canary
+            && !in_rollout(&query.product, options.device_id.as_deref(), percentage)
+        {
+            debug!(
+                "Device outside the {}% rollout of {}",
+                percentage, latest_version
+            );
+            match latest_release_before(
+                data,
+                &github,
+                &query.product,
+                &product_config,
+                &query.feature,
+                &latest_version,
+            )
+            .await?
+            {
+                Some(previous_release) => {
+                    latest_version = parse_release_version(&product_config, &previous_release)?;
+                    release = previous_release;
+                }
+                None => return Ok(None),
+            }
+        }
+    }
 
-    // Parse versions and compare
-    let latest_version = Version::parse(release.tag_name.trim_start_matches('v')).map_err(|e| {
-        error!("Failed to parse latest version: {}", e);
-        actix_web::error::ErrorInternalServerError("Invalid version format")
-    })?;
-    let current_version = Version::parse(&current_version).unwrap();
+    // Fall back past versions whose rollout was halted
+    while options.to.is_none()
+        && !canary
+        && is_halted(data, &query.product, &product_config, &latest_version)
+    {
+        debug!("Rollout of {} is halted", latest_version);
+        match latest_release_before(
+            data,
+            &github,
+            &query.product,
+            &product_config,
+            &query.feature,
+            &latest_version,
+        )
+        .await?
+        {
+            Some(previous_release) => {
+                latest_version = parse_release_version(&product_config, &previous_release)?;
+                release = previous_release;
+            }
+            None => return Ok(None),
+        }
+    }
 
-    if latest_version > current_version {
-        let platform = Platform { target, arch };
+    // Fall back past releases that need a newer OS than the client's
+    if let Some(client_os) = &client_os {
+        while target_tag.is_none() {
+            let Some(required) = required_os(
+                &product_config.min_os_versions,
+                &latest_version,
+                release.body.as_deref().unwrap_or_default(),
+                &query.platform.target,
+            ) else {
+                break;
+            };
+            if *client_os >= required {
+                break;
+            }
+            debug!(
+                "{} needs OS {}, client runs {}",
+                latest_version, required, client_os
+            );
+            match latest_release_before(
+                data,
+                &github,
+                &query.product,
+                &product_config,
+                &query.feature,
+                &latest_version,
+            )
+            .await?
+            {
+                Some(previous_release) => {
+                    latest_version = parse_release_version(&product_config, &previous_release)?;
+                    release = previous_release;
+                }
+                None => return Ok(None),
+            }
+        }
+    }
 
-        let matcher = PlatformMatcher::new();
-        let assets: Vec<String> = release
-            .assets
-            .iter()
-            .map(|asset| asset.name.clone())
-            .collect();
+    // Keep clients on stable versions off prereleases, if configured
+    if product_config.stable_skips_prereleases
+        && target_tag.is_none()
+        && query.current_version.pre.is_empty()
+        && !latest_version.pre.is_empty()
+    {
+        match latest_stable_release(
+            data,
+            &github,
+            &query.product,
+            &product_config,
+            &query.feature,
+        )
+        .await?
+        {
+            Some(stable_release) => {
+                latest_version = parse_release_version(&product_config, &stable_release)?;
+                release = stable_release;
+            }
+            None => return Ok(None),
+        }
+    }
 
-        let asset_match = matcher.find_matching_asset(&platform, &assets, Some(&feature))?;
+    let claims = product_config.entitlement.as_ref().and_then(|verifier| {
+        let token = options.entitlement.as_deref()?;
+        verifier.verify(token)
+    });
 
-        let hostname = std::env::var("HOSTNAME").unwrap_or_else(|_| "localhost".to_string());
+    // Hold back new major versions from clients whose maintenance has lapsed
+    if product_config.entitlement.is_some()
+        && target_tag.is_none()
+        && latest_version.major > query.current_version.major
+    {
+        let published_at = release.published_at.unwrap_or_else(Utc::now);
 
-        // Find asset ID for the installer
-        let asset_id = release
-            .assets
-            .iter()
-            .find(|a| a.name == asset_match.filename)
-            .map(|a| a.id.0)
-            .ok_or_else(|| actix_web::error::ErrorInternalServerError("Asset not found"))?;
-
-        let url = format!(
-            "{}/{}/download/{}/{}",
-            hostname, product_name, asset_id, asset_match.filename
-        );
-
-        let signature = if let Some(sig_filename) = asset_match.signature_filename.clone() {
-            let sig_asset = release
-                .assets
-                .iter()
-                .find(|a| a.name == sig_filename)
-                .ok_or_else(|| actix_web::error::ErrorInternalServerError("Signature not found"))?;
-
-            let sig_bytes = github
-                .download_asset(
-                    sig_asset.id.0,
-                    &product_config.repo_owner,
-                    &product_config.repo_name,
-                )
+        if !claims
+            .as_ref()
+            .is_some_and(|claims| claims.covers(published_at))
+        {
+            debug!(
+                "Client not entitled to {}, staying on major version {}",
+                latest_version, query.current_version.major
+            );
+            match latest_release_in_major(
+                data,
+                &github,
+                &query.product,
+                &product_config,
+                &query.feature,
+                query.current_version.major,
+            )
+            .await?
+            {
+                Some(patch_release) => {
+                    latest_version = parse_release_version(&product_config, &patch_release)?;
+                    release = patch_release;
+                }
+                None => return Ok(None),
+            }
+        }
+    }
+
+    // The entitlement's edition takes precedence over targeting rules,
+    // which take precedence over the one an authenticated client requested;
+    // anyone else could just ask for the full edition
+    let edition = claims
+        .as_ref()
+        .and_then(|claims| claims.edition.as_deref())
+        .or(decision.edition.as_deref())
+        .or(options.edition.as_deref().filter(|_| options.authenticated));
+
+    if product_config.is_update(&latest_version, &query.current_version) {
+        let asset_request = AssetRequest {
+            product: &query.product,
+            platform: &query.platform,
+            feature: &query.feature,
+            edition,
+        };
+        let mut update =
+            resolve_update(data, &github, &product_config, &release, &asset_request).await?;
+        if let Some(mirror) = product_config.mirrors.select(&options.location) {
+            update.url = mirror_url(mirror, &release.tag_name, &latest_version, &update.filename);
+        } else if let Some(url) = offloaded_url(data, &product_config, update.asset_id) {
+            update.url = url;
+        } else {
+            update.url =
+                with_download_token(update.url, &product_config, &query.product, update.asset_id);
+        }
+
+        let notes = if product_config.aggregate_notes {
+            let all_releases = data
+                .release_cache
+                .releases(&query.product, &github, &product_config)
                 .await?;
+            // The changelog as of the latest release covers the ones before it
+            let changelog = data
+                .changelog_cache
+                .changelog(&github, &product_config, &release.tag_name)
+                .await;
+            let releases: Vec<(Version, String)> = all_releases
+                .iter()
+                .filter(|r| !r.draft)
+                .filter_map(|r| {
+                    let version = product_config.tag_version(&r.tag_name)?;
+                    let notes = changelog
+                        .as_deref()
+                        .and_then(|changelog| changelog_section(changelog, &version))
+                        .unwrap_or_else(|| {
+                            let body = r.body.as_deref().unwrap_or_default();
+                            localized_notes(body, &options.languages).to_string()
+                        });
+                    Some((version, notes))
+                })
+                .collect();
 
-            String::from_utf8(sig_bytes.to_vec())
-                .unwrap_or_else(|_| "Failed to read signature".to_string())
+            aggregate_notes(
+                &releases,
+                &query.current_version,
+                &latest_version,
+                product_config.notes_max_releases,
+            )
         } else {
-            return Err(actix_web::error::ErrorInternalServerError(
-                "No signature file found",
-            ));
+            release_notes(
+                data,
+                &github,
+                &product_config,
+                &release,
+                &latest_version,
+                &options.languages,
+            )
+            .await
         };
 
-        debug!(
-            "Found signature file: {}",
-            asset_match.signature_filename.unwrap_or_default()
-        );
-        debug!("Signature length: {}", signature.len());
-
-        let update_response = UpdateResponse {
-            version: latest_version.to_string(),
-            pub_date: release.published_at.unwrap().to_rfc3339(),
-            url,
-            signature,
-            notes: release.body.unwrap_or_default(),
+        let mut notes = product_config.notes_sanitizer.sanitize(&notes);
+        if product_config.feedback_link {
+            notes = append_feedback_link(
+                notes,
+                release.html_url.as_str(),
+                product_config.notes_sanitizer.max_length,
+            );
+        }
+
+        let patch = if product_config.delta_updates {
+            data.delta_cache
+                .get(update.asset_id)
+                .filter(|patch| patch.from_version == query.current_version)
+                .map(|patch| PatchUpdate {
+                    url: format!(
+                        "{}/{}/delta/{}/{}",
+                        data.hostname, query.product, patch.from_asset_id, patch.to_asset_id
+                    ),
+                    from_version: patch.from_version.to_string(),
+                    size: patch.data.len() as u64,
+                    sha256: patch.sha256.clone(),
+                    format: "zstd-patch",
+                })
+        } else {
+            None
         };
 
-        Ok(HttpResponse::Ok().json(update_response))
+        let update_response = UpdateResponseBuilder::new(&latest_version)
+            .pub_date(release.published_at.map(|d| d.to_rfc3339()))
+            .notes(Some(options.notes.render(notes)))
+            .patch(patch)
+            .dynamic(options.format, update);
+
+        Ok(Some(update_response))
     } else {
-        Ok(HttpResponse::NoContent().finish())
+        Ok(None)
     }
 }
+
+/// The notes of `release` in the client's preferred language: from the
+/// product's changelog if it has a section for `version`, otherwise from
+/// the release body, either possibly replaced by a notes asset in that
+/// language.
+pub(crate) async fn release_notes(
+    data: &AppState,
+    github: &GitHubClient,
+    product_config: &ProductConfig,
+    release: &Release,
+    version: &Version,
+    languages: &[String],
+) -> String {
+    let body = match data
+        .changelog_cache
+        .changelog(github, product_config, &release.tag_name)
+        .await
+        .and_then(|changelog| changelog_section(&changelog, version))
+    {
+        Some(section) => section,
+        None => release.body.clone().unwrap_or_default(),
+    };
+    data.notes_cache
+        .localized(github, product_config, release, &body, languages)
+        .await
+}
+
+/// The CDN URL of an asset, if the product offloads downloads and the asset
+/// was replicated.
+pub(crate) fn offloaded_url(
+    data: &AppState,
+    product_config: &ProductConfig,
+    asset_id: u64,
+) -> Option<String> {
+    data.cdn
+        .as_ref()
+        .filter(|_| product_config.cdn_offload)?
+        .url(asset_id)
+}
+
+/// Whether the rollout of `version` was halted, by hand or because too many
+/// of its installs failed.
+pub(crate) fn is_halted(
+    data: &AppState,
+    product: &str,
+    product_config: &ProductConfig,
+    version: &Version,
+) -> bool {
+    let version = version.to_string();
+    let health = data.events.verified_health(product, &version);
+    data.rollout_halts.is_halted(
+        product,
+        &version,
+        product_config.halt_policy.as_ref(),
+        &health,
+    )
+}
+
+pub(crate) fn parse_release_version(
+    product_config: &ProductConfig,
+    release: &Release,
+) -> Result<Version, ServerError> {
+    product_config
+        .tag_version(&release.tag_name)
+        .ok_or_else(|| {
+            error!("Failed to parse version of release {}", release.tag_name);
+            ServerError::VersionParse(release.tag_name.clone())
+        })
+}
+
+/// The client-specific inputs that select an installer from a release.
+pub(crate) struct AssetRequest<'a> {
+    pub product: &'a str,
+    pub platform: &'a Platform,
+    pub feature: &'a str,
+    pub edition: Option<&'a str>,
+}
+
+/// Like [`platform_update`], but served from the decision cache when possible.
+pub(crate) async fn resolve_update(
+    state: &AppState,
+    github: &GitHubClient,
+    product_config: &ProductConfig,
+    release: &Release,
+    request: &AssetRequest<'_>,
+) -> Result<PlatformUpdate, Error> {
+    let key = DecisionKey {
+        product: request.product.to_string(),
+        release_id: release.id.0,
+        platform: request.platform.key(),
+        channel: request.feature.to_string(),
+        edition: request.edition.map(str::to_string),
+    };
+
+    if let Some(update) = state.decision_cache.get(&key).await {
+        return Ok(update);
+    }
+
+    let update = platform_update(
+        github,
+        state.local_store.as_deref(),
+        product_config,
+        release,
+        &state.hostname,
+        request,
+    )
+    .await?;
+    state
+        .signing_keys
+        .record(request.product, &release.tag_name, &update.key_id);
+    state.decision_cache.insert(key, update.clone()).await;
+    Ok(update)
+}
+
+/// Resolves the installer and signature of `release` for the requested
+/// platform. Signatures are read from the local store when mirrored there.
+pub(crate) async fn platform_update(
+    github: &GitHubClient,
+    local_store: Option<&LocalStore>,
+    product_config: &ProductConfig,
+    release: &Release,
+    hostname: &str,
+    request: &AssetRequest<'_>,
+) -> Result<PlatformUpdate, Error> {
+    let matcher = PlatformMatcher::new()
+        .with_asset_prefix(product_config.asset_prefix.clone())
+        .with_asset_pattern(product_config.asset_pattern.clone());
+    let assets: Vec<&str> = release
+        .assets
+        .iter()
+        .map(|asset| asset.name.as_str())
+        .collect();
+
+    let asset_match = matcher.find_matching_asset(
+        request.platform,
+        &assets,
+        Some(request.feature),
+        request.edition,
+    )?;
+
+    let asset = release
+        .assets
+        .iter()
+        .find(|a| a.name == asset_match.filename)
+        .ok_or_else(|| ServerError::AssetNotFound(asset_match.filename.clone()))?;
+    let url = download_url(hostname, request.product, asset);
+
+    let (signature, key_id) = if let Some(sig_filename) = &asset_match.signature_filename {
+        let sig_asset = release
+            .assets
+            .iter()
+            .find(|a| &a.name == sig_filename)
+            .ok_or_else(|| ServerError::AssetNotFound(sig_filename.clone()))?;
+
+        let stored = match local_store {
+            Some(store) => store.read(request.product, sig_asset.id.0).await,
+            None => None,
+        };
+        let sig_bytes = match stored {
+            Some(bytes) => bytes,
+            None => {
+                github
+                    .download_asset(
+                        sig_asset.id.0,
+                        &product_config.repo_owner,
+                        &product_config.repo_name,
+                    )
+                    .await?
+            }
+        };
+
+        let signature = String::from_utf8_lossy(&sig_bytes).trim().to_string();
+        let parsed = Signature::parse(&signature).map_err(|e| {
+            error!(
+                "Signature {} of release {}: {}",
+                sig_filename, release.tag_name, e
+            );
+            ServerError::InvalidSignature(sig_filename.clone())
+        })?;
+        let keys = product_config.public_keys();
+        if !keys.is_empty() && !keys.iter().any(|key| parsed.verify(key)) {
+            error!(
+                "Signature {} of release {} isn't made by a configured key (key id {})",
+                sig_filename,
+                release.tag_name,
+                parsed.key_id_hex()
+            );
+            return Err(ServerError::InvalidSignature(sig_filename.clone()).into());
+        }
+        (signature, parsed.key_id_hex())
+    } else {
+        return Err(MatchError::NoSignature(asset_match.filename).into());
+    };
+
+    debug!(
+        "Found signature file: {}",
+        asset_match.signature_filename.unwrap_or_default()
+    );
+    debug!("Signature length: {}", signature.len());
+
+    Ok(PlatformUpdate {
+        signature,
+        url,
+        asset_id: asset.id.0,
+        filename: asset.name.clone(),
+        key_id,
+    })
+}
@@ -0,0 +1,101 @@
+use actix_web::middleware::from_fn;
+use actix_web::{get, web, Error, HttpResponse};
+use serde::Deserialize;
+
+use crate::auth::authenticator::RouteGroup;
+use crate::auth::middleware::RequireAuth;
+use crate::config::AppState;
+use crate::cors::policy as cors;
+use crate::formats::package::download_url_with_token;
+use crate::formats::winget::{classify_installer, ManifestPart, WingetInstaller, WingetPackage};
+use crate::handlers::package::package_release;
+use crate::handlers::query::{slug, QueryError};
+use crate::logging::log_context;
+use crate::platform::matcher::MatchError;
+use crate::response::cache_control::cache_control;
+use crate::response::compression::compress;
+use crate::response::error::json_errors;
+
+#[derive(Debug, Deserialize)]
+pub struct WingetQuery {
+    /// `version`, `installer` or `locale`; all three when unset.
+    pub part: Option<String>,
+}
+
+/// winget manifests of the newest stable release, ready to submit to winget-pkgs.
+#[get(
+    "/{product_name}/winget/manifest",
+    wrap = "RequireAuth::new(RouteGroup::Update)",
+    wrap = "from_fn(cache_control)",
+    wrap = "compress()",
+    wrap = "from_fn(json_errors)",
+    wrap = "cors::for_group(RouteGroup::Update)",
+    wrap = "from_fn(log_context)"
+)]
+pub async fn winget_manifest(
+    path: web::Path<String>,
+    query: web::Query<WingetQuery>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    let product_name = slug("product", &path.into_inner())?;
+    let part = match query.part.as_deref() {
+        Some(part) => Some(
+            ManifestPart::parse(part).ok_or_else(|| QueryError::Invalid {
+                field: "part",
+                value: part.to_string(),
+                reason: "expected one of version, installer, locale".to_string(),
+            })?,
+        ),
+        None => None,
+    };
+
+    let Some(package) = package_release(&data, &product_name, "stable").await? else {
+        return Ok(HttpResponse::NoContent().finish());
+    };
+
+    let mut installers = Vec::new();
+    for asset in package.packages(|name| classify_installer(name).is_some()) {
+        let Some((architecture, installer_type)) = classify_installer(&asset.name) else {
+            continue;
+        };
+        let digests = data
+            .digest_cache
+            .digests(&package.github, &package.product_config, asset.id.0)
+            .await?;
+        installers.push(WingetInstaller {
+            architecture,
+            installer_type,
+            url: download_url_with_token(
+                &data.hostname,
+                &package.product_config,
+                &product_name,
+                asset,
+            ),
+            sha256: digests.sha256.to_uppercase(),
+        });
+    }
+    if installers.is_empty() {
+        return Err(MatchError::NoMatch {
+            target: "windows".to_string(),
+            arch: "any".to_string(),
+        }
+        .into());
+    }
+
+    let metadata = &package.product_config.package;
+    let manifest = WingetPackage {
+        id: metadata.winget_id(),
+        version: package.version.to_string(),
+        metadata,
+        release_notes_url: Some(package.release.html_url.to_string()),
+        installers,
+    };
+    let body = match part {
+        Some(part) => manifest.render(part),
+        None => manifest.render_all(),
+    };
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/yaml; charset=utf-8")
+        .body(body))
+}
@@ -0,0 +1,182 @@
+use actix_web::http::header::LOCATION;
+use actix_web::middleware::from_fn;
+use actix_web::{get, web, Error, HttpResponse};
+use chrono::Utc;
+use log::{debug, error};
+
+use crate::auth::authenticator::RouteGroup;
+use crate::auth::middleware::RequireAuth;
+use crate::config::AppState;
+use crate::cors::policy as cors;
+use crate::error::ServerError;
+use crate::formats::package::download_url_with_token;
+use crate::formats::yum::{gzip, render_primary, render_repomd};
+use crate::handlers::query::slug;
+use crate::logging::log_context;
+use crate::platform::matcher::PlatformMatcher;
+use crate::release::selection::eligible_releases;
+use crate::response::cache_control::cache_control;
+use crate::response::compression::compress;
+use crate::response::error::json_errors;
+
+/// Releases whose packages are listed; dnf only installs the newest, older
+/// ones just allow downgrades.
+const MAX_RELEASES: usize = 10;
+
+/// Renders the primary metadata of the channel's repository from the `.rpm`
+/// assets of its newest releases, with the repository's revision. The
+/// repository is empty while the product's updates are unavailable.
+async fn primary(
+    data: &AppState,
+    product_name: &str,
+    channel: &str,
+) -> Result<(String, i64), Error> {
+    let Some(product_config) = data.product(product_name) else {
+        error!("Product {} not found in configuration", product_name);
+        return Err(ServerError::ProductNotFound(product_name.to_string()).into());
+    };
+
+    let channel = product_config.resolve_channel(channel);
+    if !product_config.allows_channel(&channel) {
+        return Err(ServerError::UnknownChannel {
+            product: product_name.to_string(),
+            channel,
+        }
+        .into());
+    }
+
+    let mut packages = Vec::new();
+    let mut revision = None;
+    if data.maintenance.is_enabled() || data.kill_switch.is_paused(product_name) {
+        debug!(
+            "Updates of {} are unavailable, serving an empty YUM repository",
+            product_name
+        );
+    } else {
+        let github = data.github_client(product_name, &product_config)?;
+        let releases = data
+            .release_cache
+            .releases(product_name, &github, &product_config)
+            .await?;
+        let matcher = PlatformMatcher::new()
+            .with_asset_prefix(product_config.asset_prefix.clone())
+            .with_asset_pattern(product_config.asset_pattern.clone());
+
+        for (_, index) in eligible_releases(&releases, &product_config, &channel)
+            .into_iter()
+            .take(MAX_RELEASES)
+        {
+            let release = &releases[index];
+            revision = revision.max(release.published_at);
+            let names: Vec<&str> = release.assets.iter().map(|a| a.name.as_str()).collect();
+            let rpms = matcher.find_packages(&names, Some(&channel), |name| {
+                let name = name.to_lowercase();
+                name.ends_with(".rpm") && !name.ends_with(".src.rpm")
+            });
+            for asset in release
+                .assets
+                .iter()
+                .filter(|a| rpms.contains(&a.name.as_str()))
+            {
+                if let Some(package) = data
+                    .rpm_cache
+                    .package(&github, &product_config, asset.id.0)
+                    .await?
+                {
+                    packages.push((package, format!("packages/{}/{}", asset.id.0, asset.name)));
+                }
+            }
+        }
+    }
+
+    let revision = revision.unwrap_or_else(Utc::now).timestamp();
+    Ok((render_primary(&packages), revision))
+}
+
+/// The repository's index, pointing dnf at its primary metadata.
+#[get(
+    "/{product_name}/yum/{channel}/repodata/repomd.xml",
+    wrap = "RequireAuth::new(RouteGroup::Update)",
+    wrap = "from_fn(cache_control)",
+    wrap = "compress()",
+    wrap = "from_fn(json_errors)",
+    wrap = "cors::for_group(RouteGroup::Update)",
+    wrap = "from_fn(log_context)"
+)]
+pub async fn yum_repomd(
+    path: web::Path<(String, String)>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    let (product_name, channel) = path.into_inner();
+    let product_name = slug("product", &product_name)?;
+    let channel = slug("channel", &channel)?;
+
+    let (primary, revision) = primary(&data, &product_name, &channel).await?;
+    let repomd = render_repomd(revision, primary.as_bytes(), &gzip(primary.as_bytes()));
+    Ok(HttpResponse::Ok()
+        .content_type("application/xml; charset=utf-8")
+        .body(repomd))
+}
+
+/// The repository's gzipped primary metadata.
+#[get(
+    "/{product_name}/yum/{channel}/repodata/primary.xml.gz",
+    wrap = "RequireAuth::new(RouteGroup::Update)",
+    wrap = "from_fn(cache_control)",
+    wrap = "from_fn(json_errors)",
+    wrap = "cors::for_group(RouteGroup::Update)",
+    wrap = "from_fn(log_context)"
+)]
+pub async fn yum_primary(
+    path: web::Path<(String, String)>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    let (product_name, channel) = path.into_inner();
+    let product_name = slug("product", &product_name)?;
+    let channel = slug("channel", &channel)?;
+
+    let (primary, _) = primary(&data, &product_name, &channel).await?;
+    Ok(HttpResponse::Ok()
+        .content_type("application/gzip")
+        .body(gzip(primary.as_bytes())))
+}
+
+/// A package file listed in the primary metadata, redirected to the
+/// asset's download URL.
+#[get(
+    "/{product_name}/yum/{channel}/packages/{asset_id}/{filename}",
+    wrap = "RequireAuth::new(RouteGroup::Download)",
+    wrap = "from_fn(json_errors)",
+    wrap = "cors::for_group(RouteGroup::Download)",
+    wrap = "from_fn(log_context)"
+)]
+pub async fn yum_package(
+    path: web::Path<(String, String, u64, String)>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    let (product_name, _, asset_id, filename) = path.into_inner();
+    let product_name = slug("product", &product_name)?;
+
+    let Some(product_config) = data.product(&product_name) else {
+        error!("Product {} not found in configuration", product_name);
+        return Err(ServerError::ProductNotFound(product_name).into());
+    };
+
+    let github = data.github_client(&product_name, &product_config)?;
+    let releases = data
+        .release_cache
+        .releases(&product_name, &github, &product_config)
+        .await?;
+    let asset = releases
+        .iter()
+        .flat_map(|r| &r.assets)
+        .find(|a| a.id.0 == asset_id && a.name == filename)
+        .ok_or(ServerError::AssetNotFound(filename))?;
+
+    Ok(HttpResponse::Found()
+        .insert_header((
+            LOCATION,
+            download_url_with_token(&data.hostname, &product_config, &product_name, asset),
+        ))
+        .finish())
+}
@@ -0,0 +1,29 @@
+pub mod admin;
+pub mod auth;
+pub mod build_info;
+pub mod cache;
+pub mod cdn;
+pub mod cluster;
+pub mod config;
+pub mod cors;
+pub mod delta;
+pub mod error;
+pub mod formats;
+pub mod geo;
+pub mod github;
+pub mod handlers;
+pub mod license;
+pub mod local;
+pub mod logging;
+pub mod notes;
+pub mod platform;
+pub mod release;
+pub mod response;
+pub mod server;
+pub mod systemd;
+pub mod telemetry;
+#[cfg(feature = "test-support")]
+pub mod test_support;
+
+pub use config::{AppState, ProductConfig};
+pub use server::{configure, run_server, ServerConfig};
@@ -0,0 +1,108 @@
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+use log::debug;
+use serde::Deserialize;
+
+/// Claims of an entitlement token issued by the licensing service. Tokens
+/// must expire (`exp`), so a leaked one stops working.
+#[derive(Debug, Clone, Deserialize)]
+pub struct EntitlementClaims {
+    /// Unix timestamp until which the customer's maintenance is active.
+    pub maintenance_expires: Option<i64>,
+    /// Installer edition the customer is licensed for, e.g. `full`.
+    pub edition: Option<String>,
+}
+
+impl EntitlementClaims {
+    /// Whether a release published at `published_at` is covered by maintenance.
+    pub fn covers(&self, published_at: DateTime<Utc>) -> bool {
+        self.maintenance_expires
+            .is_some_and(|expires| published_at.timestamp() <= expires)
+    }
+}
+
+/// Verifies entitlement tokens with the licensing service's key.
+#[derive(Clone)]
+pub struct EntitlementVerifier {
+    key: DecodingKey,
+    validation: Validation,
+}
+
+impl fmt::Debug for EntitlementVerifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EntitlementVerifier")
+            .field("algorithms", &self.validation.algorithms)
+            .finish_non_exhaustive()
+    }
+}
+
+impl EntitlementVerifier {
+    pub fn from_secret(secret: &str) -> Self {
+        Self::new(
+            DecodingKey::from_secret(secret.as_bytes()),
+            Algorithm::HS256,
+        )
+    }
+
+    pub fn from_rsa_pem(pem: &str) -> Result<Self, jsonwebtoken::errors::Error> {
+        Ok(Self::new(
+            DecodingKey::from_rsa_pem(pem.as_bytes())?,
+            Algorithm::RS256,
+        ))
+    }
+
+    fn new(key: DecodingKey, algorithm: Algorithm) -> Self {
+        let mut validation = Validation::new(algorithm);
+        validation.validate_aud = false;
+        validation.set_required_spec_claims(&["exp"]);
+        Self { key, validation }
+    }
+
+    /// Returns the token's claims, or `None` if it is invalid or expired.
+    pub fn verify(&self, token: &str) -> Option<EntitlementClaims> {
+        decode::<EntitlementClaims>(token, &self.key, &self.validation)
+            .map(|data| data.claims)
+            .map_err(|e| debug!("Rejected entitlement token: {}", e))
+            .ok()
+    }
+}
+
+#[test]
+fn test_entitlement_covers_releases_before_expiry() {
+    use jsonwebtoken::{encode, EncodingKey, Header};
+
+    let sign = |claims: serde_json::Value| {
+        encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(b"secret"),
+        )
+        .unwrap()
+    };
+    let exp = Utc::now().timestamp() + 3600;
+    let token = sign(serde_json::json!({ "maintenance_expires": 1_700_000_000, "exp": exp }));
+
+    let claims = EntitlementVerifier::from_secret("secret")
+        .verify(&token)
+        .unwrap();
+    assert!(claims.covers(DateTime::from_timestamp(1_600_000_000, 0).unwrap()));
+    assert!(!claims.covers(DateTime::from_timestamp(1_800_000_000, 0).unwrap()));
+    assert!(EntitlementVerifier::from_secret("other")
+        .verify(&token)
+        .is_none());
+
+    // Tokens without an expiry, or past it, are rejected
+    let verifier = EntitlementVerifier::from_secret("secret");
+    assert!(verifier
+        .verify(&sign(
+            serde_json::json!({ "maintenance_expires": 1_700_000_000 })
+        ))
+        .is_none());
+    assert!(verifier
+        .verify(&sign(
+            serde_json::json!({ "maintenance_expires": 1_700_000_000, "exp": 1_600_000_000 })
+        ))
+        .is_none());
+}
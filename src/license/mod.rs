@@ -0,0 +1,3 @@
+pub mod entitlement;
+pub mod validator;
+pub mod webhook;
@@ -0,0 +1,65 @@
+use std::fmt;
+
+use futures_util::future::BoxFuture;
+use semver::Version;
+
+use crate::platform::matcher::Platform;
+use crate::telemetry::store::ClientMetadata;
+
+/// The client an update check is for, as a license check sees it.
+#[derive(Debug, Clone, Copy)]
+pub struct LicenseRequest<'a> {
+    pub product: &'a str,
+    pub channel: &'a str,
+    pub platform: &'a Platform,
+    pub current_version: &'a Version,
+    /// Entitlement token sent by the client, if any.
+    pub entitlement: Option<&'a str>,
+    pub device_id: Option<&'a str>,
+    /// OS version, app build and installation id, where the client sent
+    /// them.
+    pub client: &'a ClientMetadata,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum LicenseError {
+    /// The client isn't licensed for updates; the reason is reported to it.
+    #[error("{0}")]
+    Denied(String),
+    /// The licensing backend couldn't be asked; the details are logged.
+    #[error("{0}")]
+    Unavailable(String),
+}
+
+/// Decides whether a client may be served updates of a product, e.g. by
+/// asking a licensing backend. Runs before every update check of the
+/// product; implement this to gate updates on your own licenses.
+pub trait LicenseValidator: Send + Sync + fmt::Debug {
+    fn validate<'a>(
+        &'a self,
+        request: LicenseRequest<'a>,
+    ) -> BoxFuture<'a, Result<(), LicenseError>>;
+
+    /// Whether every client is allowed, so responses don't depend on who
+    /// asked and may be shared between clients.
+    fn allows_all(&self) -> bool {
+        false
+    }
+}
+
+/// Serves updates to every client, the default.
+#[derive(Debug)]
+pub struct AllowAll;
+
+impl LicenseValidator for AllowAll {
+    fn validate<'a>(
+        &'a self,
+        _request: LicenseRequest<'a>,
+    ) -> BoxFuture<'a, Result<(), LicenseError>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn allows_all(&self) -> bool {
+        true
+    }
+}
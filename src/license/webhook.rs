@@ -0,0 +1,93 @@
+use std::time::Duration;
+
+use futures_util::future::BoxFuture;
+use log::debug;
+use reqwest::StatusCode;
+use serde::Serialize;
+
+use crate::license::validator::{LicenseError, LicenseRequest, LicenseValidator};
+use crate::telemetry::store::ClientMetadata;
+
+/// How long a webhook may take to answer.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Payload posted to a product's entitlement webhook on each update check.
+#[derive(Debug, Serialize)]
+pub struct EntitlementCheck<'a> {
+    pub product: &'a str,
+    pub channel: &'a str,
+    pub target: &'a str,
+    pub arch: &'a str,
+    pub current_version: String,
+    pub device_id: Option<&'a str>,
+    pub entitlement: Option<&'a str>,
+    #[serde(flatten)]
+    pub client: &'a ClientMetadata,
+}
+
+impl<'a> From<LicenseRequest<'a>> for EntitlementCheck<'a> {
+    fn from(request: LicenseRequest<'a>) -> Self {
+        EntitlementCheck {
+            product: request.product,
+            channel: request.channel,
+            target: &request.platform.target,
+            arch: &request.platform.arch,
+            current_version: request.current_version.to_string(),
+            device_id: request.device_id,
+            entitlement: request.entitlement,
+            client: request.client,
+        }
+    }
+}
+
+/// Asks an external endpoint whether a client is entitled to updates, e.g.
+/// to stop serving cancelled subscriptions: anything but `200 OK` refuses
+/// the update.
+#[derive(Debug, Clone)]
+pub struct EntitlementWebhook {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl EntitlementWebhook {
+    /// Posts to `url` with `client`, e.g. the product's GitHub client so the
+    /// webhook is reached through the same proxy and trusts the same root
+    /// certificates.
+    pub fn new(url: impl Into<String>, client: reqwest::Client) -> Self {
+        Self {
+            url: url.into(),
+            client,
+        }
+    }
+}
+
+impl LicenseValidator for EntitlementWebhook {
+    fn validate<'a>(
+        &'a self,
+        request: LicenseRequest<'a>,
+    ) -> BoxFuture<'a, Result<(), LicenseError>> {
+        Box::pin(async move {
+            let response = self
+                .client
+                .post(&self.url)
+                .timeout(TIMEOUT)
+                .json(&EntitlementCheck::from(request))
+                .send()
+                .await
+                .map_err(|e| LicenseError::Unavailable(format!("entitlement webhook: {}", e)))?;
+            match response.status() {
+                StatusCode::OK => Ok(()),
+                status => {
+                    debug!(
+                        "Entitlement webhook refused {} for device {:?}: {}",
+                        request.product, request.device_id, status
+                    );
+                    Err(LicenseError::Denied(format!(
+                        "entitlement check answered {}",
+                        status.as_u16()
+                    )))
+                }
+            }
+        })
+    }
+}
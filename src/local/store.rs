@@ -0,0 +1,250 @@
+use std::collections::HashSet;
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+
+use actix_web::web;
+use bytes::Bytes;
+use futures_util::stream::{self, Stream};
+use log::warn;
+use octocrab::models::repos::Release;
+
+/// Releases per product whose assets are kept by default, newest first.
+pub const DEFAULT_RELEASES: usize = 5;
+
+/// Size of the chunks local assets are streamed in.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// A directory holding copies of the products' newest release assets and
+/// release lists, so updates can be served while GitHub is slow or down.
+/// Assets are stored as `<product>/assets/<asset id>`, release lists as
+/// `<product>/releases.json`.
+#[derive(Debug)]
+pub struct LocalStore {
+    dir: PathBuf,
+    releases: usize,
+    max_bytes: Option<u64>,
+}
+
+impl LocalStore {
+    pub fn new(dir: impl Into<PathBuf>, releases: usize) -> Self {
+        LocalStore {
+            dir: dir.into(),
+            releases: releases.max(1),
+            max_bytes: None,
+        }
+    }
+
+    /// Caps the total size of the stored assets; assets of older releases
+    /// are left out when the newer ones fill it.
+    pub fn with_max_bytes(mut self, max_bytes: Option<u64>) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    pub fn max_bytes(&self) -> Option<u64> {
+        self.max_bytes
+    }
+
+    /// Reads `ASSET_CACHE_DIR` and `ASSET_CACHE_RELEASES`. Returns `None`
+    /// when no directory is configured.
+    pub fn from_env() -> Option<Self> {
+        let dir = env::var("ASSET_CACHE_DIR").ok()?;
+        let releases = env::var("ASSET_CACHE_RELEASES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_RELEASES);
+        Some(LocalStore::new(dir, releases))
+    }
+
+    /// How many of each product's newest releases are kept.
+    pub fn releases(&self) -> usize {
+        self.releases
+    }
+
+    fn product_dir(&self, product: &str) -> PathBuf {
+        self.dir.join(product)
+    }
+
+    fn asset_path(&self, product: &str, asset_id: u64) -> PathBuf {
+        self.product_dir(product)
+            .join("assets")
+            .join(asset_id.to_string())
+    }
+
+    /// Total size of the product's stored assets.
+    pub fn usage(&self, product: &str) -> u64 {
+        let Ok(entries) = fs::read_dir(self.product_dir(product).join("assets")) else {
+            return 0;
+        };
+        entries
+            .flatten()
+            .filter_map(|entry| entry.metadata().ok())
+            .map(|metadata| metadata.len())
+            .sum()
+    }
+
+    /// Whether the asset is stored with the expected size.
+    pub fn contains(&self, product: &str, asset_id: u64, size: u64) -> bool {
+        fs::metadata(self.asset_path(product, asset_id)).is_ok_and(|m| m.len() == size)
+    }
+
+    /// The stored asset's contents.
+    pub async fn read(&self, product: &str, asset_id: u64) -> Option<Bytes> {
+        let path = self.asset_path(product, asset_id);
+        web::block(move || fs::read(path))
+            .await
+            .ok()?
+            .ok()
+            .map(Bytes::from)
+    }
+
+    /// The stored asset's length and a stream of its contents.
+    pub fn open(
+        &self,
+        product: &str,
+        asset_id: u64,
+    ) -> Option<(u64, impl Stream<Item = Result<Bytes, io::Error>> + 'static)> {
+        let file = File::open(self.asset_path(product, asset_id)).ok()?;
+        let length = file.metadata().ok()?.len();
+        let chunks = stream::try_unfold(file, |mut file| async move {
+            let (file, chunk) = web::block(move || {
+                let mut chunk = vec![0; CHUNK_SIZE];
+                let read = file.read(&mut chunk)?;
+                chunk.truncate(read);
+                Ok::<_, io::Error>((file, chunk))
+            })
+            .await
+            .map_err(io::Error::other)??;
+            Ok((!chunk.is_empty()).then(|| (Bytes::from(chunk), file)))
+        });
+        Some((length, chunks))
+    }
+
+    /// Stores an asset from a stream of its contents. The asset only
+    /// becomes visible once it's complete.
+    pub async fn write<S, E>(&self, product: &str, asset_id: u64, chunks: S) -> io::Result<()>
+    where
+        S: Stream<Item = Result<Bytes, E>>,
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        use futures_util::StreamExt;
+
+        let path = self.asset_path(product, asset_id);
+        let partial = path.with_extension("partial");
+        let mut file = {
+            let partial = partial.clone();
+            web::block(move || {
+                fs::create_dir_all(partial.parent().expect("asset path has a parent"))?;
+                File::create(partial)
+            })
+            .await
+            .map_err(io::Error::other)??
+        };
+
+        futures_util::pin_mut!(chunks);
+        while let Some(chunk) = chunks.next().await {
+            let chunk = chunk.map_err(io::Error::other)?;
+            file = web::block(move || file.write_all(&chunk).map(|_| file))
+                .await
+                .map_err(io::Error::other)??;
+        }
+        web::block(move || {
+            file.sync_all()?;
+            fs::rename(partial, path)
+        })
+        .await
+        .map_err(io::Error::other)?
+    }
+
+    /// Deletes the product's stored assets other than `keep`, including
+    /// incomplete downloads of them. Returns how many files were deleted and
+    /// their total size.
+    pub fn retain(&self, product: &str, keep: &HashSet<u64>) -> (usize, u64) {
+        let Ok(entries) = fs::read_dir(self.product_dir(product).join("assets")) else {
+            return (0, 0);
+        };
+        let (mut removed, mut reclaimed) = (0, 0);
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            let Some(asset_id) = name
+                .to_str()
+                .and_then(|name| name.split('.').next())
+                .and_then(|id| id.parse::<u64>().ok())
+            else {
+                continue;
+            };
+            if keep.contains(&asset_id) {
+                continue;
+            }
+            let size = entry.metadata().map(|m| m.len()).unwrap_or_default();
+            match fs::remove_file(entry.path()) {
+                Ok(()) => {
+                    removed += 1;
+                    reclaimed += size;
+                }
+                Err(e) => warn!("Failed to remove {}: {}", entry.path().display(), e),
+            }
+        }
+        (removed, reclaimed)
+    }
+
+    pub fn save_releases(&self, product: &str, releases: &[Release]) -> io::Result<()> {
+        let dir = self.product_dir(product);
+        fs::create_dir_all(&dir)?;
+        let json = serde_json::to_vec(releases).map_err(io::Error::other)?;
+        let partial = dir.join("releases.json.partial");
+        fs::write(&partial, json)?;
+        fs::rename(partial, dir.join("releases.json"))
+    }
+
+    /// The product's release list as of the last sync.
+    pub fn load_releases(&self, product: &str) -> Option<Vec<Release>> {
+        let json = fs::read(self.product_dir(product).join("releases.json")).ok()?;
+        serde_json::from_slice(&json)
+            .inspect_err(|e| warn!("Stored releases of {} are unreadable: {}", product, e))
+            .ok()
+    }
+}
+
+#[actix_web::test]
+async fn test_local_store() {
+    use futures_util::TryStreamExt;
+
+    let dir = env::temp_dir().join(format!("local-store-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let store = LocalStore::new(&dir, 0);
+    assert_eq!(store.releases(), 1);
+    assert!(store.read("myapp", 42).await.is_none());
+
+    let chunks =
+        stream::iter([Bytes::from("contents "), Bytes::from("of 42")].map(Ok::<_, io::Error>));
+    store.write("myapp", 42, chunks).await.unwrap();
+    assert!(store.contains("myapp", 42, 14));
+    assert!(!store.contains("myapp", 42, 13));
+    assert_eq!(store.read("myapp", 42).await.unwrap(), "contents of 42");
+
+    let (length, chunks) = store.open("myapp", 42).unwrap();
+    let contents: Vec<Bytes> = chunks.try_collect().await.unwrap();
+    assert_eq!(length, 14);
+    assert_eq!(contents.concat(), b"contents of 42");
+
+    store
+        .write(
+            "myapp",
+            43,
+            stream::iter([Ok::<_, io::Error>(Bytes::from("43"))]),
+        )
+        .await
+        .unwrap();
+    assert_eq!(store.retain("myapp", &HashSet::from([42])), (1, 2));
+    assert!(store.contains("myapp", 42, 14));
+    assert!(!store.contains("myapp", 43, 2));
+    assert_eq!(store.usage("myapp"), 14);
+
+    assert!(store.load_releases("myapp").is_none());
+    store.save_releases("myapp", &[]).unwrap();
+    assert_eq!(store.load_releases("myapp").unwrap().len(), 0);
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,131 @@
+use std::time::Duration;
+
+use actix_web::rt::time;
+use log::{debug, info, warn};
+use octocrab::models::repos::{Asset, Release};
+
+use crate::config::{AppState, ProductConfig};
+use crate::error::ServerError;
+use crate::local::store::LocalStore;
+use crate::logging;
+
+/// How often the sync task looks for new releases by default.
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Downloads new release assets every `interval`, until the server shuts
+/// down. With several instances sharing the directory, only the one holding
+/// the lock downloads.
+pub async fn run(state: AppState, interval: Duration) {
+    let mut ticker = time::interval(interval);
+    loop {
+        ticker.tick().await;
+        if state
+            .leadership
+            .acquire("asset-mirroring", interval * 2)
+            .await
+        {
+            refresh(&state).await;
+        }
+    }
+}
+
+/// Downloads every asset of the newest releases of every product to the
+/// local store, unless it's already there, and stores the release lists.
+/// Returns how many products failed.
+pub async fn refresh(state: &AppState) -> usize {
+    let Some(store) = &state.local_store else {
+        return 0;
+    };
+    let mut failed = 0;
+    let mut budget = store.max_bytes().unwrap_or(u64::MAX);
+    for (product_name, product_config) in state.products.load().iter() {
+        let refreshed = logging::scope(
+            product_name,
+            refresh_product(state, store, product_name, product_config, &mut budget),
+        );
+        if let Err(e) = refreshed.await {
+            // Whatever the product has stored stays, and counts against the limit
+            budget = budget.saturating_sub(store.usage(product_name));
+            warn!("Failed to mirror assets of {}: {}", product_name, e);
+            state
+                .recent_errors
+                .record(Some(product_name), "asset_mirroring", &e);
+            failed += 1;
+        }
+    }
+    failed
+}
+
+/// The assets the local store keeps of a product: those of its newest
+/// `count` releases, newest first, as long as they fit in `budget` bytes,
+/// which they're deducted from.
+pub fn mirrored_assets<'a>(
+    releases: &'a [Release],
+    product_config: &ProductConfig,
+    count: usize,
+    budget: &mut u64,
+) -> Vec<&'a Asset> {
+    let mut assets = Vec::new();
+    let newest = releases
+        .iter()
+        .filter(|r| !r.draft && product_config.tag_version(&r.tag_name).is_some())
+        .take(count);
+    for release in newest {
+        for asset in &release.assets {
+            let size = u64::try_from(asset.size).unwrap_or_default();
+            match budget.checked_sub(size) {
+                Some(left) => {
+                    *budget = left;
+                    assets.push(asset);
+                }
+                None => debug!(
+                    "{} of {} exceeds CACHE_MAX_DISK_BYTES",
+                    asset.name, release.tag_name
+                ),
+            }
+        }
+    }
+    assets
+}
+
+async fn refresh_product(
+    state: &AppState,
+    store: &LocalStore,
+    product_name: &str,
+    product_config: &ProductConfig,
+    budget: &mut u64,
+) -> Result<(), ServerError> {
+    let github = state.github_client(product_name, product_config)?;
+    let releases = state
+        .release_cache
+        .releases(product_name, &github, product_config)
+        .await?;
+    if let Err(e) = store.save_releases(product_name, &releases) {
+        warn!("Failed to store releases of {}: {}", product_name, e);
+    }
+
+    for asset in mirrored_assets(&releases, product_config, store.releases(), budget) {
+        let size = u64::try_from(asset.size).unwrap_or_default();
+        if store.contains(product_name, asset.id.0, size) {
+            debug!("{} is already stored", asset.name);
+            continue;
+        }
+
+        let upstream = github
+            .open_asset(
+                asset.id.0,
+                &product_config.repo_owner,
+                &product_config.repo_name,
+                None,
+            )
+            .await?;
+        match store
+            .write(product_name, asset.id.0, upstream.bytes_stream())
+            .await
+        {
+            Ok(()) => info!("Mirrored {} ({} bytes)", asset.name, size),
+            Err(e) => warn!("Failed to store {}: {}", asset.name, e),
+        }
+    }
+    Ok(())
+}
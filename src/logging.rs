@@ -0,0 +1,464 @@
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::future::Future;
+use std::io::Write;
+use std::str::FromStr;
+use std::sync::{Arc, LazyLock};
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::HeaderValue;
+use actix_web::middleware::Next;
+use actix_web::{Error, HttpMessage};
+use arc_swap::ArcSwap;
+use chrono::{SecondsFormat, Utc};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use serde::{Deserialize, Serialize};
+
+use crate::config::ProductConfig;
+use crate::response::error::{request_id, RequestId, REQUEST_ID};
+
+/// What is being handled when a message is logged.
+#[derive(Debug, Clone, Default)]
+struct LogContext {
+    product: Option<String>,
+    request_id: Option<String>,
+}
+
+tokio::task_local! {
+    static CONTEXT: LogContext;
+}
+
+/// Which messages are logged: a default level, overridden by module and by
+/// product. Product levels apply to everything logged on behalf of the
+/// product, whichever module logs it, e.g. to debug a single product.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "LevelNames", try_from = "LevelNames")]
+pub struct LogLevels {
+    pub default: LevelFilter,
+    /// By module path, e.g. `tauri_update_server::github`; the longest
+    /// matching prefix wins.
+    pub modules: BTreeMap<String, LevelFilter>,
+    pub products: BTreeMap<String, LevelFilter>,
+}
+
+impl Default for LogLevels {
+    fn default() -> Self {
+        LogLevels {
+            default: LevelFilter::Error,
+            modules: BTreeMap::new(),
+            products: BTreeMap::new(),
+        }
+    }
+}
+
+impl LogLevels {
+    /// Parses `RUST_LOG`-style directives, e.g.
+    /// `info,tauri_update_server::github=debug`.
+    pub fn parse(directives: &str) -> Result<Self, String> {
+        let mut levels = LogLevels::default();
+        for directive in directives
+            .split(',')
+            .map(str::trim)
+            .filter(|d| !d.is_empty())
+        {
+            let parse = |level: &str| {
+                level
+                    .parse::<LevelFilter>()
+                    .map_err(|_| format!("invalid log level {:?}", level))
+            };
+            match directive.split_once('=') {
+                Some((module, level)) => {
+                    levels.modules.insert(module.to_string(), parse(level)?);
+                }
+                // A bare word is a level, or a module logged at every level
+                None => match directive.parse::<LevelFilter>() {
+                    Ok(level) => levels.default = level,
+                    Err(_) => {
+                        levels
+                            .modules
+                            .insert(directive.to_string(), LevelFilter::Trace);
+                    }
+                },
+            }
+        }
+        Ok(levels)
+    }
+
+    fn level(&self, target: &str, product: Option<&str>) -> LevelFilter {
+        if let Some(level) = product.and_then(|product| self.products.get(product)) {
+            return *level;
+        }
+        self.modules
+            .iter()
+            .filter(|(module, _)| target.starts_with(module.as_str()))
+            .max_by_key(|(module, _)| module.len())
+            .map_or(self.default, |(_, level)| *level)
+    }
+
+    fn max(&self) -> LevelFilter {
+        self.modules
+            .values()
+            .chain(self.products.values())
+            .copied()
+            .fold(self.default, Ord::max)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct LevelNames {
+    default: String,
+    #[serde(default)]
+    modules: BTreeMap<String, String>,
+    #[serde(default)]
+    products: BTreeMap<String, String>,
+}
+
+impl From<LogLevels> for LevelNames {
+    fn from(levels: LogLevels) -> Self {
+        let name = |level: LevelFilter| level.as_str().to_lowercase();
+        LevelNames {
+            default: name(levels.default),
+            modules: levels
+                .modules
+                .into_iter()
+                .map(|(m, l)| (m, name(l)))
+                .collect(),
+            products: levels
+                .products
+                .into_iter()
+                .map(|(p, l)| (p, name(l)))
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<LevelNames> for LogLevels {
+    type Error = String;
+
+    fn try_from(names: LevelNames) -> Result<Self, String> {
+        let parse = |level: String| {
+            level
+                .parse::<LevelFilter>()
+                .map_err(|_| format!("invalid log level {:?}", level))
+        };
+        let parse_all = |levels: BTreeMap<String, String>| {
+            levels
+                .into_iter()
+                .map(|(name, level)| Ok((name, parse(level)?)))
+                .collect::<Result<BTreeMap<_, _>, String>>()
+        };
+        Ok(LogLevels {
+            default: parse(names.default)?,
+            modules: parse_all(names.modules)?,
+            products: parse_all(names.products)?
+                .into_iter()
+                .map(|(product, level)| (product.to_lowercase(), level))
+                .collect(),
+        })
+    }
+}
+
+static LEVELS: LazyLock<ArcSwap<LogLevels>> =
+    LazyLock::new(|| ArcSwap::from_pointee(LogLevels::default()));
+
+pub fn levels() -> Arc<LogLevels> {
+    LEVELS.load_full()
+}
+
+/// Replaces the levels; they take effect immediately.
+pub fn set_levels(levels: LogLevels) {
+    log::set_max_level(levels.max());
+    LEVELS.store(Arc::new(levels));
+}
+
+/// Sets or, with `None`, clears the level of a product.
+pub fn set_product_level(product: &str, level: Option<LevelFilter>) {
+    let mut levels = LogLevels::clone(&levels());
+    match level {
+        Some(level) => levels.products.insert(product.to_lowercase(), level),
+        None => levels.products.remove(&product.to_lowercase()),
+    };
+    set_levels(levels);
+}
+
+/// Applies the `LOG_LEVEL` setting of each product, replacing every product
+/// level set before.
+pub fn set_product_levels(products: &HashMap<String, Arc<ProductConfig>>) {
+    let mut levels = LogLevels::clone(&levels());
+    levels.products = products
+        .iter()
+        .filter_map(|(name, config)| Some((name.clone(), config.log_level?)))
+        .collect();
+    set_levels(levels);
+}
+
+/// Runs `future` on behalf of `product`, so the product's level applies to
+/// what it logs, and the product is named in JSON logs.
+pub async fn scope<F: Future>(product: &str, future: F) -> F::Output {
+    let context = LogContext {
+        product: Some(product.to_string()),
+        request_id: None,
+    };
+    CONTEXT.scope(context, future).await
+}
+
+/// The product a request is for: the first path segment, or the one after
+/// `/admin/products/`.
+fn request_product(path: &str) -> Option<String> {
+    let mut segments = path.trim_start_matches('/').split('/');
+    let first = segments.next()?;
+    let product = if first == "admin" {
+        segments
+            .nth(1)
+            .filter(|_| path.starts_with("/admin/products/"))?
+    } else {
+        first
+    };
+    Some(product.to_lowercase())
+}
+
+/// Assigns each request an id and applies product log levels to it. Every
+/// route of the server is wrapped in it; wrapping an `App` with
+/// `from_fn(log_context)` covers the app's other responses too, e.g. `404`s.
+/// The id is returned in the `X-Request-Id` response header and named in the
+/// request's log messages, including those about the GitHub calls made for
+/// it.
+pub async fn log_context(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    // Already in the context set up by an outer `log_context`
+    if req.extensions().contains::<RequestId>() {
+        return next.call(req).await;
+    }
+    let request_id = request_id(req.request());
+    req.extensions_mut().insert(RequestId(request_id.clone()));
+    let header = HeaderValue::from_str(&request_id).ok();
+    let context = LogContext {
+        product: request_product(req.path()),
+        request_id: Some(request_id),
+    };
+    let mut res = CONTEXT.scope(context, next.call(req)).await?;
+    if let Some(header) = header {
+        res.headers_mut().insert(REQUEST_ID, header);
+    }
+    Ok(res)
+}
+
+/// The id of the request being handled, if any.
+pub fn current_request_id() -> Option<String> {
+    CONTEXT
+        .try_with(|context| context.request_id.clone())
+        .ok()
+        .flatten()
+}
+
+/// Filters with the current [`LogLevels`] and writes with `env_logger`.
+struct Logger {
+    writer: env_logger::Logger,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        let levels = LEVELS.load();
+        let level = CONTEXT
+            .try_with(|context| levels.level(metadata.target(), context.product.as_deref()))
+            .unwrap_or_else(|_| levels.level(metadata.target(), None));
+        metadata.level() <= level
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            self.writer.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.writer.flush();
+    }
+}
+
+/// How log lines are written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// `env_logger`'s human-readable lines.
+    #[default]
+    Text,
+    /// One JSON object per line, for log collectors.
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!(
+                "invalid LOG_FORMAT {:?}, expected \"text\" or \"json\"",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonLine<'a> {
+    timestamp: String,
+    level: &'static str,
+    module: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    product: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<&'a str>,
+    message: String,
+}
+
+/// Renders a record as a line of JSON, naming the product and request it
+/// was logged for.
+fn json_line(record: &Record) -> String {
+    let line = |context: Option<&LogContext>| {
+        serde_json::to_string(&JsonLine {
+            timestamp: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            level: match record.level() {
+                Level::Error => "error",
+                Level::Warn => "warn",
+                Level::Info => "info",
+                Level::Debug => "debug",
+                Level::Trace => "trace",
+            },
+            module: record.target(),
+            product: context.and_then(|c| c.product.as_deref()),
+            request_id: context.and_then(|c| c.request_id.as_deref()),
+            message: record.args().to_string(),
+        })
+        .unwrap_or_default()
+    };
+    CONTEXT
+        .try_with(|context| line(Some(context)))
+        .unwrap_or_else(|_| line(None))
+}
+
+/// Installs the logger, with levels from `RUST_LOG` and the format from
+/// `LOG_FORMAT`.
+pub fn init() {
+    let levels = match env::var("RUST_LOG") {
+        Ok(directives) => LogLevels::parse(&directives).unwrap_or_else(|e| {
+            eprintln!("Ignoring RUST_LOG: {}", e);
+            LogLevels::default()
+        }),
+        Err(_) => LogLevels::default(),
+    };
+    let format = match env::var("LOG_FORMAT") {
+        Ok(format) => format.parse().unwrap_or_else(|e| {
+            eprintln!("Ignoring LOG_FORMAT: {}", e);
+            LogFormat::default()
+        }),
+        Err(_) => LogFormat::default(),
+    };
+
+    let mut writer = env_logger::Builder::new();
+    writer.filter_level(LevelFilter::Trace);
+    match format {
+        LogFormat::Json => writer.format(|buf, record| writeln!(buf, "{}", json_line(record))),
+        // env_logger's format, with the request id after the module
+        LogFormat::Text => writer.format(|buf, record| {
+            let style = buf.default_level_style(record.level());
+            write!(
+                buf,
+                "[{} {style}{:<5}{style:#} {}",
+                buf.timestamp(),
+                record.level(),
+                record.target()
+            )?;
+            if let Some(request_id) = current_request_id() {
+                write!(buf, " {}", request_id)?;
+            }
+            writeln!(buf, "] {}", record.args())
+        }),
+    };
+    if log::set_boxed_logger(Box::new(Logger {
+        writer: writer.build(),
+    }))
+    .is_ok()
+    {
+        set_levels(levels);
+    }
+}
+
+#[test]
+fn test_log_levels() {
+    let levels = LogLevels::parse("info, tauri_update_server::github=debug,actix_server").unwrap();
+    assert_eq!(levels.default, LevelFilter::Info);
+    assert_eq!(
+        levels.level("tauri_update_server::github::client", None),
+        LevelFilter::Debug
+    );
+    assert_eq!(
+        levels.level("tauri_update_server::handlers", None),
+        LevelFilter::Info
+    );
+    assert_eq!(
+        levels.level("actix_server::worker", None),
+        LevelFilter::Trace
+    );
+    assert!(LogLevels::parse("tauri_update_server=loud").is_err());
+
+    let mut levels = levels;
+    levels
+        .products
+        .insert("myapp".to_string(), LevelFilter::Error);
+    assert_eq!(
+        levels.level("tauri_update_server::github", Some("myapp")),
+        LevelFilter::Error
+    );
+    assert_eq!(
+        levels.level("tauri_update_server::github", Some("other")),
+        LevelFilter::Debug
+    );
+    assert_eq!(levels.max(), LevelFilter::Trace);
+
+    let json = serde_json::to_value(&levels).unwrap();
+    assert_eq!(json["default"], "info");
+    assert_eq!(json["products"]["myapp"], "error");
+    assert_eq!(serde_json::from_value::<LogLevels>(json).unwrap(), levels);
+
+    assert_eq!(
+        request_product("/MyApp/stable/windows/x86_64/1.0.0").as_deref(),
+        Some("myapp")
+    );
+    assert_eq!(
+        request_product("/admin/products/myapp/paused").as_deref(),
+        Some("myapp")
+    );
+    assert_eq!(request_product("/admin/maintenance"), None);
+}
+
+#[test]
+fn test_json_line() {
+    let line = |message: &str| {
+        json_line(
+            &Record::builder()
+                .level(Level::Warn)
+                .target("tauri_update_server::github")
+                .args(format_args!("{}", message))
+                .build(),
+        )
+    };
+    let json: serde_json::Value = serde_json::from_str(&line("rate \"limited\"")).unwrap();
+    assert_eq!(json["level"], "warn");
+    assert_eq!(json["module"], "tauri_update_server::github");
+    assert_eq!(json["message"], "rate \"limited\"");
+    assert!(json.get("product").is_none());
+
+    let context = LogContext {
+        product: Some("myapp".to_string()),
+        request_id: Some("req-42".to_string()),
+    };
+    let json = CONTEXT.sync_scope(context, || line("hello"));
+    assert!(json.contains("\"product\":\"myapp\",\"request_id\":\"req-42\""));
+    assert_eq!("JSON".parse(), Ok(LogFormat::Json));
+    assert!("xml".parse::<LogFormat>().is_err());
+}
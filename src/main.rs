@@ -1,38 +1,48 @@
-mod config;
-mod github;
-mod handlers;
-mod platform;
+mod cli;
 
-use actix_web::{web, App, HttpServer};
-use dotenvy::dotenv;
-use log::info;
+use std::process::ExitCode;
 
-use crate::config::AppState;
-use crate::handlers::{download::download_asset, update::check_update};
+use clap::Parser;
+use tauri_update_server::logging;
 
-#[actix_web::main]
-async fn main() -> std::io::Result<()> {
-    dotenv().ok();
-    env_logger::init();
+use crate::cli::args::{Cli, Command};
+use crate::cli::commands;
 
-    let address = std::env::var("ADDRESS").unwrap_or_else(|_| "0.0.0.0".to_string());
-    let port = std::env::var("PORT").unwrap_or_else(|_| "8080".to_string());
-    let bind_address = format!("{}:{}", address, port);
+#[actix_web::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match &cli.env_file {
+        Some(path) => {
+            if let Err(e) = dotenvy::from_path(path) {
+                eprintln!("Failed to load {}: {}", path.display(), e);
+                return ExitCode::FAILURE;
+            }
+        }
+        None => {
+            dotenvy::dotenv().ok();
+        }
+    }
+    logging::init();
 
-    let app_state = AppState::load_config().await;
+    // Parse again so flags can fall back to variables from the env file
+    let cli = Cli::parse();
+    let command = match cli.command {
+        Some(command) => command,
+        None if cli.check_config => Command::CheckConfig,
+        None if cli.sync => Command::Sync,
+        None => Command::Serve(cli.serve),
+    };
 
-    info!(
-        "Starting the multi-product update server on {}",
-        &bind_address
-    );
+    let result = match command {
+        Command::Serve(args) => commands::serve(args).await,
+        Command::CheckConfig => commands::check_config().await,
+        Command::WarmCache(args) => commands::warm_cache(args).await,
+        Command::ListProducts => commands::list_products().await,
+        Command::Sync => commands::sync().await,
+    };
 
-    HttpServer::new(move || {
-        App::new()
-            .app_data(web::Data::new(app_state.clone()))
-            .service(check_update)
-            .service(download_asset)
+    result.unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        ExitCode::FAILURE
     })
-    .bind(&bind_address)?
-    .run()
-    .await
 }
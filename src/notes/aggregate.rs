@@ -0,0 +1,58 @@
+use semver::Version;
+
+/// Concatenates the notes of every release in `(current, latest]`, newest
+/// first, keeping at most `max_releases` entries.
+pub fn aggregate_notes<S: AsRef<str>>(
+    releases: &[(Version, S)],
+    current: &Version,
+    latest: &Version,
+    max_releases: usize,
+) -> String {
+    let mut missed: Vec<&(Version, S)> = releases
+        .iter()
+        .filter(|(version, _)| version > current && version <= latest)
+        .collect();
+    missed.sort_by(|a, b| b.0.cmp(&a.0));
+
+    let skipped = missed.len().saturating_sub(max_releases);
+    let mut notes: Vec<String> = missed
+        .into_iter()
+        .take(max_releases)
+        .map(|(version, body)| format!("## v{}\n\n{}", version, body.as_ref().trim()))
+        .collect();
+
+    if skipped > 0 {
+        notes.push(format!("…and {} earlier releases.", skipped));
+    }
+
+    notes.join("\n\n")
+}
+
+#[test]
+fn test_aggregate_notes_between_versions() {
+    let releases = vec![
+        (Version::new(1, 0, 0), "first".to_string()),
+        (Version::new(1, 2, 0), "third".to_string()),
+        (Version::new(1, 1, 0), "second".to_string()),
+    ];
+
+    let notes = aggregate_notes(
+        &releases,
+        &Version::new(1, 0, 0),
+        &Version::new(1, 2, 0),
+        10,
+    );
+    assert_eq!(notes, "## v1.2.0\n\nthird\n\n## v1.1.0\n\nsecond");
+}
+
+#[test]
+fn test_aggregate_notes_capped() {
+    let releases = vec![
+        (Version::new(1, 1, 0), "a".to_string()),
+        (Version::new(1, 2, 0), "b".to_string()),
+        (Version::new(1, 3, 0), "c".to_string()),
+    ];
+
+    let notes = aggregate_notes(&releases, &Version::new(1, 0, 0), &Version::new(1, 3, 0), 1);
+    assert_eq!(notes, "## v1.3.0\n\nc\n\n…and 2 earlier releases.");
+}
@@ -0,0 +1,70 @@
+use regex::Regex;
+use semver::Version;
+
+/// The section of a Markdown changelog for `version`: the lines after the
+/// first heading naming the version, e.g. `## [1.2.0] - 2024-05-01` or
+/// `### v1.2.0`, up to the next heading of the same or a higher level.
+pub fn changelog_section(changelog: &str, version: &Version) -> Option<String> {
+    let names_version = Regex::new(&format!(
+        r"(?:^|[^\w.])v?{}(?:$|[^\w.+-])",
+        regex::escape(&version.to_string())
+    ))
+    .ok()?;
+
+    let mut section: Option<(usize, Vec<&str>)> = None;
+    for line in changelog.lines() {
+        let heading = heading_level(line);
+        match (&mut section, heading) {
+            (None, Some(level)) if names_version.is_match(line[level..].trim()) => {
+                section = Some((level, Vec::new()));
+            }
+            (Some((section_level, _)), Some(level)) if level <= *section_level => break,
+            (Some((_, lines)), _) => lines.push(line),
+            _ => {}
+        }
+    }
+
+    let (_, lines) = section?;
+    let text = lines.join("\n").trim().to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+/// The level of an ATX heading such as `## Title`.
+fn heading_level(line: &str) -> Option<usize> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    ((1..=6).contains(&level) && line[level..].starts_with([' ', '\t'])).then_some(level)
+}
+
+#[test]
+fn test_changelog_section() {
+    let changelog = "\
+# Changelog
+
+## [Unreleased]
+
+- Work in progress
+
+## [1.2.0] - 2024-05-01
+
+### Fixed
+
+- Crash on startup
+
+## [1.2.0-beta.1] - 2024-04-20
+
+- Beta
+
+## v1.1.0
+
+- First
+";
+    let section = |version: &str| changelog_section(changelog, &Version::parse(version).unwrap());
+    assert_eq!(
+        section("1.2.0").as_deref(),
+        Some("### Fixed\n\n- Crash on startup")
+    );
+    assert_eq!(section("1.2.0-beta.1").as_deref(), Some("- Beta"));
+    assert_eq!(section("1.1.0").as_deref(), Some("- First"));
+    assert_eq!(section("1.0.0"), None);
+    assert_eq!(section("2.0.0"), None);
+}
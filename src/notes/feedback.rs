@@ -0,0 +1,57 @@
+use crate::notes::sanitize::truncate;
+
+const SEPARATOR: &str = "\n\n";
+
+/// Appends a link inviting users to react to or comment on the release.
+/// With a `max_length`, the notes are shortened so that the result still
+/// fits; a link that doesn't fit on its own is left out.
+pub fn append_feedback_link(notes: String, url: &str, max_length: Option<usize>) -> String {
+    let link = format!("[Share feedback on this release]({})", url);
+    let Some(max) = max_length else {
+        return join(&notes, &link);
+    };
+    let link_length = link.chars().count();
+    if link_length > max {
+        return notes;
+    }
+    let room = max - link_length;
+    if notes.is_empty() || room <= SEPARATOR.len() {
+        return link;
+    }
+    join(&truncate(&notes, room - SEPARATOR.len()), &link)
+}
+
+fn join(notes: &str, link: &str) -> String {
+    if notes.is_empty() {
+        link.to_string()
+    } else {
+        format!("{}{}{}", notes, SEPARATOR, link)
+    }
+}
+
+#[test]
+fn test_append_feedback_link() {
+    let url = "https://github.com/acme/app/releases/tag/v1.0.0";
+    let link = format!("[Share feedback on this release]({})", url);
+
+    assert_eq!(
+        append_feedback_link("Fixes".to_string(), url, None),
+        format!("Fixes\n\n{}", link)
+    );
+    assert_eq!(append_feedback_link(String::new(), url, None), link);
+
+    // The cap applies to the notes and the link together
+    let capped = append_feedback_link("A".repeat(200), url, Some(100));
+    assert_eq!(capped.chars().count(), 100);
+    assert!(capped.ends_with(&format!("…\n\n{}", link)));
+    assert_eq!(
+        append_feedback_link("Fixes".to_string(), url, Some(200)),
+        format!("Fixes\n\n{}", link)
+    );
+
+    // A link that can't fit is left out rather than cut
+    assert_eq!(
+        append_feedback_link("Fixes".to_string(), url, Some(20)),
+        "Fixes"
+    );
+}
@@ -0,0 +1,103 @@
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+/// Starts a section of release notes in another language, e.g.
+/// `<!-- locale: de -->`. The section runs until the next marker.
+static MARKER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?im)^[ \t]*<!--\s*locale:\s*([a-z]{2,3}(?:[-_][a-z0-9]{2,8})*)\s*-->[ \t]*\r?$")
+        .expect("valid regex")
+});
+
+/// Language tags of an `Accept-Language` header, most preferred first and
+/// lowercased. Tags refused with `q=0` and the `*` wildcard are left out.
+pub fn accepted_languages(header: &str) -> Vec<String> {
+    let mut languages: Vec<(String, f32)> = header
+        .split(',')
+        .filter_map(|item| {
+            let mut parts = item.split(';');
+            let tag = normalize_tag(parts.next()?.trim());
+            let quality = parts
+                .find_map(|param| param.trim().strip_prefix("q="))
+                .map_or(Some(1.0), |q| q.trim().parse::<f32>().ok())?;
+            (!tag.is_empty() && tag != "*" && quality > 0.0).then_some((tag, quality))
+        })
+        .collect();
+    // Stable, so equally preferred tags keep their order
+    languages.sort_by(|a, b| b.1.total_cmp(&a.1));
+    languages.into_iter().map(|(tag, _)| tag).collect()
+}
+
+/// Index of the tag in `tags` that best serves the first of `languages`
+/// any of them serves, matching `de-at` to `de` and `de` to `de-ch` if
+/// needed. Tags are expected lowercase, with `-` separators.
+pub fn best_match<S: AsRef<str>>(tags: &[S], languages: &[String]) -> Option<usize> {
+    let primary = |tag: &str| tag.split('-').next().unwrap_or_default().to_string();
+    languages.iter().find_map(|language| {
+        let position =
+            |matches: &dyn Fn(&str) -> bool| tags.iter().position(|t| matches(t.as_ref()));
+        position(&|tag| tag == language)
+            .or_else(|| position(&|tag| tag == primary(language)))
+            .or_else(|| position(&|tag| primary(tag) == primary(language)))
+    })
+}
+
+/// Normalizes a language tag for [`best_match`], e.g. `pt_BR` to `pt-br`.
+pub fn normalize_tag(tag: &str) -> String {
+    tag.to_lowercase().replace('_', "-")
+}
+
+/// The notes of `body` in the language of [`best_match`] that it has a
+/// section for. Without a match, the notes before the first section are
+/// used; a body without sections is returned as is.
+pub fn localized_notes<'a>(body: &'a str, languages: &[String]) -> &'a str {
+    let markers: Vec<_> = MARKER.captures_iter(body).collect();
+    let Some(first) = markers.first() else {
+        return body;
+    };
+
+    let (tags, sections): (Vec<String>, Vec<&str>) = markers
+        .iter()
+        .enumerate()
+        .map(|(i, captures)| {
+            let marker = captures.get(0).expect("whole match");
+            let end = markers
+                .get(i + 1)
+                .map_or(body.len(), |next| next.get(0).expect("whole match").start());
+            (normalize_tag(&captures[1]), body[marker.end()..end].trim())
+        })
+        .unzip();
+
+    match best_match(&tags, languages) {
+        Some(index) => sections[index],
+        None => body[..first.get(0).expect("whole match").start()].trim(),
+    }
+}
+
+#[test]
+fn test_accepted_languages() {
+    assert_eq!(
+        accepted_languages("fr-CH, fr;q=0.9, en;q=0.8, de;q=0.95, *;q=0.5, it;q=0"),
+        vec!["fr-ch", "de", "fr", "en"]
+    );
+    assert!(accepted_languages("").is_empty());
+}
+
+#[test]
+fn test_localized_notes() {
+    let body = "Bug fixes.\n\n<!-- locale: de -->\nFehlerbehebungen.\n\n<!-- locale: pt-BR -->\nCorreções.\n";
+    let notes = |languages: &[&str]| {
+        let languages: Vec<String> = languages.iter().map(|l| l.to_string()).collect();
+        localized_notes(body, &languages).to_string()
+    };
+    assert_eq!(notes(&["de"]), "Fehlerbehebungen.");
+    assert_eq!(notes(&["de-at", "en"]), "Fehlerbehebungen.");
+    assert_eq!(notes(&["pt"]), "Correções.");
+    assert_eq!(notes(&["fr", "pt-br"]), "Correções.");
+    assert_eq!(notes(&["fr"]), "Bug fixes.");
+    assert_eq!(notes(&[]), "Bug fixes.");
+    assert_eq!(
+        localized_notes("Just notes", &["de".to_string()]),
+        "Just notes"
+    );
+}
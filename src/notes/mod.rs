@@ -0,0 +1,6 @@
+pub mod aggregate;
+pub mod changelog;
+pub mod feedback;
+pub mod locale;
+pub mod render;
+pub mod sanitize;
@@ -0,0 +1,42 @@
+use pulldown_cmark::{html, Options, Parser};
+use serde::Deserialize;
+
+/// Format of the `notes` field in update responses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotesFormat {
+    #[default]
+    Markdown,
+    Html,
+}
+
+impl NotesFormat {
+    pub fn render(&self, notes: String) -> String {
+        match self {
+            NotesFormat::Markdown => notes,
+            NotesFormat::Html => render_html(&notes),
+        }
+    }
+}
+
+/// Renders GitHub-flavored Markdown to HTML with scripts, styles and
+/// event handlers stripped.
+pub fn render_html(markdown: &str) -> String {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_FOOTNOTES;
+
+    let mut unsafe_html = String::new();
+    html::push_html(&mut unsafe_html, Parser::new_ext(markdown, options));
+
+    ammonia::clean(&unsafe_html)
+}
+
+#[test]
+fn test_render_html_sanitizes() {
+    let html = render_html("# Title\n\n- [x] done\n\n<script>alert(1)</script>");
+    assert!(html.contains("<h1>Title</h1>"));
+    assert!(html.contains("<li>"));
+    assert!(!html.contains("<script>"));
+}
@@ -0,0 +1,164 @@
+use std::sync::LazyLock;
+
+use regex::{Captures, Regex};
+use serde::Deserialize;
+
+static COMMENT: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?s)<!--.*?-->").unwrap());
+static MARKDOWN_LINK: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\[([^\]]*)\]\(([^)\s]+)[^)]*\)").unwrap());
+static BARE_URL: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"<?https?://[^\s)>\]]+>?").unwrap());
+
+/// Per-product rules applied to release notes before they are returned.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct NotesSanitizer {
+    /// Remove `<!-- ... -->` comments.
+    #[serde(default)]
+    pub strip_comments: bool,
+    /// Remove Markdown sections whose heading contains one of these markers.
+    #[serde(default)]
+    pub strip_sections: Vec<String>,
+    /// Unlink (or drop, for bare URLs) links pointing to these hosts.
+    #[serde(default)]
+    pub strip_link_hosts: Vec<String>,
+    /// Truncate notes longer than this many characters, appending an ellipsis.
+    #[serde(default)]
+    pub max_length: Option<usize>,
+}
+
+/// Cuts `notes` down to at most `max` characters, ending truncated notes with
+/// an ellipsis.
+pub fn truncate(notes: &str, max: usize) -> String {
+    if notes.chars().count() <= max {
+        return notes.to_string();
+    }
+    if max == 0 {
+        return String::new();
+    }
+    let truncated: String = notes.chars().take(max - 1).collect();
+    format!("{}…", truncated.trim_end())
+}
+
+impl NotesSanitizer {
+    pub fn sanitize(&self, notes: &str) -> String {
+        let mut notes = notes.to_string();
+
+        if self.strip_comments {
+            notes = COMMENT.replace_all(&notes, "").into_owned();
+        }
+        if !self.strip_sections.is_empty() {
+            notes = self.strip_marked_sections(&notes);
+        }
+        if !self.strip_link_hosts.is_empty() {
+            notes = self.strip_links(&notes);
+        }
+
+        let notes = notes.trim();
+        match self.max_length {
+            Some(max) => truncate(notes, max),
+            None => notes.to_string(),
+        }
+    }
+
+    fn strip_marked_sections(&self, notes: &str) -> String {
+        let markers: Vec<String> = self
+            .strip_sections
+            .iter()
+            .map(|m| m.to_lowercase())
+            .collect();
+        let mut skipping: Option<usize> = None;
+        let mut kept = Vec::new();
+
+        for line in notes.lines() {
+            if let Some((level, title)) = heading(line) {
+                if skipping.is_some_and(|skip_level| level <= skip_level) {
+                    skipping = None;
+                }
+                let title = title.to_lowercase();
+                if skipping.is_none() && markers.iter().any(|m| title.contains(m.as_str())) {
+                    skipping = Some(level);
+                }
+            }
+
+            if skipping.is_none() {
+                kept.push(line);
+            }
+        }
+
+        kept.join("\n")
+    }
+
+    fn strip_links(&self, notes: &str) -> String {
+        let notes = MARKDOWN_LINK.replace_all(notes, |caps: &Captures| {
+            if self.is_stripped_host(&caps[2]) {
+                caps[1].to_string()
+            } else {
+                caps[0].to_string()
+            }
+        });
+
+        BARE_URL
+            .replace_all(&notes, |caps: &Captures| {
+                let url = caps[0].trim_start_matches('<').trim_end_matches('>');
+                if self.is_stripped_host(url) {
+                    String::new()
+                } else {
+                    caps[0].to_string()
+                }
+            })
+            .into_owned()
+    }
+
+    fn is_stripped_host(&self, url: &str) -> bool {
+        let Some((_, rest)) = url.split_once("://") else {
+            return false;
+        };
+        let host = rest
+            .split(['/', ':', '?', '#'])
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+
+        self.strip_link_hosts.iter().any(|stripped| {
+            let stripped = stripped.to_lowercase();
+            host == stripped || host.ends_with(&format!(".{}", stripped))
+        })
+    }
+}
+
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    if level == 0 || level > 6 {
+        return None;
+    }
+    line[level..].strip_prefix(' ').map(|title| (level, title))
+}
+
+#[test]
+fn test_sanitize_strips_comments_and_sections() {
+    let sanitizer = NotesSanitizer {
+        strip_comments: true,
+        strip_sections: vec!["internal".to_string()],
+        ..Default::default()
+    };
+    let notes =
+        "## Fixes\n- crash <!-- JIRA-1 -->\n### Internal notes\n- secret\n## Features\n- new";
+
+    assert_eq!(
+        sanitizer.sanitize(notes),
+        "## Fixes\n- crash \n## Features\n- new"
+    );
+}
+
+#[test]
+fn test_sanitize_strips_internal_links_and_truncates() {
+    let sanitizer = NotesSanitizer {
+        strip_link_hosts: vec!["corp.example.com".to_string()],
+        max_length: Some(30),
+        ..Default::default()
+    };
+    let notes =
+        "See [ticket](https://jira.corp.example.com/X-1) and https://example.org/docs for details";
+
+    assert_eq!(sanitizer.sanitize(notes), "See ticket and https://exampl…");
+}
@@ -3,6 +3,7 @@ use std::fmt;
 use actix_web::http::StatusCode;
 use actix_web::ResponseError;
 use log::{debug, error, info};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -40,6 +41,30 @@ impl ResponseError for MatchError {
     }
 }
 
+impl Platform {
+    /// Platforms covered by the built-in matching rules.
+    pub fn supported() -> Vec<Platform> {
+        [
+            ("windows", "x86_64"),
+            ("windows", "i686"),
+            ("darwin", "x86_64"),
+            ("darwin", "aarch64"),
+            ("linux", "x86_64"),
+        ]
+        .into_iter()
+        .map(|(target, arch)| Platform {
+            target: target.to_string(),
+            arch: arch.to_string(),
+        })
+        .collect()
+    }
+
+    /// Key used in the `platforms` map of static manifests, e.g. `darwin-aarch64`.
+    pub fn key(&self) -> String {
+        format!("{}-{}", self.target, self.arch)
+    }
+}
+
 impl fmt::Display for Platform {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{} {}", self.target, self.arch)
@@ -48,12 +73,49 @@ impl fmt::Display for Platform {
 
 pub struct PlatformMatcher {
     rules: Vec<Box<dyn MatchRule>>,
+    asset_prefix: Option<String>,
+    asset_pattern: Option<Regex>,
 }
 
 pub trait MatchRule: Send + Sync {
     fn matches(&self, platform: &Platform, filename: &str) -> bool;
 }
 
+// Allocation-free ASCII case-insensitive helpers for the matching hot path
+pub fn contains_ignore_case(haystack: &str, needle: &str) -> bool {
+    haystack
+        .as_bytes()
+        .windows(needle.len())
+        .any(|window| window.eq_ignore_ascii_case(needle.as_bytes()))
+}
+
+pub fn ends_with_ignore_case(haystack: &str, suffix: &str) -> bool {
+    haystack.len() >= suffix.len()
+        && haystack.as_bytes()[haystack.len() - suffix.len()..]
+            .eq_ignore_ascii_case(suffix.as_bytes())
+}
+
+/// Updater artifact extensions per target. Besides Tauri's defaults, each
+/// platform accepts zstd-compressed bundles (`.tar.zst`, or `.zst` for
+/// single files), which are signed like any other artifact.
+const ARTIFACT_EXTENSIONS: &[(&str, &[&str])] = &[
+    ("windows", &[".msi", ".msi.zst", ".msi.tar.zst"]),
+    ("darwin", &[".app.tar.gz", ".app.tar.zst", ".dmg"]),
+    (
+        "linux",
+        &[".appimage", ".appimage.zst", ".appimage.tar.zst"],
+    ),
+];
+
+/// Whether `filename` has an updater artifact extension of `target`.
+pub fn is_updater_artifact(target: &str, filename: &str) -> bool {
+    ARTIFACT_EXTENSIONS
+        .iter()
+        .filter(|(t, _)| *t == target)
+        .flat_map(|(_, extensions)| extensions.iter())
+        .any(|extension| ends_with_ignore_case(filename, extension))
+}
+
 // Windows MSI Rule
 pub struct WindowsMsiRule;
 impl MatchRule for WindowsMsiRule {
@@ -62,14 +124,14 @@ impl MatchRule for WindowsMsiRule {
             return false;
         }
 
-        let filename_lower = filename.to_lowercase();
-        match platform.arch.as_str() {
-            "x86_64" => filename_lower.contains("_x64") && filename_lower.ends_with(".msi"),
-            "i686" => filename_lower.contains("_x86") && filename_lower.ends_with(".msi"),
+        let arch_match = match platform.arch.as_str() {
+            "x86_64" => contains_ignore_case(filename, "_x64"),
+            "i686" => contains_ignore_case(filename, "_x86"),
             _ => false,
-        }
-    }
+        };
 
+        arch_match && is_updater_artifact("windows", filename)
+    }
 }
 
 // macOS Rule
@@ -80,16 +142,14 @@ impl MatchRule for MacOSRule {
             return false;
         }
 
-        let filename_lower = filename.to_lowercase();
         let arch_match = match platform.arch.as_str() {
-            "x86_64" => filename_lower.contains("_x64"),
-            "aarch64" => filename_lower.contains("aarch64"),
+            "x86_64" => contains_ignore_case(filename, "_x64"),
+            "aarch64" => contains_ignore_case(filename, "aarch64"),
             _ => false,
         };
 
-        arch_match && (filename_lower.ends_with(".app.tar.gz") || filename_lower.ends_with(".dmg"))
+        arch_match && is_updater_artifact("darwin", filename)
     }
-
 }
 
 // Linux Rule
@@ -100,12 +160,55 @@ impl MatchRule for LinuxRule {
             return false;
         }
 
-        let filename_lower = filename.to_lowercase();
         platform.arch == "x86_64"
-            && filename_lower.contains("amd64")
-            && filename_lower.ends_with(".appimage")
+            && contains_ignore_case(filename, "amd64")
+            && is_updater_artifact("linux", filename)
     }
+}
 
+/// Strips a `FEATURE.` prefix, where the feature is matched in upper case.
+fn strip_feature_prefix<'a>(asset: &'a str, feature: &str) -> Option<&'a str> {
+    let head = asset.get(..feature.len())?;
+    let upper_matches = head
+        .bytes()
+        .zip(feature.bytes())
+        .all(|(a, f)| a == f.to_ascii_uppercase());
+    if upper_matches {
+        asset[feature.len()..].strip_prefix('.')
+    } else {
+        None
+    }
+}
+
+/// Whether the filename carries a `-{edition}` or `_{edition}` marker.
+fn has_edition_marker(asset: &str, edition: &str) -> bool {
+    let bytes = asset.as_bytes();
+    (1..bytes.len()).any(|i| {
+        matches!(bytes[i - 1], b'-' | b'_')
+            && bytes[i..]
+                .get(..edition.len())
+                .is_some_and(|window| window.eq_ignore_ascii_case(edition.as_bytes()))
+    })
+}
+
+/// Whether `asset` is an edition of another of `names`: its name with a
+/// `-{edition}` or `_{edition}` marker added, e.g. `MyApp-trial.msi` next to
+/// `MyApp.msi`.
+fn is_edition_of_another(asset: &str, names: &[&str]) -> bool {
+    asset.match_indices(['-', '_']).any(|(start, _)| {
+        let edition = asset[start + 1..]
+            .split(['-', '_', '.'])
+            .next()
+            .unwrap_or_default();
+        let plain = format!("{}{}", &asset[..start], &asset[start + 1 + edition.len()..]);
+        !edition.is_empty() && names.contains(&plain.as_str())
+    })
+}
+
+impl Default for PlatformMatcher {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl PlatformMatcher {
@@ -115,59 +218,121 @@ impl PlatformMatcher {
             Box::new(MacOSRule),
             Box::new(LinuxRule),
         ];
-        PlatformMatcher { rules }
+        PlatformMatcher {
+            rules,
+            asset_prefix: None,
+            asset_pattern: None,
+        }
+    }
+
+    /// Only match assets whose name, after any feature prefix, starts with
+    /// `prefix`. Lets several products share one repository.
+    pub fn with_asset_prefix(mut self, prefix: Option<String>) -> Self {
+        self.asset_prefix = prefix;
+        self
+    }
+
+    /// Only match assets whose full name matches `pattern`.
+    pub fn with_asset_pattern(mut self, pattern: Option<Regex>) -> Self {
+        self.asset_pattern = pattern;
+        self
+    }
+
+    /// Whether `asset` carries the feature prefix (if any) and satisfies the
+    /// product's asset prefix and pattern.
+    fn belongs_to(&self, asset: &str, feature: Option<&str>) -> bool {
+        let unprefixed = match feature {
+            Some(feature) => match strip_feature_prefix(asset, feature) {
+                Some(rest) => rest,
+                None => return false,
+            },
+            None => asset,
+        };
+
+        if let Some(prefix) = &self.asset_prefix {
+            if !unprefixed.starts_with(prefix.as_str()) {
+                return false;
+            }
+        }
+
+        self.asset_pattern
+            .as_ref()
+            .is_none_or(|pattern| pattern.is_match(asset))
     }
 
-    pub fn find_matching_asset(
+    /// Assets of the product and feature that `accept` recognises, in their
+    /// original order. Used by package formats that don't need signatures.
+    pub fn find_packages<'a, S: AsRef<str>>(
+        &self,
+        assets: &'a [S],
+        feature: Option<&str>,
+        accept: impl Fn(&str) -> bool,
+    ) -> Vec<&'a str> {
+        let feature = feature.filter(|f| !f.eq_ignore_ascii_case("stable"));
+        assets
+            .iter()
+            .map(AsRef::as_ref)
+            .filter(|asset| self.belongs_to(asset, feature) && accept(asset))
+            .collect()
+    }
+
+    /// Finds the installer for `platform` in `assets`. When `edition` is set,
+    /// only installers marked with a `-{edition}` or `_{edition}` suffix match;
+    /// otherwise editions of another installer, such as `-trial` next to the
+    /// plain one, are skipped.
+    pub fn find_matching_asset<S: AsRef<str>>(
         &self,
         platform: &Platform,
-        assets: &[String],
+        assets: &[S],
         feature: Option<&str>,
+        edition: Option<&str>,
     ) -> Result<AssetMatch, MatchError> {
-        let feature_prefix = feature.map(|f| {
-            if f.eq_ignore_ascii_case("stable") {
-                String::new()
-            } else {
-                format!("{}.", f.to_uppercase())
+        // Non-stable features prefix their assets with `FEATURE.`
+        let feature = feature.filter(|f| !f.eq_ignore_ascii_case("stable"));
+
+        if log::log_enabled!(log::Level::Debug) {
+            let names: Vec<&str> = assets.iter().map(AsRef::as_ref).collect();
+            debug!("Available assets: {:?}", names);
+            if let Some(feature) = feature {
+                debug!("Looking for feature prefix: {}.", feature.to_uppercase());
             }
-        });
-
-        debug!("Available assets: {:?}", assets);
-        if let Some(prefix) = &feature_prefix {
-            debug!("Looking for feature prefix: {}", prefix);
         }
 
-        let mut signatureless_match: Option<String> = None;
-
-        for asset in assets.iter() {
-            let passes_feature = match &feature_prefix {
-                Some(prefix) if !prefix.is_empty() => asset.starts_with(prefix),
-                _ => true,
-            };
+        let mut signatureless_match: Option<&str> = None;
+        let names: Vec<&str> = assets.iter().map(AsRef::as_ref).collect();
 
-            if !passes_feature {
+        for asset in names.iter().copied() {
+            if !self.belongs_to(asset, feature) {
                 continue;
             }
 
+            match edition {
+                Some(edition) if !has_edition_marker(asset, edition) => continue,
+                None if is_edition_of_another(asset, &names) => continue,
+                _ => {}
+            }
+
             if !self.rules.iter().any(|rule| rule.matches(platform, asset)) {
                 continue;
             }
 
-            let signature_filename = format!("{}.sig", asset);
-            if assets.contains(&signature_filename) {
+            let signature = names
+                .iter()
+                .find(|name| name.strip_suffix(".sig") == Some(asset));
+            if let Some(signature_filename) = signature {
                 return Ok(AssetMatch {
-                    filename: asset.clone(),
-                    signature_filename: Some(signature_filename),
+                    filename: asset.to_string(),
+                    signature_filename: Some(signature_filename.to_string()),
                 });
             }
 
             error!("No signature file found for {}", asset);
-            info!("Expected signature file: {}", signature_filename);
-            signatureless_match = Some(asset.clone());
+            info!("Expected signature file: {}.sig", asset);
+            signatureless_match = Some(asset);
         }
 
         if let Some(asset) = signatureless_match {
-            return Err(MatchError::NoSignature(asset));
+            return Err(MatchError::NoSignature(asset.to_string()));
         }
 
         Err(MatchError::NoMatch {
@@ -193,7 +358,7 @@ fn test_windows_msi_matching() {
     ];
 
     let result = matcher
-        .find_matching_asset(&platform, &assets, Some("fas2"))
+        .find_matching_asset(&platform, &assets, Some("fas2"), None)
         .unwrap();
     assert_eq!(result.filename, "FAS2.Lumina_2.0.11_x64_de-DE.msi");
     assert_eq!(
@@ -216,7 +381,7 @@ fn test_stable_feature_matching() {
     ];
 
     let result = matcher
-        .find_matching_asset(&platform, &assets, Some("stable"))
+        .find_matching_asset(&platform, &assets, Some("stable"), None)
         .unwrap();
     assert_eq!(result.filename, "COMPANY.-.Product_1.2.0_x64_en-US.msi");
 }
@@ -236,7 +401,7 @@ fn test_macos_matching() {
     ];
 
     let result = matcher
-        .find_matching_asset(&platform, &assets, None)
+        .find_matching_asset(&platform, &assets, None, None)
         .unwrap();
     assert_eq!(
         result.filename,
@@ -258,11 +423,44 @@ fn test_linux_matching() {
     ];
 
     let result = matcher
-        .find_matching_asset(&platform, &assets, None)
+        .find_matching_asset(&platform, &assets, None, None)
         .unwrap();
     assert_eq!(result.filename, "COMPANY.-.Product_1.2.0_amd64.AppImage");
 }
 
+#[test]
+fn test_zstd_artifact_matching() {
+    let matcher = PlatformMatcher::new();
+    let assets = vec![
+        "MyApp_1.2.0_x64_en-US.msi.tar.zst".to_string(),
+        "MyApp_1.2.0_x64_en-US.msi.tar.zst.sig".to_string(),
+        "MyApp_aarch64.app.tar.zst".to_string(),
+        "MyApp_aarch64.app.tar.zst.sig".to_string(),
+        "MyApp_1.2.0_amd64.AppImage.tar.zst".to_string(),
+        "MyApp_1.2.0_amd64.AppImage.tar.zst.sig".to_string(),
+    ];
+
+    for (target, arch, expected) in [
+        ("windows", "x86_64", "MyApp_1.2.0_x64_en-US.msi.tar.zst"),
+        ("darwin", "aarch64", "MyApp_aarch64.app.tar.zst"),
+        ("linux", "x86_64", "MyApp_1.2.0_amd64.AppImage.tar.zst"),
+    ] {
+        let platform = Platform {
+            target: target.to_string(),
+            arch: arch.to_string(),
+        };
+        let result = matcher
+            .find_matching_asset(&platform, &assets, None, None)
+            .unwrap();
+        assert_eq!(result.filename, expected);
+        assert_eq!(
+            result.signature_filename.as_deref(),
+            Some(format!("{}.sig", expected).as_str())
+        );
+    }
+    assert!(!is_updater_artifact("darwin", "MyApp.tar.zst"));
+}
+
 #[test]
 fn test_no_matching_asset() {
     let matcher = PlatformMatcher::new();
@@ -274,7 +472,7 @@ fn test_no_matching_asset() {
     let assets = vec!["COMPANY.-.Product_1.2.0_aarch64.app.tar.gz".to_string()];
 
     assert!(matcher
-        .find_matching_asset(&platform, &assets, None)
+        .find_matching_asset(&platform, &assets, None, None)
         .is_err());
 }
 
@@ -292,6 +490,82 @@ fn test_feature_mismatch() {
     ];
 
     assert!(matcher
-        .find_matching_asset(&platform, &assets, Some("fas2"))
+        .find_matching_asset(&platform, &assets, Some("fas2"), None)
         .is_err());
 }
+
+#[test]
+fn test_edition_matching() {
+    let matcher = PlatformMatcher::new();
+    let platform = Platform {
+        target: "windows".to_string(),
+        arch: "x86_64".to_string(),
+    };
+
+    let assets = vec![
+        "Product_1.2.0_x64_en-US-trial.msi".to_string(),
+        "Product_1.2.0_x64_en-US-trial.msi.sig".to_string(),
+        "Product_1.2.0_x64_en-US-full.msi".to_string(),
+        "Product_1.2.0_x64_en-US-full.msi.sig".to_string(),
+    ];
+
+    let result = matcher
+        .find_matching_asset(&platform, &assets, None, Some("full"))
+        .unwrap();
+    assert_eq!(result.filename, "Product_1.2.0_x64_en-US-full.msi");
+}
+
+#[test]
+fn test_plain_edition_matching() {
+    let matcher = PlatformMatcher::new();
+    let platform = Platform {
+        target: "windows".to_string(),
+        arch: "x86_64".to_string(),
+    };
+
+    let assets = vec![
+        "Product_1.2.0_x64_en-US-trial.msi".to_string(),
+        "Product_1.2.0_x64_en-US-trial.msi.sig".to_string(),
+        "Product_1.2.0_x64_en-US.msi".to_string(),
+        "Product_1.2.0_x64_en-US.msi.sig".to_string(),
+    ];
+
+    // Clients that don't ask for an edition get the plain installer
+    let result = matcher
+        .find_matching_asset(&platform, &assets, None, None)
+        .unwrap();
+    assert_eq!(result.filename, "Product_1.2.0_x64_en-US.msi");
+
+    let result = matcher
+        .find_matching_asset(&platform, &assets, None, Some("trial"))
+        .unwrap();
+    assert_eq!(result.filename, "Product_1.2.0_x64_en-US-trial.msi");
+}
+
+#[test]
+fn test_asset_prefix_matching() {
+    let matcher = PlatformMatcher::new().with_asset_prefix(Some("AppB_".to_string()));
+    let platform = Platform {
+        target: "windows".to_string(),
+        arch: "x86_64".to_string(),
+    };
+
+    let assets = vec![
+        "AppA_1.2.0_x64_en-US.msi".to_string(),
+        "AppA_1.2.0_x64_en-US.msi.sig".to_string(),
+        "BETA.AppB_1.2.0_x64_en-US.msi".to_string(),
+        "BETA.AppB_1.2.0_x64_en-US.msi.sig".to_string(),
+        "AppB_1.2.0_x64_en-US.msi".to_string(),
+        "AppB_1.2.0_x64_en-US.msi.sig".to_string(),
+    ];
+
+    let stable = matcher
+        .find_matching_asset(&platform, &assets, Some("stable"), None)
+        .unwrap();
+    assert_eq!(stable.filename, "AppB_1.2.0_x64_en-US.msi");
+
+    let beta = matcher
+        .find_matching_asset(&platform, &assets, Some("beta"), None)
+        .unwrap();
+    assert_eq!(beta.filename, "BETA.AppB_1.2.0_x64_en-US.msi");
+}
@@ -0,0 +1,199 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::sync::LazyLock;
+
+use regex::Regex;
+use semver::Version;
+use serde::Deserialize;
+
+use crate::config::parse_list;
+use crate::release::version::VersionScheme;
+
+/// Declares the minimum OS versions of a release in its body, e.g.
+/// `<!-- min-os: darwin=11.0, windows=10.0.19041 -->`.
+static MARKER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)<!--\s*min-os:\s*(.*?)\s*-->").expect("valid regex"));
+
+/// A dotted OS version such as `10.0.19045` or `14.5`, compared part by
+/// part with missing parts counting as zero.
+#[derive(Debug, Clone, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct OsVersion(Vec<u64>);
+
+impl OsVersion {
+    /// Parses the leading digits of each part, so `22.04.4 LTS` reads as
+    /// `22.4.4`.
+    pub fn parse(version: &str) -> Option<Self> {
+        let parts: Vec<u64> = version
+            .trim()
+            .split('.')
+            .map_while(|part| {
+                let digits: String = part.chars().take_while(char::is_ascii_digit).collect();
+                digits.parse().ok()
+            })
+            .collect();
+        (!parts.is_empty()).then_some(OsVersion(parts))
+    }
+}
+
+impl TryFrom<String> for OsVersion {
+    type Error = String;
+
+    fn try_from(version: String) -> Result<Self, Self::Error> {
+        OsVersion::parse(&version).ok_or_else(|| format!("invalid OS version {}", version))
+    }
+}
+
+impl Ord for OsVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let length = self.0.len().max(other.0.len());
+        (0..length)
+            .map(|i| {
+                let part = |v: &OsVersion| v.0.get(i).copied().unwrap_or(0);
+                part(self).cmp(&part(other))
+            })
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for OsVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for OsVersion {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl fmt::Display for OsVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts: Vec<String> = self.0.iter().map(u64::to_string).collect();
+        write!(f, "{}", parts.join("."))
+    }
+}
+
+/// The OS version releases need on a target platform, from the product's
+/// config.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct MinOsVersion {
+    /// `windows`, `darwin` or `linux`, as in update check URLs.
+    pub target: String,
+    pub min_os: OsVersion,
+    /// Releases from this version on; every release when unset.
+    #[serde(default)]
+    pub since: Option<Version>,
+}
+
+/// Parses `target=min_os[@since]` entries such as
+/// `darwin=11.0@2.0.0,windows=10.0.19041`.
+pub fn parse_min_os_versions(
+    value: &str,
+    scheme: &dyn VersionScheme,
+) -> Result<Vec<MinOsVersion>, String> {
+    parse_list(value)
+        .into_iter()
+        .map(|entry| {
+            let (target, rest) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("expected target=version, got {}", entry))?;
+            let (min_os, since) = match rest.split_once('@') {
+                Some((min_os, since)) => {
+                    let since = scheme
+                        .parse(since.trim().trim_start_matches('v'))
+                        .ok_or_else(|| format!("invalid release version {}", since))?;
+                    (min_os, Some(since))
+                }
+                None => (rest, None),
+            };
+            Ok(MinOsVersion {
+                target: normalize_target(target),
+                min_os: OsVersion::try_from(min_os.to_string())?,
+                since,
+            })
+        })
+        .collect()
+}
+
+/// The OS version `release_body` declares for `target`, if any.
+fn declared_min_os(release_body: &str, target: &str) -> Option<OsVersion> {
+    MARKER
+        .captures_iter(release_body)
+        .flat_map(|captures| {
+            captures[1]
+                .split(',')
+                .filter_map(|entry| {
+                    let (entry_target, version) = entry.split_once('=')?;
+                    (normalize_target(entry_target) == target)
+                        .then(|| OsVersion::parse(version))
+                        .flatten()
+                })
+                .collect::<Vec<_>>()
+        })
+        .max()
+}
+
+/// The OS version a release needs on `target`: the highest of the
+/// product's config and the release body's declaration.
+pub fn required_os(
+    configured: &[MinOsVersion],
+    release_version: &Version,
+    release_body: &str,
+    target: &str,
+) -> Option<OsVersion> {
+    configured
+        .iter()
+        .filter(|min| min.target == target)
+        .filter(|min| {
+            min.since
+                .as_ref()
+                .is_none_or(|since| release_version >= since)
+        })
+        .map(|min| min.min_os.clone())
+        .chain(declared_min_os(release_body, target))
+        .max()
+}
+
+/// `macos` is accepted for `darwin`.
+fn normalize_target(target: &str) -> String {
+    match target.trim().to_lowercase().as_str() {
+        "macos" => "darwin".to_string(),
+        target => target.to_string(),
+    }
+}
+
+#[test]
+fn test_os_version_order() {
+    let v = |s: &str| OsVersion::parse(s).unwrap();
+    assert!(v("10.0.19045") > v("10.0.19041"));
+    assert!(v("14.5") > v("11"));
+    assert_eq!(v("11"), v("11.0.0"));
+    assert_eq!(v("22.04.4 LTS"), v("22.4.4"));
+    assert!(OsVersion::parse("unknown").is_none());
+}
+
+#[test]
+fn test_required_os() {
+    let configured = parse_min_os_versions(
+        "macos=10.15, darwin=11.0@2.0.0",
+        &crate::release::version::Semver,
+    )
+    .unwrap();
+    let body = "Fixes.\n<!-- min-os: darwin=12.0, windows=10.0.19041 -->";
+    let required = |version: &str, body: &str, target: &str| {
+        required_os(&configured, &Version::parse(version).unwrap(), body, target)
+            .map(|v| v.to_string())
+    };
+    assert_eq!(required("1.9.0", "", "darwin").as_deref(), Some("10.15"));
+    assert_eq!(required("2.0.0", "", "darwin").as_deref(), Some("11.0"));
+    assert_eq!(required("2.0.0", body, "darwin").as_deref(), Some("12.0"));
+    assert_eq!(
+        required("2.0.0", body, "windows").as_deref(),
+        Some("10.0.19041")
+    );
+    assert_eq!(required("2.0.0", body, "linux"), None);
+    assert!(parse_min_os_versions("darwin", &crate::release::version::Semver).is_err());
+}
@@ -0,0 +1,96 @@
+use chrono::{DateTime, Duration, Utc};
+use log::warn;
+use semver::Version;
+
+/// Returns the index of the newest candidate whose publish date is plausible.
+///
+/// `candidates` must be sorted by version, newest first. A candidate is
+/// rejected if it was published in the future (beyond `max_skew`) or before
+/// the next older version, which happens when a release is re-created with
+/// a stale date. Publish dates are instants, whatever offset the API wrote
+/// them with, so the guard doesn't depend on any time zone.
+pub fn select_fresh(
+    candidates: &[(Version, Option<DateTime<Utc>>)],
+    now: DateTime<Utc>,
+    max_skew: Duration,
+) -> Option<usize> {
+    for (index, (version, published_at)) in candidates.iter().enumerate() {
+        let Some(published_at) = published_at else {
+            return Some(index);
+        };
+
+        if *published_at > now + max_skew {
+            warn!(
+                "Release {} is published in the future ({}), skipping",
+                version, published_at
+            );
+            continue;
+        }
+
+        let predecessor = candidates[index + 1..]
+            .iter()
+            .find_map(|(older, date)| date.map(|d| (older, d)));
+        if let Some((older, older_date)) = predecessor {
+            if *published_at + max_skew < older_date {
+                warn!(
+                    "Release {} ({}) is older than release {} ({}), skipping",
+                    version, published_at, older, older_date
+                );
+                continue;
+            }
+        }
+
+        return Some(index);
+    }
+
+    None
+}
+
+#[cfg(test)]
+fn date(s: &str) -> Option<DateTime<Utc>> {
+    Some(s.parse().unwrap())
+}
+
+#[test]
+fn test_select_fresh_skips_future_release() {
+    let candidates = vec![
+        (Version::new(1, 2, 0), date("2030-01-01T00:00:00Z")),
+        (Version::new(1, 1, 0), date("2024-01-01T00:00:00Z")),
+    ];
+    let now = "2024-06-01T00:00:00Z".parse().unwrap();
+
+    assert_eq!(
+        select_fresh(&candidates, now, Duration::minutes(5)),
+        Some(1)
+    );
+}
+
+#[test]
+fn test_select_fresh_skips_stale_retagged_release() {
+    let candidates = vec![
+        (Version::new(1, 2, 0), date("2023-01-01T00:00:00Z")),
+        (Version::new(1, 1, 0), date("2024-01-01T00:00:00Z")),
+        (Version::new(1, 0, 0), date("2023-06-01T00:00:00Z")),
+    ];
+    let now = "2024-06-01T00:00:00Z".parse().unwrap();
+
+    assert_eq!(
+        select_fresh(&candidates, now, Duration::minutes(5)),
+        Some(1)
+    );
+}
+
+#[test]
+fn test_select_fresh_compares_instants() {
+    // Published at 23:30 UTC, written with the offset of a server east of it
+    let candidates = vec![
+        (Version::new(1, 2, 0), date("2024-06-01T01:30:00+02:00")),
+        (Version::new(1, 1, 0), date("2024-05-31T23:00:00Z")),
+    ];
+    let now = "2024-05-31T23:45:00Z".parse().unwrap();
+
+    assert_eq!(
+        select_fresh(&candidates, now, Duration::minutes(5)),
+        Some(0)
+    );
+}
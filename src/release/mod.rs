@@ -0,0 +1,8 @@
+pub mod compatibility;
+pub mod freshness;
+pub mod rollout;
+pub mod rules;
+pub mod schedule;
+pub mod selection;
+pub mod signing;
+pub mod version;
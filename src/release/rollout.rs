@@ -0,0 +1,40 @@
+use ring::digest::{digest, SHA256};
+
+/// Number of rollout buckets; a percentage maps to that many buckets.
+pub const BUCKETS: u32 = 100;
+
+/// Hashes a device identifier into a bucket in `0..BUCKETS`. The same device
+/// always lands in the same bucket for a given `salt` (e.g. the product), so
+/// repeated checks get the same decision.
+pub fn bucket(salt: &str, device_id: &str) -> u32 {
+    let hash = digest(&SHA256, format!("{}:{}", salt, device_id.trim()).as_bytes());
+    let mut prefix = [0u8; 8];
+    prefix.copy_from_slice(&hash.as_ref()[..8]);
+    (u64::from_be_bytes(prefix) % u64::from(BUCKETS)) as u32
+}
+
+/// Whether a device is among the first `percentage` percent of a rollout.
+/// Devices that don't identify themselves are only included at 100%.
+pub fn in_rollout(salt: &str, device_id: Option<&str>, percentage: u8) -> bool {
+    if percentage >= 100 {
+        return true;
+    }
+    device_id
+        .filter(|id| !id.trim().is_empty())
+        .is_some_and(|id| bucket(salt, id) < u32::from(percentage))
+}
+
+#[test]
+fn test_rollout_buckets() {
+    assert_eq!(bucket("myapp", "device-1"), bucket("myapp", " device-1 "));
+    assert!(bucket("myapp", "device-1") < BUCKETS);
+
+    let included = (0..1000)
+        .filter(|i| in_rollout("myapp", Some(&format!("device-{}", i)), 25))
+        .count();
+    assert!((150..350).contains(&included), "{} of 1000", included);
+
+    assert!(in_rollout("myapp", None, 100));
+    assert!(!in_rollout("myapp", None, 99));
+    assert!(!in_rollout("myapp", Some("device-1"), 0));
+}
@@ -0,0 +1,276 @@
+use std::cmp::Ordering;
+
+use serde::Deserialize;
+
+use crate::notes::locale::{best_match, normalize_tag};
+use crate::release::compatibility::OsVersion;
+use crate::release::rollout::bucket;
+
+/// Comparison operators of rule conditions, longest first so `<=` isn't
+/// read as `<`.
+const OPERATORS: [(&str, Operator); 6] = [
+    ("!=", Operator::Ne),
+    ("<=", Operator::Le),
+    (">=", Operator::Ge),
+    ("=", Operator::Eq),
+    ("<", Operator::Lt),
+    (">", Operator::Gt),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Operator {
+    fn holds(self, ordering: Ordering) -> bool {
+        match self {
+            Operator::Eq => ordering.is_eq(),
+            Operator::Ne => ordering.is_ne(),
+            Operator::Lt => ordering.is_lt(),
+            Operator::Le => ordering.is_le(),
+            Operator::Gt => ordering.is_gt(),
+            Operator::Ge => ordering.is_ge(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Condition {
+    Channel(bool, String),
+    Target(bool, String),
+    Arch(bool, String),
+    /// Matches the client's most preferred language, `de` also matching
+    /// `de-at`.
+    Locale(bool, String),
+    Os(Operator, OsVersion),
+    /// The device's rollout bucket, in `0..100`.
+    Bucket(Operator, u32),
+}
+
+/// What a matching rule serves instead of the latest release.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Decision {
+    /// Release tag to serve.
+    pub tag: Option<String>,
+    /// Installer edition to serve.
+    pub edition: Option<String>,
+    /// Offer no update at all.
+    pub hold: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Rule {
+    conditions: Vec<Condition>,
+    decision: Decision,
+}
+
+/// What rules know about an update check.
+#[derive(Debug, Clone, Copy)]
+pub struct RuleContext<'a> {
+    pub product: &'a str,
+    pub channel: &'a str,
+    pub target: &'a str,
+    pub arch: &'a str,
+    pub languages: &'a [String],
+    pub os_version: Option<&'a OsVersion>,
+    pub device_id: Option<&'a str>,
+}
+
+/// A product's targeting rules, checked in order; the first rule whose
+/// conditions all hold decides.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Rules(Vec<Rule>);
+
+impl Rules {
+    /// Parses `;`-separated rules of the form `conditions -> actions`, such
+    /// as `channel=beta & locale=de -> tag=v2.0.0-de; os<10.0.19041 ->
+    /// tag=v1.9.3; bucket<10 -> edition=preview`. Conditions on `channel`,
+    /// `target`, `arch` and `locale` take `=` or `!=`; `os` and `bucket`
+    /// take any comparison. Actions are `tag=...`, `edition=...` or `none`,
+    /// separated by `,`.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        value
+            .split(';')
+            .map(str::trim)
+            .filter(|rule| !rule.is_empty())
+            .map(parse_rule)
+            .collect::<Result<_, _>>()
+            .map(Rules)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// The decision of the first rule matching `context`, if any.
+    pub fn decide(&self, context: &RuleContext) -> Option<&Decision> {
+        self.0
+            .iter()
+            .find(|rule| rule.conditions.iter().all(|c| c.matches(context)))
+            .map(|rule| &rule.decision)
+    }
+}
+
+impl TryFrom<String> for Rules {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Rules::parse(&value)
+    }
+}
+
+impl Condition {
+    fn matches(&self, context: &RuleContext) -> bool {
+        match self {
+            Condition::Channel(equal, channel) => (context.channel == channel) == *equal,
+            Condition::Target(equal, target) => (context.target == target) == *equal,
+            Condition::Arch(equal, arch) => (context.arch == arch) == *equal,
+            Condition::Locale(equal, locale) => {
+                let preferred = &context.languages[..context.languages.len().min(1)];
+                best_match(std::slice::from_ref(locale), preferred).is_some() == *equal
+            }
+            Condition::Os(operator, version) => context
+                .os_version
+                .is_some_and(|os| operator.holds(os.cmp(version))),
+            Condition::Bucket(operator, value) => context
+                .device_id
+                .filter(|id| !id.trim().is_empty())
+                .is_some_and(|id| operator.holds(bucket(context.product, id).cmp(value))),
+        }
+    }
+}
+
+fn parse_rule(rule: &str) -> Result<Rule, String> {
+    let (conditions, actions) = rule
+        .split_once("->")
+        .ok_or_else(|| format!("expected conditions -> actions, got {}", rule))?;
+    let conditions = conditions
+        .split('&')
+        .map(str::trim)
+        .filter(|condition| !condition.is_empty())
+        .map(parse_condition)
+        .collect::<Result<_, _>>()?;
+
+    let mut decision = Decision::default();
+    for action in actions.split(',').map(str::trim) {
+        match action.split_once('=') {
+            Some(("tag", tag)) if !tag.trim().is_empty() => {
+                decision.tag = Some(tag.trim().to_string())
+            }
+            Some(("edition", edition)) if !edition.trim().is_empty() => {
+                decision.edition = Some(edition.trim().to_lowercase())
+            }
+            None if action == "none" => decision.hold = true,
+            _ => return Err(format!("unknown action {}", action)),
+        }
+    }
+    Ok(Rule {
+        conditions,
+        decision,
+    })
+}
+
+fn parse_condition(condition: &str) -> Result<Condition, String> {
+    let (attribute, operator, value) = OPERATORS
+        .iter()
+        .filter_map(|(symbol, operator)| {
+            let (attribute, value) = condition.split_once(symbol)?;
+            Some((attribute.trim(), *operator, value.trim()))
+        })
+        .min_by_key(|(attribute, _, _)| attribute.len())
+        .ok_or_else(|| format!("expected attribute, operator and value, got {}", condition))?;
+    let equality = |make: fn(bool, String) -> Condition| match operator {
+        Operator::Eq | Operator::Ne => Ok(make(operator == Operator::Eq, value.to_lowercase())),
+        _ => Err(format!("{} only supports = and !=", attribute)),
+    };
+
+    match attribute {
+        "channel" => equality(Condition::Channel),
+        "target" => equality(Condition::Target),
+        "arch" => equality(Condition::Arch),
+        "locale" => equality(|equal, locale| Condition::Locale(equal, normalize_tag(&locale))),
+        "os" => OsVersion::parse(value)
+            .map(|version| Condition::Os(operator, version))
+            .ok_or_else(|| format!("invalid OS version {}", value)),
+        "bucket" => value
+            .parse()
+            .map(|bucket| Condition::Bucket(operator, bucket))
+            .map_err(|_| format!("invalid bucket {}", value)),
+        _ => Err(format!("unknown attribute {}", attribute)),
+    }
+}
+
+#[test]
+fn test_rules() {
+    let rules = Rules::parse(
+        "channel=beta & locale=de -> tag=v2.0.0-de; \
+         target=windows & os<10.0.19041 -> tag=v1.9.3, edition=legacy; \
+         locale!=en & bucket<=100 -> none",
+    )
+    .unwrap();
+    assert_eq!(rules.len(), 3);
+
+    let languages = |tags: &[&str]| -> Vec<String> { tags.iter().map(|t| t.to_string()).collect() };
+    let german = languages(&["de-at", "en"]);
+    let english = languages(&["en-us", "de"]);
+    let old_windows = OsVersion::parse("10.0.17763").unwrap();
+    let context = RuleContext {
+        product: "myapp",
+        channel: "beta",
+        target: "windows",
+        arch: "x86_64",
+        languages: &german,
+        os_version: None,
+        device_id: None,
+    };
+    let tag = |context: &RuleContext| rules.decide(context).and_then(|d| d.tag.clone());
+
+    assert_eq!(tag(&context).as_deref(), Some("v2.0.0-de"));
+    let stable = RuleContext {
+        channel: "stable",
+        languages: &english,
+        ..context
+    };
+    assert_eq!(rules.decide(&stable), None);
+    let old = RuleContext {
+        os_version: Some(&old_windows),
+        ..stable
+    };
+    assert_eq!(
+        rules.decide(&old),
+        Some(&Decision {
+            tag: Some("v1.9.3".to_string()),
+            edition: Some("legacy".to_string()),
+            hold: false,
+        })
+    );
+    // Devices without an id never match bucket conditions
+    let french = languages(&["fr"]);
+    let held = RuleContext {
+        languages: &french,
+        ..stable
+    };
+    assert_eq!(rules.decide(&held), None);
+    let held = RuleContext {
+        device_id: Some("device-1"),
+        ..held
+    };
+    assert!(rules.decide(&held).is_some_and(|d| d.hold));
+
+    assert!(Rules::parse("channel<beta -> none").is_err());
+    assert!(Rules::parse("colour=red -> none").is_err());
+    assert!(Rules::parse("channel=beta -> upgrade").is_err());
+    assert!(Rules::parse("channel=beta").is_err());
+    assert!(Rules::parse("").unwrap().is_empty());
+}
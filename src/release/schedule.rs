@@ -0,0 +1,41 @@
+use std::sync::LazyLock;
+
+use chrono::{DateTime, Utc};
+use octocrab::models::repos::Release;
+use regex::Regex;
+
+/// Schedules a release's publication in its body, e.g.
+/// `<!-- publish-at: 2024-06-01T09:00:00Z -->`.
+static MARKER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)<!--\s*publish-at:\s*(\S+?)\s*-->").expect("valid regex"));
+
+/// When the release is scheduled to be published, if its body says so.
+/// Unparseable timestamps are ignored.
+pub fn publish_at(release_body: &str) -> Option<DateTime<Utc>> {
+    MARKER.captures_iter(release_body).find_map(|captures| {
+        DateTime::parse_from_rfc3339(&captures[1])
+            .ok()
+            .map(|at| at.with_timezone(&Utc))
+    })
+}
+
+/// Whether `release` is scheduled for a later publication than `now`, so
+/// it mustn't be served yet.
+pub fn is_scheduled(release: &Release, now: DateTime<Utc>) -> bool {
+    release
+        .body
+        .as_deref()
+        .and_then(publish_at)
+        .is_some_and(|at| at > now)
+}
+
+#[test]
+fn test_publish_at() {
+    let body = "New features.\n<!-- publish-at: 2024-06-01T11:00:00+02:00 -->\n";
+    assert_eq!(
+        publish_at(body),
+        Some("2024-06-01T09:00:00Z".parse().unwrap())
+    );
+    assert_eq!(publish_at("<!-- publish-at: tomorrow -->"), None);
+    assert_eq!(publish_at("New features."), None);
+}
@@ -0,0 +1,204 @@
+use std::ops::Deref;
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, Utc};
+use octocrab::models::repos::Release;
+use semver::Version;
+
+use crate::config::{AppState, ProductConfig};
+use crate::error::ServerError;
+use crate::github::client::GitHubClient;
+use crate::release::freshness::select_fresh;
+use crate::release::schedule::is_scheduled;
+
+/// A release within a shared (cached) release list, dereferencing to the
+/// release without cloning it.
+#[derive(Clone)]
+pub struct ReleaseRef {
+    releases: Arc<Vec<Release>>,
+    index: usize,
+}
+
+impl From<Release> for ReleaseRef {
+    fn from(release: Release) -> Self {
+        Self {
+            releases: Arc::new(vec![release]),
+            index: 0,
+        }
+    }
+}
+
+impl Deref for ReleaseRef {
+    type Target = Release;
+
+    fn deref(&self) -> &Release {
+        &self.releases[self.index]
+    }
+}
+
+/// Fetches the newest release that the product's config allows to be served.
+pub async fn latest_release(
+    state: &AppState,
+    github: &GitHubClient,
+    product: &str,
+    product_config: &ProductConfig,
+    channel: &str,
+) -> Result<ReleaseRef, ServerError> {
+    let releases = state
+        .release_cache
+        .releases(product, github, product_config)
+        .await?;
+
+    let index =
+        select_latest(&releases, product_config, channel).ok_or(ServerError::NoEligibleRelease)?;
+    Ok(ReleaseRef { releases, index })
+}
+
+/// Fetches the newest release on `channel`, including releases scheduled for
+/// a later publication and regardless of the freshness guard, for canaries.
+pub async fn newest_release(
+    state: &AppState,
+    github: &GitHubClient,
+    product: &str,
+    product_config: &ProductConfig,
+    channel: &str,
+) -> Result<ReleaseRef, ServerError> {
+    let releases = state
+        .release_cache
+        .releases(product, github, product_config)
+        .await?;
+
+    let index = channel_releases(&releases, product_config, channel, None)
+        .first()
+        .map(|(_, index)| *index)
+        .ok_or(ServerError::NoEligibleRelease)?;
+    Ok(ReleaseRef { releases, index })
+}
+
+/// Fetches the newest eligible release within the given major version, if any.
+pub async fn latest_release_in_major(
+    state: &AppState,
+    github: &GitHubClient,
+    product: &str,
+    product_config: &ProductConfig,
+    channel: &str,
+    major: u64,
+) -> Result<Option<ReleaseRef>, ServerError> {
+    let releases = state
+        .release_cache
+        .releases(product, github, product_config)
+        .await?;
+
+    let index = select_latest_matching(&releases, product_config, channel, |v| v.major == major);
+    Ok(index.map(|index| ReleaseRef { releases, index }))
+}
+
+/// Fetches the newest eligible release older than `version`, if any.
+pub async fn latest_release_before(
+    state: &AppState,
+    github: &GitHubClient,
+    product: &str,
+    product_config: &ProductConfig,
+    channel: &str,
+    version: &Version,
+) -> Result<Option<ReleaseRef>, ServerError> {
+    let releases = state
+        .release_cache
+        .releases(product, github, product_config)
+        .await?;
+
+    let index = select_latest_matching(&releases, product_config, channel, |v| v < version);
+    Ok(index.map(|index| ReleaseRef { releases, index }))
+}
+
+/// Fetches the newest eligible release that isn't a prerelease version, if any.
+pub async fn latest_stable_release(
+    state: &AppState,
+    github: &GitHubClient,
+    product: &str,
+    product_config: &ProductConfig,
+    channel: &str,
+) -> Result<Option<ReleaseRef>, ServerError> {
+    let releases = state
+        .release_cache
+        .releases(product, github, product_config)
+        .await?;
+
+    let index = select_latest_matching(&releases, product_config, channel, |v| v.pre.is_empty());
+    Ok(index.map(|index| ReleaseRef { releases, index }))
+}
+
+/// Returns the index of the newest published release with a version tag
+/// that satisfies the product's version ceiling and freshness guard.
+/// Prereleases are only eligible on channels that opted in to them.
+pub fn select_latest(
+    releases: &[Release],
+    product_config: &ProductConfig,
+    channel: &str,
+) -> Option<usize> {
+    select_latest_matching(releases, product_config, channel, |_| true)
+}
+
+/// Like [`select_latest`], additionally requiring `predicate` to accept the version.
+pub fn select_latest_matching(
+    releases: &[Release],
+    product_config: &ProductConfig,
+    channel: &str,
+    predicate: impl Fn(&Version) -> bool,
+) -> Option<usize> {
+    let mut candidates = eligible_releases(releases, product_config, channel);
+    candidates.retain(|(version, _)| predicate(version));
+
+    let position = if product_config.freshness_guard {
+        let dated: Vec<(Version, Option<DateTime<Utc>>)> = candidates
+            .iter()
+            .map(|(version, index)| (version.clone(), releases[*index].published_at))
+            .collect();
+        select_fresh(
+            &dated,
+            Utc::now(),
+            Duration::seconds(product_config.max_clock_skew_secs),
+        )?
+    } else {
+        0
+    };
+
+    candidates.get(position).map(|(_, index)| *index)
+}
+
+/// Versions and indexes of the releases that may be served on `channel`,
+/// newest first. Releases scheduled for a later publication are left out;
+/// unlike [`select_latest`], the freshness guard isn't applied.
+pub fn eligible_releases(
+    releases: &[Release],
+    product_config: &ProductConfig,
+    channel: &str,
+) -> Vec<(Version, usize)> {
+    channel_releases(releases, product_config, channel, Some(Utc::now()))
+}
+
+/// Like [`eligible_releases`], leaving out releases scheduled after
+/// `published_by` only if it's set.
+fn channel_releases(
+    releases: &[Release],
+    product_config: &ProductConfig,
+    channel: &str,
+    published_by: Option<DateTime<Utc>>,
+) -> Vec<(Version, usize)> {
+    let include_prereleases = product_config.allows_prereleases(channel);
+    let mut candidates: Vec<(Version, usize)> = releases
+        .iter()
+        .enumerate()
+        .filter(|(_, r)| !r.draft && (include_prereleases || !r.prerelease))
+        .filter(|(_, r)| published_by.is_none_or(|now| !is_scheduled(r, now)))
+        .filter_map(|(index, r)| Some((product_config.tag_version(&r.tag_name)?, index)))
+        .filter(|(version, _)| {
+            product_config
+                .max_version
+                .as_ref()
+                .is_none_or(|max| version <= max)
+        })
+        .collect();
+    candidates.sort_by(|a, b| b.0.cmp(&a.0));
+    candidates
+}
@@ -0,0 +1,45 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use dashmap::DashMap;
+
+/// Which updater keys signed each release, as seen when its signatures were
+/// served, to follow a key rotation.
+#[derive(Debug, Default)]
+pub struct SigningKeys {
+    /// Key ids by release tag, by product.
+    products: DashMap<String, BTreeMap<String, BTreeSet<String>>>,
+}
+
+impl SigningKeys {
+    pub fn record(&self, product: &str, tag: &str, key_id: &str) {
+        let mut releases = self.products.entry(product.to_string()).or_default();
+        if !releases.get(tag).is_some_and(|keys| keys.contains(key_id)) {
+            releases
+                .entry(tag.to_string())
+                .or_default()
+                .insert(key_id.to_string());
+        }
+    }
+
+    pub fn get(&self, product: &str) -> BTreeMap<String, BTreeSet<String>> {
+        self.products
+            .get(product)
+            .map(|releases| releases.clone())
+            .unwrap_or_default()
+    }
+}
+
+#[test]
+fn test_signing_keys() {
+    let keys = SigningKeys::default();
+    assert!(keys.get("myapp").is_empty());
+
+    keys.record("myapp", "v1.1.0", "OLD");
+    keys.record("myapp", "v1.2.0", "NEW");
+    keys.record("myapp", "v1.2.0", "NEW");
+    keys.record("myapp", "v1.2.0", "OLD");
+    let releases = keys.get("myapp");
+    assert_eq!(releases["v1.1.0"], BTreeSet::from(["OLD".to_string()]));
+    assert_eq!(releases["v1.2.0"].len(), 2);
+    assert!(keys.get("otherapp").is_empty());
+}
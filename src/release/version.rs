@@ -0,0 +1,143 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::sync::Arc;
+
+use semver::{BuildMetadata, Prerelease, Version};
+
+/// How a product numbers its releases. Schemes map their versions onto
+/// [`Version`]s that order the same way, so the rest of the server can
+/// compare them; implement this to plug in a custom scheme.
+pub trait VersionScheme: Send + Sync + fmt::Debug {
+    fn name(&self) -> &str;
+
+    /// Parses a version without tag prefix or leading `v`.
+    fn parse(&self, version: &str) -> Option<Version>;
+
+    /// Orders versions of this scheme while ignoring build metadata, for
+    /// products that set `IGNORE_BUILD_METADATA`.
+    fn precedence(&self, a: &Version, b: &Version) -> Ordering {
+        a.cmp_precedence(b)
+    }
+}
+
+/// Semantic versions such as `1.2.3-beta.1`.
+#[derive(Debug)]
+pub struct Semver;
+
+impl VersionScheme for Semver {
+    fn name(&self) -> &str {
+        "semver"
+    }
+
+    fn parse(&self, version: &str) -> Option<Version> {
+        Version::parse(version).ok()
+    }
+}
+
+/// Calendar versions such as `2024.06.1` or `24.6`, with an optional
+/// `-suffix` that marks a prerelease. Leading zeros are allowed.
+#[derive(Debug)]
+pub struct CalVer;
+
+impl VersionScheme for CalVer {
+    fn name(&self) -> &str {
+        "calver"
+    }
+
+    fn parse(&self, version: &str) -> Option<Version> {
+        let (numbers, pre) = match version.split_once('-') {
+            Some((numbers, pre)) => (numbers, Prerelease::new(pre).ok()?),
+            None => (version, Prerelease::EMPTY),
+        };
+        let parts = numeric_parts(numbers)?;
+        if !(2..=3).contains(&parts.len()) {
+            return None;
+        }
+
+        let mut parsed = Version::new(parts[0], parts[1], parts.get(2).copied().unwrap_or(0));
+        parsed.pre = pre;
+        Some(parsed)
+    }
+}
+
+/// Windows-style four-part versions such as `1.2.3.4`. The fourth part, the
+/// revision, is kept in the build field as a plain number, so it orders
+/// numerically, and it counts toward precedence like the other three parts.
+#[derive(Debug)]
+pub struct FourPart;
+
+impl VersionScheme for FourPart {
+    fn name(&self) -> &str {
+        "four-part"
+    }
+
+    fn parse(&self, version: &str) -> Option<Version> {
+        let parts = numeric_parts(version)?;
+        if parts.len() != 4 {
+            return None;
+        }
+
+        let mut parsed = Version::new(parts[0], parts[1], parts[2]);
+        parsed.build = BuildMetadata::new(&parts[3].to_string()).ok()?;
+        Some(parsed)
+    }
+
+    fn precedence(&self, a: &Version, b: &Version) -> Ordering {
+        a.cmp_precedence(b)
+            .then_with(|| revision(a).cmp(&revision(b)))
+    }
+}
+
+/// The revision of a four-part version; `0` for three-part ones.
+fn revision(version: &Version) -> u64 {
+    version.build.as_str().parse().unwrap_or(0)
+}
+
+fn numeric_parts(version: &str) -> Option<Vec<u64>> {
+    version
+        .split('.')
+        .map(|part| {
+            if part.is_empty() || !part.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            part.parse().ok()
+        })
+        .collect()
+}
+
+/// The built-in scheme called `name` (`semver`, `calver` or `four-part`).
+pub fn scheme_by_name(name: &str) -> Option<Arc<dyn VersionScheme>> {
+    match name.trim().to_lowercase().as_str() {
+        "semver" => Some(Arc::new(Semver)),
+        "calver" => Some(Arc::new(CalVer)),
+        "four-part" | "fourpart" | "windows" => Some(Arc::new(FourPart)),
+        _ => None,
+    }
+}
+
+#[test]
+fn test_version_schemes() {
+    assert_eq!(CalVer.parse("2024.06.1"), Some(Version::new(2024, 6, 1)));
+    assert_eq!(CalVer.parse("24.6"), Some(Version::new(24, 6, 0)));
+    assert!(CalVer.parse("2024.06.1-rc1").unwrap().pre.as_str() == "rc1");
+    assert_eq!(CalVer.parse("2024.06.1.2"), None);
+
+    let older = FourPart.parse("1.2.3.9").unwrap();
+    let newer = FourPart.parse("1.2.3.10").unwrap();
+    assert!(newer > older);
+    assert!(older > Version::new(1, 2, 3));
+    assert_eq!(FourPart.precedence(&newer, &older), Ordering::Greater);
+    assert_eq!(FourPart.precedence(&older, &newer), Ordering::Less);
+    assert_eq!(
+        FourPart.precedence(&older, &FourPart.parse("1.2.4.0").unwrap()),
+        Ordering::Less
+    );
+    assert_eq!(FourPart.parse("1.2.3"), None);
+
+    // Other schemes ignore build metadata as semver does
+    let build = Version::parse("1.2.3+10").unwrap();
+    assert_eq!(
+        Semver.precedence(&build, &Version::parse("1.2.3+9").unwrap()),
+        Ordering::Equal
+    );
+}
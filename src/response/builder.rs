@@ -0,0 +1,213 @@
+use std::collections::BTreeMap;
+
+use semver::Version;
+use serde::{Deserialize, Serialize};
+
+/// Response shape expected by the client's updater.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResponseFormat {
+    V1,
+    #[default]
+    V2,
+}
+
+/// Download location and signature of the update for a single platform.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PlatformUpdate {
+    pub signature: String,
+    pub url: String,
+    /// GitHub id of the artifact behind `url`.
+    #[serde(skip)]
+    pub asset_id: u64,
+    /// File name of the artifact behind `url`.
+    #[serde(skip)]
+    pub filename: String,
+    /// Id of the key that made `signature`.
+    #[serde(skip)]
+    pub key_id: String,
+}
+
+/// A binary patch from the client's version to the update, built by the
+/// delta worker. Clients that can't apply it download `url` instead.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PatchUpdate {
+    pub url: String,
+    pub from_version: String,
+    pub size: u64,
+    pub sha256: String,
+    /// Always `zstd-patch`: apply with `zstd -d --patch-from=<current artifact>`.
+    pub format: &'static str,
+}
+
+/// Tauri v1 dynamic response. Every field is always present.
+#[derive(Debug, Serialize)]
+pub struct V1Response {
+    version: String,
+    pub_date: String,
+    url: String,
+    signature: String,
+    notes: String,
+}
+
+/// Tauri v2 dynamic response. Optional fields are omitted when unknown.
+#[derive(Debug, Serialize)]
+pub struct V2Response {
+    version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    notes: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub_date: Option<String>,
+    url: String,
+    signature: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    patch: Option<PatchUpdate>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum DynamicResponse {
+    V1(V1Response),
+    V2(V2Response),
+}
+
+/// Static `latest.json` manifest listing every supported platform.
+#[derive(Debug, Serialize)]
+pub struct StaticManifest {
+    version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    notes: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub_date: Option<String>,
+    platforms: BTreeMap<String, PlatformUpdate>,
+}
+
+/// Collects the release-level fields shared by every response format.
+pub struct UpdateResponseBuilder {
+    version: String,
+    pub_date: Option<String>,
+    notes: Option<String>,
+    patch: Option<PatchUpdate>,
+}
+
+impl UpdateResponseBuilder {
+    pub fn new(version: &Version) -> Self {
+        Self {
+            version: version.to_string(),
+            pub_date: None,
+            notes: None,
+            patch: None,
+        }
+    }
+
+    pub fn pub_date(mut self, pub_date: Option<String>) -> Self {
+        self.pub_date = pub_date;
+        self
+    }
+
+    pub fn notes(mut self, notes: Option<String>) -> Self {
+        self.notes = notes.filter(|n| !n.is_empty());
+        self
+    }
+
+    /// Offers a patch alongside the full download; v2 responses only.
+    pub fn patch(mut self, patch: Option<PatchUpdate>) -> Self {
+        self.patch = patch;
+        self
+    }
+
+    pub fn dynamic(self, format: ResponseFormat, update: PlatformUpdate) -> DynamicResponse {
+        match format {
+            ResponseFormat::V1 => DynamicResponse::V1(V1Response {
+                version: self.version,
+                pub_date: self.pub_date.unwrap_or_default(),
+                url: update.url,
+                signature: update.signature,
+                notes: self.notes.unwrap_or_default(),
+            }),
+            ResponseFormat::V2 => DynamicResponse::V2(V2Response {
+                version: self.version,
+                notes: self.notes,
+                pub_date: self.pub_date,
+                url: update.url,
+                signature: update.signature,
+                patch: self.patch,
+            }),
+        }
+    }
+
+    pub fn static_manifest(self, platforms: BTreeMap<String, PlatformUpdate>) -> StaticManifest {
+        StaticManifest {
+            version: self.version,
+            notes: self.notes,
+            pub_date: self.pub_date,
+            platforms,
+        }
+    }
+}
+
+#[cfg(test)]
+fn golden_builder() -> UpdateResponseBuilder {
+    UpdateResponseBuilder::new(&Version::new(1, 2, 0))
+        .pub_date(Some("2024-06-01T12:00:00+00:00".to_string()))
+        .notes(Some("Bug fixes".to_string()))
+}
+
+#[cfg(test)]
+fn golden_update(platform: &str, filename: &str) -> PlatformUpdate {
+    PlatformUpdate {
+        signature: format!("sig-{}", platform),
+        url: format!("https://updates.example.com/myapp/download/1/{}", filename),
+        asset_id: 1,
+        filename: filename.to_string(),
+        key_id: String::new(),
+    }
+}
+
+#[cfg(test)]
+fn assert_golden<T: Serialize>(value: &T, golden: &str) {
+    assert_eq!(
+        serde_json::to_string_pretty(value).unwrap(),
+        golden.trim_end()
+    );
+}
+
+#[test]
+fn test_v1_dynamic_golden() {
+    let response = golden_builder().dynamic(
+        ResponseFormat::V1,
+        golden_update("windows-x86_64", "MyApp_1.2.0_x64_en-US.msi"),
+    );
+    assert_golden(
+        &response,
+        include_str!("../../tests/golden/v1_dynamic.json"),
+    );
+}
+
+#[test]
+fn test_v2_dynamic_golden() {
+    let response = golden_builder().dynamic(
+        ResponseFormat::V2,
+        golden_update("windows-x86_64", "MyApp_1.2.0_x64_en-US.msi"),
+    );
+    assert_golden(
+        &response,
+        include_str!("../../tests/golden/v2_dynamic.json"),
+    );
+}
+
+#[test]
+fn test_static_manifest_golden() {
+    let platforms = BTreeMap::from([
+        (
+            "darwin-aarch64".to_string(),
+            golden_update("darwin-aarch64", "MyApp_1.2.0_aarch64.app.tar.gz"),
+        ),
+        (
+            "windows-x86_64".to_string(),
+            golden_update("windows-x86_64", "MyApp_1.2.0_x64_en-US.msi"),
+        ),
+    ]);
+    let manifest = golden_builder().static_manifest(platforms);
+    assert_golden(&manifest, include_str!("../../tests/golden/static.json"));
+}
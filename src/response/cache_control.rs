@@ -0,0 +1,187 @@
+use std::env;
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderValue, CACHE_CONTROL, VARY};
+use actix_web::http::StatusCode;
+use actix_web::middleware::Next;
+use actix_web::{web, Error};
+
+use crate::auth::authenticator::RouteGroup;
+use crate::config::{AppState, ProductConfig};
+use crate::geo::region::COUNTRY_HEADERS;
+
+/// Lifetimes advertised for update-check responses; nothing is cacheable by
+/// default.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CachePolicy {
+    /// `max-age`, for clients and every cache.
+    pub max_age: Option<u64>,
+    /// `s-maxage`, for shared caches such as CDNs.
+    pub s_maxage: Option<u64>,
+}
+
+impl CachePolicy {
+    /// Reads `CACHE_MAX_AGE_SECS` and `CACHE_S_MAXAGE_SECS`.
+    pub fn from_env() -> Self {
+        let secs = |key: &str| env::var(key).ok().and_then(|v| v.parse().ok());
+        CachePolicy {
+            max_age: secs("CACHE_MAX_AGE_SECS"),
+            s_maxage: secs("CACHE_S_MAXAGE_SECS"),
+        }
+    }
+
+    /// The `Cache-Control` value, or `None` when nothing is cacheable.
+    /// Responses that aren't `shared` are kept out of shared caches, and
+    /// none is cached for longer than `lifetime`, if given.
+    fn header_value(&self, shared: bool, lifetime: Option<u64>) -> Option<String> {
+        let cap = |secs: u64| lifetime.map_or(secs, |lifetime| secs.min(lifetime));
+        let max_age = self.max_age.map(cap);
+        let s_maxage = self.s_maxage.filter(|_| shared).map(cap);
+        if max_age.is_none() && s_maxage.is_none() {
+            return None;
+        }
+
+        let mut value = String::from(if shared { "public" } else { "private" });
+        if let Some(max_age) = max_age {
+            value.push_str(&format!(", max-age={}", max_age));
+        }
+        if let Some(s_maxage) = s_maxage {
+            value.push_str(&format!(", s-maxage={}", s_maxage));
+        }
+        Some(value)
+    }
+}
+
+/// Adds the configured `Cache-Control` header to successful update checks,
+/// including `204 No Content`, so a CDN can absorb repeated checks.
+pub async fn cache_control(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let data = req.app_data::<web::Data<AppState>>();
+    let policy = data.map(|data| data.cache_policy).unwrap_or_default();
+    let product = data
+        .zip(req.match_info().get("product_name"))
+        .and_then(|(data, product)| data.product(&product.to_lowercase()));
+    let shared = !is_personalized(&req) && product.as_deref().is_none_or(is_shared_product);
+    // Download links expire with their token, so a cached response must
+    // leave clients at least half the token's lifetime to download
+    let lifetime = product.as_ref().and_then(|product| {
+        product
+            .download_token_secret
+            .as_ref()
+            .map(|_| product.download_token_ttl_secs / 2)
+    });
+    let has_mirrors = product.is_some_and(|product| !product.mirrors.is_empty());
+    let mut res = next.call(req).await?;
+
+    if matches!(res.status(), StatusCode::OK | StatusCode::NO_CONTENT)
+        && !res.headers().contains_key(CACHE_CONTROL)
+    {
+        // Release notes may be localized
+        res.headers_mut()
+            .append(VARY, HeaderValue::from_static("accept-language"));
+        // Releases may need a newer OS than the client's
+        res.headers_mut()
+            .append(VARY, HeaderValue::from_static("x-os-version"));
+        // The download mirror depends on the client's country
+        if has_mirrors {
+            for name in COUNTRY_HEADERS {
+                res.headers_mut()
+                    .append(VARY, HeaderValue::from_static(name));
+            }
+        }
+        if let Some(value) = policy
+            .header_value(shared, lifetime)
+            .and_then(|v| HeaderValue::from_str(&v).ok())
+        {
+            res.headers_mut().insert(CACHE_CONTROL, value);
+        }
+    }
+
+    Ok(res)
+}
+
+/// Whether the product answers every client alike. Entitlement webhooks and
+/// license checks decide per client.
+fn is_shared_product(product: &ProductConfig) -> bool {
+    product.entitlement_webhook.is_none() && product.license_validator.allows_all()
+}
+
+/// Whether the response depends on the caller's credentials or device:
+/// shared caches would otherwise hand it to other clients or bypass
+/// authentication and rollouts.
+fn is_personalized(req: &ServiceRequest) -> bool {
+    req.headers().contains_key("X-Entitlement-Token")
+        || req.headers().contains_key("X-Device-Id")
+        || req.headers().contains_key("X-Distribution-Token")
+        || req.headers().contains_key("X-Canary")
+        || req.cookie("canary").is_some()
+        || req.query_string().split('&').any(|param| {
+            param.starts_with("device_id=") || param.starts_with("distribution_token=")
+        })
+        || req
+            .app_data::<web::Data<AppState>>()
+            .is_some_and(|data| data.auth.is_protected(RouteGroup::Update))
+}
+
+#[test]
+fn test_cache_control_value() {
+    let policy = CachePolicy {
+        max_age: Some(60),
+        s_maxage: Some(300),
+    };
+    assert_eq!(
+        policy.header_value(true, None).as_deref(),
+        Some("public, max-age=60, s-maxage=300")
+    );
+    assert_eq!(
+        policy.header_value(false, None).as_deref(),
+        Some("private, max-age=60")
+    );
+
+    // Responses with expiring download links are cached for less than
+    // the links live
+    assert_eq!(
+        policy.header_value(true, Some(120)).as_deref(),
+        Some("public, max-age=60, s-maxage=120")
+    );
+    assert_eq!(
+        policy.header_value(true, Some(30)).as_deref(),
+        Some("public, max-age=30, s-maxage=30")
+    );
+
+    let shared_only = CachePolicy {
+        max_age: None,
+        s_maxage: Some(300),
+    };
+    assert_eq!(shared_only.header_value(false, None), None);
+    assert_eq!(CachePolicy::default().header_value(true, None), None);
+}
+
+#[test]
+fn test_shared_product() {
+    use std::sync::Arc;
+
+    use futures_util::future::BoxFuture;
+
+    use crate::license::validator::{LicenseError, LicenseRequest, LicenseValidator};
+
+    #[derive(Debug)]
+    struct DenyAll;
+
+    impl LicenseValidator for DenyAll {
+        fn validate<'a>(
+            &'a self,
+            _request: LicenseRequest<'a>,
+        ) -> BoxFuture<'a, Result<(), LicenseError>> {
+            Box::pin(async { Err(LicenseError::Denied("no license".to_string())) })
+        }
+    }
+
+    let mut product = ProductConfig::new("token", "acme", "myapp");
+    assert!(is_shared_product(&product));
+    product.license_validator = Arc::new(DenyAll);
+    assert!(!is_shared_product(&product));
+}
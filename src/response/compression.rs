@@ -0,0 +1,78 @@
+use std::future::{ready, Ready};
+use std::rc::Rc;
+
+use actix_web::body::{BoxBody, EitherBody, MessageBody};
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::middleware::Compress;
+use actix_web::{web, Error};
+use futures_util::future::{FutureExt, LocalBoxFuture};
+
+use crate::config::AppState;
+
+type Compressed<S> = <Compress as Transform<Rc<S>, ServiceRequest>>::Transform;
+type CompressedResponse<S> = <Compress as Transform<Rc<S>, ServiceRequest>>::Response;
+
+/// Compresses responses with the best encoding the client accepts (brotli,
+/// gzip, ...), unless the app's state turns compression off.
+pub fn compress() -> Compression {
+    Compression
+}
+
+pub struct Compression;
+
+impl<S, B> Transform<S, ServiceRequest> for Compression
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = CompressedResponse<S>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = CompressionMiddleware<S, Compressed<S>>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        let service = Rc::new(service);
+        // Compress is ready right away
+        let compress = Compress::default()
+            .new_transform(Rc::clone(&service))
+            .now_or_never()
+            .unwrap_or(Err(()));
+        ready(compress.map(|compress| CompressionMiddleware { service, compress }))
+    }
+}
+
+/// Passes requests around [`Compress`] when compression is off, so responses
+/// go out as they are. The request's headers can't be edited instead: outer
+/// middleware may still hold the request.
+pub struct CompressionMiddleware<S, T> {
+    service: Rc<S>,
+    compress: T,
+}
+
+impl<S, B> Service<ServiceRequest> for CompressionMiddleware<S, Compressed<S>>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = CompressedResponse<S>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    actix_web::dev::forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let enabled = req
+            .app_data::<web::Data<AppState>>()
+            .is_none_or(|data| data.compression);
+        if enabled {
+            return self.compress.call(req).boxed_local();
+        }
+        let res = self.service.call(req);
+        async move {
+            res.await
+                .map(|res| res.map_body(|_, body| EitherBody::right(BoxBody::new(body))))
+        }
+        .boxed_local()
+    }
+}
@@ -0,0 +1,185 @@
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::LazyLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use actix_web::body::{BoxBody, EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{
+    HeaderName, HeaderValue, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE,
+};
+use actix_web::http::StatusCode;
+use actix_web::middleware::Next;
+use actix_web::{web, Error, HttpMessage, HttpRequest};
+use serde::Serialize;
+
+use crate::auth::authenticator::AuthError;
+use crate::config::AppState;
+use crate::error::ServerError;
+use crate::handlers::query::QueryError;
+use crate::platform::matcher::MatchError;
+
+pub const REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+/// The JSON body of every error response.
+#[derive(Debug, Serialize)]
+pub struct ErrorEnvelope<'a> {
+    /// Stable, machine-readable error code, e.g. `product_not_found`.
+    pub code: Cow<'a, str>,
+    pub message: Cow<'a, str>,
+    pub request_id: Option<Cow<'a, str>>,
+}
+
+/// The code reported for `error`, falling back to one derived from the status.
+pub(crate) fn error_code(error: &Error, status: StatusCode) -> &'static str {
+    if let Some(error) = error.as_error::<ServerError>() {
+        return error.code();
+    }
+    if let Some(error) = error.as_error::<QueryError>() {
+        return match error {
+            QueryError::Missing { .. } => "missing_parameter",
+            QueryError::Invalid { .. } => "invalid_parameter",
+        };
+    }
+    if let Some(error) = error.as_error::<AuthError>() {
+        return auth_error_code(error);
+    }
+    if let Some(error) = error.as_error::<MatchError>() {
+        return match error {
+            MatchError::NoMatch { .. } => "no_matching_asset",
+            MatchError::NoSignature(_) => "no_signature",
+        };
+    }
+    status_code(status)
+}
+
+fn auth_error_code(error: &AuthError) -> &'static str {
+    match error {
+        AuthError::MissingCredentials => "missing_credentials",
+        AuthError::InvalidCredentials => "invalid_credentials",
+        AuthError::Misconfigured => "auth_misconfigured",
+        AuthError::AddressNotAllowed => "address_not_allowed",
+        AuthError::Challenged { error, .. } => auth_error_code(error),
+    }
+}
+
+fn status_code(status: StatusCode) -> &'static str {
+    match status {
+        StatusCode::BAD_REQUEST => "bad_request",
+        StatusCode::UNAUTHORIZED => "unauthorized",
+        StatusCode::FORBIDDEN => "forbidden",
+        StatusCode::NOT_FOUND => "not_found",
+        StatusCode::METHOD_NOT_ALLOWED => "method_not_allowed",
+        StatusCode::PAYLOAD_TOO_LARGE => "payload_too_large",
+        StatusCode::UNSUPPORTED_MEDIA_TYPE => "unsupported_media_type",
+        StatusCode::RANGE_NOT_SATISFIABLE => "range_not_satisfiable",
+        StatusCode::TOO_MANY_REQUESTS => "rate_limited",
+        StatusCode::BAD_GATEWAY => "upstream_error",
+        StatusCode::SERVICE_UNAVAILABLE => "unavailable",
+        StatusCode::GATEWAY_TIMEOUT => "upstream_timeout",
+        status if status.is_client_error() => "client_error",
+        _ => "internal_error",
+    }
+}
+
+static ID_SEED: LazyLock<u64> = LazyLock::new(|| {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or_default()
+});
+static ID_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// The id a request was assigned, e.g. for logging, if any.
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+/// The id assigned to the request, or else the caller's `X-Request-Id`, or
+/// else a new id unique to this process.
+pub fn request_id(req: &HttpRequest) -> String {
+    if let Some(RequestId(id)) = req.extensions().get::<RequestId>() {
+        return id.clone();
+    }
+    req.headers()
+        .get(REQUEST_ID)
+        .and_then(|v| v.to_str().ok())
+        .filter(|id| !id.is_empty() && id.len() <= 128)
+        .map(str::to_string)
+        .unwrap_or_else(|| {
+            format!(
+                "{:x}-{:x}",
+                *ID_SEED,
+                ID_COUNTER.fetch_add(1, Ordering::Relaxed)
+            )
+        })
+}
+
+/// Replaces the body of error responses with an [`ErrorEnvelope`], so
+/// clients can tell failures apart by `code` whichever layer produced them.
+pub async fn json_errors(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let http_req = req.request().clone();
+
+    let res = match next.call(req).await {
+        Ok(res) if !res.status().is_client_error() && !res.status().is_server_error() => {
+            return Ok(res.map_into_left_body());
+        }
+        Ok(res) => res.map_into_boxed_body(),
+        // Errors raised by middleware, e.g. failed authentication
+        Err(error) => ServiceResponse::from_err(error, http_req),
+    };
+
+    let (req, res) = res.into_parts();
+    let status = res.status();
+    let (code, message) = match res.error() {
+        Some(error) => (error_code(error, status), error.to_string()),
+        None => (
+            status_code(status),
+            status.canonical_reason().unwrap_or("Error").to_string(),
+        ),
+    };
+    let request_id = request_id(&req);
+    if status.is_server_error() {
+        if let Some(data) = req.app_data::<web::Data<AppState>>() {
+            data.recent_errors
+                .record(req.match_info().get("product_name"), code, &message);
+        }
+    }
+
+    let body = match serde_json::to_vec(&ErrorEnvelope {
+        code: code.into(),
+        message: message.into(),
+        request_id: Some(request_id.as_str().into()),
+    }) {
+        Ok(body) => body,
+        Err(_) => return Ok(ServiceResponse::new(req, res).map_into_right_body()),
+    };
+
+    let mut res = res.set_body(BoxBody::new(body));
+    let headers = res.headers_mut();
+    headers.remove(CONTENT_LENGTH);
+    headers.remove(CONTENT_ENCODING);
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        headers.insert(REQUEST_ID, value);
+    }
+
+    Ok(ServiceResponse::new(req, res).map_into_right_body())
+}
+
+#[test]
+fn test_error_code() {
+    let error: Error = ServerError::ProductNotFound("myapp".to_string()).into();
+    assert_eq!(
+        error_code(&error, StatusCode::NOT_FOUND),
+        "product_not_found"
+    );
+
+    let error = actix_web::error::ErrorBadGateway("GitHub is down");
+    assert_eq!(
+        error_code(&error, StatusCode::BAD_GATEWAY),
+        "upstream_error"
+    );
+}
@@ -0,0 +1,96 @@
+use actix_web::body::{BoxBody, MessageBody};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::HttpResponse;
+use chrono::{DateTime, Utc};
+use ring::hmac;
+use serde::Serialize;
+
+use crate::cache::digest::hex;
+use crate::config::ProductConfig;
+use crate::response::json::json_ok;
+
+/// Header carrying `sha256=<hex>`, the HMAC-SHA256 of the response body.
+pub const UPDATE_SIGNATURE: HeaderName = HeaderName::from_static("x-update-signature");
+
+/// The value of [`UPDATE_SIGNATURE`] for `body`, keyed with `secret`.
+pub fn body_signature(secret: &str, body: &[u8]) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    let tag = hmac::sign(&key, body);
+    format!("sha256={}", hex(tag.as_ref()))
+}
+
+/// An update response stamped with when it was issued and for how many
+/// seconds clients should accept it.
+#[derive(Serialize)]
+struct Stamped<'a, T> {
+    #[serde(flatten)]
+    body: &'a T,
+    issued_at: DateTime<Utc>,
+    max_age: u64,
+}
+
+/// Serializes `value` as a `200 OK` JSON response. With a response secret,
+/// the body is stamped with `issued_at` and `max_age` and signed, so
+/// clients can reject manifests that were altered, or captured and replayed
+/// after they expired.
+pub fn signed_json_ok<T: Serialize>(value: &T, product_config: &ProductConfig) -> HttpResponse {
+    let Some(secret) = &product_config.response_secret else {
+        return json_ok(value);
+    };
+    let stamped = Stamped {
+        body: value,
+        issued_at: Utc::now(),
+        max_age: product_config.response_max_age_secs,
+    };
+    sign_response(json_ok(&stamped), secret)
+}
+
+/// Signs the body of `res`, as serialized before any compression, with
+/// `secret`.
+pub fn sign_response(res: HttpResponse, secret: &str) -> HttpResponse {
+    let (mut res, body) = res.into_parts();
+    match body.try_into_bytes() {
+        Ok(bytes) => {
+            if let Ok(value) = HeaderValue::from_str(&body_signature(secret, &bytes)) {
+                res.headers_mut().insert(UPDATE_SIGNATURE, value);
+            }
+            res.set_body(BoxBody::new(bytes))
+        }
+        Err(body) => res.set_body(body),
+    }
+}
+
+#[test]
+fn test_body_signature() {
+    // RFC 4231, test case 2
+    assert_eq!(
+        body_signature("Jefe", b"what do ya want for nothing?"),
+        "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+    );
+
+    let res = sign_response(HttpResponse::Ok().body("{}"), "secret");
+    assert_eq!(
+        res.headers().get(UPDATE_SIGNATURE).unwrap(),
+        body_signature("secret", b"{}").as_str()
+    );
+}
+
+#[test]
+fn test_signed_json_ok() {
+    let mut product = ProductConfig::new("token", "acme", "myapp");
+    let body = serde_json::json!({"version": "1.2.0"});
+    let res = signed_json_ok(&body, &product);
+    assert!(!res.headers().contains_key(UPDATE_SIGNATURE));
+
+    product.response_secret = Some("secret".to_string());
+    product.response_max_age_secs = 600;
+    let res = signed_json_ok(&body, &product);
+    let signature = res.headers().get(UPDATE_SIGNATURE).unwrap().clone();
+    let bytes = res.into_body().try_into_bytes().unwrap();
+    assert_eq!(signature, body_signature("secret", &bytes).as_str());
+
+    let stamped: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(stamped["version"], "1.2.0");
+    assert_eq!(stamped["max_age"], 600);
+    assert!(stamped["issued_at"].is_string());
+}
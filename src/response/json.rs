@@ -0,0 +1,33 @@
+use actix_web::http::header::ContentType;
+use actix_web::HttpResponse;
+use bytes::{BufMut, BytesMut};
+use log::error;
+use serde::Serialize;
+
+/// Room reserved for a response up front; update responses fit.
+const INITIAL_CAPACITY: usize = 1024;
+
+/// Serializes `value` as a `200 OK` JSON response. The body is written once
+/// into shared bytes that the response, and a signature over it, reuse
+/// without copying.
+pub fn json_ok<T: Serialize>(value: &T) -> HttpResponse {
+    let mut writer = BytesMut::with_capacity(INITIAL_CAPACITY).writer();
+    if let Err(e) = serde_json::to_writer(&mut writer, value) {
+        error!("Failed to serialize response: {}", e);
+        return HttpResponse::InternalServerError().finish();
+    }
+
+    HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .body(writer.into_inner().freeze())
+}
+
+#[test]
+fn test_json_ok() {
+    use actix_web::body::MessageBody;
+
+    let res = json_ok(&serde_json::json!({ "version": "1.2.0" }));
+    assert_eq!(res.status(), actix_web::http::StatusCode::OK);
+    let body = res.into_body().try_into_bytes().unwrap();
+    assert_eq!(&body[..], br#"{"version":"1.2.0"}"#);
+}
@@ -0,0 +1,8 @@
+pub mod builder;
+pub mod cache_control;
+pub mod compression;
+pub mod error;
+pub mod hmac;
+pub mod json;
+pub mod slots;
+pub mod throttle;
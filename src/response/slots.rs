@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+use std::env;
+use std::sync::{Arc, Mutex};
+
+use log::error;
+
+use crate::error::ServerError;
+
+/// How long clients turned away are asked to wait before retrying.
+const RETRY_AFTER_SECS: u64 = 30;
+
+#[derive(Debug, Default)]
+struct Active {
+    total: usize,
+    products: HashMap<String, usize>,
+}
+
+/// Caps the downloads proxied at once, in total and per product, so a burst
+/// of them sheds load instead of exhausting memory and sockets.
+#[derive(Debug, Default)]
+pub struct DownloadSlots {
+    max_total: Option<usize>,
+    active: Mutex<Active>,
+}
+
+impl DownloadSlots {
+    pub fn new(max_total: Option<usize>) -> Self {
+        DownloadSlots {
+            max_total,
+            active: Mutex::default(),
+        }
+    }
+
+    /// Reads `MAX_CONCURRENT_DOWNLOADS`.
+    pub fn from_env() -> Self {
+        let max_total = env::var("MAX_CONCURRENT_DOWNLOADS").ok().and_then(|v| {
+            let max = v.parse().ok().filter(|max| *max > 0);
+            if max.is_none() {
+                error!("Ignoring invalid MAX_CONCURRENT_DOWNLOADS {:?}", v);
+            }
+            max
+        });
+        DownloadSlots::new(max_total)
+    }
+
+    /// Takes a slot for a download of `product`, which is released when the
+    /// returned guard is dropped. Fails once either limit is reached.
+    pub fn acquire(
+        self: &Arc<Self>,
+        product: &str,
+        max_product: Option<usize>,
+    ) -> Result<DownloadSlot, ServerError> {
+        let mut active = self.active.lock().unwrap_or_else(|e| e.into_inner());
+        let product_active = active.products.get(product).copied().unwrap_or_default();
+        if self.max_total.is_some_and(|max| active.total >= max)
+            || max_product.is_some_and(|max| product_active >= max)
+        {
+            return Err(ServerError::Overloaded {
+                retry_after_secs: RETRY_AFTER_SECS,
+            });
+        }
+
+        active.total += 1;
+        *active.products.entry(product.to_string()).or_default() += 1;
+        Ok(DownloadSlot {
+            slots: Arc::clone(self),
+            product: product.to_string(),
+        })
+    }
+
+    /// Number of downloads in progress.
+    pub fn active(&self) -> usize {
+        self.active.lock().unwrap_or_else(|e| e.into_inner()).total
+    }
+}
+
+/// A download in progress; frees its slot when dropped.
+#[derive(Debug)]
+pub struct DownloadSlot {
+    slots: Arc<DownloadSlots>,
+    product: String,
+}
+
+impl Drop for DownloadSlot {
+    fn drop(&mut self) {
+        let mut active = self.slots.active.lock().unwrap_or_else(|e| e.into_inner());
+        active.total -= 1;
+        if let Some(count) = active.products.get_mut(&self.product) {
+            *count -= 1;
+            if *count == 0 {
+                active.products.remove(&self.product);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_download_slots() {
+    let slots = Arc::new(DownloadSlots::new(Some(3)));
+    let first = slots.acquire("myapp", Some(2)).unwrap();
+    let _second = slots.acquire("myapp", Some(2)).unwrap();
+    assert!(matches!(
+        slots.acquire("myapp", Some(2)),
+        Err(ServerError::Overloaded { .. })
+    ));
+
+    let _other = slots.acquire("other", None).unwrap();
+    assert!(slots.acquire("third", None).is_err());
+    assert_eq!(slots.active(), 3);
+
+    drop(first);
+    assert_eq!(slots.active(), 2);
+    assert!(slots.acquire("myapp", Some(2)).is_ok());
+}
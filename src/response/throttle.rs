@@ -0,0 +1,181 @@
+use std::env;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+use actix_web::rt::time::sleep;
+use bytes::Bytes;
+use dashmap::DashMap;
+use futures_util::{Stream, StreamExt};
+use log::error;
+
+use crate::config::parse_size;
+
+/// Limits a byte rate, letting up to a second's worth of bytes through at
+/// once. A chunk larger than what's available is let through and the wait
+/// is paid by the next one.
+#[derive(Debug)]
+pub struct TokenBucket {
+    rate: u64,
+    /// Available bytes (negative when in debt) and when they were counted.
+    state: Mutex<(f64, Instant)>,
+}
+
+impl TokenBucket {
+    /// A bucket for `rate` bytes per second.
+    pub fn new(rate: u64) -> Self {
+        TokenBucket {
+            rate,
+            state: Mutex::new((rate as f64, Instant::now())),
+        }
+    }
+
+    /// Takes `bytes` from the bucket and returns how long to wait before
+    /// sending them.
+    fn take(&self, bytes: usize) -> Duration {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let (available, counted_at) = &mut *state;
+        let now = Instant::now();
+        let rate = self.rate as f64;
+        *available = (*available + now.duration_since(*counted_at).as_secs_f64() * rate).min(rate);
+        *counted_at = now;
+        *available -= bytes as f64;
+
+        if *available >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-*available / rate)
+        }
+    }
+
+    pub async fn acquire(&self, bytes: usize) {
+        let wait = self.take(bytes);
+        if !wait.is_zero() {
+            sleep(wait).await;
+        }
+    }
+}
+
+/// Bandwidth limits of proxied downloads: a rate per client connection and
+/// a cap on the server's total download egress, so a release day doesn't
+/// starve the update checks.
+///
+/// Downloads sharing a keep-alive or HTTP/2 connection share its rate.
+/// Connections are told apart by their peer address, so behind a reverse
+/// proxy the limit applies to the proxy's connections.
+#[derive(Debug, Default, Clone)]
+pub struct Throttle {
+    per_connection: Option<u64>,
+    egress: Option<Arc<TokenBucket>>,
+    /// Buckets of connections with a download in flight.
+    connections: Arc<DashMap<SocketAddr, Weak<TokenBucket>>>,
+}
+
+impl Throttle {
+    /// `per_connection` and `egress` are in bytes per second.
+    pub fn new(per_connection: Option<u64>, egress: Option<u64>) -> Self {
+        Throttle {
+            per_connection,
+            egress: egress.map(|rate| Arc::new(TokenBucket::new(rate))),
+            connections: Arc::default(),
+        }
+    }
+
+    /// Reads `DOWNLOAD_RATE_LIMIT` and `EGRESS_RATE_LIMIT`, in bytes per
+    /// second with an optional `K`, `M` or `G` suffix.
+    pub fn from_env() -> Self {
+        let rate = |key: &str| {
+            let value = env::var(key).ok()?;
+            let rate = parse_size(&value);
+            if rate.is_none() {
+                error!("Ignoring invalid {} {:?}", key, value);
+            }
+            rate
+        };
+        Throttle::new(rate("DOWNLOAD_RATE_LIMIT"), rate("EGRESS_RATE_LIMIT"))
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.per_connection.is_some() || self.egress.is_some()
+    }
+
+    /// The bucket of the connection from `peer`, shared with the downloads
+    /// in flight on it. Without a peer address the download gets its own.
+    fn connection_bucket(&self, peer: Option<SocketAddr>) -> Option<Arc<TokenBucket>> {
+        let rate = self.per_connection?;
+        let Some(peer) = peer else {
+            return Some(Arc::new(TokenBucket::new(rate)));
+        };
+
+        let mut entry = self.connections.entry(peer).or_default();
+        if let Some(bucket) = entry.upgrade() {
+            return Some(bucket);
+        }
+        let bucket = Arc::new(TokenBucket::new(rate));
+        *entry = Arc::downgrade(&bucket);
+        drop(entry);
+        // Forget connections whose downloads are done
+        self.connections
+            .retain(|_, bucket| bucket.strong_count() > 0);
+        Some(bucket)
+    }
+
+    /// Paces `stream`, sent over the connection from `peer`, to the
+    /// configured limits.
+    pub fn wrap<S, E>(
+        &self,
+        peer: Option<SocketAddr>,
+        stream: S,
+    ) -> impl Stream<Item = Result<Bytes, E>> + 'static
+    where
+        S: Stream<Item = Result<Bytes, E>> + 'static,
+        E: 'static,
+    {
+        let connection = self.connection_bucket(peer);
+        let egress = self.egress.clone();
+        stream.then(move |chunk| {
+            let (connection, egress) = (connection.clone(), egress.clone());
+            async move {
+                if let Ok(bytes) = &chunk {
+                    for bucket in [connection, egress].into_iter().flatten() {
+                        bucket.acquire(bytes.len()).await;
+                    }
+                }
+                chunk
+            }
+        })
+    }
+}
+
+#[test]
+fn test_token_bucket() {
+    let bucket = TokenBucket::new(1000);
+    assert_eq!(bucket.take(600), Duration::ZERO);
+    assert_eq!(bucket.take(400), Duration::ZERO);
+    let wait = bucket.take(500);
+    assert!(wait > Duration::from_millis(450) && wait <= Duration::from_millis(500));
+}
+
+#[test]
+fn test_connection_buckets() {
+    let throttle = Throttle::new(Some(1000), None);
+    let peer: SocketAddr = "192.0.2.1:50000".parse().unwrap();
+    let first = throttle.connection_bucket(Some(peer)).unwrap();
+    let second = throttle.connection_bucket(Some(peer)).unwrap();
+    assert!(Arc::ptr_eq(&first, &second));
+
+    let other = throttle
+        .connection_bucket(Some("192.0.2.1:50001".parse().unwrap()))
+        .unwrap();
+    assert!(!Arc::ptr_eq(&first, &other));
+    assert!(throttle.connection_bucket(None).is_some());
+
+    // The connection's bucket goes with its last download
+    drop((first, second, other));
+    throttle.connection_bucket(Some(peer));
+    assert_eq!(throttle.connections.len(), 1);
+
+    assert!(Throttle::new(None, Some(1000))
+        .connection_bucket(Some(peer))
+        .is_none());
+}
@@ -0,0 +1,697 @@
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::http::KeepAlive;
+use actix_web::middleware::from_fn;
+use actix_web::{web, App, HttpServer};
+use log::{info, warn};
+#[cfg(unix)]
+use socket2::{Domain, Socket, Type};
+
+use crate::build_info::BuildInfo;
+use crate::cache::gc;
+use crate::cdn::sync;
+use crate::config::{parse_flag, AppState, ConfigError, ProductStatus};
+use crate::cors;
+use crate::delta::worker;
+use crate::handlers::{
+    admin::{
+        admin_dashboard, get_audit, get_events, get_experiments, get_log_levels, get_maintenance,
+        get_paused, get_quota, set_halted, set_log_levels, set_maintenance, set_paused,
+        start_experiment, stop_experiment,
+    },
+    appcast::appcast,
+    apt::{apt_in_release, apt_key, apt_packages, apt_pool, apt_release, apt_release_signature},
+    batch::batch_check,
+    delta::delta_patch,
+    download::download_asset,
+    electron::electron_feed,
+    events::report_event,
+    feedback::submit_feedback,
+    homebrew::homebrew_cask,
+    manifest::latest_manifest,
+    nuget::{nuget_metadata, nuget_package, nuget_packages, nuget_service},
+    pubkey::updater_pubkey,
+    scoop::{scoop_download, scoop_manifest},
+    status::{build_version, server_status},
+    update::check_update,
+    winget::winget_manifest,
+    yum::{yum_package, yum_primary, yum_repomd},
+};
+use crate::local;
+use crate::logging::{self, log_context};
+use crate::systemd;
+
+/// The update server's background jobs: building delta patches, copying
+/// assets to the CDN bucket and the local store, and collecting cache
+/// garbage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BackgroundJobs {
+    /// Whether [`configure`] starts the jobs; turn this off to run them
+    /// yourself, e.g. in tests.
+    pub enabled: bool,
+    pub delta_interval: Duration,
+    pub cdn_sync_interval: Duration,
+    pub asset_sync_interval: Duration,
+    pub cache_gc_interval: Duration,
+}
+
+impl Default for BackgroundJobs {
+    fn default() -> Self {
+        BackgroundJobs {
+            enabled: true,
+            delta_interval: worker::DEFAULT_INTERVAL,
+            cdn_sync_interval: sync::DEFAULT_INTERVAL,
+            asset_sync_interval: local::sync::DEFAULT_INTERVAL,
+            cache_gc_interval: gc::DEFAULT_INTERVAL,
+        }
+    }
+}
+
+/// Settings of a standalone update server.
+pub struct ServerConfig {
+    bind_address: String,
+    state: AppState,
+    workers: Option<usize>,
+    keep_alive: Option<Duration>,
+    client_request_timeout: Option<Duration>,
+    max_connections: Option<usize>,
+    http2: bool,
+    tls: Option<TlsFiles>,
+    reuse_port: bool,
+    shutdown_timeout: Option<Duration>,
+}
+
+/// PEM files of the certificate chain and private key to serve HTTPS with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TlsFiles {
+    cert: PathBuf,
+    key: PathBuf,
+}
+
+impl ServerConfig {
+    /// Serves `state` on `0.0.0.0:8080` unless [`bind`](Self::bind) says otherwise.
+    pub fn new(state: AppState) -> Self {
+        ServerConfig {
+            bind_address: "0.0.0.0:8080".to_string(),
+            state,
+            workers: None,
+            keep_alive: None,
+            client_request_timeout: None,
+            max_connections: None,
+            http2: false,
+            tls: None,
+            reuse_port: false,
+            shutdown_timeout: None,
+        }
+    }
+
+    /// Reads `ADDRESS`, `PORT`, `DELTA_INTERVAL_SECS`, `CDN_SYNC_INTERVAL_SECS`,
+    /// `ASSET_SYNC_INTERVAL_SECS`, `CACHE_GC_INTERVAL_SECS`, the
+    /// [tuning](Self::tune_from_env) settings and the products from the
+    /// environment.
+    pub async fn from_env() -> Result<Self, ConfigError> {
+        let address = std::env::var("ADDRESS").unwrap_or_else(|_| "0.0.0.0".to_string());
+        let port = std::env::var("PORT").unwrap_or_else(|_| "8080".to_string());
+
+        let mut config =
+            ServerConfig::new(AppState::load_config().await?).bind(format!("{}:{}", address, port));
+        if let Some(secs) = std::env::var("DELTA_INTERVAL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+        {
+            config = config.delta_interval(Duration::from_secs(secs));
+        }
+        if let Some(secs) = std::env::var("CDN_SYNC_INTERVAL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+        {
+            config = config.cdn_sync_interval(Duration::from_secs(secs));
+        }
+        if let Some(secs) = std::env::var("ASSET_SYNC_INTERVAL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+        {
+            config = config.asset_sync_interval(Duration::from_secs(secs));
+        }
+        if let Some(secs) = std::env::var("CACHE_GC_INTERVAL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+        {
+            config = config.cache_gc_interval(Duration::from_secs(secs));
+        }
+        Ok(config.tune_from_env())
+    }
+
+    /// Applies `WORKERS`, `KEEP_ALIVE_SECS`, `CLIENT_REQUEST_TIMEOUT_SECS`,
+    /// `MAX_CONNECTIONS`, `HTTP2`, `TLS_CERT`/`TLS_KEY`, `REUSE_PORT` and
+    /// `SHUTDOWN_TIMEOUT_SECS`; unset or invalid values keep actix-web's
+    /// defaults.
+    pub fn tune_from_env(self) -> Self {
+        self.tune(|name| std::env::var(name).ok())
+    }
+
+    /// Applies the tuning settings that `setting` looks up by name.
+    fn tune(mut self, setting: impl Fn(&str) -> Option<String>) -> Self {
+        let number = |name: &str| {
+            let value = setting(name)?;
+            let number = value.trim().parse::<u64>().ok();
+            if number.is_none() {
+                warn!("Ignoring invalid {} {:?}", name, value);
+            }
+            number
+        };
+        if let Some(workers) = number("WORKERS").filter(|n| *n > 0) {
+            self.workers = Some(workers as usize);
+        }
+        if let Some(secs) = number("KEEP_ALIVE_SECS") {
+            self.keep_alive = Some(Duration::from_secs(secs));
+        }
+        if let Some(secs) = number("CLIENT_REQUEST_TIMEOUT_SECS") {
+            self.client_request_timeout = Some(Duration::from_secs(secs));
+        }
+        if let Some(max) = number("MAX_CONNECTIONS").filter(|n| *n > 0) {
+            self.max_connections = Some(max as usize);
+        }
+        if let Some(value) = setting("HTTP2") {
+            self.http2 = parse_flag(&value);
+        }
+        match (setting("TLS_CERT"), setting("TLS_KEY")) {
+            (Some(cert), Some(key)) => self = self.tls(cert, key),
+            (None, None) => {}
+            _ => warn!("Ignoring TLS_CERT/TLS_KEY: both must be set"),
+        }
+        if let Some(value) = setting("REUSE_PORT") {
+            self.reuse_port = parse_flag(&value);
+        }
+        if let Some(secs) = number("SHUTDOWN_TIMEOUT_SECS") {
+            self.shutdown_timeout = Some(Duration::from_secs(secs));
+        }
+        self
+    }
+
+    /// Socket address to listen on, e.g. `127.0.0.1:8080`.
+    pub fn bind(mut self, address: impl Into<String>) -> Self {
+        self.bind_address = address.into();
+        self
+    }
+
+    /// How often delta patches are built for new releases of products with
+    /// delta updates.
+    pub fn delta_interval(mut self, interval: Duration) -> Self {
+        self.state.jobs.delta_interval = interval;
+        self
+    }
+
+    /// How often new release assets are replicated to the CDN bucket.
+    pub fn cdn_sync_interval(mut self, interval: Duration) -> Self {
+        self.state.jobs.cdn_sync_interval = interval;
+        self
+    }
+
+    /// How often new release assets are mirrored to the local store.
+    pub fn asset_sync_interval(mut self, interval: Duration) -> Self {
+        self.state.jobs.asset_sync_interval = interval;
+        self
+    }
+
+    /// How often expired cache entries and superseded stored assets are
+    /// dropped.
+    pub fn cache_gc_interval(mut self, interval: Duration) -> Self {
+        self.state.jobs.cache_gc_interval = interval;
+        self
+    }
+
+    /// Number of worker threads; one per CPU core by default.
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.workers = Some(workers);
+        self
+    }
+
+    /// How long idle keep-alive connections are kept open; zero disables
+    /// keep-alive.
+    pub fn keep_alive(mut self, keep_alive: Duration) -> Self {
+        self.keep_alive = Some(keep_alive);
+        self
+    }
+
+    /// How long a client may take to send its request headers; zero disables
+    /// the limit.
+    pub fn client_request_timeout(mut self, timeout: Duration) -> Self {
+        self.client_request_timeout = Some(timeout);
+        self
+    }
+
+    /// Maximum connections each worker accepts at once.
+    pub fn max_connections(mut self, max: usize) -> Self {
+        self.max_connections = Some(max);
+        self
+    }
+
+    /// Also accept HTTP/2 without TLS (h2c with prior knowledge) on the same
+    /// port, e.g. from a TLS-terminating proxy that speaks HTTP/2 upstream.
+    /// Over [TLS](Self::tls), HTTP/2 is always offered.
+    pub fn http2(mut self, enabled: bool) -> Self {
+        self.http2 = enabled;
+        self
+    }
+
+    /// Serve HTTPS with the certificate chain and private key in these PEM
+    /// files; clients pick HTTP/2 or HTTP/1.1 through ALPN.
+    pub fn tls(mut self, cert: impl Into<PathBuf>, key: impl Into<PathBuf>) -> Self {
+        self.tls = Some(TlsFiles {
+            cert: cert.into(),
+            key: key.into(),
+        });
+        self
+    }
+
+    /// Bind with `SO_REUSEPORT`, so a new instance can listen on the same
+    /// port while the old one drains during a deploy.
+    pub fn reuse_port(mut self, enabled: bool) -> Self {
+        self.reuse_port = enabled;
+        self
+    }
+
+    /// How long a stopping server lets requests in flight finish; 30 seconds
+    /// by default.
+    pub fn shutdown_timeout(mut self, timeout: Duration) -> Self {
+        self.shutdown_timeout = Some(timeout);
+        self
+    }
+}
+
+/// Registers the update server's routes and state, e.g. on an existing
+/// application with `App::new().configure(configure(state))`, and starts
+/// its [background jobs](start_background_jobs).
+pub fn configure(state: AppState) -> impl FnOnce(&mut web::ServiceConfig) {
+    move |cfg| {
+        start_background_jobs(&state);
+        cors::policy::configure(cfg, &state);
+        cfg.app_data(web::Data::new(state))
+            .service(latest_manifest)
+            .service(appcast)
+            .service(electron_feed)
+            .service(winget_manifest)
+            .service(homebrew_cask)
+            .service(scoop_manifest)
+            .service(scoop_download)
+            .service(nuget_service)
+            .service(nuget_metadata)
+            .service(nuget_packages)
+            .service(nuget_package)
+            .service(apt_release)
+            .service(apt_in_release)
+            .service(apt_release_signature)
+            .service(apt_packages)
+            .service(apt_key)
+            .service(apt_pool)
+            .service(yum_repomd)
+            .service(yum_primary)
+            .service(yum_package)
+            .service(updater_pubkey)
+            .service(check_update)
+            .service(batch_check)
+            .service(download_asset)
+            .service(delta_patch)
+            .service(submit_feedback)
+            .service(report_event)
+            .service(get_maintenance)
+            .service(set_maintenance)
+            .service(get_paused)
+            .service(set_paused)
+            .service(get_experiments)
+            .service(start_experiment)
+            .service(stop_experiment)
+            .service(get_events)
+            .service(set_halted)
+            .service(get_audit)
+            .service(get_log_levels)
+            .service(set_log_levels)
+            .service(get_quota)
+            .service(admin_dashboard)
+            .service(server_status)
+            .service(build_version);
+    }
+}
+
+/// Starts the enabled [`BackgroundJobs`] of `state` on the current runtime,
+/// unless they are already running.
+pub fn start_background_jobs(state: &AppState) {
+    let jobs = state.jobs;
+    if !jobs.enabled || state.jobs_started.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    actix_web::rt::spawn(worker::run(state.clone(), jobs.delta_interval));
+    if state.cdn.is_some() {
+        actix_web::rt::spawn(sync::run(state.clone(), jobs.cdn_sync_interval));
+    }
+    if state.local_store.is_some() {
+        actix_web::rt::spawn(local::sync::run(state.clone(), jobs.asset_sync_interval));
+    }
+    actix_web::rt::spawn(gc::run(state.clone(), jobs.cache_gc_interval));
+}
+
+/// Runs a standalone update server until it is shut down.
+pub async fn run_server(config: ServerConfig) -> io::Result<()> {
+    let ServerConfig {
+        bind_address,
+        state,
+        workers,
+        keep_alive,
+        client_request_timeout,
+        max_connections,
+        http2,
+        tls,
+        reuse_port,
+        shutdown_timeout,
+    } = config;
+    let tls = tls.as_ref().map(tls_config).transpose()?;
+
+    let build = BuildInfo::current();
+    info!(
+        "Starting tauri-update-server {} ({})",
+        build.version,
+        build.short_commit()
+    );
+    for (product, status) in state.product_status.iter() {
+        match status {
+            ProductStatus::Loaded => info!("Product {}: loaded", product),
+            ProductStatus::Skipped { reason } => {
+                warn!("Product {}: skipped ({})", product, reason)
+            }
+        }
+    }
+
+    start_background_jobs(&state);
+
+    // Under systemd, dependent units start once releases can be served
+    if systemd::expects_notify() {
+        warm_release_caches(&state).await;
+    }
+
+    logging::set_product_levels(&state.products.load());
+    let mut server = HttpServer::new(move || {
+        App::new()
+            .wrap(from_fn(log_context))
+            .configure(configure(state.clone()))
+    });
+    if let Some(workers) = workers {
+        server = server.workers(workers);
+    }
+    if let Some(keep_alive) = keep_alive {
+        server = server.keep_alive(match keep_alive {
+            Duration::ZERO => KeepAlive::Disabled,
+            keep_alive => KeepAlive::Timeout(keep_alive),
+        });
+    }
+    if let Some(timeout) = client_request_timeout {
+        server = server.client_request_timeout(timeout);
+    }
+    if let Some(max) = max_connections {
+        server = server.max_connections(max);
+    }
+    if let Some(timeout) = shutdown_timeout {
+        server = server.shutdown_timeout(timeout.as_secs());
+    }
+
+    let mut listeners = systemd::listeners();
+    if listeners.is_empty() && reuse_port {
+        info!("Binding {} with SO_REUSEPORT", &bind_address);
+        listeners = reuse_port_listeners(&bind_address)?;
+    }
+    if listeners.is_empty() {
+        info!(
+            "Starting the multi-product update server on {}",
+            &bind_address
+        );
+        server = match &tls {
+            Some(tls) => server.bind_rustls_0_23(&bind_address, tls.clone())?,
+            None if http2 => server.bind_auto_h2c(&bind_address)?,
+            None => server.bind(&bind_address)?,
+        };
+    } else {
+        info!(
+            "Starting the multi-product update server on {} socket(s)",
+            listeners.len()
+        );
+        for listener in listeners {
+            server = match &tls {
+                Some(tls) => server.listen_rustls_0_23(listener, tls.clone())?,
+                None if http2 => server.listen_auto_h2c(listener)?,
+                None => server.listen(listener)?,
+            };
+        }
+    }
+
+    let server = server.run();
+    systemd::notify("READY=1");
+    let result = server.await;
+    systemd::notify("STOPPING=1");
+    result
+}
+
+/// Loads the certificate chain and key of `files`. actix-web offers `h2`
+/// and `http/1.1` through ALPN itself.
+fn tls_config(files: &TlsFiles) -> io::Result<rustls::ServerConfig> {
+    let invalid = |e: String| io::Error::new(io::ErrorKind::InvalidData, e);
+    let certs = rustls_pemfile::certs(&mut BufReader::new(File::open(&files.cert)?))
+        .collect::<Result<Vec<_>, _>>()?;
+    if certs.is_empty() {
+        return Err(invalid(format!(
+            "no certificate in {}",
+            files.cert.display()
+        )));
+    }
+    let key = rustls_pemfile::private_key(&mut BufReader::new(File::open(&files.key)?))?
+        .ok_or_else(|| invalid(format!("no private key in {}", files.key.display())))?;
+    rustls::ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(|e| invalid(e.to_string()))?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|e| invalid(format!("invalid TLS_CERT/TLS_KEY: {}", e)))
+}
+
+/// Listening sockets for every address of `address` that other processes
+/// can bind too.
+#[cfg(unix)]
+fn reuse_port_listeners(address: &str) -> io::Result<Vec<TcpListener>> {
+    use std::net::ToSocketAddrs;
+
+    address
+        .to_socket_addrs()?
+        .map(|address| {
+            let socket = Socket::new(Domain::for_address(address), Type::STREAM, None)?;
+            socket.set_reuse_address(true)?;
+            socket.set_reuse_port(true)?;
+            socket.set_nonblocking(true)?;
+            socket.bind(&address.into())?;
+            socket.listen(1024)?;
+            Ok(socket.into())
+        })
+        .collect()
+}
+
+#[cfg(not(unix))]
+fn reuse_port_listeners(_address: &str) -> io::Result<Vec<TcpListener>> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "SO_REUSEPORT is not supported on this platform",
+    ))
+}
+
+/// Fetches every product's releases, so the first update checks don't wait
+/// for GitHub.
+async fn warm_release_caches(state: &AppState) {
+    for (product_name, product_config) in state.products.load().iter() {
+        let warmed = match state.github_client(product_name, product_config) {
+            Ok(github) => state
+                .release_cache
+                .releases(product_name, &github, product_config)
+                .await
+                .map(|_| ()),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = warmed {
+            warn!(
+                "Failed to warm the release cache of {}: {}",
+                product_name, e
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+fn test_config() -> ServerConfig {
+    let state = AppState::builder()
+        .jobs(BackgroundJobs {
+            enabled: false,
+            ..BackgroundJobs::default()
+        })
+        .build();
+    ServerConfig::new(state)
+}
+
+#[test]
+fn test_tune() {
+    let settings: std::collections::HashMap<&str, &str> = [
+        ("WORKERS", "4"),
+        ("KEEP_ALIVE_SECS", "0"),
+        ("MAX_CONNECTIONS", "0"),
+        ("HTTP2", "on"),
+        ("TLS_CERT", "/etc/tls/cert.pem"),
+        ("REUSE_PORT", "yes"),
+        ("SHUTDOWN_TIMEOUT_SECS", "soon"),
+    ]
+    .into();
+    let config = test_config().tune(|name| settings.get(name).map(|v| v.to_string()));
+    assert_eq!(config.workers, Some(4));
+    assert_eq!(config.keep_alive, Some(Duration::ZERO));
+    assert_eq!(config.client_request_timeout, None);
+    // Zero connections and invalid numbers keep the defaults
+    assert_eq!(config.max_connections, None);
+    assert_eq!(config.shutdown_timeout, None);
+    assert!(config.http2);
+    // A certificate without its key is ignored
+    assert_eq!(config.tls, None);
+    assert!(config.reuse_port);
+
+    let config = config.tune(|name| (name == "HTTP2").then(|| "off".to_string()));
+    assert!(!config.http2);
+    assert_eq!(config.workers, Some(4));
+}
+
+#[test]
+fn test_builder() {
+    let config = test_config()
+        .bind("127.0.0.1:9000")
+        .workers(2)
+        .keep_alive(Duration::from_secs(30))
+        .max_connections(100)
+        .http2(true)
+        .tls("cert.pem", "key.pem")
+        .reuse_port(true);
+    assert_eq!(config.bind_address, "127.0.0.1:9000");
+    assert_eq!(config.workers, Some(2));
+    assert_eq!(config.keep_alive, Some(Duration::from_secs(30)));
+    assert_eq!(config.max_connections, Some(100));
+    assert!(config.http2);
+    assert_eq!(
+        config.tls,
+        Some(TlsFiles {
+            cert: "cert.pem".into(),
+            key: "key.pem".into(),
+        })
+    );
+    assert!(config.reuse_port);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_reuse_port_listeners() {
+    let first = reuse_port_listeners("127.0.0.1:0").unwrap();
+    assert_eq!(first.len(), 1);
+    let address = first[0].local_addr().unwrap();
+    // A second process, e.g. the next release, can bind the same port
+    let second = reuse_port_listeners(&address.to_string()).unwrap();
+    assert_eq!(second[0].local_addr().unwrap(), address);
+    assert!(TcpListener::bind(address).is_err());
+}
+
+/// Runs a server with `configure`d settings on a free local port and
+/// connects to it.
+#[cfg(test)]
+fn connect_test_server(
+    configure: impl FnOnce(ServerConfig) -> ServerConfig,
+) -> std::net::TcpStream {
+    let address = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let config = configure(test_config().bind(address.to_string()).workers(1));
+    std::thread::spawn(move || actix_web::rt::System::new().block_on(run_server(config)));
+
+    (0..50)
+        .find_map(|_| {
+            std::net::TcpStream::connect(address).ok().or_else(|| {
+                std::thread::sleep(Duration::from_millis(100));
+                None
+            })
+        })
+        .unwrap()
+}
+
+#[cfg(unix)]
+#[test]
+fn test_http2_with_reuse_port() {
+    use std::io::{Read, Write};
+
+    let mut stream = connect_test_server(|config| config.http2(true).reuse_port(true));
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    // Connection preface of a client with prior knowledge, then empty SETTINGS
+    stream
+        .write_all(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n\0\0\0\x04\0\0\0\0\0")
+        .unwrap();
+    let mut frame = [0; 9];
+    stream.read_exact(&mut frame).unwrap();
+    // The server answers with a SETTINGS frame of its own
+    assert_eq!(frame[3], 0x04);
+}
+
+#[test]
+fn test_tls_alpn() {
+    use std::io::{Read, Write};
+
+    let tls = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/tls");
+    let mut roots = rustls::RootCertStore::empty();
+    let ca = File::open(format!("{}/ca.pem", tls)).unwrap();
+    for cert in rustls_pemfile::certs(&mut BufReader::new(ca)) {
+        roots.add(cert.unwrap()).unwrap();
+    }
+    let handshake = |alpn: &[&[u8]]| {
+        let mut config = rustls::ClientConfig::builder_with_provider(Arc::new(
+            rustls::crypto::ring::default_provider(),
+        ))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(roots.clone())
+        .with_no_client_auth();
+        config.alpn_protocols = alpn.iter().map(|p| p.to_vec()).collect();
+        let connection =
+            rustls::ClientConnection::new(Arc::new(config), "localhost".try_into().unwrap())
+                .unwrap();
+        let stream = connect_test_server(|config| {
+            config.tls(format!("{}/cert.pem", tls), format!("{}/key.pem", tls))
+        });
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let mut stream = rustls::StreamOwned::new(connection, stream);
+        while stream.conn.is_handshaking() {
+            stream.conn.complete_io(&mut stream.sock).unwrap();
+        }
+        stream
+    };
+
+    // HTTP/2 clients get HTTP/2
+    let stream = handshake(&[b"h2", b"http/1.1"]);
+    assert_eq!(stream.conn.alpn_protocol(), Some(&b"h2"[..]));
+
+    // and HTTP/1.1 clients are still served
+    let mut stream = handshake(&[b"http/1.1"]);
+    assert_eq!(stream.conn.alpn_protocol(), Some(&b"http/1.1"[..]));
+    stream
+        .write_all(b"GET /version HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+        .unwrap();
+    let mut response = String::new();
+    let _ = stream.read_to_string(&mut response);
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+}
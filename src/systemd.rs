@@ -0,0 +1,99 @@
+use std::env;
+use std::net::TcpListener;
+
+use log::{debug, warn};
+
+/// First file descriptor passed by socket activation (`SD_LISTEN_FDS_START`).
+const LISTEN_FDS_START: i32 = 3;
+
+/// The descriptors passed to this process by systemd, from `LISTEN_PID` and
+/// `LISTEN_FDS`; empty when they're meant for another process.
+fn passed_fds(listen_pid: Option<&str>, listen_fds: Option<&str>, pid: u32) -> Vec<i32> {
+    let (Some(listen_pid), Some(listen_fds)) = (listen_pid, listen_fds) else {
+        return Vec::new();
+    };
+    if listen_pid.trim().parse::<u32>().ok() != Some(pid) {
+        return Vec::new();
+    }
+    let count = listen_fds.trim().parse::<i32>().unwrap_or_default();
+    (LISTEN_FDS_START..LISTEN_FDS_START + count.max(0)).collect()
+}
+
+/// Takes the listening sockets systemd passed with socket activation, if
+/// any; later calls return none. The variables are left set, since changing
+/// the environment isn't safe once the runtime's threads run; child
+/// processes ignore them as `LISTEN_PID` names this one.
+#[cfg(unix)]
+pub fn listeners() -> Vec<TcpListener> {
+    use std::os::fd::FromRawFd;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    static TAKEN: AtomicBool = AtomicBool::new(false);
+    if TAKEN.swap(true, Ordering::SeqCst) {
+        return Vec::new();
+    }
+    let fds = passed_fds(
+        env::var("LISTEN_PID").ok().as_deref(),
+        env::var("LISTEN_FDS").ok().as_deref(),
+        std::process::id(),
+    );
+
+    fds.into_iter()
+        .map(|fd| {
+            // SAFETY: systemd hands these descriptors to this process only,
+            // and they are taken exactly once.
+            let listener = unsafe { TcpListener::from_raw_fd(fd) };
+            if let Err(e) = listener.set_nonblocking(true) {
+                warn!("Failed to configure socket {} from systemd: {}", fd, e);
+            }
+            listener
+        })
+        .collect()
+}
+
+#[cfg(not(unix))]
+pub fn listeners() -> Vec<TcpListener> {
+    Vec::new()
+}
+
+/// Sends `state` (e.g. `READY=1`) to systemd's notification socket, if the
+/// service was started with `Type=notify`.
+#[cfg(unix)]
+pub fn notify(state: &str) {
+    use std::os::unix::net::UnixDatagram;
+
+    let Ok(path) = env::var("NOTIFY_SOCKET") else {
+        return;
+    };
+    let sent = UnixDatagram::unbound().and_then(|socket| match path.strip_prefix('@') {
+        #[cfg(target_os = "linux")]
+        Some(name) => {
+            use std::os::linux::net::SocketAddrExt;
+            use std::os::unix::net::SocketAddr;
+
+            let address = SocketAddr::from_abstract_name(name)?;
+            socket.send_to_addr(state.as_bytes(), &address)
+        }
+        _ => socket.send_to(state.as_bytes(), &path),
+    });
+    match sent {
+        Ok(_) => debug!("Notified systemd: {}", state),
+        Err(e) => warn!("Failed to notify systemd at {}: {}", path, e),
+    }
+}
+
+#[cfg(not(unix))]
+pub fn notify(_state: &str) {}
+
+/// Whether systemd waits for a readiness notification.
+pub fn expects_notify() -> bool {
+    env::var_os("NOTIFY_SOCKET").is_some()
+}
+
+#[test]
+fn test_passed_fds() {
+    assert_eq!(passed_fds(Some("42"), Some("2"), 42), vec![3, 4]);
+    assert!(passed_fds(Some("41"), Some("2"), 42).is_empty());
+    assert!(passed_fds(None, Some("1"), 42).is_empty());
+    assert!(passed_fds(Some("42"), Some("x"), 42).is_empty());
+}
@@ -0,0 +1,70 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// Errors kept for the dashboard; older ones are only in the logs.
+const MAX_ERRORS: usize = 100;
+
+/// A failed request or background job.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RecentError {
+    pub at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub product: Option<String>,
+    /// The error code of a failed request, or the job that failed.
+    pub code: String,
+    pub message: String,
+}
+
+/// The most recent server-side failures, for a quick look at what's going
+/// wrong without digging through logs.
+#[derive(Debug, Default)]
+pub struct RecentErrors {
+    errors: Mutex<VecDeque<RecentError>>,
+}
+
+impl RecentErrors {
+    pub fn record(&self, product: Option<&str>, code: &str, message: impl ToString) {
+        let mut errors = self.errors.lock().unwrap_or_else(|e| e.into_inner());
+        if errors.len() == MAX_ERRORS {
+            errors.pop_front();
+        }
+        errors.push_back(RecentError {
+            at: Utc::now(),
+            product: product.map(str::to_string),
+            code: code.to_string(),
+            message: message.to_string(),
+        });
+    }
+
+    /// Up to `limit` errors of `product`, newest first.
+    pub fn recent(&self, product: &str, limit: usize) -> Vec<RecentError> {
+        self.errors
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .rev()
+            .filter(|e| e.product.as_deref() == Some(product))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+}
+
+#[test]
+fn test_recent_errors() {
+    let errors = RecentErrors::default();
+    for i in 0..MAX_ERRORS + 5 {
+        errors.record(Some("myapp"), "upstream_error", format!("failure {}", i));
+    }
+    errors.record(Some("other"), "delta", "patch failed");
+    errors.record(None, "internal_error", "oops");
+
+    let recent = errors.recent("myapp", 3);
+    assert_eq!(recent.len(), 3);
+    assert_eq!(recent[0].message, format!("failure {}", MAX_ERRORS + 4));
+    assert_eq!(errors.recent("myapp", usize::MAX).len(), MAX_ERRORS - 2);
+    assert_eq!(errors.recent("other", 10)[0].code, "delta");
+}
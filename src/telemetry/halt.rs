@@ -0,0 +1,164 @@
+use std::time::Duration;
+
+use dashmap::{DashMap, DashSet};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+
+use crate::telemetry::store::VersionHealth;
+
+/// Halts the rollout of a version once too many of its installs fail.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct HaltPolicy {
+    /// Failure rate, between 0 and 1, above which a version is halted.
+    pub max_failure_rate: f64,
+    /// Finished installs (successful or failed) needed before the rate counts.
+    pub min_reports: u64,
+}
+
+/// Finished installs needed by default before a version can be halted.
+pub const DEFAULT_MIN_REPORTS: u64 = 20;
+
+impl HaltPolicy {
+    pub fn exceeded(&self, health: &VersionHealth) -> bool {
+        health.installed + health.failed >= self.min_reports
+            && health
+                .failure_rate()
+                .is_some_and(|rate| rate > self.max_failure_rate)
+    }
+}
+
+/// Rollout halts that were decided by hand, and the halts that were already
+/// alerted. Versions without an override are halted by their product's
+/// [`HaltPolicy`].
+#[derive(Debug, Default)]
+pub struct RolloutHalts {
+    overrides: DashMap<(String, String), bool>,
+    alerted: DashSet<(String, String)>,
+}
+
+impl RolloutHalts {
+    /// Whether `version` of `product` must no longer be offered.
+    pub fn is_halted(
+        &self,
+        product: &str,
+        version: &str,
+        policy: Option<&HaltPolicy>,
+        health: &VersionHealth,
+    ) -> bool {
+        match self
+            .overrides
+            .get(&(product.to_string(), version.to_string()))
+        {
+            Some(halted) => *halted,
+            None => policy.is_some_and(|policy| policy.exceeded(health)),
+        }
+    }
+
+    /// Halts or resumes a version regardless of its failure rate.
+    pub fn set_halted(&self, product: &str, version: &str, halted: bool) {
+        self.overrides
+            .insert((product.to_lowercase(), version.to_string()), halted);
+    }
+
+    /// Versions of `product` halted or resumed by hand.
+    pub fn overrides(&self, product: &str) -> Vec<(String, bool)> {
+        let mut overrides: Vec<(String, bool)> = self
+            .overrides
+            .iter()
+            .filter(|entry| entry.key().0 == product)
+            .map(|entry| (entry.key().1.clone(), *entry.value()))
+            .collect();
+        overrides.sort();
+        overrides
+    }
+
+    /// Returns `true` the first time it's called for a version, so each
+    /// halt is only alerted once.
+    pub fn first_alert(&self, product: &str, version: &str) -> bool {
+        self.alerted
+            .insert((product.to_string(), version.to_string()))
+    }
+}
+
+/// Payload posted to a product's halt webhook.
+#[derive(Debug, Clone, Serialize)]
+pub struct HaltAlert {
+    pub product: String,
+    pub version: String,
+    pub installed: u64,
+    pub failed: u64,
+    pub failure_rate: f64,
+    /// Human-readable summary, e.g. for chat webhooks that display `text`.
+    pub text: String,
+}
+
+impl HaltAlert {
+    pub fn new(product: &str, version: &str, health: &VersionHealth) -> Self {
+        let failure_rate = health.failure_rate().unwrap_or_default();
+        HaltAlert {
+            product: product.to_string(),
+            version: version.to_string(),
+            installed: health.installed,
+            failed: health.failed,
+            failure_rate,
+            text: format!(
+                "Rollout of {} {} halted: {} of {} installs failed ({:.1}%)",
+                product,
+                version,
+                health.failed,
+                health.installed + health.failed,
+                failure_rate * 100.0
+            ),
+        }
+    }
+}
+
+/// Posts `alert` to `url` as JSON; failures are logged.
+pub async fn send_alert(url: &str, alert: &HaltAlert) {
+    let sent = reqwest::Client::new()
+        .post(url)
+        .timeout(Duration::from_secs(10))
+        .json(alert)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status());
+    match sent {
+        Ok(_) => info!("Sent halt alert for {} {}", alert.product, alert.version),
+        Err(e) => error!(
+            "Failed to send halt alert for {} {}: {}",
+            alert.product, alert.version, e
+        ),
+    }
+}
+
+#[test]
+fn test_rollout_halts() {
+    let policy = HaltPolicy {
+        max_failure_rate: 0.1,
+        min_reports: 10,
+    };
+    let health = |installed, failed| VersionHealth {
+        downloaded: 0,
+        installed,
+        failed,
+    };
+    assert!(!policy.exceeded(&health(4, 2)));
+    assert!(policy.exceeded(&health(8, 2)));
+    assert!(!policy.exceeded(&health(18, 2)));
+
+    let halts = RolloutHalts::default();
+    assert!(halts.is_halted("myapp", "1.2.0", Some(&policy), &health(8, 2)));
+    assert!(!halts.is_halted("myapp", "1.2.0", None, &health(8, 2)));
+
+    halts.set_halted("MyApp", "1.2.0", false);
+    assert!(!halts.is_halted("myapp", "1.2.0", Some(&policy), &health(8, 2)));
+    halts.set_halted("myapp", "1.3.0", true);
+    assert!(halts.is_halted("myapp", "1.3.0", None, &health(0, 0)));
+    assert_eq!(
+        halts.overrides("myapp"),
+        vec![("1.2.0".to_string(), false), ("1.3.0".to_string(), true)]
+    );
+
+    assert!(halts.first_alert("myapp", "1.2.0"));
+    assert!(!halts.first_alert("myapp", "1.2.0"));
+}
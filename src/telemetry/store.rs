@@ -0,0 +1,491 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+
+/// What happened to an update on a client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    UpdateDownloaded,
+    UpdateInstalled,
+    UpdateFailed,
+}
+
+/// An update outcome reported by a client.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpdateEvent {
+    pub product: String,
+    pub event: EventKind,
+    /// The version being updated to.
+    pub version: String,
+    #[serde(default)]
+    pub from_version: Option<String>,
+    /// What went wrong, for `update_failed`.
+    #[serde(default)]
+    pub error: Option<String>,
+    /// The installation that reported it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub installation_id: Option<String>,
+    /// The authenticated client that reported it, if the request had
+    /// credentials.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actor: Option<String>,
+    pub received_at: DateTime<Utc>,
+}
+
+impl UpdateEvent {
+    /// Who reported the event, which it's counted once for: the
+    /// installation, or else the authenticated client.
+    fn reporter(&self) -> Option<String> {
+        self.installation_id
+            .as_deref()
+            .or(self.actor.as_deref())
+            .map(truncate)
+    }
+}
+
+/// Outcome counts of updates to one version.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct VersionHealth {
+    pub downloaded: u64,
+    pub installed: u64,
+    pub failed: u64,
+}
+
+impl VersionHealth {
+    /// Share of finished installs that failed, once any have finished.
+    pub fn failure_rate(&self) -> Option<f64> {
+        let finished = self.installed + self.failed;
+        (finished > 0).then(|| self.failed as f64 / finished as f64)
+    }
+
+    fn count(&mut self, kind: EventKind) {
+        match kind {
+            EventKind::UpdateDownloaded => self.downloaded += 1,
+            EventKind::UpdateInstalled => self.installed += 1,
+            EventKind::UpdateFailed => self.failed += 1,
+        }
+    }
+}
+
+/// What a client told about itself when checking for updates, in headers
+/// or query parameters.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClientMetadata {
+    pub os_version: Option<String>,
+    /// Build number of the installed app, which may change without its
+    /// version.
+    pub app_build: Option<String>,
+    /// Identifies the installation, e.g. a UUID generated on first launch.
+    pub installation_id: Option<String>,
+}
+
+/// Longest metadata value kept; longer values are cut.
+const MAX_METADATA_LENGTH: usize = 64;
+
+/// Installations tracked at most; checks from further installations aren't
+/// recorded.
+const MAX_INSTALLATIONS: usize = 100_000;
+
+/// Update outcomes a client address may report per minute by default.
+pub const DEFAULT_REPORTS_PER_MINUTE: u32 = 60;
+
+/// Versions, across products, that outcomes are counted for at most; events
+/// for further versions aren't recorded.
+const MAX_VERSIONS: usize = 1_000;
+
+/// Reporters remembered at most, per version and kind of event, across
+/// products; events from further reporters aren't recorded.
+const MAX_REPORTERS: usize = 1_000_000;
+
+/// The last update check of an installation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Installation {
+    version: String,
+    platform: String,
+    os_version: Option<String>,
+    app_build: Option<String>,
+    last_seen: DateTime<Utc>,
+}
+
+/// Installations of a product by what they last reported.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct InstallBase {
+    pub installations: u64,
+    pub versions: BTreeMap<String, u64>,
+    pub platforms: BTreeMap<String, u64>,
+    pub os_versions: BTreeMap<String, u64>,
+    pub app_builds: BTreeMap<String, u64>,
+}
+
+/// Update outcomes by product and version. Each installation or client
+/// counts once per version and kind of event. Events are appended to a
+/// JSON-lines file when one is configured and replayed from it on startup,
+/// so the counts survive restarts.
+#[derive(Debug, Default)]
+pub struct EventStore {
+    health: DashMap<(String, String), VersionHealth>,
+    /// Outcomes reported by authenticated clients for an installation id,
+    /// which rollouts are halted by, since anyone can send a made-up id.
+    verified: DashMap<(String, String), VersionHealth>,
+    /// Who reported each kind of event for a product and version.
+    reporters: DashMap<(String, String, EventKind, String), ()>,
+    file: Option<Mutex<File>>,
+    /// Installations by product and installation id, kept in memory only.
+    installations: DashMap<(String, String), Installation>,
+}
+
+impl EventStore {
+    /// Replays the events in `path` and appends new ones to it.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let store = EventStore::default();
+        match File::open(path) {
+            Ok(existing) => {
+                for (number, line) in BufReader::new(existing).lines().enumerate() {
+                    match serde_json::from_str::<UpdateEvent>(&line?) {
+                        Ok(event) => {
+                            store.count(&event);
+                        }
+                        Err(e) => warn!("Skipping event on line {}: {}", number + 1, e),
+                    }
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(EventStore {
+            file: Some(Mutex::new(file)),
+            ..store
+        })
+    }
+
+    /// Persists to `EVENTS_FILE` if set; otherwise events are only kept in
+    /// memory.
+    pub fn from_env() -> Self {
+        let Ok(path) = env::var("EVENTS_FILE") else {
+            return EventStore::default();
+        };
+        match EventStore::open(Path::new(&path)) {
+            Ok(store) => {
+                info!("Recording update events in {}", path);
+                store
+            }
+            Err(e) => {
+                error!(
+                    "Failed to open EVENTS_FILE {}: {}, keeping events in memory",
+                    path, e
+                );
+                EventStore::default()
+            }
+        }
+    }
+
+    /// Counts `event` and appends it to the events file, returning the
+    /// updated health of its version. Returns `None` for repeated reports
+    /// and events past the limits, which are dropped.
+    pub fn record(&self, event: &UpdateEvent) -> Option<VersionHealth> {
+        let health = self.count(event)?;
+        if let Some(file) = &self.file {
+            let written = serde_json::to_string(event)
+                .map_err(io::Error::from)
+                .and_then(|line| {
+                    let mut file = file.lock().unwrap_or_else(|e| e.into_inner());
+                    writeln!(file, "{}", line)
+                });
+            if let Err(e) = written {
+                error!("Failed to persist update event: {}", e);
+            }
+        }
+        Some(health)
+    }
+
+    fn count(&self, event: &UpdateEvent) -> Option<VersionHealth> {
+        let version = (event.product.clone(), event.version.clone());
+        if self.health.len() >= MAX_VERSIONS && !self.health.contains_key(&version) {
+            warn!(
+                "Not counting {:?} for {} {}: {} versions are tracked already",
+                event.event, event.product, event.version, MAX_VERSIONS
+            );
+            return None;
+        }
+        // Events from before reporters were recorded can't be told apart
+        if let Some(reporter) = event.reporter() {
+            if self.reporters.len() >= MAX_REPORTERS {
+                return None;
+            }
+            let key = (version.0.clone(), version.1.clone(), event.event, reporter);
+            if self.reporters.insert(key, ()).is_some() {
+                return None;
+            }
+        }
+
+        if event.actor.is_some() && event.installation_id.is_some() {
+            self.verified
+                .entry(version.clone())
+                .or_default()
+                .count(event.event);
+        }
+        let mut health = self.health.entry(version).or_default();
+        health.count(event.event);
+        Some(*health)
+    }
+
+    /// Records an update check of `product` from a client running `version`
+    /// on `platform`. Checks without an installation id (or device id, in
+    /// `fallback_id`) can't be told apart and aren't recorded.
+    pub fn record_check(
+        &self,
+        product: &str,
+        version: &str,
+        platform: &str,
+        client: &ClientMetadata,
+        fallback_id: Option<&str>,
+    ) {
+        let Some(id) = client.installation_id.as_deref().or(fallback_id) else {
+            return;
+        };
+        let key = (product.to_string(), truncate(id));
+        if self.installations.len() >= MAX_INSTALLATIONS && !self.installations.contains_key(&key) {
+            return;
+        }
+        self.installations.insert(
+            key,
+            Installation {
+                version: version.to_string(),
+                platform: platform.to_string(),
+                os_version: client.os_version.as_deref().map(truncate),
+                app_build: client.app_build.as_deref().map(truncate),
+                last_seen: Utc::now(),
+            },
+        );
+    }
+
+    /// What the installations of `product` last reported.
+    pub fn install_base(&self, product: &str) -> InstallBase {
+        let mut base = InstallBase::default();
+        for entry in self.installations.iter().filter(|e| e.key().0 == product) {
+            let installation = entry.value();
+            base.installations += 1;
+            *base
+                .versions
+                .entry(installation.version.clone())
+                .or_default() += 1;
+            *base
+                .platforms
+                .entry(installation.platform.clone())
+                .or_default() += 1;
+            if let Some(os_version) = &installation.os_version {
+                *base.os_versions.entry(os_version.clone()).or_default() += 1;
+            }
+            if let Some(app_build) = &installation.app_build {
+                *base.app_builds.entry(app_build.clone()).or_default() += 1;
+            }
+        }
+        base
+    }
+
+    /// Outcome counts of one version of `product`.
+    pub fn version_health(&self, product: &str, version: &str) -> VersionHealth {
+        self.health
+            .get(&(product.to_string(), version.to_string()))
+            .map(|health| *health)
+            .unwrap_or_default()
+    }
+
+    /// Outcome counts of one version of `product` reported by authenticated
+    /// clients for distinct installations, which halt policies apply to.
+    pub fn verified_health(&self, product: &str, version: &str) -> VersionHealth {
+        self.verified
+            .get(&(product.to_string(), version.to_string()))
+            .map(|health| *health)
+            .unwrap_or_default()
+    }
+
+    /// Outcome counts of every version of `product` that events were
+    /// reported for.
+    pub fn health(&self, product: &str) -> BTreeMap<String, VersionHealth> {
+        self.health
+            .iter()
+            .filter(|entry| entry.key().0 == product)
+            .map(|entry| (entry.key().1.clone(), *entry.value()))
+            .collect()
+    }
+}
+
+fn truncate(value: &str) -> String {
+    value.chars().take(MAX_METADATA_LENGTH).collect()
+}
+
+#[test]
+fn test_event_store() {
+    let path = env::temp_dir().join(format!("events-{}.jsonl", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let event = |kind, version: &str, installation: &str| UpdateEvent {
+        product: "myapp".to_string(),
+        event: kind,
+        version: version.to_string(),
+        from_version: Some("1.1.0".to_string()),
+        error: None,
+        installation_id: Some(installation.to_string()),
+        actor: None,
+        received_at: Utc::now(),
+    };
+    let store = EventStore::open(&path).unwrap();
+    store.record(&event(EventKind::UpdateDownloaded, "1.2.0", "a"));
+    store.record(&event(EventKind::UpdateInstalled, "1.2.0", "a"));
+    store.record(&event(EventKind::UpdateInstalled, "1.2.0", "b"));
+    // Each installation counts once per version and kind of event
+    assert_eq!(
+        store.record(&event(EventKind::UpdateInstalled, "1.2.0", "b")),
+        None
+    );
+    let health = store
+        .record(&event(EventKind::UpdateFailed, "1.2.0", "c"))
+        .unwrap();
+    assert_eq!(health.failed, 1);
+    assert_eq!(health.failure_rate(), Some(1.0 / 3.0));
+    store.record(&event(EventKind::UpdateDownloaded, "1.3.0", "a"));
+    drop(store);
+
+    let reopened = EventStore::open(&path).unwrap();
+    let health = reopened.health("myapp");
+    assert_eq!(
+        health["1.2.0"],
+        VersionHealth {
+            downloaded: 1,
+            installed: 2,
+            failed: 1
+        }
+    );
+    assert_eq!(health["1.3.0"].failure_rate(), None);
+    assert!(reopened.health("otherapp").is_empty());
+    assert_eq!(
+        reopened.record(&event(EventKind::UpdateFailed, "1.2.0", "c")),
+        None
+    );
+    let _ = std::fs::remove_file(&path);
+
+    let store = EventStore::default();
+    for version in 0..MAX_VERSIONS {
+        let version = format!("1.0.{}", version);
+        assert!(store
+            .record(&event(EventKind::UpdateDownloaded, &version, "a"))
+            .is_some());
+    }
+    assert_eq!(
+        store.record(&event(EventKind::UpdateDownloaded, "2.0.0", "a")),
+        None
+    );
+    assert!(store
+        .record(&event(EventKind::UpdateInstalled, "1.0.0", "a"))
+        .is_some());
+}
+
+#[test]
+fn test_install_base() {
+    let store = EventStore::default();
+    let client = |id: Option<&str>, os: &str| ClientMetadata {
+        os_version: Some(os.to_string()),
+        app_build: Some("4512".to_string()),
+        installation_id: id.map(str::to_string),
+    };
+    store.record_check(
+        "myapp",
+        "1.1.0",
+        "windows-x86_64",
+        &client(Some("a"), "10.0.19045"),
+        None,
+    );
+    // The latest check of an installation replaces its earlier ones
+    store.record_check(
+        "myapp",
+        "1.2.0",
+        "windows-x86_64",
+        &client(Some("a"), "10.0.22631"),
+        None,
+    );
+    store.record_check(
+        "myapp",
+        "1.1.0",
+        "darwin-aarch64",
+        &client(None, "14.5"),
+        Some("device-1"),
+    );
+    store.record_check(
+        "myapp",
+        "1.1.0",
+        "darwin-aarch64",
+        &client(None, "14.5"),
+        None,
+    );
+    store.record_check(
+        "otherapp",
+        "2.0.0",
+        "linux-x86_64",
+        &client(Some("b"), "6.8"),
+        None,
+    );
+
+    let base = store.install_base("myapp");
+    assert_eq!(base.installations, 2);
+    assert_eq!(
+        base.versions,
+        BTreeMap::from([("1.1.0".to_string(), 1), ("1.2.0".to_string(), 1)])
+    );
+    assert_eq!(base.os_versions.get("10.0.19045"), None);
+    assert_eq!(base.os_versions["10.0.22631"], 1);
+    assert_eq!(base.app_builds["4512"], 2);
+    assert_eq!(store.install_base("unknown"), InstallBase::default());
+}
+
+#[test]
+fn test_verified_health() {
+    use crate::telemetry::halt::HaltPolicy;
+
+    let policy = HaltPolicy {
+        max_failure_rate: 0.5,
+        min_reports: 3,
+    };
+    let failed = |installation: Option<&str>, actor: Option<&str>| UpdateEvent {
+        product: "myapp".to_string(),
+        event: EventKind::UpdateFailed,
+        version: "1.2.0".to_string(),
+        from_version: None,
+        error: None,
+        installation_id: installation.map(str::to_string),
+        actor: actor.map(str::to_string),
+        received_at: Utc::now(),
+    };
+    let store = EventStore::default();
+
+    // Made-up installation ids without credentials
+    for i in 0..10 {
+        store.record(&failed(Some(&format!("spoofed-{}", i)), None));
+    }
+    // Credentials without an installation, and one installation repeating
+    // its report
+    store.record(&failed(None, Some("api_key:ab12cd34")));
+    for _ in 0..10 {
+        store.record(&failed(Some("a"), Some("api_key:ab12cd34")));
+    }
+    assert_eq!(store.health("myapp")["1.2.0"].failed, 12);
+    let verified = store.verified_health("myapp", "1.2.0");
+    assert_eq!(verified.failed, 1);
+    assert!(!policy.exceeded(&verified));
+
+    for installation in ["b", "c"] {
+        store.record(&failed(Some(installation), Some("api_key:ab12cd34")));
+    }
+    assert!(policy.exceeded(&store.verified_health("myapp", "1.2.0")));
+}
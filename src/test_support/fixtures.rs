@@ -0,0 +1,195 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use ring::signature::{Ed25519KeyPair, KeyPair};
+use serde_json::{json, Value};
+
+/// Seed of the key fixture updater signatures are made with.
+pub const UPDATER_KEY_SEED: [u8; 32] = [7; 32];
+
+/// Id of the key fixture updater signatures are made with.
+pub const UPDATER_KEY_ID: [u8; 8] = [1, 2, 3, 4, 5, 6, 7, 8];
+
+/// The fixture key's public half, as `tauri.conf.json` expects it.
+pub fn updater_public_key() -> String {
+    public_key(&UPDATER_KEY_SEED, &UPDATER_KEY_ID)
+}
+
+/// The public half of the key with `seed`, as `tauri.conf.json` expects it.
+pub fn public_key(seed: &[u8; 32], key_id: &[u8; 8]) -> String {
+    let key = Ed25519KeyPair::from_seed_unchecked(seed).expect("any seed is a valid key");
+    let file = format!(
+        "untrusted comment: minisign public key: {:016X}\n{}\n",
+        u64::from_le_bytes(*key_id),
+        STANDARD.encode([b"Ed".as_slice(), key_id, key.public_key().as_ref()].concat()),
+    );
+    STANDARD.encode(file)
+}
+
+/// Content of a fixture asset other than a signature.
+pub fn asset_content(name: &str) -> Vec<u8> {
+    format!("contents of {}", name).into_bytes()
+}
+
+/// A `.sig` file for `content`, as `tauri signer sign` writes it, made with
+/// the fixture key.
+pub fn updater_signature(filename: &str, content: &[u8]) -> Vec<u8> {
+    let key = Ed25519KeyPair::from_seed_unchecked(&UPDATER_KEY_SEED)
+        .expect("the fixture seed is a valid key");
+    let signature = key.sign(content);
+    let trusted_comment = format!("timestamp:1700000000\tfile:{}", filename);
+    let global_signature = key.sign(&[signature.as_ref(), trusted_comment.as_bytes()].concat());
+
+    let file = format!(
+        "untrusted comment: signature from tauri secret key\n{}\ntrusted comment: {}\n{}\n",
+        STANDARD.encode([b"Ed".as_slice(), &UPDATER_KEY_ID, signature.as_ref()].concat()),
+        trusted_comment,
+        STANDARD.encode(global_signature),
+    );
+    STANDARD.encode(file).into_bytes()
+}
+
+/// An asset attached to a [`ReleaseFixture`].
+#[derive(Debug, Clone)]
+pub struct AssetFixture {
+    pub id: u64,
+    pub name: String,
+    pub content: Vec<u8>,
+}
+
+/// A GitHub release as served by [`MockGitHub`](super::mock_github::MockGitHub).
+#[derive(Debug, Clone)]
+pub struct ReleaseFixture {
+    pub id: u64,
+    pub tag: String,
+    pub body: String,
+    pub draft: bool,
+    pub prerelease: bool,
+    pub published_at: DateTime<Utc>,
+    pub assets: Vec<AssetFixture>,
+}
+
+impl ReleaseFixture {
+    /// A published release without assets.
+    pub fn new(id: u64, tag: &str) -> Self {
+        ReleaseFixture {
+            id,
+            tag: tag.to_string(),
+            body: format!("Release notes of {}", tag),
+            draft: false,
+            prerelease: false,
+            published_at: DateTime::from_timestamp(1_700_000_000 + id as i64 * 86_400, 0)
+                .unwrap_or_default(),
+            assets: Vec::new(),
+        }
+    }
+
+    /// The assets `tauri-action` uploads for a Tauri v2 app built for
+    /// Windows, macOS (Intel and Apple Silicon) and Linux, with updater
+    /// signatures. Asset ids are `id * 100 + n`.
+    pub fn tauri_v2(id: u64, app: &str, version: &str) -> Self {
+        let names = [
+            format!("{}_{}_x64_en-US.msi", app, version),
+            format!("{}_{}_x64_en-US.msi.sig", app, version),
+            format!("{}_{}_x64-setup.exe", app, version),
+            format!("{}_{}_x64-setup.exe.sig", app, version),
+            format!("{}_x64.app.tar.gz", app),
+            format!("{}_x64.app.tar.gz.sig", app),
+            format!("{}_aarch64.app.tar.gz", app),
+            format!("{}_aarch64.app.tar.gz.sig", app),
+            format!("{}_{}_x64.dmg", app, version),
+            format!("{}_{}_aarch64.dmg", app, version),
+            format!("{}_{}_amd64.AppImage", app, version),
+            format!("{}_{}_amd64.AppImage.sig", app, version),
+            format!("{}_{}_amd64.deb", app, version),
+            "latest.json".to_string(),
+        ];
+
+        let mut release = ReleaseFixture::new(id, &format!("v{}", version));
+        for name in names {
+            release = release.with_asset(&name);
+        }
+        release
+    }
+
+    /// Adds an asset whose content is derived from its name.
+    pub fn with_asset(mut self, name: &str) -> Self {
+        let content = match name.strip_suffix(".sig") {
+            Some(installer) => updater_signature(installer, &asset_content(installer)),
+            None => asset_content(name),
+        };
+        self.assets.push(AssetFixture {
+            id: self.id * 100 + self.assets.len() as u64 + 1,
+            name: name.to_string(),
+            content,
+        });
+        self
+    }
+
+    pub fn prerelease(mut self) -> Self {
+        self.prerelease = true;
+        self
+    }
+
+    pub fn draft(mut self) -> Self {
+        self.draft = true;
+        self
+    }
+
+    pub fn asset(&self, name: &str) -> Option<&AssetFixture> {
+        self.assets.iter().find(|asset| asset.name == name)
+    }
+
+    /// The release in the shape of the GitHub REST API.
+    pub fn to_json(&self, api_url: &str, owner: &str, repo: &str) -> Value {
+        let repo_url = format!("{}/repos/{}/{}", api_url, owner, repo);
+        let html_url = format!("https://github.com/{}/{}", owner, repo);
+        let published_at = self.published_at.to_rfc3339();
+
+        let assets: Vec<Value> = self
+            .assets
+            .iter()
+            .map(|asset| {
+                json!({
+                    "url": format!("{}/releases/assets/{}", repo_url, asset.id),
+                    "browser_download_url": format!(
+                        "{}/releases/download/{}/{}",
+                        html_url, self.tag, asset.name
+                    ),
+                    "id": asset.id,
+                    "node_id": format!("RA_{}", asset.id),
+                    "name": asset.name,
+                    "label": null,
+                    "state": "uploaded",
+                    "content_type": "application/octet-stream",
+                    "size": asset.content.len(),
+                    "download_count": 0,
+                    "created_at": published_at,
+                    "updated_at": published_at,
+                    "uploader": null,
+                })
+            })
+            .collect();
+
+        json!({
+            "url": format!("{}/releases/{}", repo_url, self.id),
+            "html_url": format!("{}/releases/tag/{}", html_url, self.tag),
+            "assets_url": format!("{}/releases/{}/assets", repo_url, self.id),
+            "upload_url": format!("{}/releases/{}/assets{{?name,label}}", repo_url, self.id),
+            "tarball_url": null,
+            "zipball_url": null,
+            "id": self.id,
+            "node_id": format!("RE_{}", self.id),
+            "tag_name": self.tag,
+            "target_commitish": "main",
+            "name": self.tag,
+            "body": self.body,
+            "draft": self.draft,
+            "prerelease": self.prerelease,
+            "created_at": published_at,
+            "published_at": published_at,
+            "author": null,
+            "assets": assets,
+        })
+    }
+}
@@ -0,0 +1,281 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde_json::json;
+use wiremock::matchers::{any, method, path, path_regex, query_param};
+use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+use crate::config::ProductConfig;
+use crate::test_support::fixtures::ReleaseFixture;
+
+pub const TEST_TOKEN: &str = "test-token";
+
+/// An in-process stand-in for the GitHub releases and assets API.
+pub struct MockGitHub {
+    server: MockServer,
+}
+
+impl MockGitHub {
+    pub async fn start() -> Self {
+        let mock = MockGitHub {
+            server: MockServer::start().await,
+        };
+        mock.mount_not_found().await;
+        mock
+    }
+
+    /// Answers what isn't mounted like GitHub does, with a JSON `404`.
+    async fn mount_not_found(&self) {
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(404).set_body_json(json!({
+                "message": "Not Found",
+                "documentation_url": "https://docs.github.com/rest",
+            })))
+            .with_priority(u8::MAX)
+            .mount(&self.server)
+            .await;
+    }
+
+    /// Base URL to use as a product's `api_base_url`.
+    pub fn url(&self) -> String {
+        self.server.uri()
+    }
+
+    /// A product served from `owner/repo` on this mock.
+    pub fn product(&self, owner: &str, repo: &str) -> ProductConfig {
+        ProductConfig {
+            api_base_url: Some(self.url()),
+            ..ProductConfig::new(TEST_TOKEN, owner, repo)
+        }
+    }
+
+    /// Serves `releases` (newest first) for `owner/repo`: the release list,
+    /// each release by tag and the content of every asset.
+    pub async fn mount_releases(&self, owner: &str, repo: &str, releases: &[ReleaseFixture]) {
+        let repo_path = format!("/repos/{}/{}", owner, repo);
+        let list: Vec<_> = releases
+            .iter()
+            .map(|release| release.to_json(&self.url(), owner, repo))
+            .collect();
+
+        Mock::given(method("GET"))
+            .and(path(format!("{}/releases", repo_path)))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("x-ratelimit-limit", "5000")
+                    .insert_header("x-ratelimit-remaining", "4999")
+                    .insert_header("x-ratelimit-reset", "1700003600")
+                    .insert_header("x-ratelimit-resource", "core")
+                    .set_body_json(list),
+            )
+            .mount(&self.server)
+            .await;
+
+        for release in releases {
+            Mock::given(method("GET"))
+                .and(path(format!("{}/releases/tags/{}", repo_path, release.tag)))
+                .respond_with(ResponseTemplate::new(200).set_body_json(release.to_json(
+                    &self.url(),
+                    owner,
+                    repo,
+                )))
+                .mount(&self.server)
+                .await;
+
+            for asset in &release.assets {
+                Mock::given(method("GET"))
+                    .and(path(format!("{}/releases/assets/{}", repo_path, asset.id)))
+                    .respond_with(AssetResponder {
+                        content: asset.content.clone(),
+                    })
+                    .mount(&self.server)
+                    .await;
+            }
+        }
+    }
+
+    /// Serves `content` as asset `asset_id` of `owner/repo`, chunked and
+    /// without a `Content-Length`, in place of the asset's own content.
+    pub async fn mount_chunked_asset(
+        &self,
+        owner: &str,
+        repo: &str,
+        asset_id: u64,
+        content: &[u8],
+    ) {
+        Mock::given(method("GET"))
+            .and(path(format!(
+                "/repos/{}/{}/releases/assets/{}",
+                owner, repo, asset_id
+            )))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("Content-Type", "application/octet-stream")
+                    .insert_header("Transfer-Encoding", "chunked")
+                    .set_body_bytes(content),
+            )
+            .with_priority(1)
+            .mount(&self.server)
+            .await;
+    }
+
+    /// Serves `content` as the file at `file_path` in `owner/repo` as of
+    /// `reference`, through the contents API.
+    pub async fn mount_file(
+        &self,
+        owner: &str,
+        repo: &str,
+        file_path: &str,
+        reference: &str,
+        content: &str,
+    ) {
+        let url = format!(
+            "{}/repos/{}/{}/contents/{}",
+            self.url(),
+            owner,
+            repo,
+            file_path
+        );
+        Mock::given(method("GET"))
+            .and(path(format!(
+                "/repos/{}/{}/contents/{}",
+                owner, repo, file_path
+            )))
+            .and(query_param("ref", reference))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "type": "file",
+                "name": file_path.rsplit('/').next(),
+                "path": file_path,
+                "sha": "3d21ec53a331a6f037a91c368710b99387d012c1",
+                "size": content.len(),
+                "encoding": "base64",
+                "content": STANDARD.encode(content),
+                "url": url,
+                "html_url": null,
+                "git_url": null,
+                "download_url": null,
+                "_links": {"self": url, "git": null, "html": null},
+            })))
+            .mount(&self.server)
+            .await;
+    }
+
+    /// Accepts new issues in `owner/repo`, answering each as issue `number`.
+    pub async fn mount_issues(&self, owner: &str, repo: &str, number: u64) {
+        let repo_url = format!("{}/repos/{}/{}", self.url(), owner, repo);
+        let issue_url = format!("{}/issues/{}", repo_url, number);
+        let user_url = format!("{}/users/feedback-bot", self.url());
+        let user = json!({
+            "login": "feedback-bot",
+            "id": 1,
+            "node_id": "MDQ6VXNlcjE=",
+            "avatar_url": format!("{}/avatar", user_url),
+            "gravatar_id": "",
+            "url": user_url,
+            "html_url": user_url,
+            "followers_url": user_url,
+            "following_url": user_url,
+            "gists_url": user_url,
+            "starred_url": user_url,
+            "subscriptions_url": user_url,
+            "organizations_url": user_url,
+            "repos_url": user_url,
+            "events_url": user_url,
+            "received_events_url": user_url,
+            "type": "Bot",
+            "site_admin": false,
+            "patch_url": null,
+        });
+        Mock::given(method("POST"))
+            .and(path(format!("/repos/{}/{}/issues", owner, repo)))
+            .respond_with(ResponseTemplate::new(201).set_body_json(json!({
+                "id": number,
+                "node_id": "I_kwDOA",
+                "url": issue_url,
+                "repository_url": repo_url,
+                "labels_url": format!("{}/labels", issue_url),
+                "comments_url": format!("{}/comments", issue_url),
+                "events_url": format!("{}/events", issue_url),
+                "html_url": issue_url,
+                "number": number,
+                "state": "open",
+                "state_reason": null,
+                "title": "Feedback",
+                "body": null,
+                "user": user,
+                "labels": [],
+                "assignees": [],
+                "author_association": "NONE",
+                "locked": false,
+                "comments": 0,
+                "created_at": "2024-01-01T00:00:00Z",
+                "updated_at": "2024-01-01T00:00:00Z",
+            })))
+            .mount(&self.server)
+            .await;
+    }
+
+    /// Answers every request for `owner/repo` with a GitHub error, e.g. `403`
+    /// with `API rate limit exceeded`.
+    pub async fn mount_error(&self, owner: &str, repo: &str, status: u16, message: &str) {
+        Mock::given(method("GET"))
+            .and(path_regex(format!("^/repos/{}/{}/", owner, repo)))
+            .respond_with(ResponseTemplate::new(status).set_body_json(json!({
+                "message": message,
+                "documentation_url": "https://docs.github.com/rest",
+            })))
+            .mount(&self.server)
+            .await;
+    }
+
+    /// Forgets every mounted response and received request, e.g. to change
+    /// a repository's releases.
+    pub async fn reset(&self) {
+        self.server.reset().await;
+        self.mount_not_found().await;
+    }
+
+    /// Requests the mock received so far.
+    pub async fn received_requests(&self) -> Vec<Request> {
+        self.server.received_requests().await.unwrap_or_default()
+    }
+}
+
+/// Serves asset content, honoring single `bytes=start-[end]` ranges.
+struct AssetResponder {
+    content: Vec<u8>,
+}
+
+impl Respond for AssetResponder {
+    fn respond(&self, request: &Request) -> ResponseTemplate {
+        let length = self.content.len();
+        let range = request
+            .headers
+            .get("Range")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("bytes="))
+            .and_then(|v| v.split_once('-'))
+            .and_then(|(start, end)| {
+                let start: usize = start.parse().ok()?;
+                let end = match end {
+                    "" => length.saturating_sub(1),
+                    end => end.parse::<usize>().ok()?.min(length.saturating_sub(1)),
+                };
+                Some((start, end))
+            });
+
+        match range {
+            None => ResponseTemplate::new(200)
+                .insert_header("Content-Type", "application/octet-stream")
+                .set_body_bytes(self.content.clone()),
+            Some((start, end)) if start >= length || start > end => ResponseTemplate::new(416)
+                .insert_header("Content-Range", format!("bytes */{}", length).as_str()),
+            Some((start, end)) => ResponseTemplate::new(206)
+                .insert_header("Content-Type", "application/octet-stream")
+                .insert_header(
+                    "Content-Range",
+                    format!("bytes {}-{}/{}", start, end, length).as_str(),
+                )
+                .set_body_bytes(self.content[start..=end].to_vec()),
+        }
+    }
+}
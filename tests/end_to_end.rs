@@ -0,0 +1,2219 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::cookie::Cookie;
+use actix_web::http::StatusCode;
+use actix_web::middleware::from_fn;
+use actix_web::{guard, test, web, App, HttpResponse};
+use futures_util::future::BoxFuture;
+use serde_json::{json, Value};
+
+use tauri_update_server::admin::maintenance_window::MaintenanceWindows;
+use tauri_update_server::auth::authenticator::{
+    ApiKeyAuthenticator, AuthConfig, BasicAuthenticator, RouteGroup,
+};
+use tauri_update_server::auth::rate_limit::RateLimiter;
+use tauri_update_server::cache::gc;
+use tauri_update_server::cache::limits::CacheLimits;
+use tauri_update_server::cdn::offload::Cdn;
+use tauri_update_server::cdn::s3::{Credentials, S3Bucket};
+use tauri_update_server::cdn::sync;
+use tauri_update_server::cors::policy::CorsConfig;
+use tauri_update_server::delta::patch::apply_patch;
+use tauri_update_server::delta::worker::refresh;
+use tauri_update_server::error::ServerError;
+use tauri_update_server::geo::mirror::Mirrors;
+use tauri_update_server::github::client::GitHubClient;
+use tauri_update_server::github::fallback::FallbackRepo;
+use tauri_update_server::license::entitlement::EntitlementVerifier;
+use tauri_update_server::license::validator::{LicenseError, LicenseRequest, LicenseValidator};
+use tauri_update_server::local;
+use tauri_update_server::local::store::LocalStore;
+use tauri_update_server::logging::log_context;
+use tauri_update_server::release::compatibility::parse_min_os_versions;
+use tauri_update_server::release::rollout::in_rollout;
+use tauri_update_server::release::rules::Rules;
+use tauri_update_server::release::version::Semver;
+use tauri_update_server::response::hmac::body_signature;
+use tauri_update_server::server::BackgroundJobs;
+use tauri_update_server::telemetry::halt::HaltPolicy;
+use tauri_update_server::test_support::fixtures::{self, ReleaseFixture};
+use tauri_update_server::test_support::mock_github::MockGitHub;
+use tauri_update_server::{configure, AppState, ProductConfig};
+use wiremock::matchers::{body_partial_json, method};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+async fn mock_with_releases() -> MockGitHub {
+    let mock = MockGitHub::start().await;
+    mock.mount_releases(
+        "acme",
+        "myapp",
+        &[
+            ReleaseFixture::tauri_v2(3, "MyApp", "1.3.0-beta.1").prerelease(),
+            ReleaseFixture::tauri_v2(2, "MyApp", "1.2.0"),
+            ReleaseFixture::tauri_v2(1, "MyApp", "1.1.0"),
+        ],
+    )
+    .await;
+    mock
+}
+
+/// Refuses updates to devices whose subscription was cancelled.
+#[derive(Debug)]
+struct CancelledDevices(Vec<&'static str>);
+
+impl LicenseValidator for CancelledDevices {
+    fn validate<'a>(
+        &'a self,
+        request: LicenseRequest<'a>,
+    ) -> BoxFuture<'a, Result<(), LicenseError>> {
+        Box::pin(async move {
+            match request.device_id {
+                Some(device) if self.0.contains(&device) => {
+                    Err(LicenseError::Denied("subscription cancelled".to_string()))
+                }
+                _ => Ok(()),
+            }
+        })
+    }
+}
+
+/// Leaves background jobs to the test, which runs them when it needs to.
+fn without_jobs() -> BackgroundJobs {
+    BackgroundJobs {
+        enabled: false,
+        ..BackgroundJobs::default()
+    }
+}
+
+fn state(mock: &MockGitHub) -> AppState {
+    AppState::builder()
+        .product("myapp", mock.product("acme", "myapp"))
+        .hostname("http://updates.example.com")
+        .build()
+}
+
+/// Admin routes take the `admin-key` API key.
+fn admin_auth() -> AuthConfig {
+    let mut auth = AuthConfig::default();
+    auth.add(
+        RouteGroup::Admin,
+        Arc::new(ApiKeyAuthenticator::new(
+            "X-Api-Key".to_string(),
+            vec!["admin-key".to_string()],
+        )),
+    );
+    auth
+}
+
+/// Like [`admin_auth`], with update routes taking the `client-key` API key.
+fn client_auth() -> AuthConfig {
+    let mut auth = admin_auth();
+    auth.add(
+        RouteGroup::Update,
+        Arc::new(ApiKeyAuthenticator::new(
+            "X-Client-Key".to_string(),
+            vec!["client-key".to_string()],
+        )),
+    );
+    auth
+}
+
+/// An update check of `product` by a Windows client on 1.0.0.
+fn check_product(product: &str) -> test::TestRequest {
+    test::TestRequest::get().uri(&format!("/{}/stable/windows/x86_64/1.0.0", product))
+}
+
+fn admin_status() -> test::TestRequest {
+    test::TestRequest::get()
+        .uri("/status")
+        .insert_header(("X-Api-Key", "admin-key"))
+}
+
+fn report_event(installation: &str, event: Value) -> test::TestRequest {
+    test::TestRequest::post()
+        .uri("/myapp/events")
+        .insert_header(("X-Installation-Id", installation))
+        .insert_header(("X-Client-Key", "client-key"))
+        .set_json(event)
+}
+
+#[actix_web::test]
+async fn test_check_update_end_to_end() {
+    let mock = mock_with_releases().await;
+    let app = test::init_service(
+        App::new()
+            .configure(configure(state(&mock)))
+            .wrap(from_fn(log_context)),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri("/myapp/stable/windows/x86_64/1.1.0")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(resp.headers().contains_key("x-request-id"));
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["version"], "1.2.0");
+    assert_eq!(body["notes"], "Release notes of v1.2.0");
+    let signature = fixtures::updater_signature(
+        "MyApp_1.2.0_x64_en-US.msi",
+        &fixtures::asset_content("MyApp_1.2.0_x64_en-US.msi"),
+    );
+    assert_eq!(body["signature"], String::from_utf8(signature).unwrap());
+    assert_eq!(
+        body["url"],
+        "http://updates.example.com/myapp/download/201/MyApp_1.2.0_x64_en-US.msi"
+    );
+
+    let req = test::TestRequest::get()
+        .uri("/myapp/stable/darwin/aarch64/1.2.0")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+}
+
+#[actix_web::test]
+async fn test_request_id_end_to_end() {
+    let mock = mock_with_releases().await;
+    let app = test::init_service(
+        App::new()
+            .configure(configure(state(&mock)))
+            .wrap(from_fn(log_context)),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri("/otherapp/stable/darwin/aarch64/1.2.0")
+        .insert_header(("X-Request-Id", "req-42"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    assert_eq!(resp.headers().get("x-request-id").unwrap(), "req-42");
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["code"], "product_not_found");
+    assert_eq!(body["request_id"], "req-42");
+}
+
+#[actix_web::test]
+async fn test_response_signature_end_to_end() {
+    let mock = mock_with_releases().await;
+    let mut product = mock.product("acme", "myapp");
+    product.response_secret = Some("manifest-secret".to_string());
+    let state = AppState::builder().product("myapp", product).build();
+    let app = test::init_service(App::new().configure(configure(state))).await;
+
+    let req = test::TestRequest::get()
+        .uri("/myapp/stable/windows/x86_64/1.1.0")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let hmac = resp.headers().get("x-update-signature").unwrap().clone();
+    let body = test::read_body(resp).await;
+    assert_eq!(hmac, body_signature("manifest-secret", &body).as_str());
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert!(body["issued_at"].is_string());
+    assert_eq!(body["max_age"], 3600);
+}
+
+#[actix_web::test]
+async fn test_localized_notes_end_to_end() {
+    let mock = MockGitHub::start().await;
+    let mut localized = ReleaseFixture::tauri_v2(2, "MyApp", "1.2.0").with_asset("notes.fr.md");
+    localized.body = "Bug fixes.\n\n<!-- locale: de -->\nFehlerbehebungen.\n".to_string();
+    mock.mount_releases(
+        "acme",
+        "myapp",
+        &[localized, ReleaseFixture::tauri_v2(1, "MyApp", "1.1.0")],
+    )
+    .await;
+    let app = test::init_service(App::new().configure(configure(state(&mock)))).await;
+
+    let req = test::TestRequest::get()
+        .uri("/myapp/stable/windows/x86_64/1.1.0")
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["notes"], "Bug fixes.");
+
+    let req = test::TestRequest::get()
+        .uri("/myapp/stable/windows/x86_64/1.1.0")
+        .insert_header(("Accept-Language", "de-AT,de;q=0.9,en;q=0.8"))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["notes"], "Fehlerbehebungen.");
+    // A notes asset in the language takes precedence over the body
+    for req in [
+        test::TestRequest::get().uri("/myapp/stable/windows/x86_64/1.1.0?locale=fr"),
+        test::TestRequest::get()
+            .uri("/myapp/stable/latest.json")
+            .insert_header(("Accept-Language", "fr-CA")),
+    ] {
+        let body: Value = test::call_and_read_body_json(&app, req.to_request()).await;
+        assert_eq!(body["notes"], "contents of notes.fr.md");
+    }
+}
+
+#[actix_web::test]
+async fn test_changelog_notes_end_to_end() {
+    let mock = mock_with_releases().await;
+    let mut product = mock.product("acme", "myapp");
+    product.changelog = Some("CHANGELOG.md".to_string());
+    let state = AppState::builder().product("myapp", product).build();
+    let app = test::init_service(App::new().configure(configure(state))).await;
+
+    // Notes from the changelog, at the release's tag
+    mock.mount_file(
+        "acme",
+        "myapp",
+        "CHANGELOG.md",
+        "v1.2.0",
+        "# Changelog\n\n## [1.2.0]\n\n- Faster startup\n\n## [1.1.0]\n\n- First release\n",
+    )
+    .await;
+    let req = test::TestRequest::get()
+        .uri("/myapp/stable/windows/x86_64/1.1.0")
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["notes"], "- Faster startup");
+}
+
+#[actix_web::test]
+async fn test_license_end_to_end() {
+    let mock = mock_with_releases().await;
+    let mut product = mock.product("acme", "myapp");
+    product.license_validator = Arc::new(CancelledDevices(vec!["device-9"]));
+    // The entitlement webhook refuses device-7's cancelled subscription
+    let entitlements = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(json!({"device_id": "device-7"})))
+        .respond_with(ResponseTemplate::new(402))
+        .with_priority(1)
+        .mount(&entitlements)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&entitlements)
+        .await;
+    product.entitlement_webhook = Some(entitlements.uri());
+    let state = AppState::builder().product("myapp", product).build();
+    let app = test::init_service(App::new().configure(configure(state))).await;
+
+    let req = test::TestRequest::get()
+        .uri("/myapp/stable/windows/x86_64/1.1.0")
+        .insert_header(("X-Device-Id", "device-1"))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["version"], "1.2.0");
+
+    let req = test::TestRequest::get()
+        .uri("/myapp/stable/windows/x86_64/1.1.0")
+        .insert_header(("X-Device-Id", "device-9"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["code"], "license_denied");
+    assert_eq!(
+        body["message"],
+        "Not licensed for updates: subscription cancelled"
+    );
+
+    let req = test::TestRequest::get()
+        .uri("/myapp/stable/windows/x86_64/1.1.0?device_id=device-7")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["code"], "license_denied");
+    let checks = entitlements.received_requests().await.unwrap();
+    let check: Value = serde_json::from_slice(&checks.last().unwrap().body).unwrap();
+    assert_eq!(
+        check,
+        json!({
+            "product": "myapp",
+            "channel": "stable",
+            "target": "windows",
+            "arch": "x86_64",
+            "current_version": "1.1.0",
+            "device_id": "device-7",
+            "entitlement": null,
+            "os_version": null,
+            "app_build": null,
+            "installation_id": null,
+        })
+    );
+}
+
+#[actix_web::test]
+async fn test_scheduled_release_end_to_end() {
+    let mock = MockGitHub::start().await;
+    let mut scheduled = ReleaseFixture::tauri_v2(3, "MyApp", "1.3.0");
+    scheduled.body = "Big launch.\n<!-- publish-at: 2099-01-01T09:00:00Z -->".to_string();
+    mock.mount_releases(
+        "acme",
+        "launch",
+        &[scheduled, ReleaseFixture::tauri_v2(2, "MyApp", "1.2.0")],
+    )
+    .await;
+    let mut launch = mock.product("acme", "launch");
+    launch.canary_tokens = vec!["dogfood".to_string()];
+    let state = AppState::builder().product("launch", launch).build();
+    let app = test::init_service(App::new().configure(configure(state))).await;
+
+    // Scheduled releases aren't served before their publication time
+    let req = test::TestRequest::get()
+        .uri("/launch/stable/windows/x86_64/1.1.0")
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["version"], "1.2.0");
+    // except to canaries
+    for req in [
+        test::TestRequest::get().insert_header(("X-Canary", "dogfood")),
+        test::TestRequest::get().cookie(Cookie::new("canary", "dogfood")),
+    ] {
+        let req = req.uri("/launch/stable/windows/x86_64/1.1.0").to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["version"], "1.3.0");
+    }
+    let req = test::TestRequest::get()
+        .uri("/launch/stable/windows/x86_64/1.1.0")
+        .insert_header(("X-Canary", "guess"))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["version"], "1.2.0");
+}
+
+#[actix_web::test]
+async fn test_min_os_version_end_to_end() {
+    let mock = mock_with_releases().await;
+    let mut product = mock.product("acme", "myapp");
+    product.min_os_versions = parse_min_os_versions("windows=10.0.22000@1.2.0", &Semver).unwrap();
+    let state = AppState::builder().product("myapp", product).build();
+    let app = test::init_service(App::new().configure(configure(state))).await;
+
+    // Clients on an older Windows stay on the last release they can run
+    let req = test::TestRequest::get()
+        .uri("/myapp/stable/windows/x86_64/1.0.0")
+        .insert_header(("X-OS-Version", "10.0.19045"))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["version"], "1.1.0");
+    let req = test::TestRequest::get()
+        .uri("/myapp/stable/windows/x86_64/1.1.0")
+        .insert_header(("X-OS-Version", "10.0.19045"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+    let req = test::TestRequest::get()
+        .uri("/myapp/stable/windows/x86_64/1.1.0")
+        .insert_header(("X-OS-Version", "10.0.22631"))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["version"], "1.2.0");
+}
+
+#[actix_web::test]
+async fn test_key_rotation_end_to_end() {
+    let mock = mock_with_releases().await;
+    let app = test::init_service(App::new().configure(configure(state(&mock)))).await;
+    let req = test::TestRequest::get().uri("/myapp/pubkey").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["code"], "no_public_key");
+
+    // Releases are signed with the fixture key, retired in favor of a new one
+    let new_key = fixtures::public_key(&[9; 32], &[9; 8]);
+    let mut product = mock.product("acme", "myapp");
+    product.pubkeys = vec![new_key.clone(), fixtures::updater_public_key()];
+    let state = AppState::builder()
+        .auth(admin_auth())
+        .product("myapp", product)
+        .build();
+    let app = test::init_service(App::new().configure(configure(state))).await;
+
+    let req = test::TestRequest::get().uri("/myapp/pubkey").to_request();
+    let body = test::call_and_read_body(&app, req).await;
+    assert_eq!(body, new_key);
+
+    // Signing keys are recorded as releases are served
+    let req = test::TestRequest::get()
+        .uri("/myapp/stable/windows/x86_64/1.1.0")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let req = test::TestRequest::get()
+        .uri("/status")
+        .insert_header(("X-Api-Key", "admin-key"))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    let myapp = &body["products"]["myapp"];
+    assert_eq!(
+        myapp["public_keys"],
+        json!(["0909090909090909", "0807060504030201"])
+    );
+    assert_eq!(myapp["signed_with"]["v1.2.0"], json!(["0807060504030201"]));
+}
+
+#[actix_web::test]
+async fn test_version_end_to_end() {
+    let state = AppState::builder().build();
+    let app = test::init_service(App::new().configure(configure(state))).await;
+
+    let req = test::TestRequest::get().uri("/version").to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["version"], env!("CARGO_PKG_VERSION"));
+    assert!(body["built_at"].is_string());
+    assert!(body["features"]
+        .as_array()
+        .unwrap()
+        .contains(&json!("test-support")));
+}
+
+#[actix_web::test]
+async fn test_embedded_end_to_end() {
+    let mock = mock_with_releases().await;
+    let state = AppState::builder()
+        .product("myapp", mock.product("acme", "myapp"))
+        .cors(CorsConfig::new(vec![
+            "https://dashboard.example.com".to_string()
+        ]))
+        .build();
+    let app = test::init_service(
+        App::new()
+            .configure(configure(state.clone()))
+            .route("/health", web::get().to(HttpResponse::Ok))
+            .route(
+                "/api/items",
+                web::route()
+                    .guard(guard::Options())
+                    .to(|| async { HttpResponse::Ok().body("host preflight") }),
+            ),
+    )
+    .await;
+
+    // The host's own routes and preflights are left alone
+    let req = test::TestRequest::get().uri("/health").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    let req = test::TestRequest::default()
+        .method(actix_web::http::Method::OPTIONS)
+        .uri("/api/items")
+        .insert_header(("Origin", "https://dashboard.example.com"))
+        .insert_header(("Access-Control-Request-Method", "GET"))
+        .to_request();
+    let body = test::call_and_read_body(&app, req).await;
+    assert_eq!(&body[..], b"host preflight");
+
+    // The server's routes answer with request ids and CORS headers
+    let req = test::TestRequest::get()
+        .uri("/myapp/stable/windows/x86_64/1.1.0")
+        .insert_header(("Origin", "https://dashboard.example.com"))
+        .insert_header(("X-Request-Id", "embedded-1"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers().get("X-Request-Id").unwrap(), "embedded-1");
+    assert_eq!(
+        resp.headers().get("Access-Control-Allow-Origin").unwrap(),
+        "https://dashboard.example.com"
+    );
+    let req = test::TestRequest::default()
+        .method(actix_web::http::Method::OPTIONS)
+        .uri("/myapp/stable/windows/x86_64/1.1.0")
+        .insert_header(("Origin", "https://dashboard.example.com"))
+        .insert_header(("Access-Control-Request-Method", "GET"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+    assert!(resp.headers().contains_key("Access-Control-Allow-Methods"));
+
+    // Background jobs run without a standalone server
+    for _ in 0..100 {
+        if state.gc_log.last().is_some() {
+            break;
+        }
+        actix_web::rt::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert!(state.gc_log.last().is_some());
+}
+
+#[actix_web::test]
+async fn test_batch_check_end_to_end() {
+    let mock = mock_with_releases().await;
+    // A private product is only served to clients with its distribution token
+    let mut private = mock.product("acme", "myapp");
+    private.distribution_tokens = vec!["dist-token".to_string()];
+    let state = AppState::builder()
+        .product("myapp", mock.product("acme", "myapp"))
+        .product("private", private)
+        .hostname("http://updates.example.com")
+        .build();
+    let app = test::init_service(App::new().configure(configure(state))).await;
+
+    let check = |product: &str, target: &str, arch: &str, version: &str| {
+        json!({
+            "product": product,
+            "feature": "stable",
+            "target": target,
+            "arch": arch,
+            "current_version": version,
+        })
+    };
+    let req = test::TestRequest::post()
+        .uri("/batch/check")
+        .set_json(json!([
+            check("myapp", "windows", "x86_64", "1.1.0"),
+            check("myapp", "darwin", "aarch64", "1.2.0"),
+            check("otherapp", "darwin", "aarch64", "1.2.0"),
+            check("myapp", "windows", "x86_64", "not-a-version"),
+        ]))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body[0]["status"], 200);
+    assert_eq!(body[0]["update"]["version"], "1.2.0");
+    assert_eq!(
+        body[0]["update"]["url"],
+        "http://updates.example.com/myapp/download/201/MyApp_1.2.0_x64_en-US.msi"
+    );
+    assert_eq!(body[1]["status"], 204);
+    assert!(body[1].get("update").is_none());
+    assert_eq!(body[2]["status"], 404);
+    assert_eq!(body[2]["error"]["code"], "product_not_found");
+    assert_eq!(body[3]["status"], 400);
+
+    let too_many = vec![check("myapp", "windows", "x86_64", "1.1.0"); 51];
+    let req = test::TestRequest::post()
+        .uri("/batch/check")
+        .set_json(too_many)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let private_checks = json!([check("private", "windows", "x86_64", "1.1.0")]);
+    let req = test::TestRequest::post()
+        .uri("/batch/check")
+        .set_json(&private_checks)
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body[0]["status"], 404);
+    assert_eq!(body[0]["error"]["code"], "product_not_found");
+    let req = test::TestRequest::post()
+        .uri("/batch/check")
+        .insert_header(("X-Distribution-Token", "dist-token"))
+        .set_json(&private_checks)
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body[0]["status"], 200);
+
+    // Single checks and downloads of the private product answer as if it
+    // didn't exist, unless the token is sent in the header or query string
+    for uri in [
+        "/private/stable/windows/x86_64/1.1.0",
+        "/private/stable/windows/x86_64/1.1.0?distribution_token=wrong",
+        "/private/download/201/MyApp_1.2.0_x64_en-US.msi",
+        "/private/stable/latest.json",
+    ] {
+        let req = test::TestRequest::get().uri(uri).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND, "{}", uri);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["code"], "product_not_found");
+    }
+    let req = test::TestRequest::get()
+        .uri("/private/stable/windows/x86_64/1.1.0?distribution_token=dist-token")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let req = test::TestRequest::get()
+        .uri("/private/download/201/MyApp_1.2.0_x64_en-US.msi")
+        .insert_header(("X-Distribution-Token", "dist-token"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn test_geo_mirrors_end_to_end() {
+    let mock = mock_with_releases().await;
+    let mut product = mock.product("acme", "myapp");
+    product.mirrors = Mirrors::parse(
+        "europe=https://eu.example.com/myapp/{tag},jp=https://jp.example.com/{filename}",
+    )
+    .unwrap();
+    let state = AppState::builder()
+        .product("myapp", product)
+        .hostname("http://updates.example.com")
+        .build();
+    let app = test::init_service(App::new().configure(configure(state))).await;
+
+    let check = |country: &str| {
+        test::TestRequest::get()
+            .uri("/myapp/stable/windows/x86_64/1.1.0")
+            .insert_header(("CF-IPCountry", country.to_string()))
+            .to_request()
+    };
+    for (country, url) in [
+        (
+            "DE",
+            "https://eu.example.com/myapp/v1.2.0/MyApp_1.2.0_x64_en-US.msi",
+        ),
+        ("JP", "https://jp.example.com/MyApp_1.2.0_x64_en-US.msi"),
+        (
+            "US",
+            "http://updates.example.com/myapp/download/201/MyApp_1.2.0_x64_en-US.msi",
+        ),
+    ] {
+        let resp = test::call_service(&app, check(country)).await;
+        assert!(resp.headers().get_all("Vary").any(|v| v == "CF-IPCountry"));
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["url"], url);
+    }
+}
+
+#[actix_web::test]
+async fn test_cdn_offload_end_to_end() {
+    let mock = mock_with_releases().await;
+    let storage = MockServer::start().await;
+    Mock::given(method("HEAD"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&storage)
+        .await;
+    Mock::given(method("PUT"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&storage)
+        .await;
+
+    let mut product = mock.product("acme", "myapp");
+    product.cdn_offload = true;
+    let bucket = S3Bucket::new(
+        &storage.uri(),
+        "releases",
+        "auto",
+        Credentials {
+            access_key_id: "key".to_string(),
+            secret_access_key: "secret".to_string(),
+        },
+    )
+    .unwrap();
+    let state = AppState::builder()
+        .product("myapp", product)
+        .hostname("http://updates.example.com")
+        .cdn(Cdn::new(bucket, "https://cdn.example.com"))
+        .build();
+    sync::refresh(&state).await;
+
+    let uploads = storage.received_requests().await.unwrap();
+    let upload = uploads
+        .iter()
+        .find(|r| {
+            r.method.as_str() == "PUT"
+                && r.url.path() == "/releases/myapp/v1.2.0/MyApp_1.2.0_x64_en-US.msi"
+        })
+        .unwrap();
+    assert_eq!(upload.body, b"contents of MyApp_1.2.0_x64_en-US.msi");
+    assert!(upload.headers["authorization"]
+        .to_str()
+        .unwrap()
+        .starts_with("AWS4-HMAC-SHA256 Credential=key/"));
+
+    let app = test::init_service(App::new().configure(configure(state))).await;
+    let req = test::TestRequest::get()
+        .uri("/myapp/stable/windows/x86_64/1.1.0")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(
+        body["url"],
+        "https://cdn.example.com/myapp/v1.2.0/MyApp_1.2.0_x64_en-US.msi"
+    );
+
+    let req = test::TestRequest::get()
+        .uri("/myapp/download/201/MyApp_1.2.0_x64_en-US.msi")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::FOUND);
+    assert_eq!(
+        resp.headers().get("Location").unwrap(),
+        "https://cdn.example.com/myapp/v1.2.0/MyApp_1.2.0_x64_en-US.msi"
+    );
+}
+
+#[actix_web::test]
+async fn test_staged_rollout_end_to_end() {
+    let mock = mock_with_releases().await;
+    let mut product = mock.product("acme", "myapp");
+    product.rollout_percentage = Some(50);
+    let state = AppState::builder()
+        .product("myapp", product)
+        .hostname("http://updates.example.com")
+        .build();
+    let app = test::init_service(App::new().configure(configure(state))).await;
+
+    let device = |included: bool| {
+        (0..)
+            .map(|i| format!("device-{}", i))
+            .find(|id| in_rollout("myapp", Some(id), 50) == included)
+            .unwrap()
+    };
+    let check = |device_id: Option<String>| {
+        let req = test::TestRequest::get().uri("/myapp/stable/windows/x86_64/1.0.0");
+        match device_id {
+            Some(id) => req.insert_header(("X-Device-Id", id)),
+            None => req,
+        }
+        .to_request()
+    };
+
+    for _ in 0..2 {
+        let resp = test::call_service(&app, check(Some(device(true)))).await;
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["version"], "1.2.0");
+
+        let resp = test::call_service(&app, check(Some(device(false)))).await;
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["version"], "1.1.0");
+    }
+
+    let resp = test::call_service(&app, check(None)).await;
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["version"], "1.1.0");
+
+    let req = test::TestRequest::get()
+        .uri(&format!(
+            "/myapp/stable/windows/x86_64/1.0.0?device_id={}",
+            device(true)
+        ))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["version"], "1.2.0");
+}
+
+#[actix_web::test]
+async fn test_experiments_end_to_end() {
+    let mock = mock_with_releases().await;
+    let state = AppState::builder()
+        .product("myapp", mock.product("acme", "myapp"))
+        .auth(admin_auth())
+        .build();
+    let app = test::init_service(App::new().configure(configure(state))).await;
+
+    let start = |tag: &str| {
+        test::TestRequest::put()
+            .uri("/admin/products/myapp/experiment")
+            .insert_header(("X-Api-Key", "admin-key"))
+            .set_json(json!({
+                "name": "beta-crashes",
+                "groups": [
+                    {"name": "candidate", "percentage": 50, "tag": tag},
+                    {"name": "control", "percentage": 50},
+                ],
+            }))
+            .to_request()
+    };
+    let resp = test::call_service(&app, start("v9.9.9")).await;
+    assert!(!resp.status().is_success());
+
+    let resp = test::call_service(&app, start("v1.3.0-beta.1")).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let experiment: Value = test::read_body_json(resp).await;
+    assert_eq!(experiment["channel"], "stable");
+    assert!(experiment["started_at"].is_string());
+
+    let check = |device_id: &str| {
+        test::TestRequest::get()
+            .uri("/myapp/stable/windows/x86_64/1.1.0")
+            .insert_header(("X-Device-Id", device_id.to_string()))
+            .to_request()
+    };
+    let mut versions = Vec::new();
+    for i in 0..20 {
+        let resp = test::call_service(&app, check(&format!("device-{}", i))).await;
+        let body: Value = test::read_body_json(resp).await;
+        versions.push(body["version"].as_str().unwrap().to_string());
+    }
+    assert!(versions.iter().any(|v| v == "1.3.0-beta.1"));
+    assert!(versions.iter().any(|v| v == "1.2.0"));
+
+    let req = test::TestRequest::get()
+        .uri("/admin/ui")
+        .insert_header(("X-Api-Key", "admin-key"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        resp.headers().get("Content-Type").unwrap(),
+        "text/html; charset=utf-8"
+    );
+    let page = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+    assert!(page.contains("<tr><td>myapp</td><td>1.2.0</td>"));
+    assert!(page.contains("experiment beta-crashes on stable: candidate 50% → v1.3.0-beta.1"));
+
+    let req = test::TestRequest::delete()
+        .uri("/admin/products/myapp/experiment")
+        .insert_header(("X-Api-Key", "admin-key"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    for i in 0..20 {
+        let resp = test::call_service(&app, check(&format!("device-{}", i))).await;
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["version"], "1.2.0");
+    }
+
+    let req = test::TestRequest::delete()
+        .uri("/admin/products/myapp/experiment")
+        .insert_header(("X-Api-Key", "admin-key"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["code"], "experiment_not_found");
+}
+
+#[actix_web::test]
+async fn test_targeting_rules_end_to_end() {
+    let mock = mock_with_releases().await;
+    let mut product = mock.product("acme", "myapp");
+    product.rules =
+        Rules::parse("locale=ja -> tag=v1.3.0-beta.1; target=windows & os<10.0.17763 -> none")
+            .unwrap();
+    let state = AppState::builder().product("myapp", product).build();
+    let app = test::init_service(App::new().configure(configure(state))).await;
+
+    let resp = test::call_service(&app, check_product("myapp").to_request()).await;
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["version"], "1.2.0");
+
+    let req = test::TestRequest::get()
+        .uri("/myapp/stable/windows/x86_64/1.1.0")
+        .insert_header(("Accept-Language", "ja-JP,en;q=0.5"))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["version"], "1.3.0-beta.1");
+    let req = test::TestRequest::get()
+        .uri("/myapp/stable/windows/x86_64/1.1.0?os_version=6.1.7601")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+}
+
+#[actix_web::test]
+async fn test_sync_status_end_to_end() {
+    let mock = mock_with_releases().await;
+    let state = AppState::builder()
+        .product("myapp", mock.product("acme", "myapp"))
+        .auth(admin_auth())
+        .build();
+    let app = test::init_service(App::new().configure(configure(state))).await;
+    let resp = test::call_service(&app, check_product("myapp").to_request()).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let req = test::TestRequest::get()
+        .uri("/status")
+        .insert_header(("X-Api-Key", "admin-key"))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    let myapp = &body["products"]["myapp"];
+    assert_eq!(myapp["latest_version"], "1.2.0");
+    assert_eq!(myapp["stale"], false);
+    assert_eq!(myapp["last_sync"]["changed"], false);
+    assert!(myapp["last_sync"]["releases"].as_u64().unwrap() > 0);
+}
+
+#[actix_web::test]
+async fn test_quota_end_to_end() {
+    let mock = mock_with_releases().await;
+    let state = AppState::builder()
+        .product("myapp", mock.product("acme", "myapp"))
+        .auth(admin_auth())
+        .build();
+    let app = test::init_service(App::new().configure(configure(state))).await;
+    let resp = test::call_service(&app, check_product("myapp").to_request()).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let req = test::TestRequest::get()
+        .uri("/admin/quota")
+        .insert_header(("X-Api-Key", "admin-key"))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    let tokens = body["tokens"].as_object().unwrap();
+    assert_eq!(tokens.len(), 1);
+    let quota = tokens.values().next().unwrap();
+    assert_eq!(quota["products"], json!(["myapp"]));
+    assert_eq!(quota["rate_limit"]["limit"], 5000);
+    assert_eq!(quota["rate_limit"]["remaining"], 4999);
+    assert_eq!(quota["rate_limit"]["reset"], "2023-11-14T23:13:20Z");
+}
+
+#[actix_web::test]
+async fn test_update_events_end_to_end() {
+    let mock = mock_with_releases().await;
+    let state = AppState::builder()
+        .product("myapp", mock.product("acme", "myapp"))
+        .auth(client_auth())
+        .build();
+    let app = test::init_service(App::new().configure(configure(state))).await;
+
+    for (installation, event) in [
+        ("install-1", "update_downloaded"),
+        ("install-1", "update_installed"),
+        ("install-2", "update_installed"),
+        // Repeated reports count once
+        ("install-2", "update_installed"),
+    ] {
+        let event = json!({"event": event, "version": "v1.2.0", "from_version": "1.1.0"});
+        let resp = test::call_service(&app, report_event(installation, event).to_request()).await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    }
+    let failed = json!({"event": "update_failed", "version": "1.2.0", "error": "disk full"});
+    let resp = test::call_service(&app, report_event("install-3", failed).to_request()).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+    for event in [
+        json!({"event": "update_failed", "version": "one"}),
+        json!({"event": "rebooted", "version": "1.2.0"}),
+        json!({"event": "update_failed", "version": "9.9.9"}),
+    ] {
+        let resp = test::call_service(&app, report_event("install-4", event).to_request()).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+    let req = test::TestRequest::post()
+        .uri("/myapp/events")
+        .insert_header(("X-Installation-Id", "install-4"))
+        .set_json(json!({"event": "update_failed", "version": "1.2.0"}))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    let req = test::TestRequest::get()
+        .uri("/admin/products/myapp/events")
+        .insert_header(("X-Api-Key", "admin-key"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(
+        body["versions"]["1.2.0"],
+        json!({
+            "downloaded": 1,
+            "installed": 2,
+            "failed": 1,
+            "failure_rate": 1.0 / 3.0,
+            "verified": {"downloaded": 1, "installed": 2, "failed": 1},
+            "halted": false,
+        })
+    );
+}
+
+#[actix_web::test]
+async fn test_rollout_halt_end_to_end() {
+    let mock = mock_with_releases().await;
+    let mut product = mock.product("acme", "myapp");
+    product.halt_policy = Some(HaltPolicy {
+        max_failure_rate: 0.25,
+        min_reports: 4,
+    });
+    product.halt_webhook = Some(format!("{}/hooks/halt", mock.url()));
+    let state = AppState::builder()
+        .product("myapp", product)
+        .auth(client_auth())
+        .build();
+    let app = test::init_service(App::new().configure(configure(state))).await;
+    let check = |version: &str| {
+        test::TestRequest::get()
+            .uri(&format!("/myapp/stable/windows/x86_64/{}", version))
+            .insert_header(("X-Client-Key", "client-key"))
+            .to_request()
+    };
+
+    for (installation, event) in [
+        ("install-1", "update_installed"),
+        ("install-2", "update_installed"),
+        ("install-3", "update_failed"),
+    ] {
+        let event = json!({"event": event, "version": "1.2.0"});
+        let resp = test::call_service(&app, report_event(installation, event).to_request()).await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    }
+    // Too few reports to halt yet
+    let resp = test::call_service(&app, check("1.0.0")).await;
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["version"], "1.2.0");
+
+    // A second failure crosses the halt threshold
+    let failed =
+        json!({"event": "update_failed", "version": "1.2.0", "installation_id": "install-4"});
+    let resp = test::call_service(&app, report_event("", failed).to_request()).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+    let resp = test::call_service(&app, check("1.0.0")).await;
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["version"], "1.1.0");
+    let resp = test::call_service(&app, check("1.1.0")).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+    let mut alerts = Vec::new();
+    for _ in 0..50 {
+        alerts = mock
+            .received_requests()
+            .await
+            .into_iter()
+            .filter(|r| r.url.path() == "/hooks/halt")
+            .collect();
+        if !alerts.is_empty() {
+            break;
+        }
+        actix_web::rt::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(alerts.len(), 1);
+    let alert: Value = serde_json::from_slice(&alerts[0].body).unwrap();
+    assert_eq!(alert["version"], "1.2.0");
+    assert_eq!(alert["failure_rate"], 0.5);
+
+    let req = test::TestRequest::put()
+        .uri("/admin/products/myapp/versions/v1.2.0/halted")
+        .insert_header(("X-Api-Key", "admin-key"))
+        .set_json(json!({"halted": false}))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["versions"]["1.2.0"]["halted"], false);
+
+    let resp = test::call_service(&app, check("1.1.0")).await;
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["version"], "1.2.0");
+}
+
+#[actix_web::test]
+async fn test_install_base_end_to_end() {
+    let mock = mock_with_releases().await;
+    let state = AppState::builder()
+        .product("myapp", mock.product("acme", "myapp"))
+        .auth(client_auth())
+        .build();
+    let app = test::init_service(App::new().configure(configure(state))).await;
+
+    // Checks reporting client metadata, in headers or the query string
+    let req = test::TestRequest::get()
+        .uri("/myapp/stable/windows/x86_64/1.1.0")
+        .insert_header(("X-Installation-Id", "install-1"))
+        .insert_header(("X-Client-Key", "client-key"))
+        .insert_header(("X-OS-Version", "10.0.22631"))
+        .insert_header(("X-App-Build", "4512"))
+        .to_request();
+    test::call_service(&app, req).await;
+    let req = test::TestRequest::get()
+        .uri("/myapp/stable/darwin/aarch64/1.2.0?installation_id=install-2&os_version=14.5")
+        .insert_header(("X-Client-Key", "client-key"))
+        .to_request();
+    test::call_service(&app, req).await;
+    let req = test::TestRequest::get()
+        .uri("/admin/products/myapp/events")
+        .insert_header(("X-Api-Key", "admin-key"))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(
+        body["install_base"],
+        json!({
+            "installations": 2,
+            "versions": {"1.1.0": 1, "1.2.0": 1},
+            "platforms": {"darwin-aarch64": 1, "windows-x86_64": 1},
+            "os_versions": {"10.0.22631": 1, "14.5": 1},
+            "app_builds": {"4512": 1},
+        })
+    );
+}
+
+#[actix_web::test]
+async fn test_unverified_events_end_to_end() {
+    let mock = mock_with_releases().await;
+    let mut product = mock.product("acme", "myapp");
+    product.halt_policy = Some(HaltPolicy {
+        max_failure_rate: 0.25,
+        min_reports: 2,
+    });
+    let state = AppState::builder().product("myapp", product).build();
+    let app = test::init_service(App::new().configure(configure(state.clone()))).await;
+
+    let failed = json!({"event": "update_failed", "version": "1.2.0"});
+    let req = test::TestRequest::post()
+        .uri("/myapp/events")
+        .set_json(&failed)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["message"], "Missing parameter installation_id");
+
+    // Anyone can make up installation ids, so anonymous reports don't halt
+    // the rollout, however many there are
+    for i in 0..10 {
+        let req = test::TestRequest::post()
+            .uri("/myapp/events")
+            .insert_header(("X-Installation-Id", format!("spoofed-{}", i)))
+            .set_json(&failed)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    }
+    assert_eq!(state.events.health("myapp")["1.2.0"].failed, 10);
+    assert_eq!(state.events.verified_health("myapp", "1.2.0").failed, 0);
+
+    let req = test::TestRequest::get()
+        .uri("/myapp/stable/windows/x86_64/1.1.0")
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["version"], "1.2.0");
+}
+
+#[actix_web::test]
+async fn test_feedback_end_to_end() {
+    let mock = mock_with_releases().await;
+    mock.mount_issues("acme", "myapp", 42).await;
+    let mut product = mock.product("acme", "myapp");
+    product.feedback_issues = true;
+    let state = AppState::builder()
+        .product("myapp", product)
+        .product("quiet", mock.product("acme", "myapp"))
+        .feedback_limiter(RateLimiter::new(Some(2), Vec::new()))
+        .build();
+    let app = test::init_service(App::new().configure(configure(state))).await;
+
+    let req = test::TestRequest::post()
+        .uri("/myapp/feedback/1.2.0")
+        .set_json(json!({
+            "rating": 2,
+            "message": "@maintainers ![tracker](https://evil.example/p.png)",
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+    // Mentions and images are escaped before reaching GitHub
+    let requests = mock.received_requests().await;
+    let issue = requests
+        .iter()
+        .find(|r| r.method.as_str() == "POST")
+        .expect("feedback filed as an issue");
+    let issue: Value = serde_json::from_slice(&issue.body).unwrap();
+    assert_eq!(issue["title"], "Feedback on v1.2.0: 2/5");
+    let body = issue["body"].as_str().unwrap();
+    assert!(body.contains("> &#64;maintainers !\\[tracker]"));
+    assert!(!body.contains("@maintainers"));
+
+    let req = test::TestRequest::post()
+        .uri("/quiet/feedback/1.2.0")
+        .set_json(json!({"rating": 5, "message": ""}))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["code"], "feedback_disabled");
+
+    // Each address gets two submissions a minute
+    let req = test::TestRequest::post()
+        .uri("/myapp/feedback/1.2.0")
+        .set_json(json!({"rating": 5, "message": ""}))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(resp.headers().contains_key("Retry-After"));
+}
+
+#[actix_web::test]
+async fn test_download_asset_end_to_end() {
+    let mock = mock_with_releases().await;
+    let app = test::init_service(App::new().configure(configure(state(&mock)))).await;
+
+    let req = test::TestRequest::get()
+        .uri("/myapp/download/211/MyApp_1.2.0_amd64.AppImage")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::read_body(resp).await;
+    assert_eq!(&body[..], b"contents of MyApp_1.2.0_amd64.AppImage");
+
+    let req = test::TestRequest::get()
+        .uri("/myapp/download/211/MyApp_1.2.0_amd64.AppImage")
+        .insert_header(("Range", "bytes=0-7"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(resp.headers().get("Content-Range").unwrap(), "bytes 0-7/38");
+    let body = test::read_body(resp).await;
+    assert_eq!(&body[..], b"contents");
+
+    let requests = mock.received_requests().await;
+    assert!(requests
+        .iter()
+        .all(|r| r.headers.get("Authorization").unwrap() == "Bearer test-token"));
+}
+
+#[actix_web::test]
+async fn test_max_asset_size_end_to_end() {
+    let mock = mock_with_releases().await;
+    let mut product = mock.product("acme", "myapp");
+    product.max_asset_size = Some(16);
+    let state = AppState::builder().product("myapp", product).build();
+    let app = test::init_service(App::new().configure(configure(state))).await;
+
+    let req = test::TestRequest::get()
+        .uri("/myapp/download/211/MyApp_1.2.0_amd64.AppImage")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_GATEWAY);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["code"], "asset_too_large");
+}
+
+#[actix_web::test]
+async fn test_max_asset_size_chunked_end_to_end() {
+    let mock = mock_with_releases().await;
+    // Without a Content-Length, the size is only known while streaming
+    mock.mount_chunked_asset("acme", "myapp", 211, &[0; 64 * 1024])
+        .await;
+    let mut product = mock.product("acme", "myapp");
+    product.max_asset_size = Some(16 * 1024);
+
+    let github = GitHubClient::new(&product).unwrap();
+    let downloaded = github.download_asset(211, "acme", "myapp").await;
+    assert!(matches!(downloaded, Err(ServerError::AssetTooLarge(211))));
+
+    let state = AppState::builder().product("myapp", product).build();
+    let app = test::init_service(App::new().configure(configure(state))).await;
+    let req = test::TestRequest::get()
+        .uri("/myapp/download/211/MyApp_1.2.0_amd64.AppImage")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    // The headers are already sent, so the body is cut off
+    assert!(test::try_read_body(resp).await.is_err());
+}
+
+#[actix_web::test]
+async fn test_download_token_end_to_end() {
+    let mock = mock_with_releases().await;
+    let mut product = mock.product("acme", "myapp");
+    product.download_token_secret = Some("token-secret".to_string());
+    let state = AppState::builder()
+        .product("myapp", product)
+        .hostname("http://updates.example.com")
+        .build();
+    let app = test::init_service(App::new().configure(configure(state))).await;
+
+    // Download links carry a token, and the download route requires one
+    let req = test::TestRequest::get()
+        .uri("/myapp/stable/linux/x86_64/1.1.0")
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    let url = body["url"].as_str().unwrap();
+    let path = url.strip_prefix("http://updates.example.com").unwrap();
+    assert!(path.starts_with("/myapp/download/211/MyApp_1.2.0_amd64.AppImage?token="));
+
+    let req = test::TestRequest::get().uri(path).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    for uri in [
+        "/myapp/download/211/MyApp_1.2.0_amd64.AppImage",
+        &path.replace("/211/", "/212/"),
+    ] {
+        let req = test::TestRequest::get().uri(uri).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["code"], "invalid_download_token");
+    }
+}
+
+#[actix_web::test]
+async fn test_local_mirror_end_to_end() {
+    let mock = mock_with_releases().await;
+    let dir = std::env::temp_dir().join(format!("mirror-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let state = AppState::builder()
+        .product("myapp", mock.product("acme", "myapp"))
+        .local_store(LocalStore::new(&dir, 2))
+        .build();
+    assert_eq!(local::sync::refresh(&state).await, 0);
+
+    // Once mirrored, updates are served from disk while GitHub is down
+    let offline = AppState::builder()
+        .product("myapp", mock.product("acme", "unreachable"))
+        .hostname("http://updates.example.com")
+        .local_store(LocalStore::new(&dir, 2))
+        .jobs(without_jobs())
+        .build();
+    let app = test::init_service(App::new().configure(configure(offline))).await;
+    let req = test::TestRequest::get()
+        .uri("/myapp/stable/linux/x86_64/1.1.0")
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["version"], "1.2.0");
+    assert_eq!(
+        body["url"],
+        "http://updates.example.com/myapp/download/211/MyApp_1.2.0_amd64.AppImage"
+    );
+
+    let req = test::TestRequest::get()
+        .uri("/myapp/download/211/MyApp_1.2.0_amd64.AppImage")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::read_body(resp).await;
+    assert_eq!(&body[..], b"contents of MyApp_1.2.0_amd64.AppImage");
+    // Only the two newest releases were mirrored
+    let req = test::TestRequest::get()
+        .uri("/myapp/download/111/MyApp_1.1.0_amd64.AppImage")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(!resp.status().is_success());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[actix_web::test]
+async fn test_cache_gc_end_to_end() {
+    let mock = mock_with_releases().await;
+    let dir = std::env::temp_dir().join(format!("gc-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let state = AppState::builder()
+        .product("myapp", mock.product("acme", "myapp"))
+        .local_store(LocalStore::new(&dir, 2))
+        .build();
+    assert_eq!(local::sync::refresh(&state).await, 0);
+
+    // Keeping a single release deletes the assets of 1.2.0
+    let state = AppState::builder()
+        .product("myapp", mock.product("acme", "myapp"))
+        .local_store(LocalStore::new(&dir, 1))
+        .build();
+    let report = gc::collect(&state).await;
+    let superseded = ReleaseFixture::tauri_v2(2, "MyApp", "1.2.0").assets;
+    assert_eq!(report.removed_assets, superseded.len());
+    assert_eq!(
+        report.reclaimed_bytes,
+        superseded
+            .iter()
+            .map(|a| a.content.len() as u64)
+            .sum::<u64>()
+    );
+    assert_eq!(gc::collect(&state).await.removed_assets, 0);
+
+    // Assets beyond the disk limit are neither downloaded nor kept
+    let newest = ReleaseFixture::tauri_v2(3, "MyApp", "1.3.0-beta.1").assets;
+    let newest_size: usize = newest.iter().map(|a| a.content.len()).sum();
+    let state = AppState::builder()
+        .product("myapp", mock.product("acme", "myapp"))
+        .local_store(LocalStore::new(&dir, 2))
+        .cache_limits(CacheLimits {
+            max_disk_bytes: Some(newest_size as u64),
+            ..CacheLimits::default()
+        })
+        .build();
+    assert_eq!(local::sync::refresh(&state).await, 0);
+    assert_eq!(gc::collect(&state).await.removed_assets, 0);
+    let stored = std::fs::read_dir(dir.join("myapp/assets")).unwrap().count();
+    assert_eq!(stored, newest.len());
+    std::fs::remove_dir_all(dir).unwrap();
+
+    // Metadata unused for the idle limit is dropped too
+    let state = AppState::builder()
+        .cache_limits(CacheLimits {
+            max_idle: Some(Duration::ZERO),
+            ..CacheLimits::default()
+        })
+        .build();
+    state.delta_cache.insert(211, None);
+    let report = gc::collect(&state).await;
+    assert_eq!(report.expired_metadata, 1);
+    assert!(!state.delta_cache.contains(211));
+    assert_eq!(state.gc_log.last(), Some(report));
+}
+
+#[actix_web::test]
+async fn test_maintenance_mode_end_to_end() {
+    let mock = mock_with_releases().await;
+    let mut auth = AuthConfig::default();
+    auth.add(
+        RouteGroup::Admin,
+        Arc::new(BasicAuthenticator::new(
+            [("alice".to_string(), "secret".to_string())].into(),
+        )),
+    );
+    auth.add(
+        RouteGroup::Admin,
+        Arc::new(ApiKeyAuthenticator::new(
+            "X-Api-Key".to_string(),
+            vec!["admin-key".to_string()],
+        )),
+    );
+    let state = AppState::builder()
+        .product("myapp", mock.product("acme", "myapp"))
+        .auth(auth)
+        .build();
+    let app = test::init_service(App::new().configure(configure(state))).await;
+
+    let toggle = |key: &str, enabled: bool| {
+        test::TestRequest::put()
+            .uri("/admin/maintenance")
+            .insert_header(("X-Api-Key", key))
+            .set_json(json!({ "enabled": enabled }))
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, toggle("wrong-key", true)).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        resp.headers().get("WWW-Authenticate").unwrap(),
+        "Basic realm=\"admin\""
+    );
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["code"], "invalid_credentials");
+
+    let req = test::TestRequest::get()
+        .uri("/admin/maintenance")
+        .insert_header(("Authorization", "Basic YWxpY2U6c2VjcmV0"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = test::call_service(&app, toggle("admin-key", true)).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let req = test::TestRequest::get()
+        .uri("/myapp/stable/windows/x86_64/1.1.0")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+    let req = test::TestRequest::get()
+        .uri("/myapp/download/211/MyApp_1.2.0_amd64.AppImage")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(resp.headers().get("Retry-After").unwrap(), "300");
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["code"], "maintenance");
+
+    // An empty suite would make apt drop the packages it knows
+    let req = test::TestRequest::get()
+        .uri("/myapp/apt/dists/stable/Release")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+    let resp = test::call_service(&app, toggle("admin-key", false)).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let req = test::TestRequest::get()
+        .uri("/myapp/stable/windows/x86_64/1.1.0")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let req = test::TestRequest::get()
+        .uri("/admin/audit?action=maintenance.set")
+        .insert_header(("X-Api-Key", "admin-key"))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    let entries = body["entries"].as_array().unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0]["before"], true);
+    assert_eq!(entries[0]["after"], false);
+    assert!(entries[0]["actor"]
+        .as_str()
+        .unwrap()
+        .starts_with("api_key:"));
+}
+
+#[actix_web::test]
+async fn test_maintenance_window_end_to_end() {
+    let mock = mock_with_releases().await;
+    // Business hours around the clock
+    let mut office = mock.product("acme", "myapp");
+    office.maintenance_windows = MaintenanceWindows::parse("00:00-24:00 +01:00").unwrap();
+    let state = AppState::builder()
+        .product("myapp", mock.product("acme", "myapp"))
+        .product("office", office)
+        .build();
+    let app = test::init_service(App::new().configure(configure(state))).await;
+
+    let resp = test::call_service(&app, check_product("office").to_request()).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    let resp = test::call_service(&app, check_product("myapp").to_request()).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn test_log_levels_end_to_end() {
+    let state = AppState::builder().auth(admin_auth()).build();
+    let app = test::init_service(App::new().configure(configure(state))).await;
+    let set_levels = |levels: Value| {
+        test::TestRequest::put()
+            .uri("/admin/log-levels")
+            .insert_header(("X-Api-Key", "admin-key"))
+            .set_json(levels)
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, set_levels(json!({"default": "loud"}))).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let levels = json!({
+        "default": "info",
+        "modules": {"tauri_update_server::github": "debug"},
+        "products": {"myapp": "trace"},
+    });
+    let body: Value = test::call_and_read_body_json(&app, set_levels(levels.clone())).await;
+    assert_eq!(body, levels);
+    let req = test::TestRequest::get()
+        .uri("/admin/audit?action=log_levels.set")
+        .insert_header(("X-Api-Key", "admin-key"))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["entries"][0]["after"], levels);
+}
+
+#[actix_web::test]
+async fn test_upstream_errors_end_to_end() {
+    let mock = MockGitHub::start().await;
+    mock.mount_error(
+        "acme",
+        "limited",
+        403,
+        "API rate limit exceeded for 127.0.0.1",
+    )
+    .await;
+    mock.mount_error("acme", "missing", 404, "Not Found").await;
+    mock.mount_error("acme", "broken", 500, "Server Error")
+        .await;
+    let state = AppState::builder()
+        .auth(admin_auth())
+        .product("limited", mock.product("acme", "limited"))
+        .product("missing", mock.product("acme", "missing"))
+        .product("broken", mock.product("acme", "broken"))
+        .build();
+    let app = test::init_service(App::new().configure(configure(state))).await;
+
+    let resp = test::call_service(&app, check_product("limited").to_request()).await;
+    assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(resp.headers().get("Retry-After").unwrap(), "60");
+
+    let resp = test::call_service(&app, check_product("missing").to_request()).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["code"], "upstream_not_found");
+
+    let resp = test::call_service(&app, check_product("broken").to_request()).await;
+    assert_eq!(resp.status(), StatusCode::BAD_GATEWAY);
+
+    let body: Value = test::call_and_read_body_json(&app, admin_status().to_request()).await;
+    assert_eq!(body["status"], "ok");
+    assert_eq!(body["products"]["missing"]["status"], "loaded");
+    assert_eq!(
+        body["products"]["missing"]["last_error"]["code"],
+        "upstream_not_found"
+    );
+    assert_eq!(body["products"]["broken"]["circuit"], "closed");
+    assert_eq!(body["products"]["broken"]["stale"], true);
+    assert!(body["products"]["broken"]["last_sync"].is_null());
+    assert!(body["products"]["broken"]["last_sync_attempt"].is_string());
+}
+
+#[actix_web::test]
+async fn test_invalid_signature_end_to_end() {
+    let mock = MockGitHub::start().await;
+    let mut release = ReleaseFixture::new(1, "v1.1.0")
+        .with_asset("MyApp_1.1.0_x64_en-US.msi")
+        .with_asset("MyApp_1.1.0_x64_en-US.msi.sig");
+    release.assets[1].content = b"<!DOCTYPE html><title>Unicorn!</title>".to_vec();
+    mock.mount_releases("acme", "garbled", &[release]).await;
+    // Signed with a key the product doesn't list
+    mock.mount_releases(
+        "acme",
+        "rekeyed",
+        &[ReleaseFixture::tauri_v2(1, "MyApp", "1.1.0")],
+    )
+    .await;
+    let mut rekeyed = mock.product("acme", "rekeyed");
+    rekeyed.pubkeys = vec![fixtures::public_key(&[9; 32], &[9; 8])];
+    let state = AppState::builder()
+        .product("garbled", mock.product("acme", "garbled"))
+        .product("rekeyed", rekeyed)
+        .build();
+    let app = test::init_service(App::new().configure(configure(state))).await;
+
+    for product in ["garbled", "rekeyed"] {
+        let resp = test::call_service(&app, check_product(product).to_request()).await;
+        assert_eq!(resp.status(), StatusCode::BAD_GATEWAY);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["code"], "invalid_signature");
+    }
+}
+
+#[actix_web::test]
+async fn test_circuit_breaker_end_to_end() {
+    let mock = MockGitHub::start().await;
+    mock.mount_error("acme", "missing", 404, "Not Found").await;
+    mock.mount_error("acme", "broken", 500, "Server Error")
+        .await;
+    let state = AppState::builder()
+        .auth(admin_auth())
+        .product("missing", mock.product("acme", "missing"))
+        .product("broken", mock.product("acme", "broken"))
+        .build();
+    let app = test::init_service(App::new().configure(configure(state))).await;
+
+    // Repeated outages suspend requests to GitHub
+    test::call_service(&app, check_product("missing").to_request()).await;
+    for _ in 0..5 {
+        test::call_service(&app, check_product("broken").to_request()).await;
+    }
+    let body: Value = test::call_and_read_body_json(&app, admin_status().to_request()).await;
+    assert_eq!(body["status"], "degraded");
+    assert_eq!(body["products"]["broken"]["circuit"], "open");
+    assert_eq!(
+        body["products"]["broken"]["last_error"]["code"],
+        "upstream_error"
+    );
+    // Missing releases aren't outages
+    assert_eq!(body["products"]["missing"]["circuit"], "closed");
+}
+
+#[actix_web::test]
+async fn test_fallback_repo_end_to_end() {
+    let mock = MockGitHub::start().await;
+    mock.mount_error("acme", "broken", 500, "Server Error")
+        .await;
+    // Mirrored to a second repository
+    mock.mount_releases(
+        "acme-mirror",
+        "broken",
+        &[ReleaseFixture::tauri_v2(1, "MyApp", "1.1.0")],
+    )
+    .await;
+    let mut failover = mock.product("acme", "broken");
+    failover.fallback = Some(FallbackRepo::parse("acme-mirror/broken", None, None).unwrap());
+    let state = AppState::builder()
+        .auth(admin_auth())
+        .product("broken", mock.product("acme", "broken"))
+        .product("failover", failover)
+        .build();
+    let app = test::init_service(App::new().configure(configure(state))).await;
+
+    // A product with a fallback repository keeps serving updates
+    let resp = test::call_service(&app, check_product("failover").to_request()).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["version"], "1.1.0");
+    let url = body["url"].as_str().unwrap();
+    let download = &url[url.find("/failover/download/").unwrap()..];
+    let req = test::TestRequest::get().uri(download).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = test::read_body(resp).await;
+    assert_eq!(body, fixtures::asset_content("MyApp_1.1.0_x64_en-US.msi"));
+    let body: Value = test::call_and_read_body_json(&app, admin_status().to_request()).await;
+    assert_eq!(body["products"]["failover"]["circuit"], "closed");
+    assert_eq!(body["products"]["failover"]["failover"], "open");
+    assert!(body["products"]["broken"]["failover"].is_null());
+}
+
+#[actix_web::test]
+async fn test_electron_feed_end_to_end() {
+    let mock = mock_with_releases().await;
+    let app = test::init_service(App::new().configure(configure(state(&mock)))).await;
+
+    let req = test::TestRequest::get()
+        .uri("/myapp/latest.yml")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+    assert!(body.starts_with("version: \"1.2.0\"\n"));
+    assert!(body.contains(
+        "url: \"http://updates.example.com/myapp/download/203/MyApp_1.2.0_x64-setup.exe\""
+    ));
+
+    let req = test::TestRequest::get()
+        .uri("/myapp/latest-linux.yml")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+    assert!(body.contains("MyApp_1.2.0_amd64.AppImage"));
+}
+
+#[actix_web::test]
+async fn test_winget_feed_end_to_end() {
+    let mock = mock_with_releases().await;
+    let app = test::init_service(App::new().configure(configure(state(&mock)))).await;
+
+    let req = test::TestRequest::get()
+        .uri("/myapp/winget/manifest?part=installer")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+    assert!(body.starts_with("PackageIdentifier: \"acme.myapp\"\nPackageVersion: \"1.2.0\"\n"));
+    assert!(body.contains("  - Architecture: x64\n    InstallerType: wix\n"));
+    assert!(body.contains("InstallerType: nullsoft"));
+}
+
+#[actix_web::test]
+async fn test_homebrew_feed_end_to_end() {
+    let mock = mock_with_releases().await;
+    let app = test::init_service(App::new().configure(configure(state(&mock)))).await;
+
+    let req = test::TestRequest::get()
+        .uri("/myapp/homebrew/cask.rb")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+    assert!(body.contains("  on_arm do\n"));
+    assert!(body
+        .contains("url \"http://updates.example.com/myapp/download/210/MyApp_1.2.0_aarch64.dmg\""));
+    assert!(body.contains("  app \"MyApp.app\"\n"));
+}
+
+#[actix_web::test]
+async fn test_scoop_feed_end_to_end() {
+    let mock = mock_with_releases().await;
+    let app = test::init_service(App::new().configure(configure(state(&mock)))).await;
+
+    let req = test::TestRequest::get()
+        .uri("/scoop/myapp.json")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["version"], "1.2.0");
+    assert_eq!(
+        body["architecture"]["64bit"]["url"],
+        "http://updates.example.com/myapp/download/201/MyApp_1.2.0_x64_en-US.msi"
+    );
+
+    let req = test::TestRequest::get()
+        .uri("/scoop/myapp/1.1.0/64bit")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::FOUND);
+    assert_eq!(
+        resp.headers().get("location").unwrap(),
+        "http://updates.example.com/myapp/download/101/MyApp_1.1.0_x64_en-US.msi"
+    );
+}
+
+#[actix_web::test]
+async fn test_nuget_feed_end_to_end() {
+    let mock = mock_with_releases().await;
+    let app = test::init_service(App::new().configure(configure(state(&mock)))).await;
+
+    let req = test::TestRequest::get()
+        .uri("/myapp/nuget/FindPackagesById()?id='myapp'")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+    assert!(body.contains("<d:Version>1.2.0</d:Version>"));
+
+    let req = test::TestRequest::get()
+        .uri("/myapp/nuget/package/myapp/1.2.0")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let nupkg = test::read_body(resp).await;
+    assert!(nupkg.starts_with(b"PK\x03\x04"));
+}
+
+#[actix_web::test]
+async fn test_apt_feed_end_to_end() {
+    let mock = mock_with_releases().await;
+    let app = test::init_service(App::new().configure(configure(state(&mock)))).await;
+
+    let req = test::TestRequest::get()
+        .uri("/myapp/apt/dists/stable/Release")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+    assert!(body.contains("Suite: stable\n"));
+    assert!(body.contains("Components: main\n"));
+
+    // The repository is unsigned without MYAPP_APT_GPG_KEY
+    let req = test::TestRequest::get()
+        .uri("/myapp/apt/dists/stable/InRelease")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    let req = test::TestRequest::get()
+        .uri("/myapp/apt/pool/213/MyApp_1.2.0_amd64.deb")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::FOUND);
+    assert_eq!(
+        resp.headers().get("location").unwrap(),
+        "http://updates.example.com/myapp/download/213/MyApp_1.2.0_amd64.deb"
+    );
+}
+
+#[actix_web::test]
+async fn test_yum_feed_end_to_end() {
+    let mock = mock_with_releases().await;
+    let app = test::init_service(App::new().configure(configure(state(&mock)))).await;
+
+    let req = test::TestRequest::get()
+        .uri("/myapp/yum/stable/repodata/repomd.xml")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+    assert!(body.contains("<location href=\"repodata/primary.xml.gz\"/>"));
+
+    let req = test::TestRequest::get()
+        .uri("/myapp/yum/stable/repodata/primary.xml.gz")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(test::read_body(resp).await.starts_with(&[0x1f, 0x8b]));
+}
+
+#[actix_web::test]
+async fn test_delta_updates_end_to_end() {
+    let mock = mock_with_releases().await;
+    let state = AppState::builder()
+        .product(
+            "myapp",
+            ProductConfig {
+                delta_updates: true,
+                ..mock.product("acme", "myapp")
+            },
+        )
+        .hostname("http://updates.example.com")
+        .jobs(without_jobs())
+        .build();
+    refresh(&state).await;
+    let app = test::init_service(App::new().configure(configure(state))).await;
+
+    let req = test::TestRequest::get()
+        .uri("/myapp/stable/windows/x86_64/1.1.0")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(
+        body["url"],
+        "http://updates.example.com/myapp/download/201/MyApp_1.2.0_x64_en-US.msi"
+    );
+    assert_eq!(body["patch"]["from_version"], "1.1.0");
+    assert_eq!(
+        body["patch"]["url"],
+        "http://updates.example.com/myapp/delta/101/201"
+    );
+
+    let req = test::TestRequest::get()
+        .uri("/myapp/delta/101/201")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let patch = test::read_body(resp).await;
+    assert_eq!(
+        apply_patch(b"contents of MyApp_1.1.0_x64_en-US.msi", &patch).unwrap(),
+        b"contents of MyApp_1.2.0_x64_en-US.msi"
+    );
+
+    // Only clients on the previous release get a patch
+    let req = test::TestRequest::get()
+        .uri("/myapp/stable/windows/x86_64/1.0.0")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    let body: Value = test::read_body_json(resp).await;
+    assert!(body.get("patch").is_none());
+}
+
+#[actix_web::test]
+async fn test_edition_end_to_end() {
+    let mock = MockGitHub::start().await;
+    mock.mount_releases(
+        "acme",
+        "editions",
+        &[ReleaseFixture::new(1, "v1.2.0")
+            .with_asset("MyApp_1.2.0_amd64-trial.AppImage")
+            .with_asset("MyApp_1.2.0_amd64-trial.AppImage.sig")
+            .with_asset("MyApp_1.2.0_amd64-full.AppImage")
+            .with_asset("MyApp_1.2.0_amd64-full.AppImage.sig")],
+    )
+    .await;
+    let mut product = mock.product("acme", "editions");
+    product.entitlement = Some(EntitlementVerifier::from_secret("licensing-secret"));
+    let check = |edition: &str| {
+        test::TestRequest::get().uri(&format!(
+            "/editions/stable/linux/x86_64/1.1.0?edition={}",
+            edition
+        ))
+    };
+
+    // Anyone could ask for the full edition, so only its token grants it
+    let state = AppState::builder()
+        .product("editions", product.clone())
+        .build();
+    let app = test::init_service(App::new().configure(configure(state))).await;
+    let body: Value = test::call_and_read_body_json(&app, check("full").to_request()).await;
+    assert!(body["url"].as_str().unwrap().ends_with("-trial.AppImage"));
+
+    let token = jsonwebtoken::encode(
+        &jsonwebtoken::Header::default(),
+        &json!({ "edition": "full", "exp": chrono::Utc::now().timestamp() + 3600 }),
+        &jsonwebtoken::EncodingKey::from_secret(b"licensing-secret"),
+    )
+    .unwrap();
+    let req = check("trial")
+        .insert_header(("X-Entitlement-Token", token))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert!(body["url"].as_str().unwrap().ends_with("-full.AppImage"));
+
+    // Clients the update routes authenticate choose for themselves
+    let mut auth = AuthConfig::default();
+    auth.add(
+        RouteGroup::Update,
+        Arc::new(ApiKeyAuthenticator::new(
+            "X-Api-Key".to_string(),
+            vec!["build-key".to_string()],
+        )),
+    );
+    let state = AppState::builder()
+        .product("editions", product)
+        .auth(auth)
+        .build();
+    let app = test::init_service(App::new().configure(configure(state))).await;
+    let req = check("full")
+        .insert_header(("X-Api-Key", "build-key"))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert!(body["url"].as_str().unwrap().ends_with("-full.AppImage"));
+}
+
+#[actix_web::test]
+async fn test_release_cache_end_to_end() {
+    let mock = mock_with_releases().await;
+    let check = || {
+        test::TestRequest::get()
+            .uri("/myapp/stable/linux/x86_64/1.1.0")
+            .to_request()
+    };
+    let requests = |path: &'static str| {
+        let mock = &mock;
+        async move {
+            mock.received_requests()
+                .await
+                .iter()
+                .filter(|request| request.url.path().ends_with(path))
+                .count()
+        }
+    };
+
+    // Repeated checks are answered from the cached releases and decision
+    let state = AppState::builder()
+        .product("myapp", mock.product("acme", "myapp"))
+        .build();
+    let app = test::init_service(App::new().configure(configure(state))).await;
+    for _ in 0..2 {
+        let body: Value = test::call_and_read_body_json(&app, check()).await;
+        assert!(body["url"].as_str().unwrap().contains("/download/211/"));
+    }
+    assert_eq!(requests("/releases").await, 1);
+    assert_eq!(requests("/releases/assets/212").await, 1);
+
+    // A re-uploaded installer invalidates the decisions of its release
+    let state = AppState::builder()
+        .product("myapp", mock.product("acme", "myapp"))
+        .release_cache_ttl(Duration::ZERO)
+        .build();
+    let app = test::init_service(App::new().configure(configure(state))).await;
+    let body: Value = test::call_and_read_body_json(&app, check()).await;
+    assert!(body["url"].as_str().unwrap().contains("/download/211/"));
+
+    let mut release = ReleaseFixture::tauri_v2(2, "MyApp", "1.2.0");
+    for asset in &mut release.assets {
+        if asset.name.starts_with("MyApp_1.2.0_amd64.AppImage") {
+            asset.id += 80;
+        }
+    }
+    mock.reset().await;
+    mock.mount_releases(
+        "acme",
+        "myapp",
+        &[release, ReleaseFixture::tauri_v2(1, "MyApp", "1.1.0")],
+    )
+    .await;
+    let body: Value = test::call_and_read_body_json(&app, check()).await;
+    assert!(body["url"].as_str().unwrap().contains("/download/291/"));
+}
+
+#[actix_web::test]
+async fn test_pinned_release_end_to_end() {
+    let mock = mock_with_releases().await;
+    mock.mount_error("acme", "broken", 500, "Server Error").await;
+    let state = AppState::builder()
+        .product("myapp", mock.product("acme", "myapp"))
+        .product("broken", mock.product("acme", "broken"))
+        .build();
+    let app = test::init_service(App::new().configure(configure(state))).await;
+
+    let req = test::TestRequest::get()
+        .uri("/myapp/stable/linux/x86_64/1.0.0?to=v1.1.0")
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["version"], "1.1.0");
+
+    let req = test::TestRequest::get()
+        .uri("/myapp/stable/linux/x86_64/1.0.0?to=v9.9.9")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["code"], "release_not_found");
+
+    // A failing GitHub isn't a missing release
+    let req = test::TestRequest::get()
+        .uri("/broken/stable/linux/x86_64/1.0.0?to=v1.1.0")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_GATEWAY);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["code"], "upstream_error");
+}
+
+#[actix_web::test]
+async fn test_cors_end_to_end() {
+    let mock = mock_with_releases().await;
+    let state = AppState::builder()
+        .product("myapp", mock.product("acme", "myapp"))
+        .cors(CorsConfig::new(vec![
+            "https://dashboard.example.com".to_string()
+        ]))
+        .build();
+    let app = test::init_service(App::new().configure(configure(state))).await;
+    let check = |origin: &str| {
+        test::TestRequest::get()
+            .uri("/myapp/stable/windows/x86_64/1.1.0")
+            .insert_header(("Origin", origin))
+            .to_request()
+    };
+    let preflight = |origin: &str| {
+        test::TestRequest::default()
+            .method(actix_web::http::Method::OPTIONS)
+            .uri("/myapp/stable/windows/x86_64/1.1.0")
+            .insert_header(("Origin", origin))
+            .insert_header(("Access-Control-Request-Method", "GET"))
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, check("https://dashboard.example.com")).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        resp.headers().get("Access-Control-Allow-Origin").unwrap(),
+        "https://dashboard.example.com"
+    );
+
+    let resp = test::call_service(&app, check("https://evil.example.com")).await;
+    assert!(!resp.headers().contains_key("Access-Control-Allow-Origin"));
+
+    let resp = test::call_service(&app, preflight("https://dashboard.example.com")).await;
+    assert!(resp.status().is_success());
+    let headers = resp.headers();
+    assert_eq!(
+        headers.get("Access-Control-Allow-Origin").unwrap(),
+        "https://dashboard.example.com"
+    );
+    let methods = headers
+        .get("Access-Control-Allow-Methods")
+        .unwrap()
+        .to_str()
+        .unwrap();
+    assert!(methods.contains("GET") && methods.contains("POST"));
+    assert_eq!(headers.get("Access-Control-Max-Age").unwrap(), "3600");
+
+    let resp = test::call_service(&app, preflight("https://evil.example.com")).await;
+    assert!(!resp.headers().contains_key("Access-Control-Allow-Origin"));
+}
+
+#[actix_web::test]
+async fn test_compression_end_to_end() {
+    let mock = mock_with_releases().await;
+    let manifest = || {
+        test::TestRequest::get()
+            .uri("/myapp/stable/latest.json")
+            .insert_header(("Accept-Encoding", "gzip"))
+            .to_request()
+    };
+
+    let state = AppState::builder()
+        .product("myapp", mock.product("acme", "myapp"))
+        .build();
+    let app = test::init_service(App::new().configure(configure(state))).await;
+    let resp = test::call_service(&app, manifest()).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers().get("Content-Encoding").unwrap(), "gzip");
+    assert!(test::read_body(resp).await.starts_with(&[0x1f, 0x8b]));
+
+    // Left to a reverse proxy that compresses itself
+    let state = AppState::builder()
+        .product("myapp", mock.product("acme", "myapp"))
+        .compression(false)
+        .build();
+    let app = test::init_service(App::new().configure(configure(state))).await;
+    let resp = test::call_service(&app, manifest()).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(!resp.headers().contains_key("Content-Encoding"));
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["version"], "1.2.0");
+}
+
+#[actix_web::test]
+async fn test_channels_end_to_end() {
+    // Assets of non-stable channels carry the channel's name as a prefix
+    let beta = ReleaseFixture::tauri_v2(3, "MyApp", "1.3.0-beta.1")
+        .with_asset("BETA.MyApp_1.3.0-beta.1_x64_en-US.msi")
+        .with_asset("BETA.MyApp_1.3.0-beta.1_x64_en-US.msi.sig")
+        .prerelease();
+    let mock = MockGitHub::start().await;
+    mock.mount_releases(
+        "acme",
+        "myapp",
+        &[beta, ReleaseFixture::tauri_v2(2, "MyApp", "1.2.0")],
+    )
+    .await;
+    let mut product = mock.product("acme", "myapp");
+    product.channels = vec!["beta".to_string()];
+    product.prerelease_channels = vec!["beta".to_string()];
+    product.channel_aliases = [("insider".to_string(), "beta".to_string())].into();
+    let state = AppState::builder().product("myapp", product).build();
+    let app = test::init_service(App::new().configure(configure(state))).await;
+    let check = |channel: &str| {
+        test::TestRequest::get()
+            .uri(&format!("/myapp/{}/windows/x86_64/1.2.0", channel))
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, check("stable")).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+    let body: Value = test::call_and_read_body_json(&app, check("Beta")).await;
+    assert_eq!(body["version"], "1.3.0-beta.1");
+
+    // Aliases resolve to the channel they name
+    let body: Value = test::call_and_read_body_json(&app, check("insider")).await;
+    assert_eq!(body["version"], "1.3.0-beta.1");
+
+    let resp = test::call_service(&app, check("nightly")).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["code"], "unknown_channel");
+}